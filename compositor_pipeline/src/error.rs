@@ -1,12 +1,15 @@
+use std::{path::Path, sync::Arc};
+
 use compositor_render::{
     error::{
-        InitRendererEngineError, RegisterError, RegisterRendererError, UnregisterRendererError,
-        UpdateSceneError, WgpuError,
+        InitRendererEngineError, RegisterError, RegisterRendererError, RenderSceneError,
+        UnregisterRendererError, UpdateSceneError, WgpuError,
     },
+    scene::SceneError,
     InputId, OutputId,
 };
 
-use crate::pipeline::structs::Codec;
+use crate::pipeline::{output::whip::WhipError, structs::Codec};
 
 #[derive(Debug, thiserror::Error)]
 pub enum RegisterInputError {
@@ -18,6 +21,14 @@ pub enum RegisterInputError {
 
     #[error("Input initialization error while registering input for stream \"{0}\".")]
     InputError(InputId, #[source] InputInitError),
+
+    #[error("Invalid fallback of input stream \"{0}\".")]
+    FallbackError(InputId, #[source] UpdateSceneError),
+
+    #[error(
+        "Failed to register input stream \"{0}\". Limit of {1} registered inputs was reached."
+    )]
+    LimitExceeded(InputId, usize),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -33,6 +44,94 @@ pub enum RegisterOutputError {
 
     #[error("Failed to register output stream \"{0}\". Resolution in each dimension has to be divisible by 2.")]
     UnsupportedResolution(OutputId),
+
+    #[error(
+        "Failed to register output stream \"{0}\". Limit of {1} registered outputs was reached."
+    )]
+    LimitExceeded(OutputId, usize),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateOutputResolutionError {
+    #[error("Failed to update resolution of output stream. Stream \"{0}\" does not exist.")]
+    NotFound(OutputId),
+
+    #[error("Failed to update resolution of output stream \"{0}\". Resolution in each dimension has to be divisible by 2.")]
+    UnsupportedResolution(OutputId),
+
+    #[error("Failed to update resolution of output stream \"{0}\". Resolution of MP4, RTMP and HLS outputs can't be changed.")]
+    UnsupportedOutput(OutputId),
+
+    #[error("Failed to reopen the encoder of output stream \"{0}\" with the new resolution.")]
+    EncoderError(OutputId, #[source] EncoderInitError),
+
+    #[error("Failed to update the scene after changing resolution of output stream \"{0}\".")]
+    UpdateSceneError(OutputId, #[source] UpdateSceneError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateEncoderSettingsError {
+    #[error("Failed to update encoder settings of output stream. Stream \"{0}\" does not exist.")]
+    NotFound(OutputId),
+
+    #[error("Failed to update encoder settings of output stream \"{0}\". Encoder settings of MP4, RTMP and HLS outputs can't be changed.")]
+    UnsupportedOutput(OutputId),
+
+    #[error("Failed to update encoder settings of output stream \"{0}\". Codec can't be changed, register a new output instead.")]
+    CodecChange(OutputId),
+
+    #[error("Failed to reopen the encoder of output stream \"{0}\" with the new settings.")]
+    EncoderError(OutputId, #[source] EncoderInitError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("Failed to take a snapshot of output stream. Stream \"{0}\" does not exist.")]
+    NotFound(OutputId),
+
+    #[error(
+        "Failed to take a snapshot of output stream \"{0}\". Scene of the output is not defined."
+    )]
+    NoScene(OutputId),
+
+    #[error("Failed to render a snapshot of output stream \"{0}\".")]
+    RenderError(OutputId, #[source] RenderSceneError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SaveClipError {
+    #[error("Failed to save a clip of output stream. Stream \"{0}\" does not exist.")]
+    NotFound(OutputId),
+
+    #[error("Failed to save a clip of output stream \"{0}\". Clip buffer is not enabled for this output.")]
+    NotEnabled(OutputId),
+
+    #[error("Failed to save a clip of output stream \"{0}\". No keyframe was encoded yet.")]
+    Empty(OutputId),
+
+    #[error("Failed to write a clip of output stream \"{0}\".")]
+    OutputError(OutputId, #[source] OutputInitError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RequestKeyframeError {
+    #[error("Failed to request a keyframe of output stream. Stream \"{0}\" does not exist.")]
+    NotFound(OutputId),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SetOutputEnabledError {
+    #[error("Failed to enable or disable output stream. Stream \"{0}\" does not exist.")]
+    NotFound(OutputId),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SetInputEnabledError {
+    #[error("Failed to enable or disable input stream. Stream \"{0}\" does not exist.")]
+    NotFound(InputId),
+
+    #[error("Failed to update the fallback of input stream \"{0}\".")]
+    FallbackError(InputId, #[source] UpdateSceneError),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -62,8 +161,23 @@ pub enum OutputInitError {
     #[error("An unsupported codec was requested: {0:?}.")]
     UnsupportedCodec(Codec),
 
+    #[error("Failed to connect to the RTMP server \"{0}\".")]
+    RtmpConnectionFailed(Arc<str>, #[source] ffmpeg_next::Error),
+
+    #[error("Failed to connect to the WHIP endpoint \"{0}\".")]
+    WhipConnectionFailed(Arc<str>, #[source] WhipError),
+
+    #[error("Failed to create the HLS output directory {0:?}.")]
+    HlsDirectory(Arc<Path>, #[source] std::io::Error),
+
+    #[error("Audio is not supported by RTP and WHIP outputs.")]
+    AudioNotSupported,
+
     #[error(transparent)]
     SocketError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    FfmpegError(#[from] ffmpeg_next::Error),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -71,6 +185,15 @@ pub enum EncoderInitError {
     #[error("Could not find an ffmpeg codec")]
     NoCodec,
 
+    #[error("Hardware encoder {0} is not available. Make sure that ffmpeg was built with it and a supported GPU with its drivers is available, or use a software encoder instead.")]
+    HardwareEncoderNotAvailable(&'static str, #[source] Option<ffmpeg_next::Error>),
+
+    #[error("Encoder thread is not running.")]
+    EncoderThreadStopped,
+
+    #[error("Encoder can't be reopened with a different codec ({0:?}).")]
+    CodecChange(Codec),
+
     #[error(transparent)]
     FfmpegError(#[from] ffmpeg_next::Error),
 }
@@ -79,12 +202,136 @@ pub enum EncoderInitError {
 pub enum DecoderInitError {
     #[error(transparent)]
     FfmpegError(#[from] ffmpeg_next::Error),
+
+    #[error("Input produces encoded chunks, but a decoder was not specified.")]
+    MissingDecoderOptions,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum InputInitError {
     #[error(transparent)]
     Rtp(#[from] crate::pipeline::input::rtp::RtpReceiverError),
+
+    #[error(transparent)]
+    File(#[from] crate::pipeline::input::file::FileInputError),
+
+    #[error(transparent)]
+    UdpTs(#[from] crate::pipeline::input::udp_ts::UdpTsInputError),
+
+    #[error(transparent)]
+    Srt(#[from] crate::pipeline::input::srt::SrtInputError),
+
+    #[error(transparent)]
+    Rtsp(#[from] crate::pipeline::input::rtsp::RtspInputError),
+
+    #[error(transparent)]
+    Tcp(#[from] crate::pipeline::input::tcp::TcpInputError),
+
+    #[error(transparent)]
+    StaticImage(#[from] crate::pipeline::input::static_image::StaticImageError),
+}
+
+/// Codes of errors returned by the API for errors of the pipeline. String representations
+/// are stable, so clients can match on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    InternalServerError,
+    InputStreamAlreadyRegistered,
+    InputStreamDecoderError,
+    InputStreamInputError,
+    InputStreamNotFound,
+    InputStreamStillInUse,
+    PortAlreadyInUse,
+    InputLimitExceeded,
+    OutputStreamAlreadyRegistered,
+    OutputStreamEncoderError,
+    HardwareEncoderNotAvailable,
+    OutputStreamOutputError,
+    OutputStreamNotFound,
+    NoOutputsRegistered,
+    OutputStreamStillInUse,
+    PortAndIpAlreadyInUse,
+    RtmpUrlAlreadyInUse,
+    HlsDirectoryAlreadyInUse,
+    OutputLimitExceeded,
+    UnsupportedResolution,
+    ResolutionLimitExceeded,
+    UnsupportedResolutionChange,
+    UnsupportedEncoderSettingsChange,
+    OutputSceneNotDefined,
+    SnapshotEncodingError,
+    SdpNotAvailable,
+    ClipBufferNotEnabled,
+    ClipNotAvailable,
+    ClipWriteError,
+    BuildSceneError,
+    RendererNotFound,
+    InvalidShaderParameters,
+    DuplicateComponentId,
+    EntityAlreadyRegistered,
+    EntityNotFound,
+    InvalidShader,
+    RegisterImageError,
+    RegisterWebRendererError,
+    WgpuInitError,
+    WebRendererInitError,
+    LayoutInitError,
+    ChromaKeyInitError,
+    WatermarkInitError,
+    WgpuValidationError,
+    WgpuOutOfMemoryError,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::InternalServerError => "INTERNAL_SERVER_ERROR",
+            ErrorCode::InputStreamAlreadyRegistered => "INPUT_STREAM_ALREADY_REGISTERED",
+            ErrorCode::InputStreamDecoderError => "INPUT_STREAM_DECODER_ERROR",
+            ErrorCode::InputStreamInputError => "INPUT_STREAM_INPUT_ERROR",
+            ErrorCode::InputStreamNotFound => "INPUT_STREAM_NOT_FOUND",
+            ErrorCode::InputStreamStillInUse => "INPUT_STREAM_STILL_IN_USE",
+            ErrorCode::PortAlreadyInUse => "PORT_ALREADY_IN_USE",
+            ErrorCode::InputLimitExceeded => "INPUT_LIMIT_EXCEEDED",
+            ErrorCode::OutputStreamAlreadyRegistered => "OUTPUT_STREAM_ALREADY_REGISTERED",
+            ErrorCode::OutputStreamEncoderError => "OUTPUT_STREAM_ENCODER_ERROR",
+            ErrorCode::HardwareEncoderNotAvailable => "HARDWARE_ENCODER_NOT_AVAILABLE",
+            ErrorCode::OutputStreamOutputError => "OUTPUT_STREAM_OUTPUT_ERROR",
+            ErrorCode::OutputStreamNotFound => "OUTPUT_STREAM_NOT_FOUND",
+            ErrorCode::NoOutputsRegistered => "NO_OUTPUTS_REGISTERED",
+            ErrorCode::OutputStreamStillInUse => "OUTPUT_STREAM_STILL_IN_USE",
+            ErrorCode::PortAndIpAlreadyInUse => "PORT_AND_IP_ALREADY_IN_USE",
+            ErrorCode::RtmpUrlAlreadyInUse => "RTMP_URL_ALREADY_IN_USE",
+            ErrorCode::HlsDirectoryAlreadyInUse => "HLS_DIRECTORY_ALREADY_IN_USE",
+            ErrorCode::OutputLimitExceeded => "OUTPUT_LIMIT_EXCEEDED",
+            ErrorCode::UnsupportedResolution => "UNSUPPORTED_RESOLUTION",
+            ErrorCode::ResolutionLimitExceeded => "RESOLUTION_LIMIT_EXCEEDED",
+            ErrorCode::UnsupportedResolutionChange => "UNSUPPORTED_RESOLUTION_CHANGE",
+            ErrorCode::UnsupportedEncoderSettingsChange => "UNSUPPORTED_ENCODER_SETTINGS_CHANGE",
+            ErrorCode::OutputSceneNotDefined => "OUTPUT_SCENE_NOT_DEFINED",
+            ErrorCode::SnapshotEncodingError => "SNAPSHOT_ENCODING_ERROR",
+            ErrorCode::SdpNotAvailable => "SDP_NOT_AVAILABLE",
+            ErrorCode::ClipBufferNotEnabled => "CLIP_BUFFER_NOT_ENABLED",
+            ErrorCode::ClipNotAvailable => "CLIP_NOT_AVAILABLE",
+            ErrorCode::ClipWriteError => "CLIP_WRITE_ERROR",
+            ErrorCode::BuildSceneError => "BUILD_SCENE_ERROR",
+            ErrorCode::RendererNotFound => "RENDERER_NOT_FOUND",
+            ErrorCode::InvalidShaderParameters => "INVALID_SHADER_PARAMETERS",
+            ErrorCode::DuplicateComponentId => "DUPLICATE_COMPONENT_ID",
+            ErrorCode::EntityAlreadyRegistered => "ENTITY_ALREADY_REGISTERED",
+            ErrorCode::EntityNotFound => "ENTITY_NOT_FOUND",
+            ErrorCode::InvalidShader => "INVALID_SHADER",
+            ErrorCode::RegisterImageError => "REGISTER_IMAGE_ERROR",
+            ErrorCode::RegisterWebRendererError => "REGISTER_WEB_RENDERER_ERROR",
+            ErrorCode::WgpuInitError => "WGPU_INIT_ERROR",
+            ErrorCode::WebRendererInitError => "WEB_RENDERER_INIT_ERROR",
+            ErrorCode::LayoutInitError => "LAYOUT_INIT_ERROR",
+            ErrorCode::ChromaKeyInitError => "CHROMA_KEY_INIT_ERROR",
+            ErrorCode::WatermarkInitError => "WATERMARK_INIT_ERROR",
+            ErrorCode::WgpuValidationError => "WGPU_VALIDATION_ERROR",
+            ErrorCode::WgpuOutOfMemoryError => "WGPU_OUT_OF_MEMORY_ERROR",
+        }
+    }
 }
 
 pub enum ErrorType {
@@ -94,12 +341,12 @@ pub enum ErrorType {
 }
 
 pub struct PipelineErrorInfo {
-    pub error_code: &'static str,
+    pub error_code: ErrorCode,
     pub error_type: ErrorType,
 }
 
 impl PipelineErrorInfo {
-    fn new(error_code: &'static str, error_type: ErrorType) -> Self {
+    fn new(error_code: ErrorCode, error_type: ErrorType) -> Self {
         Self {
             error_code,
             error_type,
@@ -107,173 +354,297 @@ impl PipelineErrorInfo {
     }
 }
 
-const INPUT_STREAM_ALREADY_REGISTERED: &str = "INPUT_STREAM_ALREADY_REGISTERED";
-const DECODER_ERROR: &str = "INPUT_STREAM_DECODER_ERROR";
-const INPUT_ERROR: &str = "INPUT_STREAM_INPUT_ERROR";
-
 impl From<&RegisterInputError> for PipelineErrorInfo {
     fn from(err: &RegisterInputError) -> Self {
         match err {
-            RegisterInputError::AlreadyRegistered(_) => {
-                PipelineErrorInfo::new(INPUT_STREAM_ALREADY_REGISTERED, ErrorType::UserError)
-            }
+            RegisterInputError::AlreadyRegistered(_) => PipelineErrorInfo::new(
+                ErrorCode::InputStreamAlreadyRegistered,
+                ErrorType::UserError,
+            ),
 
             RegisterInputError::DecoderError(_, _) => {
-                PipelineErrorInfo::new(DECODER_ERROR, ErrorType::ServerError)
+                PipelineErrorInfo::new(ErrorCode::InputStreamDecoderError, ErrorType::ServerError)
             }
 
             RegisterInputError::InputError(_, _) => {
-                PipelineErrorInfo::new(INPUT_ERROR, ErrorType::ServerError)
+                PipelineErrorInfo::new(ErrorCode::InputStreamInputError, ErrorType::ServerError)
+            }
+
+            RegisterInputError::FallbackError(_, err) => err.into(),
+
+            RegisterInputError::LimitExceeded(_, _) => {
+                PipelineErrorInfo::new(ErrorCode::InputLimitExceeded, ErrorType::UserError)
             }
         }
     }
 }
 
-const OUTPUT_STREAM_ALREADY_REGISTERED: &str = "OUTPUT_STREAM_ALREADY_REGISTERED";
-const ENCODER_ERROR: &str = "OUTPUT_STREAM_ENCODER_ERROR";
-const OUTPUT_ERROR: &str = "OUTPUT_STREAM_OUTPUT_ERROR";
-const UNSUPPORTED_RESOLUTION: &str = "UNSUPPORTED_RESOLUTION";
-
 impl From<&RegisterOutputError> for PipelineErrorInfo {
     fn from(err: &RegisterOutputError) -> Self {
         match err {
-            RegisterOutputError::AlreadyRegistered(_) => {
-                PipelineErrorInfo::new(OUTPUT_STREAM_ALREADY_REGISTERED, ErrorType::UserError)
-            }
+            RegisterOutputError::AlreadyRegistered(_) => PipelineErrorInfo::new(
+                ErrorCode::OutputStreamAlreadyRegistered,
+                ErrorType::UserError,
+            ),
 
-            RegisterOutputError::EncoderError(_, _) => {
-                PipelineErrorInfo::new(ENCODER_ERROR, ErrorType::ServerError)
-            }
+            RegisterOutputError::EncoderError(_, err) => err.into(),
 
             RegisterOutputError::OutputError(_, _) => {
-                PipelineErrorInfo::new(OUTPUT_ERROR, ErrorType::ServerError)
+                PipelineErrorInfo::new(ErrorCode::OutputStreamOutputError, ErrorType::ServerError)
             }
 
             RegisterOutputError::UnsupportedResolution(_) => {
-                PipelineErrorInfo::new(UNSUPPORTED_RESOLUTION, ErrorType::UserError)
+                PipelineErrorInfo::new(ErrorCode::UnsupportedResolution, ErrorType::UserError)
+            }
+
+            RegisterOutputError::LimitExceeded(_, _) => {
+                PipelineErrorInfo::new(ErrorCode::OutputLimitExceeded, ErrorType::UserError)
             }
         }
     }
 }
 
-const INPUT_STREAM_STILL_IN_USE: &str = "INPUT_STREAM_STILL_IN_USE";
-const INPUT_STREAM_NOT_FOUND: &str = "INPUT_STREAM_NOT_FOUND";
+impl From<&UpdateOutputResolutionError> for PipelineErrorInfo {
+    fn from(err: &UpdateOutputResolutionError) -> Self {
+        match err {
+            UpdateOutputResolutionError::NotFound(_) => {
+                PipelineErrorInfo::new(ErrorCode::OutputStreamNotFound, ErrorType::EntityNotFound)
+            }
+
+            UpdateOutputResolutionError::UnsupportedResolution(_) => {
+                PipelineErrorInfo::new(ErrorCode::UnsupportedResolution, ErrorType::UserError)
+            }
+
+            UpdateOutputResolutionError::UnsupportedOutput(_) => {
+                PipelineErrorInfo::new(ErrorCode::UnsupportedResolutionChange, ErrorType::UserError)
+            }
+
+            UpdateOutputResolutionError::EncoderError(_, err) => err.into(),
+
+            UpdateOutputResolutionError::UpdateSceneError(_, err) => err.into(),
+        }
+    }
+}
+
+impl From<&UpdateEncoderSettingsError> for PipelineErrorInfo {
+    fn from(err: &UpdateEncoderSettingsError) -> Self {
+        match err {
+            UpdateEncoderSettingsError::NotFound(_) => {
+                PipelineErrorInfo::new(ErrorCode::OutputStreamNotFound, ErrorType::EntityNotFound)
+            }
+
+            UpdateEncoderSettingsError::UnsupportedOutput(_)
+            | UpdateEncoderSettingsError::CodecChange(_) => PipelineErrorInfo::new(
+                ErrorCode::UnsupportedEncoderSettingsChange,
+                ErrorType::UserError,
+            ),
+
+            UpdateEncoderSettingsError::EncoderError(_, err) => err.into(),
+        }
+    }
+}
+
+impl From<&EncoderInitError> for PipelineErrorInfo {
+    fn from(err: &EncoderInitError) -> Self {
+        match err {
+            EncoderInitError::HardwareEncoderNotAvailable(_, _) => {
+                PipelineErrorInfo::new(ErrorCode::HardwareEncoderNotAvailable, ErrorType::UserError)
+            }
+            EncoderInitError::NoCodec
+            | EncoderInitError::EncoderThreadStopped
+            | EncoderInitError::CodecChange(_)
+            | EncoderInitError::FfmpegError(_) => {
+                PipelineErrorInfo::new(ErrorCode::OutputStreamEncoderError, ErrorType::ServerError)
+            }
+        }
+    }
+}
 
 impl From<&UnregisterInputError> for PipelineErrorInfo {
     fn from(err: &UnregisterInputError) -> Self {
         match err {
             UnregisterInputError::NotFound(_) => {
-                PipelineErrorInfo::new(INPUT_STREAM_NOT_FOUND, ErrorType::EntityNotFound)
+                PipelineErrorInfo::new(ErrorCode::InputStreamNotFound, ErrorType::EntityNotFound)
             }
             UnregisterInputError::StillInUse(_) => {
-                PipelineErrorInfo::new(INPUT_STREAM_STILL_IN_USE, ErrorType::UserError)
+                PipelineErrorInfo::new(ErrorCode::InputStreamStillInUse, ErrorType::UserError)
             }
         }
     }
 }
 
-const OUTPUT_STREAM_STILL_IN_USE: &str = "OUTPUT_STREAM_STILL_IN_USE";
-const OUTPUT_STREAM_NOT_FOUND: &str = "OUTPUT_STREAM_NOT_FOUND";
-
 impl From<&UnregisterOutputError> for PipelineErrorInfo {
     fn from(err: &UnregisterOutputError) -> Self {
         match err {
             UnregisterOutputError::NotFound(_) => {
-                PipelineErrorInfo::new(OUTPUT_STREAM_NOT_FOUND, ErrorType::EntityNotFound)
+                PipelineErrorInfo::new(ErrorCode::OutputStreamNotFound, ErrorType::EntityNotFound)
             }
             UnregisterOutputError::StillInUse(_) => {
-                PipelineErrorInfo::new(OUTPUT_STREAM_STILL_IN_USE, ErrorType::UserError)
+                PipelineErrorInfo::new(ErrorCode::OutputStreamStillInUse, ErrorType::UserError)
+            }
+        }
+    }
+}
+
+impl From<&SnapshotError> for PipelineErrorInfo {
+    fn from(err: &SnapshotError) -> Self {
+        match err {
+            SnapshotError::NotFound(_) => {
+                PipelineErrorInfo::new(ErrorCode::OutputStreamNotFound, ErrorType::EntityNotFound)
+            }
+            SnapshotError::NoScene(_) => {
+                PipelineErrorInfo::new(ErrorCode::OutputSceneNotDefined, ErrorType::UserError)
             }
+            SnapshotError::RenderError(_, RenderSceneError::WgpuError(err)) => err.into(),
         }
     }
 }
 
-const BUILD_SCENE_ERROR: &str = "BUILD_SCENE_ERROR";
+impl From<&SaveClipError> for PipelineErrorInfo {
+    fn from(err: &SaveClipError) -> Self {
+        match err {
+            SaveClipError::NotFound(_) => {
+                PipelineErrorInfo::new(ErrorCode::OutputStreamNotFound, ErrorType::EntityNotFound)
+            }
+            SaveClipError::NotEnabled(_) => {
+                PipelineErrorInfo::new(ErrorCode::ClipBufferNotEnabled, ErrorType::UserError)
+            }
+            SaveClipError::Empty(_) => {
+                PipelineErrorInfo::new(ErrorCode::ClipNotAvailable, ErrorType::UserError)
+            }
+            SaveClipError::OutputError(_, _) => {
+                PipelineErrorInfo::new(ErrorCode::ClipWriteError, ErrorType::ServerError)
+            }
+        }
+    }
+}
+
+impl From<&RequestKeyframeError> for PipelineErrorInfo {
+    fn from(err: &RequestKeyframeError) -> Self {
+        match err {
+            RequestKeyframeError::NotFound(_) => {
+                PipelineErrorInfo::new(ErrorCode::OutputStreamNotFound, ErrorType::EntityNotFound)
+            }
+        }
+    }
+}
+
+impl From<&SetOutputEnabledError> for PipelineErrorInfo {
+    fn from(err: &SetOutputEnabledError) -> Self {
+        match err {
+            SetOutputEnabledError::NotFound(_) => {
+                PipelineErrorInfo::new(ErrorCode::OutputStreamNotFound, ErrorType::EntityNotFound)
+            }
+        }
+    }
+}
+
+impl From<&SetInputEnabledError> for PipelineErrorInfo {
+    fn from(err: &SetInputEnabledError) -> Self {
+        match err {
+            SetInputEnabledError::NotFound(_) => {
+                PipelineErrorInfo::new(ErrorCode::InputStreamNotFound, ErrorType::EntityNotFound)
+            }
+            SetInputEnabledError::FallbackError(_, err) => err.into(),
+        }
+    }
+}
 
 impl From<&UpdateSceneError> for PipelineErrorInfo {
     fn from(err: &UpdateSceneError) -> Self {
         match err {
             UpdateSceneError::WgpuError(err) => err.into(),
             UpdateSceneError::OutputNotRegistered(_) => {
-                PipelineErrorInfo::new(OUTPUT_STREAM_NOT_FOUND, ErrorType::UserError)
+                PipelineErrorInfo::new(ErrorCode::OutputStreamNotFound, ErrorType::UserError)
             }
-            UpdateSceneError::SceneError(_) => PipelineErrorInfo {
-                error_code: BUILD_SCENE_ERROR,
-                error_type: ErrorType::UserError,
-            },
+            UpdateSceneError::NoOutputsRegistered => {
+                PipelineErrorInfo::new(ErrorCode::NoOutputsRegistered, ErrorType::UserError)
+            }
+            UpdateSceneError::SceneError(err) => err.into(),
         }
     }
 }
 
-const WGPU_INIT_ERROR: &str = "WGPU_INIT_ERROR";
-const WEB_RENDERER_INIT_ERROR: &str = "WEB_RENDERER_INIT_ERROR";
-const LAYOUT_INIT_ERROR: &str = "LAYOUT_INIT_ERROR";
-
 impl From<&InitRendererEngineError> for PipelineErrorInfo {
     fn from(err: &InitRendererEngineError) -> Self {
         match err {
             InitRendererEngineError::FailedToInitWgpuCtx(_) => {
-                PipelineErrorInfo::new(WGPU_INIT_ERROR, ErrorType::ServerError)
+                PipelineErrorInfo::new(ErrorCode::WgpuInitError, ErrorType::ServerError)
             }
             InitRendererEngineError::FailedToInitChromiumCtx(_) => {
-                PipelineErrorInfo::new(WEB_RENDERER_INIT_ERROR, ErrorType::ServerError)
+                PipelineErrorInfo::new(ErrorCode::WebRendererInitError, ErrorType::ServerError)
             }
             InitRendererEngineError::LayoutTransformationsInitError(_) => {
-                PipelineErrorInfo::new(LAYOUT_INIT_ERROR, ErrorType::ServerError)
+                PipelineErrorInfo::new(ErrorCode::LayoutInitError, ErrorType::ServerError)
+            }
+            InitRendererEngineError::ChromaKeyTransformationInitError(_) => {
+                PipelineErrorInfo::new(ErrorCode::ChromaKeyInitError, ErrorType::ServerError)
+            }
+            InitRendererEngineError::WatermarkTransformationInitError(_) => {
+                PipelineErrorInfo::new(ErrorCode::WatermarkInitError, ErrorType::ServerError)
             }
         }
     }
 }
 
-const ENTITY_ALREADY_REGISTERED: &str = "ENTITY_ALREADY_REGISTERED";
-const INVALID_SHADER: &str = "INVALID_SHADER";
-const REGISTER_IMAGE_ERROR: &str = "REGISTER_IMAGE_ERROR";
-const REGISTER_WEB_RENDERER_ERROR: &str = "REGISTER_WEB_RENDERER_ERROR";
-
 impl From<&RegisterRendererError> for PipelineErrorInfo {
     fn from(err: &RegisterRendererError) -> Self {
         match err {
             RegisterRendererError::RendererRegistry(err) => match err {
                 RegisterError::KeyTaken { .. } => {
-                    PipelineErrorInfo::new(ENTITY_ALREADY_REGISTERED, ErrorType::UserError)
+                    PipelineErrorInfo::new(ErrorCode::EntityAlreadyRegistered, ErrorType::UserError)
                 }
             },
             RegisterRendererError::Shader(_, _) => {
-                PipelineErrorInfo::new(INVALID_SHADER, ErrorType::UserError)
+                PipelineErrorInfo::new(ErrorCode::InvalidShader, ErrorType::UserError)
             }
             RegisterRendererError::Image(_, _) => {
-                PipelineErrorInfo::new(REGISTER_IMAGE_ERROR, ErrorType::UserError)
+                PipelineErrorInfo::new(ErrorCode::RegisterImageError, ErrorType::UserError)
             }
             RegisterRendererError::Web(_, _) => {
-                PipelineErrorInfo::new(REGISTER_WEB_RENDERER_ERROR, ErrorType::ServerError)
+                PipelineErrorInfo::new(ErrorCode::RegisterWebRendererError, ErrorType::ServerError)
             }
         }
     }
 }
 
-const ENTITY_NOT_FOUND: &str = "ENTITY_NOT_FOUND";
-
 impl From<&UnregisterRendererError> for PipelineErrorInfo {
     fn from(err: &UnregisterRendererError) -> Self {
         match err {
             UnregisterRendererError::RendererRegistry(_) => {
-                PipelineErrorInfo::new(ENTITY_NOT_FOUND, ErrorType::EntityNotFound)
+                PipelineErrorInfo::new(ErrorCode::EntityNotFound, ErrorType::EntityNotFound)
             }
         }
     }
 }
 
-const WGPU_VALIDATION_ERROR: &str = "WGPU_VALIDATION_ERROR";
-const WGPU_OUT_OF_MEMORY_ERROR: &str = "WGPU_OUT_OF_MEMORY_ERROR";
-
 impl From<&WgpuError> for PipelineErrorInfo {
     fn from(err: &WgpuError) -> Self {
         match err {
             WgpuError::Validation(_) => {
-                PipelineErrorInfo::new(WGPU_VALIDATION_ERROR, ErrorType::UserError)
+                PipelineErrorInfo::new(ErrorCode::WgpuValidationError, ErrorType::UserError)
             }
             WgpuError::OutOfMemory(_) => {
-                PipelineErrorInfo::new(WGPU_OUT_OF_MEMORY_ERROR, ErrorType::ServerError)
+                PipelineErrorInfo::new(ErrorCode::WgpuOutOfMemoryError, ErrorType::ServerError)
+            }
+        }
+    }
+}
+
+impl From<&SceneError> for PipelineErrorInfo {
+    fn from(err: &SceneError) -> Self {
+        match err {
+            SceneError::RendererNotFound { .. } => {
+                PipelineErrorInfo::new(ErrorCode::RendererNotFound, ErrorType::UserError)
+            }
+            SceneError::ShaderNodeParametersValidationError(_, _) => {
+                PipelineErrorInfo::new(ErrorCode::InvalidShaderParameters, ErrorType::UserError)
+            }
+            SceneError::DuplicateComponentId(_) => {
+                PipelineErrorInfo::new(ErrorCode::DuplicateComponentId, ErrorType::UserError)
+            }
+            SceneError::UnknownDimensionsForLayoutNodeRoot { .. } => {
+                PipelineErrorInfo::new(ErrorCode::BuildSceneError, ErrorType::UserError)
             }
         }
     }
@@ -0,0 +1,38 @@
+use std::sync::{Mutex, OnceLock};
+
+use compositor_render::{InputId, OutputId};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+/// Asynchronous notifications about the state of the pipeline.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// No packets were received on the input for the stream fallback timeout.
+    InputDisconnected(InputId),
+    /// Input was unregistered, because it didn't produce frames for the inactive input
+    /// timeout.
+    InputUnregistered(InputId),
+    EncoderError {
+        output_id: OutputId,
+        message: String,
+    },
+}
+
+fn subscribers() -> &'static Mutex<Vec<Sender<Event>>> {
+    static SUBSCRIBERS: OnceLock<Mutex<Vec<Sender<Event>>>> = OnceLock::new();
+    SUBSCRIBERS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Returns a receiver of all events emitted after this call. Subscription ends when
+/// the receiver is dropped.
+pub fn subscribe() -> Receiver<Event> {
+    let (sender, receiver) = unbounded();
+    subscribers().lock().unwrap().push(sender);
+    receiver
+}
+
+pub(crate) fn emit(event: Event) {
+    subscribers()
+        .lock()
+        .unwrap()
+        .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+}
@@ -1,4 +1,5 @@
 pub mod error;
+pub mod event;
 pub mod pipeline;
 pub mod queue;
 
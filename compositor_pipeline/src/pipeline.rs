@@ -1,9 +1,13 @@
-use std::collections::{hash_map, HashMap};
+use std::collections::hash_map::{self, DefaultHasher};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::sync::{Mutex, MutexGuard};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use compositor_render::error::{
     ErrorStack, InitRendererEngineError, RegisterRendererError, UnregisterRendererError,
@@ -11,26 +15,62 @@ use compositor_render::error::{
 use compositor_render::scene::Component;
 use compositor_render::web_renderer::WebRendererInitOptions;
 use compositor_render::RegistryType;
-use compositor_render::RendererOptions;
 use compositor_render::{error::UpdateSceneError, Renderer};
-use compositor_render::{scene, EventLoop, Framerate, InputId, OutputId, RendererId, RendererSpec};
+use compositor_render::{
+    scene, EventLoop, Frame, Framerate, InputId, OutputId, RendererId, RendererSpec, Resolution,
+};
+use compositor_render::{GpuAdapter, InputFallback, RendererInputOptions, RendererOptions};
 use crossbeam_channel::unbounded;
 use log::{error, warn};
 
 use crate::error::{
-    RegisterInputError, RegisterOutputError, UnregisterInputError, UnregisterOutputError,
+    DecoderInitError, RegisterInputError, RegisterOutputError, RequestKeyframeError, SaveClipError,
+    SetInputEnabledError, SetOutputEnabledError, SnapshotError, UnregisterInputError,
+    UnregisterOutputError, UpdateEncoderSettingsError, UpdateOutputResolutionError,
+};
+use crate::event::{self, Event};
+use crate::queue::{
+    BackpressureOptions, FrameGenerator, PtsNormalization, Queue, QueueSyncStrategy,
 };
-use crate::queue::Queue;
 
+use self::adaptive_bitrate::{AdaptiveBitrate, AdaptiveBitrateOptions};
+use self::audio_mixer::{AudioMixOptions, AudioMixer};
+use self::chunk_router::ChunkRouter;
+use self::clip_buffer::ClipBuffer;
+use self::decoder::{hardware::HardwareDecoder, VideoDecoderSettings};
+use self::encoder::ffmpeg_aac::LibavAacEncoder;
 use self::encoder::{Encoder, EncoderOptions};
-use self::output::{Output, OutputOptions};
+use self::frame_cadence::FrameCadence;
+use self::input::buffer::BufferedChunks;
+use self::output::mp4::{Mp4FileWriter, Mp4WriterOptions};
+use self::output::{AudioTrack, KeyframeRequester, Output, OutputOptions};
+use self::output_workers::{OutputWorkers, RenderedFrame};
+use self::scene_schedule::{SceneSchedule, ScheduledSceneUpdate};
+use self::stats::{InputStats, OutputStats, RenderStats};
+use self::structs::{AudioCodec, EncodedChunkKind};
 
+pub mod adaptive_bitrate;
+pub mod audio_mixer;
+mod chunk_router;
+mod clip_buffer;
 pub mod decoder;
 pub mod encoder;
+mod frame_cadence;
 pub mod input;
 pub mod output;
+mod output_workers;
+mod scene_schedule;
+pub mod stats;
 pub mod structs;
 
+/// Keyframes requested through the API more often are not encoded, so clients can't
+/// degrade the quality of the stream by requesting a keyframe for every frame.
+const MIN_KEYFRAME_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long unregistering a file output waits for encoders to be flushed and the file
+/// to be finalized.
+const OUTPUT_FINALIZE_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Clone)]
 pub struct OutputScene {
     pub output_id: OutputId,
@@ -39,12 +79,103 @@ pub struct OutputScene {
 
 pub struct PipelineInput {
     pub input: input::Input,
-    pub decoder: decoder::Decoder,
+    /// `None` for inputs that pass frames directly to the queue.
+    pub decoder: Option<decoder::Decoder>,
+    pub stats: Arc<InputStats>,
+}
+
+pub struct RegisterOutputOptions {
+    pub encoder: EncoderOptions,
+    pub output: OutputOptions,
+    /// Framerate of the encoded stream. If not specified, every rendered frame is encoded.
+    pub framerate: Option<Framerate>,
+    /// Inputs mixed into the audio track of the output.
+    pub audio_mix: Option<AudioMixOptions>,
+    /// Duration of the recently encoded video kept for [`Pipeline::save_clip`].
+    pub clip_buffer: Option<Duration>,
+    /// Lower the bitrate when rendering can't keep up. Only encoders configured with
+    /// a bitrate of RTP and WHIP outputs are adapted, other encoders can't be reopened.
+    pub adaptive_bitrate: Option<AdaptiveBitrateOptions>,
+    /// Don't encode anything until a frame from any input is rendered, instead of
+    /// starting with frames without input content.
+    pub start_on_first_frame: bool,
+    /// Image drawn over every scene of the output.
+    pub watermark: Option<scene::Watermark>,
 }
 
 pub struct PipelineOutput {
     pub encoder: encoder::Encoder,
+    /// Framerate of the encoded stream. If not specified, every rendered frame is encoded.
+    pub framerate: Option<Framerate>,
+    /// Inputs mixed into the audio track of the output.
+    pub audio_mix: Option<AudioMixOptions>,
+    pub watermark: Option<scene::Watermark>,
+    /// Has to be dropped before the output, so the output receives the end of the audio track.
+    audio_encoder: Option<LibavAacEncoder>,
     pub output: output::Output,
+    pub stats: Arc<OutputStats>,
+    /// Recently encoded video, saved by [`Pipeline::save_clip`].
+    clip_buffer: Option<Arc<ClipBuffer>>,
+    /// Most recent frame rendered for the output, returned by snapshots.
+    last_frame: Mutex<Option<Frame>>,
+    cadence: Option<Mutex<FrameCadence>>,
+    /// When a keyframe was last requested through [`Pipeline::request_keyframe`].
+    last_keyframe_request: Mutex<Option<Instant>>,
+    /// Changed by [`Pipeline::set_output_enabled`].
+    is_enabled: AtomicBool,
+    adaptive_bitrate: Option<Mutex<AdaptiveBitrate>>,
+    /// Cleared by the render thread on the first rendered batch with input frames.
+    is_waiting_for_first_frame: AtomicBool,
+    /// Resolution the scene is rendered in, if the resolution of the encoder was padded
+    /// to even dimensions. Changed by [`Pipeline::update_output_resolution`].
+    scene_resolution: Mutex<Option<Resolution>>,
+}
+
+impl PipelineOutput {
+    /// Disabled outputs stay registered, but nothing is encoded or sent for them.
+    pub fn is_enabled(&self) -> bool {
+        self.is_enabled.load(Ordering::Relaxed)
+    }
+
+    /// Outputs registered with `start_on_first_frame` don't receive audio or video
+    /// until a batch with frames from any input is rendered.
+    fn is_waiting_for_first_frame(&self) -> bool {
+        self.is_waiting_for_first_frame.load(Ordering::Relaxed)
+    }
+
+    /// Requests for the same encoder are ignored for [`MIN_KEYFRAME_REQUEST_INTERVAL`]
+    /// after the previous one.
+    fn request_keyframe(&self) {
+        let mut last_request = self.last_keyframe_request.lock().unwrap();
+        let now = Instant::now();
+        if last_request.is_some_and(|last| now - last < MIN_KEYFRAME_REQUEST_INTERVAL) {
+            return;
+        }
+        *last_request = Some(now);
+        self.encoder.request_keyframe();
+    }
+
+    /// MP4 and HLS outputs write to files, that are finalized when the output is stopped.
+    fn is_file(&self) -> bool {
+        matches!(self.output, output::Output::Mp4(_) | output::Output::Hls(_))
+    }
+
+    /// Flushes encoders of a file output and waits until its file is finalized. Clones of
+    /// the output held by the render thread don't delay it, because encoders are stopped
+    /// explicitly instead of being dropped. Other outputs are closed when they are dropped,
+    /// because their encoders can be shared with other outputs.
+    ///
+    /// Returns `false` if the file is not finalized before the timeout.
+    fn finalize_file(&self) -> bool {
+        if !self.is_file() {
+            return true;
+        }
+        self.encoder.stop();
+        if let Some(ref audio_encoder) = self.audio_encoder {
+            audio_encoder.stop();
+        }
+        self.output.wait_for_finalize(OUTPUT_FINALIZE_TIMEOUT)
+    }
 }
 
 pub struct Pipeline {
@@ -52,14 +183,58 @@ pub struct Pipeline {
     outputs: OutputRegistry<PipelineOutput>,
     queue: Arc<Queue>,
     renderer: Renderer,
-    is_started: bool,
+    /// Shared, so it can be read without access to the pipeline, see
+    /// [`Pipeline::is_started_flag`].
+    is_started: Arc<AtomicBool>,
+    render_thread: Option<thread::JoinHandle<()>>,
+    render_stats: Arc<RenderStats>,
+    max_render_queue: usize,
+    output_delivery_threads: usize,
+    chunk_router: ChunkRouter,
+    /// Current scene of each output, it's rebuilt after the output resolution changes.
+    /// Shared with the render thread, which applies scheduled updates.
+    scene: Arc<Mutex<Vec<OutputScene>>>,
+    scene_schedule: SceneSchedule,
+    decoder_settings: VideoDecoderSettings,
+    pad_odd_resolutions: bool,
+    max_inputs: Option<usize>,
+    max_outputs: Option<usize>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Options {
     pub framerate: Framerate,
     pub stream_fallback_timeout: Duration,
     pub web_renderer: WebRendererInitOptions,
+    /// Maximal number of frame sets waiting to be rendered. Oldest ones are dropped
+    /// when rendering can't keep up, unless `backpressure` is enabled.
+    pub max_render_queue: usize,
+    /// Pause the queue and file inputs instead of dropping frames when rendering
+    /// can't keep up.
+    pub backpressure: Option<BackpressureOptions>,
+    /// Whether the queue waits for late inputs or produces frame sets on time.
+    pub queue_sync_strategy: QueueSyncStrategy,
+    /// Frame sets are rendered this long after their timestamps, trading latency for
+    /// smoother output of jittery inputs.
+    pub output_delay: Duration,
+    /// Number of threads that pass rendered frames to outputs. With a single thread,
+    /// it's done on the render thread.
+    pub output_delivery_threads: usize,
+    /// Used to decode H264 inputs. If it can't be initialized, inputs are decoded
+    /// in software.
+    pub hardware_decoder: Option<HardwareDecoder>,
+    /// Maximal number of decoded frames held to pass them to the queue in presentation
+    /// order, for streams that reorder frames.
+    pub decoder_max_reorder_frames: usize,
+    /// GPU used for rendering on hosts with multiple GPUs.
+    pub gpu_adapter: Option<GpuAdapter>,
+    /// Outputs with odd resolutions are encoded with the dimensions rounded up to even
+    /// numbers, with the padding filled with black, instead of being rejected.
+    pub pad_odd_resolutions: bool,
+    /// Registration of more inputs fails. Not limited if `None`.
+    pub max_inputs: Option<usize>,
+    /// Registration of more outputs fails. Not limited if `None`.
+    pub max_outputs: Option<usize>,
 }
 
 impl Pipeline {
@@ -68,13 +243,33 @@ impl Pipeline {
             web_renderer: opts.web_renderer,
             framerate: opts.framerate,
             stream_fallback_timeout: opts.stream_fallback_timeout,
+            gpu_adapter: opts.gpu_adapter,
         })?;
         let pipeline = Pipeline {
             outputs: OutputRegistry::new(),
             inputs: HashMap::new(),
-            queue: Arc::new(Queue::new(opts.framerate)),
+            queue: Arc::new(Queue::new(
+                opts.framerate,
+                opts.backpressure,
+                opts.queue_sync_strategy,
+                opts.output_delay,
+            )),
             renderer,
-            is_started: false,
+            is_started: Arc::new(AtomicBool::new(false)),
+            render_thread: None,
+            render_stats: Arc::new(RenderStats::default()),
+            max_render_queue: opts.max_render_queue,
+            output_delivery_threads: opts.output_delivery_threads,
+            chunk_router: ChunkRouter::default(),
+            scene: Arc::new(Mutex::new(Vec::new())),
+            scene_schedule: SceneSchedule::default(),
+            decoder_settings: VideoDecoderSettings {
+                hardware_decoder: opts.hardware_decoder,
+                max_reorder_frames: opts.decoder_max_reorder_frames,
+            },
+            pad_odd_resolutions: opts.pad_odd_resolutions,
+            max_inputs: opts.max_inputs,
+            max_outputs: opts.max_outputs,
         };
 
         Ok((pipeline, event_loop))
@@ -84,32 +279,99 @@ impl Pipeline {
         &self.renderer
     }
 
-    pub fn queue(&self) -> &Queue {
+    pub fn queue(&self) -> &Arc<Queue> {
         &self.queue
     }
 
+    pub fn render_stats(&self) -> &RenderStats {
+        &self.render_stats
+    }
+
+    /// `decoder_opts` are required for all inputs except static images, which are
+    /// not decoded. `pts_normalization` is ignored for inputs that are not decoded.
     pub fn register_input(
         &mut self,
         input_id: InputId,
         input_opts: input::InputOptions,
-        decoder_opts: decoder::DecoderOptions,
+        decoder_opts: Option<decoder::DecoderOptions>,
+        fallback: Option<InputFallback>,
+        pts_normalization: PtsNormalization,
     ) -> Result<(), RegisterInputError> {
         if self.inputs.contains_key(&input_id) {
             return Err(RegisterInputError::AlreadyRegistered(input_id));
         }
+        if let Some(max_inputs) = self.max_inputs {
+            if self.inputs.len() >= max_inputs {
+                return Err(RegisterInputError::LimitExceeded(input_id, max_inputs));
+            }
+        }
 
-        let (input, chunks) = input::Input::new(input_opts)
+        let stream_fallback_timeout = input_opts.stream_fallback_timeout();
+        let audio_codec = input_opts.audio_codec();
+        let buffer_size = input_opts.buffer_size();
+        let frame_transform = input_opts.frame_transform();
+        let (input, stream) = input::Input::new(input_opts, self.queue.backpressure())
             .map_err(|e| RegisterInputError::InputError(input_id.clone(), e))?;
 
-        let decoder =
-            decoder::Decoder::new(decoder_opts, chunks, self.queue.clone(), input_id.clone())
-                .map_err(|e| RegisterInputError::DecoderError(input_id.clone(), e))?;
+        let stats = Arc::new(InputStats::default());
+        let (decoder, generator) = match stream {
+            input::InputStream::Chunks(chunks) => {
+                let chunks = match buffer_size {
+                    Some(size) => {
+                        Box::new(BufferedChunks::new(chunks, size, &input_id, stats.clone()))
+                    }
+                    None => chunks,
+                };
+                let decoder = decoder_opts
+                    .ok_or(DecoderInitError::MissingDecoderOptions)
+                    .and_then(|decoder_opts| {
+                        decoder::Decoder::new(
+                            decoder_opts,
+                            audio_codec,
+                            chunks,
+                            self.queue.clone(),
+                            input_id.clone(),
+                            stats.clone(),
+                            self.decoder_settings,
+                        )
+                    })
+                    .map_err(|e| RegisterInputError::DecoderError(input_id.clone(), e))?;
+                (Some(decoder), None)
+            }
+            input::InputStream::StaticFrame(frame) => {
+                let generator: FrameGenerator = Box::new(move |pts| Frame {
+                    pts,
+                    ..frame.clone()
+                });
+                (None, Some(generator))
+            }
+            input::InputStream::Generated(generator) => (None, Some(generator)),
+        };
 
-        let pipeline_input = PipelineInput { input, decoder };
+        // Input and decoder are stopped when they are dropped, if the fallback is invalid.
+        self.renderer
+            .register_input(
+                input_id.clone(),
+                RendererInputOptions {
+                    stream_fallback_timeout,
+                    fallback,
+                },
+            )
+            .map_err(|e| RegisterInputError::FallbackError(input_id.clone(), e))?;
 
-        self.inputs.insert(input_id.clone(), pipeline_input.into());
+        let pipeline_input = PipelineInput {
+            input,
+            decoder,
+            stats,
+        };
 
-        self.queue.add_input(input_id);
+        self.inputs.insert(input_id.clone(), pipeline_input.into());
+        match generator {
+            Some(generator) => self.queue.add_generated_input(input_id, generator),
+            None => self
+                .queue
+                .add_input(input_id, frame_transform, pts_normalization),
+        }
         Ok(())
     }
 
@@ -120,42 +382,238 @@ impl Pipeline {
 
         self.inputs.remove(input_id);
         self.queue.remove_input(input_id);
+        self.renderer.unregister_input(input_id);
         Ok(())
     }
 
+    /// Unregisters inputs that didn't produce frames for longer than `timeout`, e.g. streams
+    /// that never reconnected, and emits [`Event::InputUnregistered`] for each of them.
+    pub fn unregister_inactive_inputs(&mut self, timeout: Duration) {
+        for input_id in self.queue.inactive_inputs(timeout) {
+            if self.unregister_input(&input_id).is_err() {
+                continue;
+            }
+            warn!(
+                "Input {input_id} did not produce frames for {}ms, unregistering it.",
+                timeout.as_millis()
+            );
+            event::emit(Event::InputUnregistered(input_id));
+        }
+    }
+
     pub fn register_output(
         &self,
         output_id: OutputId,
-        encoder_opts: EncoderOptions,
-        output_opts: OutputOptions,
+        options: RegisterOutputOptions,
     ) -> Result<(), RegisterOutputError> {
+        let RegisterOutputOptions {
+            encoder: encoder_opts,
+            output: output_opts,
+            framerate,
+            audio_mix,
+            clip_buffer,
+            adaptive_bitrate,
+            start_on_first_frame,
+            watermark,
+        } = options;
+
         if self.outputs.contains_key(&output_id) {
             return Err(RegisterOutputError::AlreadyRegistered(output_id));
         }
+        if let Some(max_outputs) = self.max_outputs {
+            if self.outputs.lock().len() >= max_outputs {
+                return Err(RegisterOutputError::LimitExceeded(output_id, max_outputs));
+            }
+        }
 
-        let EncoderOptions::H264(ref opts) = encoder_opts;
-        if opts.resolution.width % 2 != 0 || opts.resolution.height % 2 != 0 {
+        let scene_resolution = encoder_opts.resolution();
+        let Some(resolution) = self.encoded_resolution(scene_resolution) else {
             return Err(RegisterOutputError::UnsupportedResolution(output_id));
-        }
+        };
 
-        let (encoder, packets) = Encoder::new(encoder_opts)
+        let (encoder, packets) = Encoder::new(encoder_opts.with_resolution(resolution))
             .map_err(|e| RegisterOutputError::EncoderError(output_id.clone(), e))?;
+        let audio_encoder = match audio_mix {
+            Some(_) => Some(
+                LibavAacEncoder::new(output_id.clone())
+                    .map_err(|e| RegisterOutputError::EncoderError(output_id.clone(), e))?,
+            ),
+            None => None,
+        };
 
-        let output = Output::new(output_opts, packets)
-            .map_err(|e| RegisterOutputError::OutputError(output_id.clone(), e))?;
+        let packets = self.chunk_router.add_output(output_id.clone(), packets);
+        let (audio_encoder, audio_track) = match audio_encoder {
+            Some((audio_encoder, audio_chunks)) => {
+                self.chunk_router.add_audio(output_id.clone(), audio_chunks);
+                let audio_track = AudioTrack {
+                    codec: AudioCodec::Aac,
+                    extradata: audio_encoder.extradata(),
+                };
+                (Some(audio_encoder), Some(audio_track))
+            }
+            None => (None, None),
+        };
+
+        let stats = Arc::new(OutputStats::default());
+        let stats_clone = stats.clone();
+        let clip_buffer = clip_buffer.map(|duration| Arc::new(ClipBuffer::new(duration)));
+        let clip_buffer_clone = clip_buffer.clone();
+        let packets = Box::new(packets.into_iter().inspect(move |chunk| {
+            if let EncodedChunkKind::Video(_) = chunk.kind {
+                stats_clone.on_frame_encoded(chunk.pts)
+            }
+            if let Some(ref clip_buffer) = clip_buffer_clone {
+                clip_buffer.push(chunk);
+            }
+        }));
 
-        let output = PipelineOutput { encoder, output };
+        let request_keyframe = self.outputs.keyframe_requester(output_id.clone());
+        let output = Output::new(
+            output_opts,
+            packets,
+            audio_track,
+            stats.clone(),
+            request_keyframe,
+        )
+        .map_err(|e| RegisterOutputError::OutputError(output_id.clone(), e))?;
+
+        let output = PipelineOutput {
+            encoder,
+            framerate,
+            audio_mix,
+            watermark,
+            audio_encoder,
+            output,
+            stats,
+            clip_buffer,
+            last_frame: Mutex::new(None),
+            cadence: framerate.map(|framerate| Mutex::new(FrameCadence::new(framerate))),
+            last_keyframe_request: Mutex::new(None),
+            is_enabled: AtomicBool::new(true),
+            adaptive_bitrate: adaptive_bitrate
+                .map(|options| Mutex::new(AdaptiveBitrate::new(options))),
+            is_waiting_for_first_frame: AtomicBool::new(start_on_first_frame),
+            scene_resolution: Mutex::new(
+                (resolution != scene_resolution).then_some(scene_resolution),
+            ),
+        };
 
         self.outputs.insert(output_id, output.into());
+        self.update_encoder_sharing();
         Ok(())
     }
 
-    pub fn unregister_output(&self, output_id: &OutputId) -> Result<(), UnregisterOutputError> {
+    pub fn unregister_output(&mut self, output_id: &OutputId) -> Result<(), UnregisterOutputError> {
         if !self.outputs.contains_key(output_id) {
             return Err(UnregisterOutputError::NotFound(output_id.clone()));
         }
 
-        self.outputs.remove(output_id);
+        let output = self.outputs.remove(output_id);
+        // Frames already rendered for the output are not sent to its stopped encoder.
+        self.chunk_router.set_enabled(output_id, false);
+        // Scenes are updated partially, so the scene of the output has to be removed
+        // explicitly.
+        let mut scene = self.scene.lock().unwrap();
+        if scene.iter().any(|scene| &scene.output_id == output_id) {
+            scene.retain(|scene| &scene.output_id != output_id);
+            if let Err(err) = self.renderer.remove_output_scene(output_id) {
+                error!(
+                    "Failed to remove the scene of output {output_id}: {}",
+                    ErrorStack::new(&err).into_string()
+                );
+            }
+        }
+        drop(scene);
+        self.update_encoder_sharing();
+
+        if let Some(output) = output {
+            if !output.finalize_file() {
+                error!(
+                    "Output {output_id} was not finalized within {}s. The file may be incomplete.",
+                    OUTPUT_FINALIZE_TIMEOUT.as_secs()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Reopens the encoder of the output with a new resolution and rebuilds the scene,
+    /// so it's rendered in the new size.
+    pub fn update_output_resolution(
+        &mut self,
+        output_id: &OutputId,
+        resolution: Resolution,
+    ) -> Result<(), UpdateOutputResolutionError> {
+        let Some(output) = self.outputs.lock().get(output_id).cloned() else {
+            return Err(UpdateOutputResolutionError::NotFound(output_id.clone()));
+        };
+
+        let scene_resolution = resolution;
+        let Some(resolution) = self.encoded_resolution(scene_resolution) else {
+            return Err(UpdateOutputResolutionError::UnsupportedResolution(
+                output_id.clone(),
+            ));
+        };
+
+        // MP4 and FLV store the resolution in the header. HLS players expect the same
+        // resolution in every segment of a playlist.
+        if let Output::Mp4(_) | Output::Rtmp(_) | Output::Hls(_) = output.output {
+            return Err(UpdateOutputResolutionError::UnsupportedOutput(
+                output_id.clone(),
+            ));
+        }
+
+        output
+            .encoder
+            .update_resolution(resolution)
+            .map_err(|e| UpdateOutputResolutionError::EncoderError(output_id.clone(), e))?;
+        *output.scene_resolution.lock().unwrap() =
+            (resolution != scene_resolution).then_some(scene_resolution);
+
+        let scene: Vec<_> = self
+            .scene
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|scene| &scene.output_id == output_id)
+            .cloned()
+            .collect();
+        if !scene.is_empty() {
+            self.update_scene(scene, None)
+                .map_err(|e| UpdateOutputResolutionError::UpdateSceneError(output_id.clone(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Reopens the encoder of the output with new options. The output keeps sending
+    /// to the same destination, and the stream continues with a keyframe.
+    pub fn update_encoder_settings(
+        &mut self,
+        output_id: &OutputId,
+        encoder_opts: EncoderOptions,
+    ) -> Result<(), UpdateEncoderSettingsError> {
+        let Some(output) = self.outputs.lock().get(output_id).cloned() else {
+            return Err(UpdateEncoderSettingsError::NotFound(output_id.clone()));
+        };
+
+        // Codec parameters are stored in the MP4 and FLV headers. HLS segments are split
+        // on keyframes placed by the encoder.
+        if let Output::Mp4(_) | Output::Rtmp(_) | Output::Hls(_) = output.output {
+            return Err(UpdateEncoderSettingsError::UnsupportedOutput(
+                output_id.clone(),
+            ));
+        }
+        if output.encoder.options().codec() != encoder_opts.codec() {
+            return Err(UpdateEncoderSettingsError::CodecChange(output_id.clone()));
+        }
+
+        output
+            .encoder
+            .update_options(encoder_opts)
+            .map_err(|e| UpdateEncoderSettingsError::EncoderError(output_id.clone(), e))?;
+
+        // Outputs sharing the encoder switch to their own encoders if their options differ now.
+        self.update_encoder_sharing();
         Ok(())
     }
 
@@ -176,51 +634,171 @@ impl Pipeline {
             .unregister_renderer(renderer_id, registry_type)
     }
 
-    pub fn update_scene(&mut self, outputs: Vec<OutputScene>) -> Result<(), UpdateSceneError> {
-        let outputs = outputs
-            .into_iter()
-            .map(|output| {
-                let resolution = self
-                    .outputs
-                    .lock()
-                    .get(&output.output_id)
-                    .ok_or_else(|| UpdateSceneError::OutputNotRegistered(output.output_id.clone()))?
-                    .encoder
-                    .resolution();
-                Ok(scene::OutputScene {
-                    output_id: output.output_id,
-                    root: output.root,
-                    resolution,
-                })
-            })
-            .collect::<Result<Vec<_>, UpdateSceneError>>()?;
-        self.renderer.update_scene(outputs)
+    /// Returns `true` if the input is stale and its fallback is rendered instead.
+    pub fn is_fallback_active(&self, input_id: &InputId) -> bool {
+        self.renderer.is_fallback_active(input_id)
+    }
+
+    pub fn registered_renderers(&self, registry_type: RegistryType) -> Vec<RendererId> {
+        self.renderer.registered_renderers(registry_type)
+    }
+
+    /// Updates scenes of the listed outputs. Scenes of other outputs are not changed.
+    /// If `transition` is specified, the listed outputs crossfade from their previous scenes.
+    pub fn update_scene(
+        &mut self,
+        outputs: Vec<OutputScene>,
+        transition: Option<scene::SceneTransition>,
+    ) -> Result<(), UpdateSceneError> {
+        apply_scene_update(
+            &mut self.renderer,
+            &self.outputs,
+            &self.scene,
+            &self.chunk_router,
+            outputs,
+            transition,
+        )
+    }
+
+    /// Updates scenes the same way as [`Pipeline::update_scene`], but right before the
+    /// frame set with `pts` is rendered, so the first frame with the new scenes is known
+    /// in advance. Scenes are validated immediately. If `pts` was already rendered, the
+    /// update is applied immediately.
+    pub fn schedule_scene_update(
+        &mut self,
+        outputs: Vec<OutputScene>,
+        transition: Option<scene::SceneTransition>,
+        pts: Duration,
+    ) -> Result<(), UpdateSceneError> {
+        let last_pts = self.render_stats.last_pts();
+        if self.is_started() && pts <= last_pts {
+            warn!(
+                "Scene update scheduled at {}ms, but frames up to {}ms were already rendered. Applying it immediately.",
+                pts.as_millis(),
+                last_pts.as_millis()
+            );
+            return self.update_scene(outputs, transition);
+        }
+
+        self.validate_scene(outputs.clone())?;
+        self.scene_schedule.push(ScheduledSceneUpdate {
+            pts,
+            outputs,
+            transition,
+        });
+        Ok(())
+    }
+
+    /// Validates scenes of the listed outputs the same way as [`Pipeline::update_scene`],
+    /// but doesn't apply them.
+    pub fn validate_scene(&self, outputs: Vec<OutputScene>) -> Result<(), UpdateSceneError> {
+        self.renderer
+            .validate_scene(render_scenes(&self.outputs, outputs)?)
     }
 
     pub fn start(&mut self) {
-        if self.is_started {
+        if self.is_started() {
             error!("Pipeline already started.");
             return;
         }
         let (frames_sender, frames_receiver) = unbounded();
-        let renderer = self.renderer.clone();
+        let mut renderer = self.renderer.clone();
         let outputs = self.outputs.clone();
+        let scene = self.scene.clone();
+        let scene_schedule = self.scene_schedule.clone();
+        let chunk_router = self.chunk_router.clone();
+        let render_stats = self.render_stats.clone();
+        let max_render_queue = self.max_render_queue;
+        let output_workers =
+            OutputWorkers::new(self.output_delivery_threads, self.chunk_router.clone());
+        let queue = self.queue.clone();
 
         self.queue.start(frames_sender);
+        self.is_started.store(true, Ordering::Relaxed);
 
-        thread::spawn(move || {
-            for input_frames in frames_receiver.iter() {
-                if frames_receiver.len() > 20 {
-                    warn!("Dropping frame: render queue is too long.",);
-                    continue;
+        // Render thread exits after the queue is stopped and all frames already
+        // produced by the queue are rendered and passed to outputs.
+        let render_thread = thread::spawn(move || {
+            let mut dropped_frames = DroppedFramesLogger::default();
+            let mut audio_mixer = AudioMixer::default();
+            for mut frames_batch in frames_receiver.iter() {
+                let render_queue_length = frames_receiver.len();
+                render_stats.set_queue_length(render_queue_length);
+                queue
+                    .backpressure()
+                    .on_render_queue_length(render_queue_length);
+
+                // Oldest frames are dropped, so latency does not accumulate when rendering
+                // is too slow. With backpressure, the queue is paused instead.
+                while !queue.backpressure().is_enabled() && frames_receiver.len() > max_render_queue
+                {
+                    let Ok(next_frames) = frames_receiver.try_recv() else {
+                        break;
+                    };
+                    frames_batch = next_frames;
+                    render_stats.on_frame_dropped();
+                    dropped_frames.on_frame_dropped();
+                }
+
+                let ingested_at = frames_batch.ingested_at;
+                let pts = frames_batch.frames.pts;
+                if ingested_at.is_some() {
+                    for output in outputs.lock().values() {
+                        output
+                            .is_waiting_for_first_frame
+                            .store(false, Ordering::Relaxed);
+                    }
                 }
 
-                let output = renderer.render(input_frames);
+                let mut samples = queue.take_samples(pts);
+                samples.retain(|input_id, _| renderer.is_input_enabled(input_id));
+                let audio = audio_mixer.advance(pts, samples);
+                if let Some(audio) = audio {
+                    let outputs = outputs.lock();
+                    let outputs = outputs.values().filter(|output| {
+                        output.is_enabled() && !output.is_waiting_for_first_frame()
+                    });
+                    for output in outputs {
+                        if let (Some(mix), Some(encoder)) =
+                            (&output.audio_mix, &output.audio_encoder)
+                        {
+                            encoder.send_samples(audio.mix(&mix.inputs));
+                        }
+                    }
+                }
+
+                for update in scene_schedule.take_due(pts) {
+                    let result = apply_scene_update(
+                        &mut renderer,
+                        &outputs,
+                        &scene,
+                        &chunk_router,
+                        update.outputs,
+                        update.transition,
+                    );
+                    if let Err(err) = result {
+                        error!(
+                            "Failed to apply the scene update scheduled at {}ms: {}",
+                            update.pts.as_millis(),
+                            ErrorStack::new(&err).into_string()
+                        );
+                    }
+                }
+                render_stats.set_last_pts(pts);
+
+                let output = tracing::trace_span!(
+                    target: "timings",
+                    "render",
+                    pts_ms = pts.as_millis() as u64,
+                    queue_wait_ms = ingested_at.map(|at| at.elapsed().as_millis() as u64),
+                )
+                .in_scope(|| renderer.render(frames_batch.frames));
                 let Ok(output_frames) = output else {
                     error!(
                         "Error while rendering: {}",
                         ErrorStack::new(&output.unwrap_err()).into_string()
                     );
+                    render_stats.on_frame_dropped();
                     continue;
                 };
 
@@ -230,11 +808,236 @@ impl Pipeline {
                         error!("no output with id {}", &id);
                         continue;
                     };
-
-                    output.encoder.send_frame(frame);
+                    if output.is_waiting_for_first_frame() {
+                        continue;
+                    }
+                    output_workers.send(RenderedFrame {
+                        output_id: id,
+                        output,
+                        frame,
+                        ingested_at,
+                        render_queue_length,
+                    });
                 }
             }
+            output_workers.join();
+            render_stats.set_queue_length(0);
+            queue.backpressure().on_render_queue_length(0);
         });
+        self.render_thread = Some(render_thread);
+    }
+
+    /// Stops the processing pipeline. MP4 and HLS outputs are unregistered, so their files
+    /// are finalized. Like in [`Pipeline::unregister_output`], it blocks until the files are
+    /// finalized or the finalize timeout passes. Inputs, RTP, RTMP and WHIP outputs and
+    /// their scenes stay registered, so they are rendered again after the pipeline is
+    /// started again.
+    pub fn stop(&mut self) {
+        if !self.is_started() {
+            error!("Pipeline is not started.");
+            return;
+        }
+
+        self.queue.stop();
+        if let Some(render_thread) = self.render_thread.take() {
+            if render_thread.join().is_err() {
+                error!("Render thread panicked.");
+            }
+        }
+
+        self.unregister_outputs(PipelineOutput::is_file);
+        self.is_started.store(false, Ordering::Relaxed);
+    }
+
+    /// Stops the pipeline if it is started and unregisters all outputs. Pipeline should
+    /// not be used after this call.
+    pub fn shutdown(&mut self) {
+        if self.is_started() {
+            self.stop();
+        }
+        self.unregister_outputs(|_| true);
+    }
+
+    /// Unregisters matching outputs, so their scenes are removed and files are finalized
+    /// the same way as in [`Pipeline::unregister_output`].
+    fn unregister_outputs(&mut self, filter: impl Fn(&PipelineOutput) -> bool) {
+        let output_ids: Vec<_> = self
+            .outputs
+            .lock()
+            .iter()
+            .filter(|(_, output)| filter(output))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for output_id in output_ids {
+            if let Err(err) = self.unregister_output(&output_id) {
+                error!(
+                    "Failed to unregister output {output_id}: {}",
+                    ErrorStack::new(&err).into_string()
+                );
+            }
+        }
+    }
+
+    fn update_encoder_sharing(&self) {
+        update_encoder_sharing(&self.outputs, &self.scene, &self.chunk_router);
+    }
+
+    /// Writes the most recent `duration` of the output's video, kept in its clip buffer,
+    /// to an MP4 file. The clip starts with the latest keyframe before the requested
+    /// duration, so it can be slightly longer.
+    pub fn save_clip(
+        &self,
+        output_id: &OutputId,
+        path: Arc<Path>,
+        duration: Duration,
+    ) -> Result<(), SaveClipError> {
+        let Some(output) = self.outputs.lock().get(output_id).cloned() else {
+            return Err(SaveClipError::NotFound(output_id.clone()));
+        };
+        let Some(ref clip_buffer) = output.clip_buffer else {
+            return Err(SaveClipError::NotEnabled(output_id.clone()));
+        };
+        let chunks = clip_buffer.clip(duration);
+        if chunks.is_empty() {
+            return Err(SaveClipError::Empty(output_id.clone()));
+        }
+
+        let options = Mp4WriterOptions {
+            path,
+            codec: output.encoder.options().codec(),
+            resolution: output.encoder.resolution(),
+            output_id: output_id.clone(),
+        };
+        // Writer finalizes the file when it's dropped, after all chunks are written.
+        Mp4FileWriter::new(options, Box::new(chunks.into_iter()), None)
+            .map_err(|err| SaveClipError::OutputError(output_id.clone(), err))?;
+        Ok(())
+    }
+
+    /// Encodes the next frame of the output as a keyframe, so receivers that joined
+    /// mid-stream can start decoding. If the output receives chunks from an encoder of
+    /// another output, the keyframe is requested from that encoder. Requests for the same
+    /// encoder are ignored for [`MIN_KEYFRAME_REQUEST_INTERVAL`] after the previous one.
+    pub fn request_keyframe(&self, output_id: &OutputId) -> Result<(), RequestKeyframeError> {
+        let outputs = self.outputs.lock();
+        if !outputs.contains_key(output_id) {
+            return Err(RequestKeyframeError::NotFound(output_id.clone()));
+        }
+        let encoder_id = self
+            .chunk_router
+            .source(output_id)
+            .unwrap_or_else(|| output_id.clone());
+        if let Some(encoder_output) = outputs.get(&encoder_id) {
+            encoder_output.request_keyframe();
+        }
+        Ok(())
+    }
+
+    /// Stops or resumes encoding and sending the output without unregistering it, so it
+    /// keeps its port, connection or file. Frames rendered while it's disabled are dropped.
+    /// After it's enabled again, it resumes on a keyframe.
+    pub fn set_output_enabled(
+        &self,
+        output_id: &OutputId,
+        is_enabled: bool,
+    ) -> Result<(), SetOutputEnabledError> {
+        let outputs = self.outputs.lock();
+        let Some(output) = outputs.get(output_id) else {
+            return Err(SetOutputEnabledError::NotFound(output_id.clone()));
+        };
+        if output.is_enabled.swap(is_enabled, Ordering::Relaxed) == is_enabled {
+            return Ok(());
+        }
+        self.chunk_router.set_enabled(output_id, is_enabled);
+        if !is_enabled {
+            return Ok(());
+        }
+
+        // Otherwise, frames would be repeated to fill the time the output was disabled.
+        if let (Some(cadence), Some(framerate)) = (&output.cadence, output.framerate) {
+            *cadence.lock().unwrap() = FrameCadence::new(framerate);
+        }
+        let encoder_id = self
+            .chunk_router
+            .source(output_id)
+            .unwrap_or_else(|| output_id.clone());
+        if let Some(encoder_output) = outputs.get(&encoder_id) {
+            encoder_output.encoder.request_keyframe();
+        }
+        Ok(())
+    }
+
+    /// Disabled input is not visible in any output and is not mixed into their audio,
+    /// but it's still received and decoded, so it's visible again as soon as it's enabled.
+    pub fn set_input_enabled(
+        &self,
+        input_id: &InputId,
+        is_enabled: bool,
+    ) -> Result<(), SetInputEnabledError> {
+        if !self.inputs.contains_key(input_id) {
+            return Err(SetInputEnabledError::NotFound(input_id.clone()));
+        }
+        self.renderer
+            .set_input_enabled(input_id, is_enabled)
+            .map_err(|err| SetInputEnabledError::FallbackError(input_id.clone(), err))
+    }
+
+    pub fn is_input_enabled(&self, input_id: &InputId) -> bool {
+        self.renderer.is_input_enabled(input_id)
+    }
+
+    /// Returns the frame that was most recently rendered for the output. If the pipeline
+    /// is not started, or nothing was rendered for the output yet, a single frame is
+    /// rendered from the latest frames received on inputs. Encoders are not involved.
+    pub fn snapshot(&self, output_id: &OutputId) -> Result<Frame, SnapshotError> {
+        let Some(output) = self.outputs.lock().get(output_id).cloned() else {
+            return Err(SnapshotError::NotFound(output_id.clone()));
+        };
+        if let Some(frame) = output.last_frame.lock().unwrap().clone() {
+            return Ok(frame);
+        }
+
+        let mut frames = self
+            .renderer
+            .render(self.queue.current_frames())
+            .map_err(|err| SnapshotError::RenderError(output_id.clone(), err))?;
+        frames
+            .frames
+            .remove(output_id)
+            .ok_or_else(|| SnapshotError::NoScene(output_id.clone()))
+    }
+
+    /// Returns the root component of the output scene that was most recently applied.
+    /// Scheduled updates are returned only after they are applied.
+    pub fn output_scene(&self, output_id: &OutputId) -> Option<Component> {
+        self.scene
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|scene| &scene.output_id == output_id)
+            .map(|scene| scene.root.clone())
+    }
+
+    /// Resolution of the encoder for the requested output resolution. Odd dimensions are
+    /// rounded up if `pad_odd_resolutions` is enabled, otherwise `None` is returned.
+    fn encoded_resolution(&self, resolution: Resolution) -> Option<Resolution> {
+        if is_resolution_supported(resolution) {
+            return Some(resolution);
+        }
+        self.pad_odd_resolutions.then(|| Resolution {
+            width: resolution.width.next_multiple_of(2),
+            height: resolution.height.next_multiple_of(2),
+        })
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.is_started.load(Ordering::Relaxed)
+    }
+
+    /// Flag updated when the pipeline is started or stopped, for callers that can't wait
+    /// for access to the pipeline, e.g. health checks.
+    pub fn is_started_flag(&self) -> Arc<AtomicBool> {
+        self.is_started.clone()
     }
 
     pub fn inputs(&self) -> impl Iterator<Item = (&InputId, &PipelineInput)> {
@@ -250,6 +1053,176 @@ impl Pipeline {
     }
 }
 
+/// Logs dropped frames at most once per second.
+#[derive(Default)]
+struct DroppedFramesLogger {
+    count: u64,
+    last_log: Option<Instant>,
+}
+
+impl DroppedFramesLogger {
+    fn on_frame_dropped(&mut self) {
+        self.count += 1;
+        if self
+            .last_log
+            .is_some_and(|last_log| last_log.elapsed() < Duration::from_secs(1))
+        {
+            return;
+        }
+        warn!("Dropped {} frame(s): render queue is too long.", self.count);
+        self.count = 0;
+        self.last_log = Some(Instant::now());
+    }
+}
+
+/// Applies scenes of the listed outputs. It's called directly by [`Pipeline::update_scene`]
+/// and by the render thread for scheduled updates.
+fn apply_scene_update(
+    renderer: &mut Renderer,
+    outputs: &OutputRegistry<PipelineOutput>,
+    scene: &Mutex<Vec<OutputScene>>,
+    chunk_router: &ChunkRouter,
+    updated_scenes: Vec<OutputScene>,
+    transition: Option<scene::SceneTransition>,
+) -> Result<(), UpdateSceneError> {
+    renderer.update_scene(render_scenes(outputs, updated_scenes.clone())?, transition)?;
+    {
+        let mut scene = scene.lock().unwrap();
+        scene.retain(|scene| {
+            !updated_scenes
+                .iter()
+                .any(|updated| updated.output_id == scene.output_id)
+        });
+        scene.extend(updated_scenes);
+    }
+    update_encoder_sharing(outputs, scene, chunk_router);
+    Ok(())
+}
+
+fn render_scenes(
+    outputs: &OutputRegistry<PipelineOutput>,
+    scenes: Vec<OutputScene>,
+) -> Result<Vec<scene::OutputScene>, UpdateSceneError> {
+    let outputs = outputs.lock();
+    // Scenes can only be defined for registered outputs, so without any outputs the
+    // request would do nothing.
+    if outputs.is_empty() {
+        return Err(UpdateSceneError::NoOutputsRegistered);
+    }
+    scenes
+        .into_iter()
+        .map(|scene| {
+            let output = outputs
+                .get(&scene.output_id)
+                .ok_or_else(|| UpdateSceneError::OutputNotRegistered(scene.output_id.clone()))?;
+            let root = match *output.scene_resolution.lock().unwrap() {
+                Some(scene_resolution) => letterbox(scene.root, scene_resolution),
+                None => scene.root,
+            };
+            Ok(scene::OutputScene {
+                output_id: scene.output_id,
+                root,
+                resolution: output.encoder.resolution(),
+                watermark: output.watermark.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Places the scene in the top-left corner of an output with a resolution padded to even
+/// dimensions. The padding is black.
+fn letterbox(root: Component, scene_resolution: Resolution) -> Component {
+    let scene_area = scene::ViewComponent {
+        id: None,
+        children: vec![root],
+        direction: scene::ViewChildrenDirection::Row,
+        position: scene::Position::Absolute(scene::AbsolutePosition {
+            width: scene_resolution.width as f32,
+            height: scene_resolution.height as f32,
+            position_horizontal: scene::HorizontalPosition::LeftOffset(0.0),
+            position_vertical: scene::VerticalPosition::TopOffset(0.0),
+            rotation_degrees: 0.0,
+        }),
+        transition: None,
+        overflow: scene::Overflow::Hidden,
+        z_index: 0,
+        background_color: scene::RGBAColor(0, 0, 0, 0),
+    };
+    Component::View(scene::ViewComponent {
+        id: None,
+        children: vec![Component::View(scene_area)],
+        direction: scene::ViewChildrenDirection::Row,
+        position: scene::Position::Static {
+            width: None,
+            height: None,
+        },
+        transition: None,
+        overflow: scene::Overflow::Hidden,
+        z_index: 0,
+        background_color: scene::RGBAColor(0, 0, 0, 255),
+    })
+}
+
+/// Outputs with identical scenes, encoder options and framerates share a single encoder.
+/// MP4 and RTMP outputs always use their own encoder, because the codec parameters are
+/// written to the header when the stream starts. Outputs with adaptive bitrate also use
+/// their own encoder, because its options change over time.
+fn update_encoder_sharing(
+    outputs: &OutputRegistry<PipelineOutput>,
+    scene: &Mutex<Vec<OutputScene>>,
+    chunk_router: &ChunkRouter,
+) {
+    let outputs = outputs.lock();
+    let scene = scene.lock().unwrap();
+
+    let mut scenes: Vec<_> = scene
+        .iter()
+        .filter(|scene| {
+            outputs.get(&scene.output_id).is_some_and(|output| {
+                matches!(output.output, Output::Rtp(_)) && output.adaptive_bitrate.is_none()
+            })
+        })
+        .collect();
+    scenes.sort_by(|a, b| a.output_id.0.cmp(&b.output_id.0));
+
+    let mut encoders: HashMap<u64, OutputId> = HashMap::new();
+    let mut sources: HashMap<&OutputId, OutputId> = HashMap::new();
+    for scene in scenes {
+        let output = &outputs[&scene.output_id];
+        let mut hasher = DefaultHasher::new();
+        format!("{:?}", scene.root).hash(&mut hasher);
+        // Scenes of padded outputs are rendered differently.
+        output.scene_resolution.lock().unwrap().hash(&mut hasher);
+        // Output id is the only option that differs between identical encoders.
+        output
+            .encoder
+            .options()
+            .with_output_id(OutputId(Arc::from("")))
+            .hash(&mut hasher);
+        output
+            .framerate
+            .map(|framerate| (framerate.num, framerate.den))
+            .hash(&mut hasher);
+        let encoder_id = encoders
+            .entry(hasher.finish())
+            .or_insert_with(|| scene.output_id.clone());
+        sources.insert(&scene.output_id, encoder_id.clone());
+    }
+
+    for output_id in outputs.keys() {
+        let encoder_id = sources.get(output_id).unwrap_or(output_id);
+        if chunk_router.set_source(output_id, encoder_id) {
+            // Output switches to the new encoder on a keyframe.
+            outputs[encoder_id].encoder.request_keyframe();
+        }
+    }
+}
+
+/// Encoders only support resolutions that are divisible by 2 in each dimension.
+pub fn is_resolution_supported(resolution: Resolution) -> bool {
+    resolution.width % 2 == 0 && resolution.height % 2 == 0
+}
+
 struct OutputRegistry<T>(Arc<Mutex<HashMap<OutputId, Arc<T>>>>);
 
 impl<T> Clone for OutputRegistry<T> {
@@ -280,6 +1253,26 @@ impl<T> OutputRegistry<T> {
     }
 }
 
+impl OutputRegistry<PipelineOutput> {
+    /// Only outputs with their own encoder request keyframes this way, because shared
+    /// encoders are only used by RTP outputs. Requests made before the output is added
+    /// to the registry are ignored. It holds a weak reference, so it doesn't keep
+    /// the registry alive after the pipeline is dropped.
+    fn keyframe_requester(&self, output_id: OutputId) -> KeyframeRequester {
+        let outputs = Arc::downgrade(&self.0);
+        Arc::new(move || {
+            let Some(outputs) = outputs.upgrade() else {
+                return;
+            };
+            // Output is not cloned, so it can't be dropped by the thread of the request.
+            let outputs = outputs.lock().unwrap();
+            if let Some(output) = outputs.get(&output_id) {
+                output.request_keyframe();
+            }
+        })
+    }
+}
+
 pub struct OutputIterator<'a> {
     inner_iter: hash_map::Iter<'a, OutputId, Arc<PipelineOutput>>,
 }
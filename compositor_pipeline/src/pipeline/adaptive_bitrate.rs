@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use compositor_render::{error::ErrorStack, OutputId};
+use log::{error, info};
+
+use super::encoder::Encoder;
+
+/// Render queue is considered deep if at least this many frame sets are waiting.
+const DEEP_QUEUE_LENGTH: usize = 2;
+
+/// Bitrate is lowered after the render queue is deep for this long.
+const DEEP_QUEUE_DURATION: Duration = Duration::from_secs(2);
+
+/// Bitrate is raised after the render queue is empty for this long. It's longer than
+/// [`DEEP_QUEUE_DURATION`], so the bitrate does not oscillate around the limit of the
+/// machine.
+const DRAINED_QUEUE_DURATION: Duration = Duration::from_secs(10);
+
+/// Bitrate is multiplied by this factor when it's lowered.
+const DECREASE_FACTOR: f64 = 0.75;
+
+/// Bitrate is multiplied by this factor when it's raised.
+const INCREASE_FACTOR: f64 = 1.1;
+
+/// Bitrate range of an output in the adaptive mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptiveBitrateOptions {
+    pub min_bitrate_kbps: u32,
+    pub max_bitrate_kbps: u32,
+}
+
+/// Lowers the bitrate of an output encoder while the render queue is consistently deep,
+/// which means that the machine can't keep up with rendering and encoding, and raises
+/// it back after the queue drains. Encoders are reopened with the new bitrate, so the
+/// stream continues with a keyframe after every change.
+///
+/// Encoders that are not configured with a bitrate are not changed.
+pub(super) struct AdaptiveBitrate {
+    options: AdaptiveBitrateOptions,
+    /// Since when the render queue was deep without interruption.
+    deep_since: Option<Instant>,
+    /// Since when the render queue was empty without interruption.
+    drained_since: Option<Instant>,
+}
+
+impl AdaptiveBitrate {
+    pub fn new(options: AdaptiveBitrateOptions) -> Self {
+        Self {
+            options,
+            deep_since: None,
+            drained_since: None,
+        }
+    }
+
+    /// Called for every frame rendered for the output with the length of the render
+    /// queue at the time the frame set was taken from it.
+    pub fn on_render_queue_length(
+        &mut self,
+        output_id: &OutputId,
+        encoder: &Encoder,
+        queue_length: usize,
+    ) {
+        let now = Instant::now();
+        let (deep_since, drained_since) = match queue_length {
+            0 => (None, Some(*self.drained_since.get_or_insert(now))),
+            length if length >= DEEP_QUEUE_LENGTH => {
+                (Some(*self.deep_since.get_or_insert(now)), None)
+            }
+            _ => (None, None),
+        };
+        self.deep_since = deep_since;
+        self.drained_since = drained_since;
+
+        let factor = if deep_since.is_some_and(|since| now - since >= DEEP_QUEUE_DURATION) {
+            DECREASE_FACTOR
+        } else if drained_since.is_some_and(|since| now - since >= DRAINED_QUEUE_DURATION) {
+            INCREASE_FACTOR
+        } else {
+            return;
+        };
+        // Next change requires the queue to stay deep or drained for the whole period again.
+        self.deep_since = None;
+        self.drained_since = None;
+
+        let options = encoder.options();
+        let Some(bitrate_kbps) = options.bitrate_kbps() else {
+            return;
+        };
+        let new_bitrate_kbps = ((bitrate_kbps as f64 * factor) as u32)
+            .clamp(self.options.min_bitrate_kbps, self.options.max_bitrate_kbps);
+        if new_bitrate_kbps == bitrate_kbps {
+            return;
+        }
+        let Some(new_options) = options.with_bitrate_kbps(new_bitrate_kbps) else {
+            return;
+        };
+
+        info!(
+            "[output {output_id}] Render queue length is {queue_length}, changing the bitrate from {bitrate_kbps} to {new_bitrate_kbps} kbps."
+        );
+        if let Err(err) = encoder.update_options(new_options) {
+            error!(
+                "[output {output_id}] Failed to change the encoder bitrate: {}",
+                ErrorStack::new(&err).into_string()
+            );
+        }
+    }
+}
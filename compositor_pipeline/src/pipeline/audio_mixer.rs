@@ -0,0 +1,148 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use compositor_render::InputId;
+
+/// Sample rate of decoded and mixed audio. Inputs are resampled to it after decoding.
+pub const SAMPLE_RATE: u32 = 48_000;
+
+/// Decoded and mixed audio is always stereo.
+pub const CHANNELS: usize = 2;
+
+/// Mixed windows further ahead of the previous one than this start a new timeline
+/// instead of being filled with silence, e.g. when the pipeline is restarted.
+const MAX_GAP: Duration = Duration::from_secs(1);
+
+/// Interleaved stereo samples at [`SAMPLE_RATE`].
+#[derive(Debug, Clone)]
+pub struct AudioSamples {
+    pub samples: Vec<i16>,
+    /// Timestamp of the first sample.
+    pub pts: Duration,
+}
+
+/// Audio track of an output is a mix of the audio of the listed inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioMixOptions {
+    pub inputs: Vec<InputId>,
+}
+
+/// Cuts audio received on inputs into windows that follow the rendered frames, so audio
+/// stays in sync with the video. Every window contains the audio of all inputs between
+/// the timestamps of two consecutive frame sets, with silence in place of missing samples.
+#[derive(Default)]
+pub(super) struct AudioMixer {
+    /// Samples that were received, but are not mixed yet.
+    pending: HashMap<InputId, VecDeque<AudioSamples>>,
+    /// Index of the first sample of the next window.
+    next_sample: Option<u64>,
+}
+
+/// Audio of all inputs in the same time range.
+pub(super) struct MixWindow {
+    pts: Duration,
+    len: usize,
+    inputs: HashMap<InputId, Vec<i16>>,
+}
+
+impl AudioMixer {
+    /// Returns the window that ends at `pts`. `samples` are new samples received on inputs.
+    pub fn advance(
+        &mut self,
+        pts: Duration,
+        samples: HashMap<InputId, Vec<AudioSamples>>,
+    ) -> Option<MixWindow> {
+        for (input_id, samples) in samples {
+            self.pending.entry(input_id).or_default().extend(samples);
+        }
+
+        let end = sample_index(pts);
+        let start = match self.next_sample {
+            Some(next) if next <= end && end - next <= sample_index(MAX_GAP) => next,
+            _ => end,
+        };
+        self.next_sample = Some(end);
+        if start == end {
+            return None;
+        }
+
+        let len = (end - start) as usize;
+        let inputs = self
+            .pending
+            .iter_mut()
+            .map(|(input_id, pending)| (input_id.clone(), take_window(pending, start, len)))
+            .collect();
+        self.pending.retain(|_, pending| !pending.is_empty());
+
+        Some(MixWindow {
+            pts: sample_pts(start),
+            len,
+            inputs,
+        })
+    }
+}
+
+impl MixWindow {
+    /// Sums samples of the inputs. Samples that overflow are clipped.
+    pub fn mix(&self, inputs: &[InputId]) -> AudioSamples {
+        let mut mixed = vec![0i32; self.len * CHANNELS];
+        for samples in inputs
+            .iter()
+            .filter_map(|input_id| self.inputs.get(input_id))
+        {
+            for (mixed, sample) in mixed.iter_mut().zip(samples) {
+                *mixed += *sample as i32;
+            }
+        }
+
+        AudioSamples {
+            samples: mixed
+                .into_iter()
+                .map(|sample| sample.clamp(i16::MIN as i32, i16::MAX as i32) as i16)
+                .collect(),
+            pts: self.pts,
+        }
+    }
+}
+
+/// Copies samples in range `start..start + len` and removes them from the pending ones.
+/// Samples that arrived too late for their window are dropped.
+fn take_window(pending: &mut VecDeque<AudioSamples>, start: u64, len: usize) -> Vec<i16> {
+    let mut window = vec![0; len * CHANNELS];
+    let end = start + len as u64;
+
+    while let Some(samples) = pending.front() {
+        let samples_start = sample_index(samples.pts);
+        let samples_end = samples_start + (samples.samples.len() / CHANNELS) as u64;
+        if samples_start >= end {
+            break;
+        }
+
+        let from = u64::max(samples_start, start);
+        let to = u64::min(samples_end, end);
+        if from < to {
+            let source = (from - samples_start) as usize * CHANNELS
+                ..(to - samples_start) as usize * CHANNELS;
+            let target = (from - start) as usize * CHANNELS;
+            window[target..target + source.len()].copy_from_slice(&samples.samples[source]);
+        }
+
+        // Remaining samples belong to the next window.
+        if samples_end > end {
+            break;
+        }
+        pending.pop_front();
+    }
+
+    window
+}
+
+fn sample_index(pts: Duration) -> u64 {
+    (pts.as_nanos() * SAMPLE_RATE as u128 / 1_000_000_000) as u64
+}
+
+fn sample_pts(index: u64) -> Duration {
+    Duration::from_nanos((index as u128 * 1_000_000_000 / SAMPLE_RATE as u128) as u64)
+}
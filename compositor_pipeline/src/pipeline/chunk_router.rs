@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use compositor_render::OutputId;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+use super::structs::EncodedChunk;
+
+/// Forwards chunks produced by encoders to outputs. Every output has its own encoder,
+/// but outputs with identical scenes and encoder options receive chunks from a single
+/// encoder, so the same content is encoded only once.
+///
+/// Encoders are identified by the id of the output that owns them.
+#[derive(Clone, Default)]
+pub(super) struct ChunkRouter(Arc<Mutex<RouterState>>);
+
+#[derive(Default)]
+struct RouterState {
+    outputs: HashMap<OutputId, RoutedOutput>,
+}
+
+struct RoutedOutput {
+    sender: Sender<EncodedChunk>,
+    /// Encoder that currently produces chunks for this output.
+    source: OutputId,
+    /// Encoder that will replace `source` after it produces a keyframe. Switching on
+    /// a keyframe keeps the stream decodable.
+    next_source: Option<OutputId>,
+    /// Chunks are not forwarded to disabled outputs.
+    is_enabled: bool,
+    /// Set when the output is enabled again, so it resumes on a keyframe.
+    waits_for_keyframe: bool,
+}
+
+impl ChunkRouter {
+    /// Starts forwarding chunks from the encoder owned by the output. Returns chunks that
+    /// should be sent by the output.
+    pub(super) fn add_output(
+        &self,
+        output_id: OutputId,
+        encoder_chunks: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+    ) -> Receiver<EncodedChunk> {
+        let (sender, receiver) = unbounded();
+        self.0.lock().unwrap().outputs.insert(
+            output_id.clone(),
+            RoutedOutput {
+                sender,
+                source: output_id.clone(),
+                next_source: None,
+                is_enabled: true,
+                waits_for_keyframe: false,
+            },
+        );
+
+        let router = self.clone();
+        thread::Builder::new()
+            .name(format!("Chunk router for output {output_id}"))
+            .spawn(move || {
+                for chunk in encoder_chunks {
+                    router.route(&output_id, chunk);
+                }
+                // Encoder is dropped when the output is unregistered. Dropping the sender
+                // ends the stream of chunks sent by the output.
+                router.0.lock().unwrap().outputs.remove(&output_id);
+            })
+            .unwrap();
+
+        receiver
+    }
+
+    /// Forwards chunks of the audio track to the output. Audio is encoded separately for
+    /// every output, so it's never routed between outputs.
+    pub(super) fn add_audio(
+        &self,
+        output_id: OutputId,
+        audio_chunks: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+    ) {
+        let Some(sender) = self
+            .0
+            .lock()
+            .unwrap()
+            .outputs
+            .get(&output_id)
+            .map(|output| output.sender.clone())
+        else {
+            return;
+        };
+
+        thread::Builder::new()
+            .name(format!("Audio chunk router for output {output_id}"))
+            .spawn(move || {
+                for chunk in audio_chunks {
+                    // Fails only if the output is already dropped.
+                    let _ = sender.send(chunk);
+                }
+            })
+            .unwrap();
+    }
+
+    /// Returns `true` if any enabled output receives, or will receive, chunks from
+    /// the encoder.
+    pub(super) fn is_encoder_used(&self, encoder_id: &OutputId) -> bool {
+        self.0.lock().unwrap().outputs.values().any(|output| {
+            output.is_enabled
+                && (&output.source == encoder_id || output.next_source.as_ref() == Some(encoder_id))
+        })
+    }
+
+    /// Stops or resumes forwarding chunks to the output. After it's enabled, chunks are
+    /// dropped until the next keyframe.
+    pub(super) fn set_enabled(&self, output_id: &OutputId, is_enabled: bool) {
+        let mut state = self.0.lock().unwrap();
+        if let Some(output) = state.outputs.get_mut(output_id) {
+            output.is_enabled = is_enabled;
+            output.waits_for_keyframe = is_enabled;
+        }
+    }
+
+    /// Switches the output to chunks produced by the encoder after its next keyframe.
+    /// Returns `true` if the switch is not finished yet.
+    pub(super) fn set_source(&self, output_id: &OutputId, encoder_id: &OutputId) -> bool {
+        let mut state = self.0.lock().unwrap();
+        let Some(output) = state.outputs.get_mut(output_id) else {
+            return false;
+        };
+        if &output.source == encoder_id {
+            output.next_source = None;
+            return false;
+        }
+        output.next_source = Some(encoder_id.clone());
+        true
+    }
+
+    /// Encoder that produces, or will produce after the switch, chunks for the output.
+    pub(super) fn source(&self, output_id: &OutputId) -> Option<OutputId> {
+        let state = self.0.lock().unwrap();
+        let output = state.outputs.get(output_id)?;
+        Some(
+            output
+                .next_source
+                .as_ref()
+                .unwrap_or(&output.source)
+                .clone(),
+        )
+    }
+
+    fn route(&self, encoder_id: &OutputId, chunk: EncodedChunk) {
+        let mut state = self.0.lock().unwrap();
+        for output in state.outputs.values_mut() {
+            if chunk.is_keyframe && output.next_source.as_ref() == Some(encoder_id) {
+                output.source = output.next_source.take().unwrap();
+            }
+            if &output.source != encoder_id || !output.is_enabled {
+                continue;
+            }
+            if output.waits_for_keyframe {
+                if !chunk.is_keyframe {
+                    continue;
+                }
+                output.waits_for_keyframe = false;
+            }
+            // Fails only if the output is already dropped.
+            let _ = output.sender.send(chunk.clone());
+        }
+    }
+}
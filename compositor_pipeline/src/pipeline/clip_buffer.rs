@@ -0,0 +1,102 @@
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+use super::structs::{EncodedChunk, EncodedChunkKind};
+
+/// Upper limit of the encoded video kept by the clip buffer of a single output.
+/// The oldest frames are dropped when it's exceeded, even if they are within the
+/// buffered duration.
+const MAX_CLIP_BUFFER_SIZE: usize = 256 * 1024 * 1024;
+
+/// Keeps encoded video chunks of the most recent `duration` of an output, so a clip
+/// can be saved after something interesting already happened.
+///
+/// Chunks are grouped by keyframes, and whole groups are dropped at once, so every
+/// clip starts with a keyframe.
+pub(super) struct ClipBuffer {
+    duration: Duration,
+    state: Mutex<ClipBufferState>,
+}
+
+#[derive(Default)]
+struct ClipBufferState {
+    /// Groups of chunks, each one starting with a keyframe.
+    gops: VecDeque<Vec<EncodedChunk>>,
+    size: usize,
+}
+
+impl ClipBuffer {
+    pub(super) fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            state: Mutex::new(ClipBufferState::default()),
+        }
+    }
+
+    pub(super) fn push(&self, chunk: &EncodedChunk) {
+        if !matches!(chunk.kind, EncodedChunkKind::Video(_)) {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if chunk.is_keyframe {
+            state.gops.push_back(Vec::new());
+        }
+        // Chunks before the first keyframe can't be decoded.
+        let Some(gop) = state.gops.back_mut() else {
+            return;
+        };
+        gop.push(chunk.clone());
+        state.size += chunk.data.len();
+
+        // The oldest group is kept as long as it covers the start of the buffered duration.
+        let start = chunk.pts - duration_to_pts(self.duration);
+        while state.gops.len() > 1 && state.gops[1][0].pts <= start {
+            state.pop_front();
+        }
+        // If a single group exceeds the limit, chunks are dropped until the next keyframe.
+        while state.size > MAX_CLIP_BUFFER_SIZE {
+            state.pop_front();
+        }
+    }
+
+    /// Returns chunks of the last `duration`, starting with the latest keyframe before it.
+    /// Timestamps are shifted, so the clip starts at 0.
+    pub(super) fn clip(&self, duration: Duration) -> Vec<EncodedChunk> {
+        let state = self.state.lock().unwrap();
+        let Some(last_pts) = state.gops.iter().flatten().map(|chunk| chunk.pts).max() else {
+            return Vec::new();
+        };
+
+        let start = last_pts - duration_to_pts(duration);
+        let first_gop = state
+            .gops
+            .iter()
+            .rposition(|gop| gop[0].pts <= start)
+            .unwrap_or(0);
+        let mut chunks: Vec<_> = state.gops.range(first_gop..).flatten().cloned().collect();
+
+        let offset = chunks
+            .iter()
+            .map(|chunk| chunk.dts.unwrap_or(chunk.pts))
+            .min()
+            .unwrap_or(0);
+        for chunk in chunks.iter_mut() {
+            chunk.pts -= offset;
+            chunk.dts = chunk.dts.map(|dts| dts - offset);
+        }
+        chunks
+    }
+}
+
+impl ClipBufferState {
+    fn pop_front(&mut self) {
+        if let Some(gop) = self.gops.pop_front() {
+            self.size -= gop.iter().map(|chunk| chunk.data.len()).sum::<usize>();
+        }
+    }
+}
+
+/// Converts to the 90kHz clock used by the encoded chunks.
+fn duration_to_pts(duration: Duration) -> i64 {
+    (duration.as_secs_f64() * 90000.0) as i64
+}
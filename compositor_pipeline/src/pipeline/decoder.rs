@@ -1,34 +1,155 @@
-use std::sync::Arc;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use crate::{error::DecoderInitError, queue::Queue};
 
-use self::ffmpeg_h264::H264FfmpegDecoder;
+use self::{ffmpeg::FfmpegDecoder, ffmpeg_aac::FfmpegAacDecoder, hardware::HardwareDecoder};
 
-use super::structs::EncodedChunk;
+use super::{
+    stats::InputStats,
+    structs::{AudioCodec, Codec, EncodedChunk, EncodedChunkKind},
+};
 use compositor_render::InputId;
+use crossbeam_channel::unbounded;
 
-pub mod ffmpeg_h264;
+pub mod ffmpeg;
+pub mod ffmpeg_aac;
+pub mod hardware;
+mod reorder;
+
+#[cfg(test)]
+mod reorder_test;
 
 pub enum Decoder {
-    H264(H264FfmpegDecoder),
+    H264(FfmpegDecoder),
+    H265(FfmpegDecoder),
+    Vp8(FfmpegDecoder),
+    Vp9(FfmpegDecoder),
 }
 
 impl Decoder {
+    /// If `audio_codec` is specified, audio chunks are decoded by a separate decoder and
+    /// passed to the queue together with video frames. Otherwise, they are dropped.
+    /// Hardware decoder from `settings` is only used for H264.
     pub fn new(
         parameters: DecoderOptions,
+        audio_codec: Option<AudioCodec>,
         chunks: Box<dyn Iterator<Item = EncodedChunk> + Send>,
         queue: Arc<Queue>,
         input_id: InputId,
+        stats: Arc<InputStats>,
+        settings: VideoDecoderSettings,
     ) -> Result<Self, DecoderInitError> {
-        match parameters {
-            DecoderOptions::H264 => {
-                Ok(Self::H264(H264FfmpegDecoder::new(chunks, queue, input_id)?))
+        let software_settings = VideoDecoderSettings {
+            hardware_decoder: None,
+            ..settings
+        };
+        let pts_origin = PtsOrigin::default();
+        let chunks = match audio_codec {
+            Some(audio_codec) => {
+                let (audio_sender, audio_receiver) = unbounded();
+                FfmpegAacDecoder::new(
+                    audio_codec,
+                    Box::new(audio_receiver.into_iter()),
+                    queue.clone(),
+                    input_id.clone(),
+                    pts_origin.clone(),
+                )?;
+                // Audio decoder stops when the video decoder drops the sender after
+                // the input is unregistered.
+                Box::new(chunks.filter(move |chunk| match chunk.kind {
+                    EncodedChunkKind::Video(_) => true,
+                    EncodedChunkKind::Audio(_) => {
+                        let _ = audio_sender.send(chunk.clone());
+                        false
+                    }
+                })) as Box<dyn Iterator<Item = EncodedChunk> + Send>
             }
+            None => chunks,
+        };
+
+        match parameters {
+            DecoderOptions::H264 => Ok(Self::H264(FfmpegDecoder::new(
+                Codec::H264,
+                chunks,
+                queue,
+                input_id,
+                stats,
+                pts_origin,
+                settings,
+            )?)),
+            DecoderOptions::H265 => Ok(Self::H265(FfmpegDecoder::new(
+                Codec::H265,
+                chunks,
+                queue,
+                input_id,
+                stats,
+                pts_origin,
+                software_settings,
+            )?)),
+            DecoderOptions::Vp8 => Ok(Self::Vp8(FfmpegDecoder::new(
+                Codec::Vp8,
+                chunks,
+                queue,
+                input_id,
+                stats,
+                pts_origin,
+                software_settings,
+            )?)),
+            DecoderOptions::Vp9 => Ok(Self::Vp9(FfmpegDecoder::new(
+                Codec::Vp9,
+                chunks,
+                queue,
+                input_id,
+                stats,
+                pts_origin,
+                software_settings,
+            )?)),
         }
     }
 }
 
+/// Options shared by video decoders of all inputs.
+#[derive(Debug, Clone, Copy)]
+pub struct VideoDecoderSettings {
+    /// If it can't be initialized, the stream is decoded in software.
+    pub hardware_decoder: Option<HardwareDecoder>,
+    /// Maximal number of decoded frames held to pass them to the queue in presentation
+    /// order. Frames are only held for streams that reorder them, e.g. with B-frames.
+    pub max_reorder_frames: usize,
+}
+
+/// Timestamp of the first decoded frame or samples of an input. Timestamps of video and
+/// audio are both counted from it, so they stay in sync.
+#[derive(Clone, Default)]
+pub struct PtsOrigin(Arc<Mutex<Option<i64>>>);
+
+impl PtsOrigin {
+    /// Converts a timestamp in 1/90000 units to the time since the origin. Timestamps
+    /// earlier than the origin are clamped to zero.
+    fn elapsed(&self, pts: i64) -> Duration {
+        let origin = *self.0.lock().unwrap().get_or_insert(pts);
+        Duration::from_secs_f64(f64::max((pts - origin) as f64 / 90000.0, 0.0))
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum DecoderOptions {
     H264,
+    H265,
+    Vp8,
+    Vp9,
+}
+
+impl DecoderOptions {
+    pub fn codec(&self) -> Codec {
+        match self {
+            DecoderOptions::H264 => Codec::H264,
+            DecoderOptions::H265 => Codec::H265,
+            DecoderOptions::Vp8 => Codec::Vp8,
+            DecoderOptions::Vp9 => Codec::Vp9,
+        }
+    }
 }
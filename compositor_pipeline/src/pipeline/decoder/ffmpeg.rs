@@ -0,0 +1,323 @@
+use std::{sync::Arc, time::Instant};
+
+use crate::{
+    error::DecoderInitError,
+    pipeline::{
+        stats::InputStats,
+        structs::{Codec, EncodedChunk, EncodedChunkKind},
+    },
+    queue::Queue,
+};
+
+use super::{
+    hardware,
+    hardware::HardwareDecoder,
+    reorder::{LateFrameError, ReorderBuffer},
+    PtsOrigin, VideoDecoderSettings,
+};
+
+use compositor_render::{error::ErrorStack, Frame, InputId, Resolution, YuvData};
+use ffmpeg_next::{
+    codec::{decoder, Context, Id, Parameters},
+    ffi::AV_CODEC_FLAG2_CHUNKS,
+    format::Pixel,
+    frame::Video,
+    media::Type,
+};
+use log::{debug, error, warn};
+
+pub struct FfmpegDecoder;
+
+impl FfmpegDecoder {
+    pub fn new(
+        codec: Codec,
+        chunks: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+        queue: Arc<Queue>,
+        input_id: InputId,
+        stats: Arc<InputStats>,
+        pts_origin: PtsOrigin,
+        settings: VideoDecoderSettings,
+    ) -> Result<Self, DecoderInitError> {
+        let (init_result_sender, init_result_receiver) = crossbeam_channel::bounded(0);
+
+        let mut parameters = ffmpeg_next::codec::Parameters::new();
+        unsafe {
+            let parameters = &mut *parameters.as_mut_ptr();
+
+            parameters.codec_type = Type::Video.into();
+            parameters.codec_id = Id::from(codec).into();
+        };
+
+        std::thread::Builder::new()
+            .name(format!("{codec:?} ffmpeg decoder {}", input_id.0))
+            .spawn(move || {
+                let mut decoder =
+                    match open_decoder(&parameters, settings.hardware_decoder, &input_id) {
+                        Ok(decoder) => {
+                            init_result_sender.send(Ok(())).unwrap();
+                            decoder
+                        }
+                        Err(err) => {
+                            init_result_sender.send(Err(err)).unwrap();
+                            return;
+                        }
+                    };
+
+                let mut decoded_frame = ffmpeg_next::frame::Video::empty();
+                let mut received_parameter_sets = !uses_parameter_sets(codec);
+                let mut reorder_buffer = ReorderBuffer::new(settings.max_reorder_frames);
+                let enqueue_frames = |frames: Vec<(Frame, Instant)>| {
+                    for (frame, ingested_at) in frames {
+                        if let Err(err) = queue.enqueue_frame(input_id.clone(), frame, ingested_at)
+                        {
+                            error!(
+                                "Failed to push frame: {}",
+                                ErrorStack::new(&err).into_string()
+                            );
+                        }
+                    }
+                };
+                for chunk in chunks {
+                    let ingested_at = Instant::now();
+                    // Only enabled if timings are logged, see `LIVE_COMPOSITOR_LOG_TIMINGS`.
+                    let _span =
+                        tracing::trace_span!(target: "timings", "decode", input_id = %input_id)
+                            .entered();
+                    if chunk.discontinuity {
+                        enqueue_frames(reorder_buffer.flush());
+                        decoder.flush();
+                    }
+
+                    if !received_parameter_sets {
+                        if contains_sequence_parameter_set(codec, &chunk.data) {
+                            received_parameter_sets = true;
+                        } else {
+                            stats.on_missing_parameter_set();
+                        }
+                    }
+
+                    let av_packet: ffmpeg_next::Packet = match chunk_to_av(codec, chunk) {
+                        Ok(packet) => packet,
+                        Err(err) => {
+                            warn!("Dropping frame: {}", err);
+                            stats.on_decode_error(err.to_string());
+                            continue;
+                        }
+                    };
+
+                    match decoder.send_packet(&av_packet) {
+                        Ok(()) => {}
+                        Err(e) => {
+                            warn!("Failed to send a packet to decoder: {}", e);
+                            stats.on_decode_error(format!("Failed to decode a packet: {e}"));
+                            continue;
+                        }
+                    }
+
+                    while decoder.receive_frame(&mut decoded_frame).is_ok() {
+                        // Frames decoded from a stream in a different format, or with
+                        // missing references, contain garbage.
+                        if decoded_frame.is_corrupt() {
+                            warn!("Dropping corrupted frame.");
+                            stats.on_decode_error("Decoded frame is corrupted.".to_owned());
+                            continue;
+                        }
+
+                        if let Err(err) = hardware::download_frame(&mut decoded_frame) {
+                            warn!("Dropping frame: failed to copy it from the GPU: {}", err);
+                            stats.on_decode_error(format!(
+                                "Failed to copy a frame from the GPU: {err}"
+                            ));
+                            continue;
+                        }
+                        let frame = match frame_from_av(&mut decoded_frame, &pts_origin) {
+                            Ok(frame) => frame,
+                            Err(err) => {
+                                warn!("Dropping frame: {}", err);
+                                stats.on_decode_error(err.to_string());
+                                continue;
+                            }
+                        };
+                        stats.on_frame_decoded();
+
+                        reorder_buffer.on_stream_depth(reorder_depth(&decoder));
+                        match reorder_buffer.push(frame, ingested_at) {
+                            Ok(frames) => enqueue_frames(frames),
+                            Err(err) => on_late_frame(err, &reorder_buffer, &input_id),
+                        }
+                    }
+                }
+                enqueue_frames(reorder_buffer.flush());
+            })
+            .unwrap();
+
+        init_result_receiver.recv().unwrap()?;
+
+        Ok(Self)
+    }
+}
+
+fn open_decoder(
+    parameters: &Parameters,
+    hardware_decoder: Option<HardwareDecoder>,
+    input_id: &InputId,
+) -> Result<decoder::Opened, DecoderInitError> {
+    if let Some(hardware_decoder) = hardware_decoder {
+        match hardware::open_decoder(hardware_decoder, new_context(parameters)?) {
+            Ok(decoder) => return Ok(decoder),
+            Err(err) => warn!(
+                "[input {input_id}] Failed to initialize the {hardware_decoder:?} decoder, falling back to software decoding: {}",
+                ErrorStack::new(&err).into_string()
+            ),
+        }
+    }
+
+    new_context(parameters)?
+        .decoder()
+        .open_as(Into::<Id>::into(parameters.id()))
+        .map_err(DecoderInitError::FfmpegError)
+}
+
+fn new_context(parameters: &Parameters) -> Result<Context, DecoderInitError> {
+    let mut context =
+        Context::from_parameters(parameters.clone()).map_err(DecoderInitError::FfmpegError)?;
+    // this flag allows us to send the packets in the form they come out of the depayloader
+    // wasted 6 hrs looking into this. I hate ffmpeg.
+    // and the bindings don't even expose `flags2` so we have to do the unsafe manually
+    unsafe {
+        (*context.as_mut_ptr()).flags2 |= AV_CODEC_FLAG2_CHUNKS;
+    }
+    Ok(context)
+}
+
+/// Number of frames the decoder detected to be reordered in the stream, e.g. from the
+/// bitstream restrictions in the H264 SPS.
+fn reorder_depth(decoder: &decoder::Opened) -> usize {
+    let has_b_frames = unsafe { (*decoder.as_ptr()).has_b_frames };
+    has_b_frames.max(0) as usize
+}
+
+fn on_late_frame(err: LateFrameError, reorder_buffer: &ReorderBuffer, input_id: &InputId) {
+    warn!("[input {input_id}] Dropping frame: {err}");
+    debug!(
+        "[input {input_id}] Holding up to {} decoded frames to pass them in presentation order.",
+        reorder_buffer.depth()
+    );
+}
+
+fn uses_parameter_sets(codec: Codec) -> bool {
+    matches!(codec, Codec::H264 | Codec::H265)
+}
+
+/// Looks for a sequence parameter set NAL unit in an Annex B chunk.
+fn contains_sequence_parameter_set(codec: Codec, data: &[u8]) -> bool {
+    let is_sps = |header: u8| match codec {
+        Codec::H264 => header & 0x1f == 7,
+        Codec::H265 => (header >> 1) & 0x3f == 33,
+        Codec::Av1 | Codec::Vp8 | Codec::Vp9 => false,
+    };
+    data.windows(4)
+        .any(|window| window[..3] == [0, 0, 1] && is_sps(window[3]))
+}
+
+#[derive(Debug, thiserror::Error)]
+enum DecoderChunkConversionError {
+    #[error(
+        "Cannot send a chunk of kind {0:?} to the decoder. The decoder only handles {1:?}-encoded video."
+    )]
+    BadPayloadType(EncodedChunkKind, Codec),
+}
+
+fn chunk_to_av(
+    codec: Codec,
+    chunk: EncodedChunk,
+) -> Result<ffmpeg_next::Packet, DecoderChunkConversionError> {
+    if chunk.kind != EncodedChunkKind::Video(codec) {
+        return Err(DecoderChunkConversionError::BadPayloadType(
+            chunk.kind, codec,
+        ));
+    }
+
+    let mut packet = ffmpeg_next::Packet::new(chunk.data.len());
+
+    packet.data_mut().unwrap().copy_from_slice(&chunk.data);
+    packet.set_pts(Some(chunk.pts));
+    packet.set_dts(chunk.dts);
+
+    Ok(packet)
+}
+
+#[derive(Debug, thiserror::Error)]
+enum DecoderFrameConversionError {
+    #[error("Error converting frame: {0}")]
+    FrameConversionError(String),
+
+    #[error("Unsupported pixel format: {0:?}.")]
+    UnsupportedPixelFormat(Pixel),
+}
+
+fn frame_from_av(
+    decoded: &mut Video,
+    pts_origin: &PtsOrigin,
+) -> Result<Frame, DecoderFrameConversionError> {
+    let data = match decoded.format() {
+        Pixel::YUV420P => YuvData {
+            y_plane: copy_plane_from_av(decoded, 0),
+            u_plane: copy_plane_from_av(decoded, 1),
+            v_plane: copy_plane_from_av(decoded, 2),
+        },
+        // Frames copied from hardware decoders have interleaved U and V planes.
+        Pixel::NV12 => {
+            let (u_plane, v_plane) = split_interleaved_plane_from_av(decoded, 1);
+            YuvData {
+                y_plane: copy_plane_from_av(decoded, 0),
+                u_plane,
+                v_plane,
+            }
+        }
+        format => return Err(DecoderFrameConversionError::UnsupportedPixelFormat(format)),
+    };
+    let pts = decoded
+        .pts()
+        .map(|pts| pts_origin.elapsed(pts))
+        .ok_or_else(|| {
+            DecoderFrameConversionError::FrameConversionError("missing pts".to_owned())
+        })?;
+    Ok(Frame {
+        data,
+        resolution: Resolution {
+            width: decoded.width().try_into().unwrap(),
+            height: decoded.height().try_into().unwrap(),
+        },
+        pts,
+    })
+}
+
+fn copy_plane_from_av(decoded: &Video, plane: usize) -> bytes::Bytes {
+    let mut output_buffer = bytes::BytesMut::with_capacity(
+        decoded.plane_width(plane) as usize * decoded.plane_height(plane) as usize,
+    );
+
+    decoded
+        .data(plane)
+        .chunks(decoded.stride(plane))
+        .map(|chunk| &chunk[..decoded.plane_width(plane) as usize])
+        .for_each(|chunk| output_buffer.extend_from_slice(chunk));
+
+    output_buffer.freeze()
+}
+
+fn split_interleaved_plane_from_av(decoded: &Video, plane: usize) -> (bytes::Bytes, bytes::Bytes) {
+    let width = decoded.plane_width(plane) as usize;
+    let size = width * decoded.plane_height(plane) as usize;
+    let mut first = bytes::BytesMut::with_capacity(size);
+    let mut second = bytes::BytesMut::with_capacity(size);
+
+    for row in decoded.data(plane).chunks(decoded.stride(plane)) {
+        let row = &row[..2 * width];
+        first.extend(row.iter().step_by(2).copied());
+        second.extend(row.iter().skip(1).step_by(2).copied());
+    }
+
+    (first.freeze(), second.freeze())
+}
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use crate::{
+    error::DecoderInitError,
+    pipeline::{
+        audio_mixer::{AudioSamples, CHANNELS, SAMPLE_RATE},
+        structs::{AudioCodec, EncodedChunk},
+    },
+    queue::Queue,
+};
+
+use compositor_render::{error::ErrorStack, InputId};
+use ffmpeg_next::{
+    codec::{Context, Id},
+    format::{sample, Sample},
+    frame::Audio,
+    media::Type,
+    software::resampling,
+    ChannelLayout,
+};
+use log::{error, warn};
+
+use super::PtsOrigin;
+
+/// Decodes AAC with ADTS headers and resamples it to the format used by the mixer.
+pub struct FfmpegAacDecoder;
+
+impl FfmpegAacDecoder {
+    pub fn new(
+        codec: AudioCodec,
+        chunks: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+        queue: Arc<Queue>,
+        input_id: InputId,
+        pts_origin: PtsOrigin,
+    ) -> Result<Self, DecoderInitError> {
+        let (init_result_sender, init_result_receiver) = crossbeam_channel::bounded(0);
+
+        let mut parameters = ffmpeg_next::codec::Parameters::new();
+        unsafe {
+            let parameters = &mut *parameters.as_mut_ptr();
+
+            parameters.codec_type = Type::Audio.into();
+            parameters.codec_id = Id::from(codec).into();
+        };
+
+        std::thread::Builder::new()
+            .name(format!("{codec:?} ffmpeg decoder {}", input_id.0))
+            .spawn(move || {
+                let decoder = Context::from_parameters(parameters)
+                    .and_then(|decoder| decoder.decoder().audio())
+                    .map_err(DecoderInitError::FfmpegError);
+
+                let mut decoder = match decoder {
+                    Ok(decoder) => {
+                        init_result_sender.send(Ok(())).unwrap();
+                        decoder
+                    }
+                    Err(err) => {
+                        init_result_sender.send(Err(err)).unwrap();
+                        return;
+                    }
+                };
+
+                let mut decoded = Audio::empty();
+                let mut resampler = None;
+                for chunk in chunks {
+                    if chunk.discontinuity {
+                        decoder.flush();
+                    }
+
+                    let mut packet = ffmpeg_next::Packet::copy(&chunk.data);
+                    packet.set_pts(Some(chunk.pts));
+                    packet.set_dts(chunk.dts);
+
+                    if let Err(err) = decoder.send_packet(&packet) {
+                        warn!("Failed to send a packet to the audio decoder: {err}");
+                        continue;
+                    }
+
+                    while decoder.receive_frame(&mut decoded).is_ok() {
+                        let Some(pts) = decoded.pts() else {
+                            warn!("Dropping decoded audio without a timestamp.");
+                            continue;
+                        };
+                        let samples = match resample(&mut resampler, &decoded) {
+                            Ok(samples) => samples,
+                            Err(err) => {
+                                warn!("Failed to resample decoded audio: {err}");
+                                continue;
+                            }
+                        };
+                        let samples = AudioSamples {
+                            samples,
+                            pts: pts_origin.elapsed(pts),
+                        };
+
+                        if let Err(err) = queue.enqueue_samples(input_id.clone(), samples) {
+                            error!(
+                                "Failed to push samples: {}",
+                                ErrorStack::new(&err).into_string()
+                            );
+                        }
+                    }
+                }
+            })
+            .unwrap();
+
+        init_result_receiver.recv().unwrap()?;
+
+        Ok(Self)
+    }
+}
+
+/// Converts decoded samples to interleaved 16-bit stereo at the mixer sample rate.
+/// Resampler is recreated if the format of the decoded audio changes.
+fn resample(
+    resampler: &mut Option<resampling::Context>,
+    decoded: &Audio,
+) -> Result<Vec<i16>, ffmpeg_next::Error> {
+    let layout = match decoded.channel_layout().is_empty() {
+        true => ChannelLayout::default(decoded.channels() as i32),
+        false => decoded.channel_layout(),
+    };
+    let is_outdated = match resampler {
+        Some(resampler) => {
+            let input = resampler.input();
+            input.format != decoded.format()
+                || input.channel_layout != layout
+                || input.rate != decoded.rate()
+        }
+        None => true,
+    };
+    if is_outdated {
+        *resampler = Some(resampling::Context::get(
+            decoded.format(),
+            layout,
+            decoded.rate(),
+            Sample::I16(sample::Type::Packed),
+            ChannelLayout::STEREO,
+            SAMPLE_RATE,
+        )?);
+    }
+
+    let mut resampled = Audio::empty();
+    resampler.as_mut().unwrap().run(decoded, &mut resampled)?;
+
+    let len = resampled.samples() * CHANNELS;
+    Ok(resampled.data(0)[..len * 2]
+        .chunks_exact(2)
+        .map(|sample| i16::from_ne_bytes([sample[0], sample[1]]))
+        .collect())
+}
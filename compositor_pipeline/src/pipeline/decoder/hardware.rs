@@ -0,0 +1,101 @@
+use std::ptr;
+
+use ffmpeg_next::{
+    codec::{decoder, Context},
+    ffi,
+    frame::Video,
+};
+
+/// Hardware decoder used for H264 inputs. Decoded frames are copied from the GPU
+/// to system memory before they are passed to the queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HardwareDecoder {
+    /// VA-API, supported by Intel and AMD GPUs on Linux.
+    Vaapi,
+    /// Intel Quick Sync Video.
+    Qsv,
+}
+
+impl HardwareDecoder {
+    fn device_type(&self) -> ffi::AVHWDeviceType {
+        match self {
+            HardwareDecoder::Vaapi => ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_VAAPI,
+            HardwareDecoder::Qsv => ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_QSV,
+        }
+    }
+
+    /// VA-API is used as a hwaccel of the native decoder, QSV has a separate one.
+    fn codec_name(&self) -> &'static str {
+        match self {
+            HardwareDecoder::Vaapi => "h264",
+            HardwareDecoder::Qsv => "h264_qsv",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(super) enum HardwareDecoderInitError {
+    #[error("FFmpeg was built without the \"{0}\" decoder.")]
+    DecoderNotFound(&'static str),
+
+    #[error("Failed to create the {0:?} device.")]
+    DeviceCreationFailed(HardwareDecoder, #[source] ffmpeg_next::Error),
+
+    #[error("Failed to open the decoder.")]
+    OpenFailed(#[source] ffmpeg_next::Error),
+}
+
+/// `context` has to be created from H264 parameters.
+pub(super) fn open_decoder(
+    hardware_decoder: HardwareDecoder,
+    mut context: Context,
+) -> Result<decoder::Opened, HardwareDecoderInitError> {
+    let codec_name = hardware_decoder.codec_name();
+    let codec = decoder::find_by_name(codec_name)
+        .ok_or(HardwareDecoderInitError::DecoderNotFound(codec_name))?;
+
+    unsafe {
+        let mut device_ctx = ptr::null_mut();
+        let result = ffi::av_hwdevice_ctx_create(
+            &mut device_ctx,
+            hardware_decoder.device_type(),
+            ptr::null(),
+            ptr::null_mut(),
+            0,
+        );
+        if result < 0 {
+            return Err(HardwareDecoderInitError::DeviceCreationFailed(
+                hardware_decoder,
+                result.into(),
+            ));
+        }
+        // Freed together with the codec context.
+        (*context.as_mut_ptr()).hw_device_ctx = device_ctx;
+    }
+
+    // If the hardware can't decode the stream, e.g. because of an unsupported profile,
+    // FFmpeg falls back to software decoding when the first frame is decoded.
+    context
+        .decoder()
+        .open_as(codec)
+        .map_err(HardwareDecoderInitError::OpenFailed)
+}
+
+/// Replaces a frame stored in GPU memory with its copy in system memory, usually
+/// in the NV12 format. Frames decoded in software are not changed.
+pub(super) fn download_frame(frame: &mut Video) -> Result<(), ffmpeg_next::Error> {
+    unsafe {
+        if (*frame.as_ptr()).hw_frames_ctx.is_null() {
+            return Ok(());
+        }
+
+        let mut downloaded = Video::empty();
+        let result = ffi::av_hwframe_transfer_data(downloaded.as_mut_ptr(), frame.as_ptr(), 0);
+        if result < 0 {
+            return Err(result.into());
+        }
+        downloaded.set_pts(frame.pts());
+        *frame = downloaded;
+    }
+    Ok(())
+}
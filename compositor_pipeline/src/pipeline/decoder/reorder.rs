@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+
+use compositor_render::Frame;
+
+/// Holds decoded frames to pass them to the queue in presentation order. The queue
+/// expects increasing timestamps, but decoders can output frames of streams with
+/// B-frames out of order if the stream does not signal how many frames are reordered.
+///
+/// Frames are held only if the stream reorders them, so streams without B-frames are
+/// not delayed. The number of held frames grows when the decoder reports a larger
+/// reorder depth or a frame is decoded after frames presented later, up to `max_depth`.
+pub(super) struct ReorderBuffer {
+    /// Frames with the time when their chunks were received, ordered by pts.
+    frames: Vec<(Frame, Instant)>,
+    depth: usize,
+    max_depth: usize,
+    last_pts: Option<Duration>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Frame with pts {pts:?} was decoded after a frame with pts {last_pts:?} was passed to the queue.")]
+pub(super) struct LateFrameError {
+    pts: Duration,
+    last_pts: Duration,
+}
+
+impl ReorderBuffer {
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            frames: Vec::new(),
+            depth: 0,
+            max_depth,
+            last_pts: None,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Updates the depth with the number of reordered frames detected by the decoder.
+    pub fn on_stream_depth(&mut self, depth: usize) {
+        self.depth = self.depth.max(depth.min(self.max_depth));
+    }
+
+    /// Returns frames that can be passed to the queue. Frames that are too late to be
+    /// passed in order are dropped and the depth is increased for the following frames.
+    pub fn push(
+        &mut self,
+        frame: Frame,
+        ingested_at: Instant,
+    ) -> Result<Vec<(Frame, Instant)>, LateFrameError> {
+        if let Some(last_pts) = self.last_pts.filter(|last_pts| frame.pts < *last_pts) {
+            self.depth = usize::min(self.depth + 1, self.max_depth);
+            return Err(LateFrameError {
+                pts: frame.pts,
+                last_pts,
+            });
+        }
+
+        let index = self
+            .frames
+            .partition_point(|(queued, _)| queued.pts <= frame.pts);
+        self.frames.insert(index, (frame, ingested_at));
+
+        let ready_count = self.frames.len().saturating_sub(self.depth);
+        Ok(self.take(ready_count))
+    }
+
+    /// Returns all held frames, e.g. before the decoder is flushed. Timestamps after
+    /// the flush are not compared with the previous ones.
+    pub fn flush(&mut self) -> Vec<(Frame, Instant)> {
+        let frames = self.take(self.frames.len());
+        self.last_pts = None;
+        frames
+    }
+
+    fn take(&mut self, count: usize) -> Vec<(Frame, Instant)> {
+        let frames: Vec<_> = self.frames.drain(..count).collect();
+        if let Some((frame, _)) = frames.last() {
+            self.last_pts = Some(frame.pts);
+        }
+        frames
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use compositor_render::{Frame, Resolution, YuvData};
+
+use super::reorder::ReorderBuffer;
+
+fn frame(pts_ms: u64) -> Frame {
+    Frame {
+        data: YuvData {
+            y_plane: Bytes::new(),
+            u_plane: Bytes::new(),
+            v_plane: Bytes::new(),
+        },
+        resolution: Resolution {
+            width: 0,
+            height: 0,
+        },
+        pts: Duration::from_millis(pts_ms),
+    }
+}
+
+fn pts_ms(frames: Vec<(Frame, Instant)>) -> Vec<u64> {
+    frames
+        .into_iter()
+        .map(|(frame, _)| frame.pts.as_millis() as u64)
+        .collect()
+}
+
+fn push(buffer: &mut ReorderBuffer, pts: u64) -> Vec<u64> {
+    pts_ms(buffer.push(frame(pts), Instant::now()).unwrap())
+}
+
+#[test]
+fn test_stream_without_b_frames_is_not_delayed() {
+    let mut buffer = ReorderBuffer::new(4);
+
+    for pts in [0, 33, 66, 100] {
+        buffer.on_stream_depth(0);
+        assert_eq!(push(&mut buffer, pts), vec![pts]);
+    }
+    assert_eq!(buffer.depth(), 0);
+    assert_eq!(pts_ms(buffer.flush()), Vec::<u64>::new());
+}
+
+#[test]
+fn test_ipbb_stream_with_reported_depth() {
+    let mut buffer = ReorderBuffer::new(4);
+
+    // Decode order of I0 P3 B1 B2 P6 B4 B5.
+    let mut released = Vec::new();
+    for pts in [0, 30, 10, 20, 60, 40, 50] {
+        buffer.on_stream_depth(2);
+        released.extend(push(&mut buffer, pts));
+    }
+    assert_eq!(released, vec![0, 10, 20, 30, 40]);
+    assert_eq!(pts_ms(buffer.flush()), vec![50, 60]);
+}
+
+#[test]
+fn test_reported_depth_is_limited_to_max_depth() {
+    let mut buffer = ReorderBuffer::new(2);
+
+    buffer.on_stream_depth(5);
+    assert_eq!(buffer.depth(), 2);
+    buffer.on_stream_depth(1);
+    assert_eq!(buffer.depth(), 2);
+}
+
+#[test]
+fn test_late_frame_increases_depth() {
+    let mut buffer = ReorderBuffer::new(2);
+
+    assert_eq!(push(&mut buffer, 0), vec![0]);
+    assert_eq!(push(&mut buffer, 20), vec![20]);
+    assert!(buffer.push(frame(10), Instant::now()).is_err());
+    assert_eq!(buffer.depth(), 1);
+
+    // Following frames are held, so the next reordered frame is passed in order.
+    assert_eq!(push(&mut buffer, 40), Vec::<u64>::new());
+    assert_eq!(push(&mut buffer, 30), vec![30]);
+    assert_eq!(push(&mut buffer, 60), vec![40]);
+    assert!(buffer.push(frame(35), Instant::now()).is_err());
+    assert_eq!(buffer.depth(), 2);
+
+    assert_eq!(push(&mut buffer, 80), Vec::<u64>::new());
+    assert!(buffer.push(frame(38), Instant::now()).is_err());
+    assert_eq!(buffer.depth(), 2);
+    assert_eq!(pts_ms(buffer.flush()), vec![60, 80]);
+}
+
+#[test]
+fn test_flush_returns_frames_in_order() {
+    let mut buffer = ReorderBuffer::new(3);
+    buffer.on_stream_depth(3);
+
+    for pts in [30, 10, 20] {
+        assert_eq!(push(&mut buffer, pts), Vec::<u64>::new());
+    }
+    assert_eq!(pts_ms(buffer.flush()), vec![10, 20, 30]);
+
+    // Timestamps after the flush are not compared with the previous ones.
+    assert_eq!(push(&mut buffer, 5), Vec::<u64>::new());
+    assert_eq!(pts_ms(buffer.flush()), vec![5]);
+}
@@ -1,19 +1,144 @@
-use compositor_render::{Frame, Resolution};
+use compositor_render::{Frame, OutputId, Resolution};
 
 use crate::error::EncoderInitError;
 
-use self::ffmpeg_h264::LibavH264Encoder;
+use self::{
+    ffmpeg_av1::LibavAv1Encoder, ffmpeg_h264::LibavH264Encoder, ffmpeg_h265::LibavH265Encoder,
+    ffmpeg_vpx::LibavVpxEncoder,
+};
 
-use super::structs::EncodedChunk;
+use super::structs::{Codec, EncodedChunk};
 
+pub mod ffmpeg_aac;
+pub mod ffmpeg_av1;
 pub mod ffmpeg_h264;
+pub mod ffmpeg_h265;
+pub mod ffmpeg_vpx;
+
+mod utils;
 
 pub enum Encoder {
     H264(LibavH264Encoder),
+    H265(LibavH265Encoder),
+    Av1(LibavAv1Encoder),
+    Vp8(LibavVpxEncoder),
+    Vp9(LibavVpxEncoder),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum EncoderOptions {
     H264(ffmpeg_h264::Options),
+    H265(ffmpeg_h265::Options),
+    Av1(ffmpeg_av1::Options),
+    Vp8(ffmpeg_vpx::Options),
+    Vp9(ffmpeg_vpx::Options),
+}
+
+impl EncoderOptions {
+    pub fn resolution(&self) -> Resolution {
+        match self {
+            EncoderOptions::H264(opts) => opts.resolution,
+            EncoderOptions::H265(opts) => opts.resolution,
+            EncoderOptions::Av1(opts) => opts.resolution,
+            EncoderOptions::Vp8(opts) => opts.resolution,
+            EncoderOptions::Vp9(opts) => opts.resolution,
+        }
+    }
+
+    pub fn with_output_id(self, output_id: OutputId) -> Self {
+        match self {
+            EncoderOptions::H264(opts) => {
+                EncoderOptions::H264(ffmpeg_h264::Options { output_id, ..opts })
+            }
+            EncoderOptions::H265(opts) => {
+                EncoderOptions::H265(ffmpeg_h265::Options { output_id, ..opts })
+            }
+            EncoderOptions::Av1(opts) => {
+                EncoderOptions::Av1(ffmpeg_av1::Options { output_id, ..opts })
+            }
+            EncoderOptions::Vp8(opts) => {
+                EncoderOptions::Vp8(ffmpeg_vpx::Options { output_id, ..opts })
+            }
+            EncoderOptions::Vp9(opts) => {
+                EncoderOptions::Vp9(ffmpeg_vpx::Options { output_id, ..opts })
+            }
+        }
+    }
+
+    pub fn with_resolution(self, resolution: Resolution) -> Self {
+        match self {
+            EncoderOptions::H264(opts) => {
+                EncoderOptions::H264(ffmpeg_h264::Options { resolution, ..opts })
+            }
+            EncoderOptions::H265(opts) => {
+                EncoderOptions::H265(ffmpeg_h265::Options { resolution, ..opts })
+            }
+            EncoderOptions::Av1(opts) => {
+                EncoderOptions::Av1(ffmpeg_av1::Options { resolution, ..opts })
+            }
+            EncoderOptions::Vp8(opts) => {
+                EncoderOptions::Vp8(ffmpeg_vpx::Options { resolution, ..opts })
+            }
+            EncoderOptions::Vp9(opts) => {
+                EncoderOptions::Vp9(ffmpeg_vpx::Options { resolution, ..opts })
+            }
+        }
+    }
+
+    pub fn codec(&self) -> Codec {
+        match self {
+            EncoderOptions::H264(_) => Codec::H264,
+            EncoderOptions::H265(_) => Codec::H265,
+            EncoderOptions::Av1(_) => Codec::Av1,
+            EncoderOptions::Vp8(_) => Codec::Vp8,
+            EncoderOptions::Vp9(_) => Codec::Vp9,
+        }
+    }
+
+    /// Target bitrate, `None` if the encoder is not configured with one, e.g. an H264
+    /// encoder in the CRF mode.
+    pub fn bitrate_kbps(&self) -> Option<u32> {
+        match self {
+            EncoderOptions::H264(opts) => match opts.rate_control {
+                ffmpeg_h264::RateControl::Vbr { bitrate }
+                | ffmpeg_h264::RateControl::Cbr { bitrate } => Some((bitrate / 1000) as u32),
+                ffmpeg_h264::RateControl::Crf(_) => None,
+            },
+            EncoderOptions::Vp8(opts) | EncoderOptions::Vp9(opts) => Some(opts.bitrate_kbps),
+            EncoderOptions::H265(_) | EncoderOptions::Av1(_) => None,
+        }
+    }
+
+    /// Returns `None` if the encoder is not configured with a bitrate.
+    pub fn with_bitrate_kbps(self, bitrate_kbps: u32) -> Option<Self> {
+        let bitrate = bitrate_kbps as u64 * 1000;
+        match self {
+            EncoderOptions::H264(opts) => {
+                let rate_control = match opts.rate_control {
+                    ffmpeg_h264::RateControl::Vbr { .. } => {
+                        ffmpeg_h264::RateControl::Vbr { bitrate }
+                    }
+                    ffmpeg_h264::RateControl::Cbr { .. } => {
+                        ffmpeg_h264::RateControl::Cbr { bitrate }
+                    }
+                    ffmpeg_h264::RateControl::Crf(_) => return None,
+                };
+                Some(EncoderOptions::H264(ffmpeg_h264::Options {
+                    rate_control,
+                    ..opts
+                }))
+            }
+            EncoderOptions::Vp8(opts) => Some(EncoderOptions::Vp8(ffmpeg_vpx::Options {
+                bitrate_kbps,
+                ..opts
+            })),
+            EncoderOptions::Vp9(opts) => Some(EncoderOptions::Vp9(ffmpeg_vpx::Options {
+                bitrate_kbps,
+                ..opts
+            })),
+            EncoderOptions::H265(_) | EncoderOptions::Av1(_) => None,
+        }
+    }
 }
 
 impl Encoder {
@@ -25,18 +150,91 @@ impl Encoder {
                 let (encoder, iter) = LibavH264Encoder::new(options)?;
                 Ok((Self::H264(encoder), iter))
             }
+            EncoderOptions::H265(options) => {
+                let (encoder, iter) = LibavH265Encoder::new(options)?;
+                Ok((Self::H265(encoder), iter))
+            }
+            EncoderOptions::Av1(options) => {
+                let (encoder, iter) = LibavAv1Encoder::new(options)?;
+                Ok((Self::Av1(encoder), iter))
+            }
+            EncoderOptions::Vp8(options) => {
+                let (encoder, iter) = LibavVpxEncoder::new(Codec::Vp8, options)?;
+                Ok((Self::Vp8(encoder), iter))
+            }
+            EncoderOptions::Vp9(options) => {
+                let (encoder, iter) = LibavVpxEncoder::new(Codec::Vp9, options)?;
+                Ok((Self::Vp9(encoder), iter))
+            }
         }
     }
 
     pub fn resolution(&self) -> Resolution {
         match self {
             Self::H264(encoder) => encoder.resolution(),
+            Self::H265(encoder) => encoder.resolution(),
+            Self::Av1(encoder) => encoder.resolution(),
+            Self::Vp8(encoder) | Self::Vp9(encoder) => encoder.resolution(),
+        }
+    }
+
+    pub fn update_resolution(&self, resolution: Resolution) -> Result<(), EncoderInitError> {
+        match self {
+            Self::H264(encoder) => encoder.update_resolution(resolution),
+            Self::H265(encoder) => encoder.update_resolution(resolution),
+            Self::Av1(encoder) => encoder.update_resolution(resolution),
+            Self::Vp8(encoder) | Self::Vp9(encoder) => encoder.update_resolution(resolution),
+        }
+    }
+
+    pub fn options(&self) -> EncoderOptions {
+        match self {
+            Self::H264(encoder) => EncoderOptions::H264(encoder.options()),
+            Self::H265(encoder) => EncoderOptions::H265(encoder.options()),
+            Self::Av1(encoder) => EncoderOptions::Av1(encoder.options()),
+            Self::Vp8(encoder) => EncoderOptions::Vp8(encoder.options()),
+            Self::Vp9(encoder) => EncoderOptions::Vp9(encoder.options()),
+        }
+    }
+
+    /// Reopens the encoder with new options. Codec of the encoder can't be changed.
+    pub fn update_options(&self, options: EncoderOptions) -> Result<(), EncoderInitError> {
+        match (self, options) {
+            (Self::H264(encoder), EncoderOptions::H264(options)) => encoder.update_options(options),
+            (Self::H265(encoder), EncoderOptions::H265(options)) => encoder.update_options(options),
+            (Self::Av1(encoder), EncoderOptions::Av1(options)) => encoder.update_options(options),
+            (Self::Vp8(encoder), EncoderOptions::Vp8(options)) => encoder.update_options(options),
+            (Self::Vp9(encoder), EncoderOptions::Vp9(options)) => encoder.update_options(options),
+            (_, options) => Err(EncoderInitError::CodecChange(options.codec())),
+        }
+    }
+
+    pub fn request_keyframe(&self) {
+        match self {
+            Self::H264(encoder) => encoder.request_keyframe(),
+            Self::H265(encoder) => encoder.request_keyframe(),
+            Self::Av1(encoder) => encoder.request_keyframe(),
+            Self::Vp8(encoder) | Self::Vp9(encoder) => encoder.request_keyframe(),
+        }
+    }
+
+    /// Flushes the encoder. Stream of encoded chunks ends after the remaining frames
+    /// are encoded.
+    pub fn stop(&self) {
+        match self {
+            Self::H264(encoder) => encoder.stop(),
+            Self::H265(encoder) => encoder.stop(),
+            Self::Av1(encoder) => encoder.stop(),
+            Self::Vp8(encoder) | Self::Vp9(encoder) => encoder.stop(),
         }
     }
 
     pub fn send_frame(&self, frame: Frame) {
         match self {
             Self::H264(encoder) => encoder.send_frame(frame),
+            Self::H265(encoder) => encoder.send_frame(frame),
+            Self::Av1(encoder) => encoder.send_frame(frame),
+            Self::Vp8(encoder) | Self::Vp9(encoder) => encoder.send_frame(frame),
         }
     }
 }
@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+
+use bytes::Bytes;
+use compositor_render::OutputId;
+use crossbeam_channel::{Receiver, Sender};
+use ffmpeg_next::{
+    codec::{self, Context},
+    encoder,
+    format::{sample, Sample},
+    frame, ChannelLayout, Packet, Rational,
+};
+use log::error;
+
+use crate::{
+    error::EncoderInitError,
+    pipeline::{
+        audio_mixer::{AudioSamples, CHANNELS, SAMPLE_RATE},
+        structs::{AudioCodec, EncodedChunk, EncodedChunkKind},
+    },
+};
+
+use super::utils::{encoder_error, flush, receive_chunks};
+
+const BITRATE: usize = 128_000;
+
+enum Message {
+    Samples(AudioSamples),
+    Stop,
+}
+
+/// Encodes the mixed audio track of an output. Chunks are produced in the raw AAC
+/// format, stream parameters are returned by [`LibavAacEncoder::extradata`].
+pub struct LibavAacEncoder {
+    output_id: OutputId,
+    /// AudioSpecificConfig of the stream.
+    extradata: Bytes,
+    samples_sender: Sender<Message>,
+    encoder_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl LibavAacEncoder {
+    pub fn new(
+        output_id: OutputId,
+    ) -> Result<(Self, Box<dyn Iterator<Item = EncodedChunk> + Send>), EncoderInitError> {
+        let (samples_sender, samples_receiver) = crossbeam_channel::unbounded();
+        let (packet_sender, packet_receiver) = crossbeam_channel::unbounded();
+        let (result_sender, result_receiver) = crossbeam_channel::bounded(0);
+
+        let output_id_clone = output_id.clone();
+        let encoder_thread = std::thread::Builder::new()
+            .name(format!("AAC encoder thread for output {output_id}"))
+            .spawn(move || {
+                if let Err(err) = Self::encoder_thread(
+                    &output_id_clone,
+                    samples_receiver,
+                    packet_sender,
+                    &result_sender,
+                ) {
+                    result_sender.send(Err(err)).unwrap();
+                }
+            })
+            .unwrap();
+
+        let extradata = result_receiver.recv().unwrap()?;
+
+        Ok((
+            Self {
+                output_id,
+                extradata,
+                samples_sender,
+                encoder_thread: Some(encoder_thread),
+            },
+            Box::new(packet_receiver.into_iter()),
+        ))
+    }
+
+    pub fn extradata(&self) -> Bytes {
+        self.extradata.clone()
+    }
+
+    pub fn send_samples(&self, samples: AudioSamples) {
+        if let Err(e) = self.samples_sender.send(Message::Samples(samples)) {
+            error!("[output {}] Failed to send samples to the AAC encoder: {e}. It's possible the encoder thread has crashed.", self.output_id);
+        }
+    }
+
+    /// Flushes the encoder and ends the stream of encoded chunks, without waiting for
+    /// the encoder to be dropped.
+    pub fn stop(&self) {
+        // Fails only if the encoder thread has already exited.
+        let _ = self.samples_sender.send(Message::Stop);
+    }
+
+    fn encoder_thread(
+        output_id: &OutputId,
+        samples_receiver: Receiver<Message>,
+        packet_sender: Sender<EncodedChunk>,
+        result_sender: &Sender<Result<Bytes, EncoderInitError>>,
+    ) -> Result<(), EncoderInitError> {
+        let mut encoder = Self::open_encoder()?;
+        let frame_size = encoder.frame_size() as usize;
+
+        let extradata = unsafe {
+            let encoder = &*encoder.as_ptr();
+            match encoder.extradata.is_null() {
+                true => Bytes::new(),
+                false => Bytes::copy_from_slice(std::slice::from_raw_parts(
+                    encoder.extradata,
+                    encoder.extradata_size as usize,
+                )),
+            }
+        };
+        result_sender.send(Ok(extradata)).unwrap();
+
+        let kind = EncodedChunkKind::Audio(AudioCodec::Aac);
+        let mut packet = Packet::empty();
+        let mut buffer = VecDeque::new();
+        // Timestamps are counted from the first samples, so they don't drift when the
+        // encoder frame size is not aligned to the mixed windows.
+        let mut first_pts = None;
+        let mut encoded_samples = 0;
+
+        while let Ok(Message::Samples(samples)) = samples_receiver.recv() {
+            first_pts.get_or_insert((samples.pts.as_secs_f64() * 90000.0) as i64);
+            buffer.extend(samples.samples);
+
+            while buffer.len() >= frame_size * CHANNELS {
+                let mut av_frame = frame::Audio::new(
+                    Sample::F32(sample::Type::Planar),
+                    frame_size,
+                    ChannelLayout::STEREO,
+                );
+                av_frame.set_rate(SAMPLE_RATE);
+                for index in 0..frame_size {
+                    for channel in 0..CHANNELS {
+                        let sample = buffer.pop_front().unwrap() as f32 / 32768.0;
+                        let offset = index * 4;
+                        av_frame.data_mut(channel)[offset..offset + 4]
+                            .copy_from_slice(&sample.to_ne_bytes());
+                    }
+                }
+                let pts = first_pts.unwrap_or(0) + encoded_samples * 90000 / SAMPLE_RATE as i64;
+                av_frame.set_pts(Some(pts));
+                encoded_samples += frame_size as i64;
+
+                if let Err(e) = encoder.send_frame(&av_frame) {
+                    encoder_error(output_id, e);
+                    continue;
+                }
+                receive_chunks(&mut encoder, &mut packet, kind, output_id, &packet_sender);
+            }
+        }
+
+        // Samples that don't fill the whole frame are dropped.
+        flush(&mut encoder, &mut packet, kind, output_id, &packet_sender);
+
+        Ok(())
+    }
+
+    fn open_encoder() -> Result<encoder::audio::Encoder, EncoderInitError> {
+        let codec = ffmpeg_next::codec::encoder::find(AudioCodec::Aac.into())
+            .ok_or(EncoderInitError::NoCodec)?;
+
+        let mut encoder = Context::new().encoder().audio()?;
+        encoder.set_time_base(Rational::new(1, 90000));
+        encoder.set_rate(SAMPLE_RATE as i32);
+        encoder.set_format(Sample::F32(sample::Type::Planar));
+        encoder.set_channel_layout(ChannelLayout::STEREO);
+        encoder.set_bit_rate(BITRATE);
+        // Stream parameters are needed in the MP4 and FLV headers.
+        encoder.set_flags(codec::Flags::GLOBAL_HEADER);
+
+        Ok(encoder.open_as(codec)?)
+    }
+}
+
+impl Drop for LibavAacEncoder {
+    fn drop(&mut self) {
+        self.stop();
+        match self.encoder_thread.take() {
+            Some(handle) => handle.join().unwrap(),
+            None => error!(
+                "[output {}] AAC encoder thread was already joined. This should not happen.",
+                self.output_id
+            ),
+        }
+    }
+}
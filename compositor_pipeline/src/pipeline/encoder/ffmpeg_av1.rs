@@ -0,0 +1,88 @@
+use compositor_render::{OutputId, Resolution};
+use ffmpeg_next::{format::Pixel, Dictionary};
+
+use crate::{
+    error::EncoderInitError,
+    pipeline::structs::{Codec, EncodedChunk},
+};
+
+use super::utils::{
+    video_encoder_context, OpenedEncoder, VideoCodec, VideoEncoder, VideoEncoderOptions,
+};
+
+/// Default value of libaom's `cpu-used` option. Higher values trade quality for speed,
+/// 8 is the fastest setting and the only one that is reasonable for real-time encoding
+/// of higher resolutions.
+pub const DEFAULT_SPEED: u8 = 8;
+
+pub const MAX_SPEED: u8 = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Options {
+    /// Maps to libaom `cpu-used` option (0-8).
+    pub speed: u8,
+    pub resolution: Resolution,
+    pub output_id: OutputId,
+}
+
+/// Encodes AV1 with libaom.
+pub type LibavAv1Encoder = VideoEncoder<Av1Codec>;
+
+impl LibavAv1Encoder {
+    pub fn new(
+        options: Options,
+    ) -> Result<(Self, Box<dyn Iterator<Item = EncodedChunk> + Send>), EncoderInitError> {
+        VideoEncoder::spawn(Av1Codec, options)
+    }
+}
+
+impl VideoEncoderOptions for Options {
+    fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    fn output_id(&self) -> &OutputId {
+        &self.output_id
+    }
+
+    fn with_resolution(self, resolution: Resolution) -> Self {
+        Self { resolution, ..self }
+    }
+}
+
+pub struct Av1Codec;
+
+impl VideoCodec for Av1Codec {
+    type Options = Options;
+
+    fn codec(&self) -> Codec {
+        Codec::Av1
+    }
+
+    fn open_encoder(&self, options: &Options) -> Result<OpenedEncoder, EncoderInitError> {
+        let codec = ffmpeg_next::codec::encoder::find_by_name("libaom-av1")
+            .ok_or(EncoderInitError::NoCodec)?;
+
+        let encoder = video_encoder_context(options.resolution, Pixel::YUV420P)?;
+
+        let speed = options.speed.min(MAX_SPEED).to_string();
+        Ok(encoder
+            .open_as_with(
+                codec,
+                Dictionary::from_iter([
+                    // Optimize for low latency instead of compression efficiency.
+                    ("usage", "realtime"),
+                    ("cpu-used", speed.as_str()),
+                    // Lookahead delays output by the number of frames, disable it for live streams.
+                    ("lag-in-frames", "0"),
+                    // Constant quality mode - libaom requires either bitrate or crf to be set.
+                    ("crf", "30"),
+                    ("b", "0"),
+                    ("row-mt", "1"),
+                    // Match the x264 default GOP size.
+                    ("g", "250"),
+                ]),
+            )?
+            .into())
+    }
+}
@@ -1,15 +1,13 @@
-use compositor_render::{Frame, OutputId, Resolution};
-use crossbeam_channel::{Receiver, Sender};
-use ffmpeg_next::{
-    codec::{Context, Id},
-    format::Pixel,
-    frame, Dictionary, Packet, Rational,
-};
-use log::{error, warn};
+use compositor_render::{OutputId, Resolution};
+use ffmpeg_next::{codec::Id, encoder, format::Pixel, software::scaling, Dictionary};
 
 use crate::{
     error::EncoderInitError,
-    pipeline::structs::{Codec, EncodedChunk, EncodedChunkKind},
+    pipeline::structs::{Codec, EncodedChunk},
+};
+
+use super::utils::{
+    video_encoder_context, OpenedEncoder, VideoCodec, VideoEncoder, VideoEncoderOptions,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
@@ -28,7 +26,7 @@ pub enum EncoderPreset {
 }
 
 impl EncoderPreset {
-    fn to_str(&self) -> &'static str {
+    pub(super) fn to_str(&self) -> &'static str {
         match self {
             EncoderPreset::Ultrafast => "ultrafast",
             EncoderPreset::Superfast => "superfast",
@@ -43,6 +41,19 @@ impl EncoderPreset {
         }
     }
 
+    /// NVENC presets range from `p1` (fastest) to `p7` (best quality).
+    fn nvenc_preset(&self) -> &'static str {
+        match self {
+            EncoderPreset::Ultrafast | EncoderPreset::Superfast => "p1",
+            EncoderPreset::Veryfast => "p2",
+            EncoderPreset::Faster | EncoderPreset::Fast => "p3",
+            EncoderPreset::Medium => "p4",
+            EncoderPreset::Slow => "p5",
+            EncoderPreset::Slower => "p6",
+            EncoderPreset::Veryslow | EncoderPreset::Placebo => "p7",
+        }
+    }
+
     fn default_partitions(&self) -> &'static str {
         match self {
             EncoderPreset::Ultrafast => "none",
@@ -75,247 +86,365 @@ impl EncoderPreset {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Options {
-    pub preset: EncoderPreset,
-    pub resolution: Resolution,
-    pub output_id: OutputId,
-}
-
-enum Message {
-    Frame(Frame),
-    Stop,
+pub enum RateControl {
+    /// Quality-based VBR (0-51)
+    Crf(u8),
+    /// Average bitrate in bits per second.
+    Vbr { bitrate: u64 },
+    /// Constant bitrate in bits per second.
+    Cbr { bitrate: u64 },
 }
 
-pub struct LibavH264Encoder {
-    resolution: Resolution,
-    output_id: OutputId,
-    frame_sender: Sender<Message>,
-    encoder_thread: Option<std::thread::JoinHandle<()>>,
+impl Default for RateControl {
+    fn default() -> Self {
+        Self::Crf(23)
+    }
 }
 
-impl LibavH264Encoder {
-    pub fn new(
-        options: Options,
-    ) -> Result<(Self, Box<dyn Iterator<Item = EncodedChunk> + Send>), EncoderInitError> {
-        let (frame_sender, frame_receiver) = crossbeam_channel::unbounded();
-        let (packet_sender, packet_receiver) = crossbeam_channel::unbounded();
-        let (result_sender, result_receiver) = crossbeam_channel::bounded(0);
-
-        let options_clone = options.clone();
-
-        let encoder_thread = std::thread::Builder::new()
-            .name(format!("Encoder thread for output {}", options.output_id))
-            .spawn(move || {
-                match Self::encoder_thread(
-                    options_clone,
-                    frame_receiver,
-                    packet_sender,
-                    &result_sender,
-                ) {
-                    Ok(_) => log::debug!("Encoder thread exited normally."),
-                    Err(e) => result_sender.send(Err(e)).unwrap(),
-                }
-            })
-            .unwrap();
-
-        result_receiver.recv().unwrap()?;
-
-        Ok((
-            Self {
-                encoder_thread: Some(encoder_thread),
-                frame_sender,
-                output_id: options.output_id,
-                resolution: options.resolution,
-            },
-            Box::new(packet_receiver.into_iter()),
-        ))
+impl RateControl {
+    fn ffmpeg_options(&self) -> Vec<(&'static str, String)> {
+        match self {
+            RateControl::Crf(crf) => vec![("crf", crf.to_string())],
+            RateControl::Vbr { bitrate } => vec![("b", bitrate.to_string())],
+            RateControl::Cbr { bitrate } => vec![
+                ("b", bitrate.to_string()),
+                ("minrate", bitrate.to_string()),
+                ("maxrate", bitrate.to_string()),
+                // VBV buffer of 1 second
+                ("bufsize", bitrate.to_string()),
+                // Pad the stream with filler data if the encoder undershoots
+                ("nal-hrd", "cbr".to_string()),
+            ],
+        }
     }
 
-    pub fn send_frame(&self, frame: Frame) {
-        match self.frame_sender.send(Message::Frame(frame)) {
-            Ok(_) => {}
-            Err(e) => {
-                error!("[output {}] Failed to send frame to encoder: {e}. It's possible the encoder thread has crashed.", self.output_id);
+    /// NVENC has no CRF mode, constant quality is configured with `cq` and no bitrate limit.
+    fn nvenc_options(&self) -> Vec<(&'static str, String)> {
+        match self {
+            RateControl::Crf(crf) => vec![
+                ("rc", "vbr".to_string()),
+                ("cq", crf.to_string()),
+                ("b", "0".to_string()),
+            ],
+            RateControl::Vbr { bitrate } => {
+                vec![("rc", "vbr".to_string()), ("b", bitrate.to_string())]
             }
+            RateControl::Cbr { bitrate } => vec![
+                ("rc", "cbr".to_string()),
+                ("b", bitrate.to_string()),
+                ("maxrate", bitrate.to_string()),
+                ("bufsize", bitrate.to_string()),
+            ],
         }
     }
+}
 
-    pub fn resolution(&self) -> Resolution {
-        self.resolution
+/// Implementation used to encode H264.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum H264Encoder {
+    /// Software encoder.
+    #[default]
+    Libx264,
+    /// Hardware encoder on NVIDIA GPUs.
+    Nvenc,
+}
+
+impl H264Encoder {
+    fn ffmpeg_name(&self) -> &'static str {
+        match self {
+            H264Encoder::Libx264 => "libx264",
+            H264Encoder::Nvenc => "h264_nvenc",
+        }
     }
+}
 
-    fn encoder_thread(
-        options: Options,
-        frame_receiver: Receiver<Message>,
-        packet_sender: Sender<EncodedChunk>,
-        result_sender: &Sender<Result<(), EncoderInitError>>,
-    ) -> Result<(), EncoderInitError> {
-        let codec = ffmpeg_next::codec::encoder::find(Id::H264).ok_or(EncoderInitError::NoCodec)?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum H264Profile {
+    Baseline,
+    Main,
+    High,
+    /// High 4:4:4 Predictive, required for [`PixelFormat::Yuv444p`].
+    High444,
+}
 
-        let mut encoder = Context::new().encoder().video()?;
-
-        let pts_unit_secs = Rational::new(1, 90000);
-        encoder.set_time_base(pts_unit_secs);
-        encoder.set_format(Pixel::YUV420P);
-        encoder.set_width(options.resolution.width as u32);
-        encoder.set_height(options.resolution.height as u32);
-
-        let mut encoder = encoder.open_as_with(
-            codec,
-            // TODO: audit settings bellow
-            // Those values are copied from somewhere, they have to be set because libx264
-            // is throwing an error if it detects default ffmpeg settings.
-            Dictionary::from_iter([
-                ("preset", options.preset.to_str()),
-                // Quality-based VBR (0-51)
-                ("crf", "23"),
-                // Override ffmpeg defaults from https://github.com/mirror/x264/blob/eaa68fad9e5d201d42fde51665f2d137ae96baf0/encoder/encoder.c#L674
-                // QP curve compression - libx264 defaults to 0.6 (in case of tune=grain to 0.8)
-                ("qcomp", "0.6"),
-                //  Maximum motion vector search range - libx264 defaults to 16 (in case of placebo
-                //  or veryslow preset to 24)
-                ("me_range", "16"),
-                // Max QP step - libx264 defaults to 4
-                ("qdiff", "4"),
-                // Min QP - libx264 defaults to 0
-                ("qmin", "0"),
-                // Max QP - libx264 defaults to QP_MAX = 69
-                ("qmax", "69"),
-                //  Maximum GOP (Group of Pictures) size - libx264 defaults to 250
-                ("g", "250"),
-                // QP factor between I and P frames - libx264 defaults to 1.4 (in case of tune=grain to 1.1)
-                ("i_qfactor", "1.4"),
-                // QP factor between P and B frames - libx264 defaults to 1.4 (in case of tune=grain to 1.1)
-                ("f_pb_factor", "1.3"),
-                // A comma-separated list of partitions to consider. Possible values: p8x8, p4x4, b8x8, i8x8, i4x4, none, all
-                ("partitions", options.preset.default_partitions()),
-                // Subpixel motion estimation and mode decision (decision quality: 1=fast, 11=best)
-                ("subq", options.preset.default_subq_mode()),
-            ]),
-        )?;
+impl H264Profile {
+    /// `profile_idc` value, libx264 disables features that are not allowed in the profile.
+    fn profile_idc(&self) -> i32 {
+        match self {
+            H264Profile::Baseline => 66,
+            H264Profile::Main => 77,
+            H264Profile::High => 100,
+            H264Profile::High444 => 244,
+        }
+    }
 
-        result_sender.send(Ok(())).unwrap();
+    /// Value of the `profile` option of NVENC.
+    fn nvenc_name(&self) -> &'static str {
+        match self {
+            H264Profile::Baseline => "baseline",
+            H264Profile::Main => "main",
+            H264Profile::High => "high",
+            H264Profile::High444 => "high444p",
+        }
+    }
+}
 
-        let mut packet = Packet::empty();
+/// Pixel format of frames passed to the encoder. Frames are rendered as YUV420P and
+/// converted to other formats before they are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PixelFormat {
+    #[default]
+    Yuv420p,
+    Nv12,
+    /// Chroma is upsampled from the rendered frame, so it doesn't add detail, but some
+    /// tools require 4:4:4 streams. Only supported by the High 4:4:4 profile.
+    Yuv444p,
+}
 
-        loop {
-            let frame = match frame_receiver.recv() {
-                Ok(Message::Frame(f)) => f,
-                Ok(Message::Stop) => break,
-                Err(_) => break,
-            };
+impl PixelFormat {
+    fn pixel(&self) -> Pixel {
+        match self {
+            PixelFormat::Yuv420p => Pixel::YUV420P,
+            PixelFormat::Nv12 => Pixel::NV12,
+            PixelFormat::Yuv444p => Pixel::YUV444P,
+        }
+    }
+}
 
-            if frame_receiver.len() > 20 {
-                warn!("Dropping frame: render queue is too long.",);
-                continue;
-            }
+/// H264 level, e.g. `3.1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct H264Level {
+    level_idc: u8,
+    /// Maximum frame size in macroblocks.
+    max_frame_size: usize,
+}
 
-            let mut av_frame = frame::Video::new(
-                Pixel::YUV420P,
-                options.resolution.width as u32,
-                options.resolution.height as u32,
-            );
-
-            if let Err(e) = frame_into_av(frame, &mut av_frame) {
-                error!(
-                    "Failed to convert a frame to an ffmpeg frame: {}. Dropping",
-                    e.0
-                );
-                continue;
-            }
+/// Levels with `level_idc` and maximum frame size from Table A-1 of the H264 spec.
+const H264_LEVELS: [(&str, u8, usize); 20] = [
+    ("1", 10, 99),
+    ("1b", 9, 99),
+    ("1.1", 11, 396),
+    ("1.2", 12, 396),
+    ("1.3", 13, 396),
+    ("2", 20, 396),
+    ("2.1", 21, 792),
+    ("2.2", 22, 1620),
+    ("3", 30, 1620),
+    ("3.1", 31, 3600),
+    ("3.2", 32, 5120),
+    ("4", 40, 8192),
+    ("4.1", 41, 8192),
+    ("4.2", 42, 8704),
+    ("5", 50, 22080),
+    ("5.1", 51, 36864),
+    ("5.2", 52, 36864),
+    ("6", 60, 139264),
+    ("6.1", 61, 139264),
+    ("6.2", 62, 139264),
+];
+
+impl H264Level {
+    /// Returns `None` if the level is not defined by the spec.
+    pub fn new(level: &str) -> Option<Self> {
+        H264_LEVELS.iter().find(|(name, _, _)| *name == level).map(
+            |(_, level_idc, max_frame_size)| Self {
+                level_idc: *level_idc,
+                max_frame_size: *max_frame_size,
+            },
+        )
+    }
 
-            if let Err(e) = encoder.send_frame(&av_frame) {
-                error!("Encoder error: {e}.");
-                continue;
-            }
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        H264_LEVELS.iter().map(|(name, _, _)| *name)
+    }
 
-            loop {
-                match encoder.receive_packet(&mut packet) {
-                    Ok(_) => match EncodedChunk::from_av_packet(
-                        &packet,
-                        EncodedChunkKind::Video(Codec::H264),
-                    ) {
-                        Ok(chunk) => {
-                            packet_sender.send(chunk).unwrap();
-                        }
-                        Err(e) => {
-                            warn!("failed to parse an ffmpeg packet received from encoder: {e}",);
-                            break;
-                        }
-                    },
-
-                    Err(ffmpeg_next::Error::Other {
-                        errno: ffmpeg_next::error::EAGAIN,
-                    }) => break, // encoder needs more frames to produce a packet
-
-                    Err(e) => {
-                        error!("Encoder error: {e}.");
-                        break;
-                    }
-                }
-            }
-        }
+    fn name(&self) -> &'static str {
+        H264_LEVELS
+            .iter()
+            .find(|(_, level_idc, _)| *level_idc == self.level_idc)
+            .map(|(name, _, _)| *name)
+            .unwrap_or("auto")
+    }
 
-        Ok(())
+    /// Returns `false` if the frame size exceeds the limit of the level.
+    pub fn supports_resolution(&self, resolution: Resolution) -> bool {
+        let macroblocks = resolution.width.div_ceil(16) * resolution.height.div_ceil(16);
+        macroblocks <= self.max_frame_size
     }
 }
 
-impl Drop for LibavH264Encoder {
-    fn drop(&mut self) {
-        self.frame_sender.send(Message::Stop).unwrap();
-        match self.encoder_thread.take() {
-            Some(handle) => handle.join().unwrap(),
-            None => error!(
-                "[output {}] Encoder thread was already joined. This should not happen.",
-                self.output_id
-            ),
-        }
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Options {
+    pub preset: EncoderPreset,
+    pub rate_control: RateControl,
+    /// If not specified, libx264 selects the profile based on the enabled features.
+    pub profile: Option<H264Profile>,
+    /// If not specified, libx264 selects the level based on the resolution and bitrate.
+    pub level: Option<H264Level>,
+    /// Fixed distance between keyframes. If not specified, libx264 places keyframes on
+    /// scene changes, at most 250 frames apart.
+    pub keyframe_interval: Option<u32>,
+    pub encoder: H264Encoder,
+    pub pixel_format: PixelFormat,
+    pub resolution: Resolution,
+    pub output_id: OutputId,
+}
+
+/// Encodes H264 with libx264 or NVENC.
+pub type LibavH264Encoder = VideoEncoder<H264Codec>;
+
+impl LibavH264Encoder {
+    pub fn new(
+        options: Options,
+    ) -> Result<(Self, Box<dyn Iterator<Item = EncodedChunk> + Send>), EncoderInitError> {
+        VideoEncoder::spawn(H264Codec, options)
     }
 }
 
-#[derive(Debug)]
-struct FrameConversionError(String);
-
-fn frame_into_av(frame: Frame, av_frame: &mut frame::Video) -> Result<(), FrameConversionError> {
-    let expected_y_plane_size = (av_frame.plane_width(0) * av_frame.plane_height(0)) as usize;
-    let expected_u_plane_size = (av_frame.plane_width(1) * av_frame.plane_height(1)) as usize;
-    let expected_v_plane_size = (av_frame.plane_width(2) * av_frame.plane_height(2)) as usize;
-    if expected_y_plane_size != frame.data.y_plane.len() {
-        return Err(FrameConversionError(format!(
-            "Y plane is a wrong size, expected: {} received: {}",
-            expected_y_plane_size,
-            frame.data.y_plane.len()
-        )));
+impl VideoEncoderOptions for Options {
+    fn resolution(&self) -> Resolution {
+        self.resolution
     }
-    if expected_u_plane_size != frame.data.u_plane.len() {
-        return Err(FrameConversionError(format!(
-            "U plane is a wrong size, expected: {} received: {}",
-            expected_u_plane_size,
-            frame.data.u_plane.len()
-        )));
+
+    fn output_id(&self) -> &OutputId {
+        &self.output_id
     }
-    if expected_v_plane_size != frame.data.v_plane.len() {
-        return Err(FrameConversionError(format!(
-            "V plane is a wrong size, expected: {} received: {}",
-            expected_v_plane_size,
-            frame.data.v_plane.len()
-        )));
+
+    fn with_resolution(self, resolution: Resolution) -> Self {
+        Self { resolution, ..self }
     }
+}
+
+pub struct H264Codec;
 
-    av_frame.set_pts(Some((frame.pts.as_secs_f64() * 90000.0) as i64));
+impl VideoCodec for H264Codec {
+    type Options = Options;
 
-    write_plane_to_av(av_frame, 0, &frame.data.y_plane);
-    write_plane_to_av(av_frame, 1, &frame.data.u_plane);
-    write_plane_to_av(av_frame, 2, &frame.data.v_plane);
+    fn codec(&self) -> Codec {
+        Codec::H264
+    }
 
-    Ok(())
+    fn open_encoder(&self, options: &Options) -> Result<OpenedEncoder, EncoderInitError> {
+        let encoder = match options.encoder {
+            H264Encoder::Libx264 => Self::open_x264_encoder(options)?,
+            H264Encoder::Nvenc => Self::open_nvenc_encoder(options)?,
+        };
+        let converter = Self::pixel_format_converter(options)?
+            .map(|converter| (converter, options.pixel_format.pixel()));
+        Ok(OpenedEncoder { encoder, converter })
+    }
 }
 
-fn write_plane_to_av(frame: &mut frame::Video, plane: usize, data: &[u8]) {
-    let stride = frame.stride(plane);
-    let width = frame.plane_width(plane) as usize;
+impl H264Codec {
+    fn open_x264_encoder(options: &Options) -> Result<encoder::video::Encoder, EncoderInitError> {
+        let codec = ffmpeg_next::codec::encoder::find(Id::H264).ok_or(EncoderInitError::NoCodec)?;
+
+        let mut encoder = video_encoder_context(options.resolution, options.pixel_format.pixel())?;
+        // libx264 maps these fields to its profile and level options.
+        unsafe {
+            let encoder = &mut *encoder.as_mut_ptr();
+            if let Some(profile) = options.profile {
+                encoder.profile = profile.profile_idc();
+            }
+            if let Some(level) = options.level {
+                encoder.level = level.level_idc.into();
+            }
+        }
+
+        // TODO: audit settings bellow
+        // Those values are copied from somewhere, they have to be set because libx264
+        // is throwing an error if it detects default ffmpeg settings.
+        let mut encoder_options = Dictionary::from_iter([
+            ("preset", options.preset.to_str()),
+            // Override ffmpeg defaults from https://github.com/mirror/x264/blob/eaa68fad9e5d201d42fde51665f2d137ae96baf0/encoder/encoder.c#L674
+            // QP curve compression - libx264 defaults to 0.6 (in case of tune=grain to 0.8)
+            ("qcomp", "0.6"),
+            //  Maximum motion vector search range - libx264 defaults to 16 (in case of placebo
+            //  or veryslow preset to 24)
+            ("me_range", "16"),
+            // Max QP step - libx264 defaults to 4
+            ("qdiff", "4"),
+            // Min QP - libx264 defaults to 0
+            ("qmin", "0"),
+            // Max QP - libx264 defaults to QP_MAX = 69
+            ("qmax", "69"),
+            //  Maximum GOP (Group of Pictures) size - libx264 defaults to 250
+            ("g", "250"),
+            // QP factor between I and P frames - libx264 defaults to 1.4 (in case of tune=grain to 1.1)
+            ("i_qfactor", "1.4"),
+            // QP factor between P and B frames - libx264 defaults to 1.4 (in case of tune=grain to 1.1)
+            ("f_pb_factor", "1.3"),
+            // A comma-separated list of partitions to consider. Possible values: p8x8, p4x4, b8x8, i8x8, i4x4, none, all
+            ("partitions", options.preset.default_partitions()),
+            // Subpixel motion estimation and mode decision (decision quality: 1=fast, 11=best)
+            ("subq", options.preset.default_subq_mode()),
+        ]);
+        for (key, value) in options.rate_control.ffmpeg_options() {
+            encoder_options.set(key, &value);
+        }
+        if let Some(keyframe_interval) = options.keyframe_interval {
+            let keyframe_interval = keyframe_interval.to_string();
+            encoder_options.set("g", &keyframe_interval);
+            encoder_options.set("keyint_min", &keyframe_interval);
+            // Disable scene cut detection, so keyframes are only placed every `keyframe_interval` frames.
+            encoder_options.set("sc_threshold", "0");
+        }
+
+        Ok(encoder.open_as_with(codec, encoder_options)?)
+    }
+
+    /// Returns `None` if rendered frames are passed to the encoder without conversion.
+    fn pixel_format_converter(
+        options: &Options,
+    ) -> Result<Option<scaling::Context>, EncoderInitError> {
+        if options.pixel_format == PixelFormat::Yuv420p {
+            return Ok(None);
+        }
+        let Resolution { width, height } = options.resolution;
+        let converter = scaling::Context::get(
+            Pixel::YUV420P,
+            width as u32,
+            height as u32,
+            options.pixel_format.pixel(),
+            width as u32,
+            height as u32,
+            scaling::Flags::BILINEAR,
+        )?;
+        Ok(Some(converter))
+    }
+
+    /// Fails instead of falling back to libx264 if NVENC is not available, e.g. when
+    /// ffmpeg was built without it or there is no NVIDIA GPU.
+    fn open_nvenc_encoder(options: &Options) -> Result<encoder::video::Encoder, EncoderInitError> {
+        let name = options.encoder.ffmpeg_name();
+        let codec = ffmpeg_next::codec::encoder::find_by_name(name)
+            .ok_or(EncoderInitError::HardwareEncoderNotAvailable(name, None))?;
+
+        let encoder = video_encoder_context(options.resolution, options.pixel_format.pixel())?;
+
+        let mut encoder_options = Dictionary::from_iter([
+            ("preset", options.preset.nvenc_preset()),
+            ("tune", "ll"),
+            // Keyframes requested with `picture::Type::I` are encoded as IDR frames.
+            ("forced-idr", "1"),
+            ("g", "250"),
+        ]);
+        for (key, value) in options.rate_control.nvenc_options() {
+            encoder_options.set(key, &value);
+        }
+        if let Some(profile) = options.profile {
+            encoder_options.set("profile", profile.nvenc_name());
+        }
+        if let Some(level) = options.level {
+            encoder_options.set("level", level.name());
+        }
+        if let Some(keyframe_interval) = options.keyframe_interval {
+            encoder_options.set("g", &keyframe_interval.to_string());
+            encoder_options.set("no-scenecut", "1");
+        }
 
-    data.chunks(width)
-        .zip(frame.data_mut(plane).chunks_mut(stride))
-        .for_each(|(data, target)| target[..width].copy_from_slice(data));
+        encoder
+            .open_as_with(codec, encoder_options)
+            .map_err(|err| EncoderInitError::HardwareEncoderNotAvailable(name, Some(err)))
+    }
 }
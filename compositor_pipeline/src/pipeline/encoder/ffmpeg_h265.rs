@@ -0,0 +1,77 @@
+use compositor_render::{OutputId, Resolution};
+use ffmpeg_next::{format::Pixel, Dictionary};
+
+use crate::{
+    error::EncoderInitError,
+    pipeline::structs::{Codec, EncodedChunk},
+};
+
+use super::{
+    ffmpeg_h264::EncoderPreset,
+    utils::{video_encoder_context, OpenedEncoder, VideoCodec, VideoEncoder, VideoEncoderOptions},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Options {
+    pub preset: EncoderPreset,
+    pub resolution: Resolution,
+    pub output_id: OutputId,
+}
+
+/// Encodes H265 with libx265.
+pub type LibavH265Encoder = VideoEncoder<H265Codec>;
+
+impl LibavH265Encoder {
+    pub fn new(
+        options: Options,
+    ) -> Result<(Self, Box<dyn Iterator<Item = EncodedChunk> + Send>), EncoderInitError> {
+        VideoEncoder::spawn(H265Codec, options)
+    }
+}
+
+impl VideoEncoderOptions for Options {
+    fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    fn output_id(&self) -> &OutputId {
+        &self.output_id
+    }
+
+    fn with_resolution(self, resolution: Resolution) -> Self {
+        Self { resolution, ..self }
+    }
+}
+
+pub struct H265Codec;
+
+impl VideoCodec for H265Codec {
+    type Options = Options;
+
+    fn codec(&self) -> Codec {
+        Codec::H265
+    }
+
+    fn open_encoder(&self, options: &Options) -> Result<OpenedEncoder, EncoderInitError> {
+        let codec = ffmpeg_next::codec::encoder::find_by_name("libx265")
+            .ok_or(EncoderInitError::NoCodec)?;
+
+        let encoder = video_encoder_context(options.resolution, Pixel::YUV420P)?;
+
+        Ok(encoder
+            .open_as_with(
+                codec,
+                Dictionary::from_iter([
+                    ("preset", options.preset.to_str()),
+                    // Quality-based VBR (0-51), libx265 default.
+                    ("crf", "28"),
+                    // Match the x264 default GOP size.
+                    ("g", "250"),
+                    // Without global headers libx265 writes VPS/SPS/PPS only before the first
+                    // keyframe. Repeat them so receivers can join the stream at any keyframe.
+                    ("x265-params", "repeat-headers=1:log-level=error"),
+                ]),
+            )?
+            .into())
+    }
+}
@@ -0,0 +1,96 @@
+use compositor_render::{OutputId, Resolution};
+use ffmpeg_next::{format::Pixel, Dictionary};
+
+use crate::{
+    error::EncoderInitError,
+    pipeline::structs::{Codec, EncodedChunk},
+};
+
+use super::utils::{
+    video_encoder_context, OpenedEncoder, VideoCodec, VideoEncoder, VideoEncoderOptions,
+};
+
+/// Default value of libvpx's `cpu-used` option. In the real-time mode, values 5-8 are
+/// recommended for live streams, lower values are often too slow for higher resolutions.
+pub const DEFAULT_SPEED: u8 = 6;
+
+pub const MAX_SPEED: u8 = 8;
+
+pub const DEFAULT_BITRATE_KBPS: u32 = 2500;
+
+/// Options of a VP8 or VP9 encoder. WebRTC receivers adapt to the bitrate, so libvpx is
+/// used in a bitrate mode instead of a constant quality mode.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Options {
+    /// Maps to libvpx `cpu-used` option (0-8).
+    pub speed: u8,
+    pub bitrate_kbps: u32,
+    pub resolution: Resolution,
+    pub output_id: OutputId,
+}
+
+/// Encodes VP8 or VP9 with libvpx.
+pub type LibavVpxEncoder = VideoEncoder<VpxCodec>;
+
+impl LibavVpxEncoder {
+    /// `codec` has to be [`Codec::Vp8`] or [`Codec::Vp9`].
+    pub fn new(
+        codec: Codec,
+        options: Options,
+    ) -> Result<(Self, Box<dyn Iterator<Item = EncodedChunk> + Send>), EncoderInitError> {
+        VideoEncoder::spawn(VpxCodec(codec), options)
+    }
+}
+
+impl VideoEncoderOptions for Options {
+    fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    fn output_id(&self) -> &OutputId {
+        &self.output_id
+    }
+
+    fn with_resolution(self, resolution: Resolution) -> Self {
+        Self { resolution, ..self }
+    }
+}
+
+pub struct VpxCodec(Codec);
+
+impl VideoCodec for VpxCodec {
+    type Options = Options;
+
+    fn codec(&self) -> Codec {
+        self.0
+    }
+
+    fn open_encoder(&self, options: &Options) -> Result<OpenedEncoder, EncoderInitError> {
+        let encoder_name = match self.0 {
+            Codec::Vp8 => "libvpx",
+            Codec::Vp9 => "libvpx-vp9",
+            _ => return Err(EncoderInitError::NoCodec),
+        };
+        let libvpx = ffmpeg_next::codec::encoder::find_by_name(encoder_name)
+            .ok_or(EncoderInitError::NoCodec)?;
+
+        let mut encoder = video_encoder_context(options.resolution, Pixel::YUV420P)?;
+        encoder.set_bit_rate(options.bitrate_kbps as usize * 1000);
+
+        let speed = options.speed.min(MAX_SPEED).to_string();
+        let mut encoder_options = Dictionary::from_iter([
+            // Optimize for low latency instead of compression efficiency.
+            ("deadline", "realtime"),
+            ("cpu-used", speed.as_str()),
+            // Lookahead delays output by the number of frames, disable it for live streams.
+            ("lag-in-frames", "0"),
+            // Match the x264 default GOP size.
+            ("g", "250"),
+        ]);
+        if self.0 == Codec::Vp9 {
+            encoder_options.set("row-mt", "1");
+        }
+
+        Ok(encoder.open_as_with(libvpx, encoder_options)?.into())
+    }
+}
@@ -0,0 +1,413 @@
+use std::{sync::Mutex, time::Duration};
+
+use compositor_render::{Frame, OutputId, Resolution};
+use crossbeam_channel::{Receiver, Sender};
+use ffmpeg_next::{
+    codec::Context, encoder, format::Pixel, frame, picture, software::scaling, Packet, Rational,
+};
+use log::{error, warn};
+
+use crate::{
+    error::EncoderInitError,
+    event::{self, Event},
+    pipeline::structs::{Codec, EncodedChunk, EncodedChunkKind},
+};
+
+/// Codec specific part of a video encoder. Encoding on a separate thread, dropping frames
+/// when the encoder can't keep up, reopening it with new options and flushing it on stop
+/// are shared by all codecs in [`VideoEncoder`].
+pub trait VideoCodec: Send + 'static {
+    type Options: VideoEncoderOptions;
+
+    fn codec(&self) -> Codec;
+
+    fn open_encoder(&self, options: &Self::Options) -> Result<OpenedEncoder, EncoderInitError>;
+}
+
+pub trait VideoEncoderOptions: Clone + Send + 'static {
+    fn resolution(&self) -> Resolution;
+
+    fn output_id(&self) -> &OutputId;
+
+    fn with_resolution(self, resolution: Resolution) -> Self;
+}
+
+pub struct OpenedEncoder {
+    pub encoder: encoder::video::Encoder,
+    /// Converts rendered YUV420P frames to the pixel format of the encoder. `None` if
+    /// frames are passed to the encoder without conversion.
+    pub converter: Option<(scaling::Context, Pixel)>,
+}
+
+impl From<encoder::video::Encoder> for OpenedEncoder {
+    fn from(encoder: encoder::video::Encoder) -> Self {
+        Self {
+            encoder,
+            converter: None,
+        }
+    }
+}
+
+/// Encoder context with the settings shared by all codecs, before it's opened with
+/// codec specific options. Timestamps are in the 90kHz clock of rendered frames.
+pub(super) fn video_encoder_context(
+    resolution: Resolution,
+    format: Pixel,
+) -> Result<encoder::Video, EncoderInitError> {
+    let mut encoder = Context::new().encoder().video()?;
+    encoder.set_time_base(Rational::new(1, 90000));
+    encoder.set_format(format);
+    encoder.set_width(resolution.width as u32);
+    encoder.set_height(resolution.height as u32);
+    Ok(encoder)
+}
+
+enum Message<Options> {
+    Frame(Frame),
+    UpdateOptions(Options, Sender<Result<(), EncoderInitError>>),
+    /// Encode the next frame as a keyframe.
+    RequestKeyframe,
+    Stop,
+}
+
+pub struct VideoEncoder<C: VideoCodec> {
+    options: Mutex<C::Options>,
+    output_id: OutputId,
+    frame_sender: Sender<Message<C::Options>>,
+    encoder_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl<C: VideoCodec> VideoEncoder<C> {
+    pub(super) fn spawn(
+        codec: C,
+        options: C::Options,
+    ) -> Result<(Self, Box<dyn Iterator<Item = EncodedChunk> + Send>), EncoderInitError> {
+        let (frame_sender, frame_receiver) = crossbeam_channel::unbounded();
+        let (packet_sender, packet_receiver) = crossbeam_channel::unbounded();
+        let (result_sender, result_receiver) = crossbeam_channel::bounded(0);
+
+        let options_clone = options.clone();
+
+        let encoder_thread = std::thread::Builder::new()
+            .name(format!(
+                "{:?} encoder thread for output {}",
+                codec.codec(),
+                options.output_id()
+            ))
+            .spawn(move || {
+                match encoder_thread(
+                    codec,
+                    options_clone,
+                    frame_receiver,
+                    packet_sender,
+                    &result_sender,
+                ) {
+                    Ok(_) => log::debug!("Encoder thread exited normally."),
+                    Err(e) => result_sender.send(Err(e)).unwrap(),
+                }
+            })
+            .unwrap();
+
+        result_receiver.recv().unwrap()?;
+
+        Ok((
+            Self {
+                encoder_thread: Some(encoder_thread),
+                frame_sender,
+                output_id: options.output_id().clone(),
+                options: Mutex::new(options),
+            },
+            Box::new(packet_receiver.into_iter()),
+        ))
+    }
+
+    pub fn send_frame(&self, frame: Frame) {
+        if let Err(e) = self.frame_sender.send(Message::Frame(frame)) {
+            error!("[output {}] Failed to send frame to encoder: {e}. It's possible the encoder thread has crashed.", self.output_id);
+        }
+    }
+
+    /// Next frame is encoded as a keyframe.
+    pub fn request_keyframe(&self) {
+        if let Err(e) = self.frame_sender.send(Message::RequestKeyframe) {
+            error!("[output {}] Failed to request a keyframe from encoder: {e}. It's possible the encoder thread has crashed.", self.output_id);
+        }
+    }
+
+    /// Flushes the encoder and ends the stream of encoded chunks, without waiting for
+    /// the encoder to be dropped. Frames sent after this call are not encoded.
+    pub fn stop(&self) {
+        // Fails only if the encoder thread has already exited.
+        let _ = self.frame_sender.send(Message::Stop);
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.options.lock().unwrap().resolution()
+    }
+
+    pub fn options(&self) -> C::Options {
+        self.options.lock().unwrap().clone()
+    }
+
+    pub fn update_resolution(&self, resolution: Resolution) -> Result<(), EncoderInitError> {
+        self.update_options(self.options().with_resolution(resolution))
+    }
+
+    /// Reopens the encoder with new options. Frames buffered in the old encoder are
+    /// flushed and the new one starts with a keyframe.
+    pub fn update_options(&self, options: C::Options) -> Result<(), EncoderInitError> {
+        let (result_sender, result_receiver) = crossbeam_channel::bounded(1);
+        self.frame_sender
+            .send(Message::UpdateOptions(options.clone(), result_sender))
+            .map_err(|_| EncoderInitError::EncoderThreadStopped)?;
+        result_receiver
+            .recv()
+            .map_err(|_| EncoderInitError::EncoderThreadStopped)??;
+
+        *self.options.lock().unwrap() = options;
+        Ok(())
+    }
+}
+
+impl<C: VideoCodec> Drop for VideoEncoder<C> {
+    fn drop(&mut self) {
+        self.stop();
+        match self.encoder_thread.take() {
+            Some(handle) => handle.join().unwrap(),
+            None => error!(
+                "[output {}] Encoder thread was already joined. This should not happen.",
+                self.output_id
+            ),
+        }
+    }
+}
+
+fn encoder_thread<C: VideoCodec>(
+    codec: C,
+    mut options: C::Options,
+    frame_receiver: Receiver<Message<C::Options>>,
+    packet_sender: Sender<EncodedChunk>,
+    result_sender: &Sender<Result<(), EncoderInitError>>,
+) -> Result<(), EncoderInitError> {
+    let kind = EncodedChunkKind::Video(codec.codec());
+    let mut opened = codec.open_encoder(&options)?;
+
+    result_sender.send(Ok(())).unwrap();
+
+    let mut packet = Packet::empty();
+    let mut force_keyframe = false;
+
+    loop {
+        let frame = match frame_receiver.recv() {
+            Ok(Message::Frame(f)) => f,
+            Ok(Message::UpdateOptions(new_options, result_sender)) => {
+                match codec.open_encoder(&new_options) {
+                    Ok(new_encoder) => {
+                        flush(
+                            &mut opened.encoder,
+                            &mut packet,
+                            kind,
+                            options.output_id(),
+                            &packet_sender,
+                        );
+                        opened = new_encoder;
+                        options = new_options;
+                        result_sender.send(Ok(())).unwrap();
+                    }
+                    Err(err) => result_sender.send(Err(err)).unwrap(),
+                }
+                continue;
+            }
+            Ok(Message::RequestKeyframe) => {
+                force_keyframe = true;
+                continue;
+            }
+            Ok(Message::Stop) => break,
+            Err(_) => break,
+        };
+
+        if frame_receiver.len() > 20 {
+            warn!("Dropping frame: render queue is too long.",);
+            continue;
+        }
+
+        let resolution = options.resolution();
+        let mut av_frame = frame::Video::new(
+            Pixel::YUV420P,
+            resolution.width as u32,
+            resolution.height as u32,
+        );
+
+        let _span = encode_span(options.output_id(), frame.pts);
+        if let Err(e) = frame_into_av(frame, &mut av_frame) {
+            error!(
+                "Failed to convert a frame to an ffmpeg frame: {}. Dropping",
+                e.0
+            );
+            continue;
+        }
+
+        if let Some((ref mut converter, format)) = opened.converter {
+            let mut converted =
+                frame::Video::new(format, resolution.width as u32, resolution.height as u32);
+            if let Err(err) = converter.run(&av_frame, &mut converted) {
+                error!(
+                    "[output {}] Failed to convert a frame to {format:?}: {err}. Dropping",
+                    options.output_id()
+                );
+                continue;
+            }
+            converted.set_pts(av_frame.pts());
+            av_frame = converted;
+        }
+
+        if force_keyframe {
+            av_frame.set_kind(picture::Type::I);
+            force_keyframe = false;
+        }
+
+        if let Err(e) = opened.encoder.send_frame(&av_frame) {
+            encoder_error(options.output_id(), e);
+            continue;
+        }
+
+        receive_chunks(
+            &mut opened.encoder,
+            &mut packet,
+            kind,
+            options.output_id(),
+            &packet_sender,
+        );
+    }
+
+    // Flush frames buffered in the encoder, otherwise the end of the stream would be
+    // lost when the output is unregistered.
+    flush(
+        &mut opened.encoder,
+        &mut packet,
+        kind,
+        options.output_id(),
+        &packet_sender,
+    );
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub(super) struct FrameConversionError(pub(super) String);
+
+pub(super) fn frame_into_av(
+    frame: Frame,
+    av_frame: &mut frame::Video,
+) -> Result<(), FrameConversionError> {
+    let expected_y_plane_size = (av_frame.plane_width(0) * av_frame.plane_height(0)) as usize;
+    let expected_u_plane_size = (av_frame.plane_width(1) * av_frame.plane_height(1)) as usize;
+    let expected_v_plane_size = (av_frame.plane_width(2) * av_frame.plane_height(2)) as usize;
+    if expected_y_plane_size != frame.data.y_plane.len() {
+        return Err(FrameConversionError(format!(
+            "Y plane is a wrong size, expected: {} received: {}",
+            expected_y_plane_size,
+            frame.data.y_plane.len()
+        )));
+    }
+    if expected_u_plane_size != frame.data.u_plane.len() {
+        return Err(FrameConversionError(format!(
+            "U plane is a wrong size, expected: {} received: {}",
+            expected_u_plane_size,
+            frame.data.u_plane.len()
+        )));
+    }
+    if expected_v_plane_size != frame.data.v_plane.len() {
+        return Err(FrameConversionError(format!(
+            "V plane is a wrong size, expected: {} received: {}",
+            expected_v_plane_size,
+            frame.data.v_plane.len()
+        )));
+    }
+
+    av_frame.set_pts(Some((frame.pts.as_secs_f64() * 90000.0) as i64));
+
+    write_plane_to_av(av_frame, 0, &frame.data.y_plane);
+    write_plane_to_av(av_frame, 1, &frame.data.u_plane);
+    write_plane_to_av(av_frame, 2, &frame.data.v_plane);
+
+    Ok(())
+}
+
+fn write_plane_to_av(frame: &mut frame::Video, plane: usize, data: &[u8]) {
+    let stride = frame.stride(plane);
+    let width = frame.plane_width(plane) as usize;
+
+    data.chunks(width)
+        .zip(frame.data_mut(plane).chunks_mut(stride))
+        .for_each(|(data, target)| target[..width].copy_from_slice(data));
+}
+
+/// Span of encoding a single frame. It's only enabled if timings are logged, see
+/// `LIVE_COMPOSITOR_LOG_TIMINGS`.
+pub(super) fn encode_span(output_id: &OutputId, pts: Duration) -> tracing::span::EnteredSpan {
+    tracing::trace_span!(
+        target: "timings",
+        "encode",
+        output_id = %output_id,
+        pts_ms = pts.as_millis() as u64,
+    )
+    .entered()
+}
+
+/// Sends all packets that the encoder is currently able to produce.
+pub(super) fn receive_chunks(
+    encoder: &mut ffmpeg_next::encoder::Encoder,
+    packet: &mut Packet,
+    kind: EncodedChunkKind,
+    output_id: &OutputId,
+    packet_sender: &Sender<EncodedChunk>,
+) {
+    loop {
+        match encoder.receive_packet(packet) {
+            Ok(_) => match EncodedChunk::from_av_packet(packet, kind) {
+                Ok(chunk) => {
+                    packet_sender.send(chunk).unwrap();
+                }
+                Err(e) => {
+                    warn!("failed to parse an ffmpeg packet received from encoder: {e}",);
+                    break;
+                }
+            },
+
+            Err(ffmpeg_next::Error::Other {
+                errno: ffmpeg_next::error::EAGAIN,
+            }) => break, // encoder needs more frames to produce a packet
+
+            Err(ffmpeg_next::Error::Eof) => break, // encoder was flushed
+
+            Err(e) => {
+                encoder_error(output_id, e);
+                break;
+            }
+        }
+    }
+}
+
+/// Drains frames buffered in the encoder and sends the remaining packets.
+pub(super) fn flush(
+    encoder: &mut ffmpeg_next::encoder::Encoder,
+    packet: &mut Packet,
+    kind: EncodedChunkKind,
+    output_id: &OutputId,
+    packet_sender: &Sender<EncodedChunk>,
+) {
+    match encoder.send_eof() {
+        Ok(()) => receive_chunks(encoder, packet, kind, output_id, packet_sender),
+        Err(e) => error!("Failed to flush the encoder: {e}."),
+    }
+}
+
+/// Logs the error and notifies event subscribers about it.
+pub(super) fn encoder_error(output_id: &OutputId, err: ffmpeg_next::Error) {
+    error!("[output {output_id}] Encoder error: {err}.");
+    event::emit(Event::EncoderError {
+        output_id: output_id.clone(),
+        message: err.to_string(),
+    });
+}
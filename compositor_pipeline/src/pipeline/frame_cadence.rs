@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use compositor_render::{Frame, Framerate};
+
+/// Difference between timestamps of a rendered frame and an output frame that is treated
+/// as a rounding error, so outputs with the render framerate receive every frame.
+const PTS_TOLERANCE: Duration = Duration::from_millis(1);
+
+/// Rendered frames further ahead of the next output frame than this start a new cadence
+/// instead of being repeated, e.g. when the pipeline is restarted.
+const MAX_GAP: Duration = Duration::from_secs(1);
+
+/// Resamples frames produced at the render framerate to the framerate of an output.
+///
+/// Output frames are placed every frame interval, starting from the first rendered
+/// frame. Each rendered frame is sent in place of all output frames up to its
+/// timestamp, so it's dropped if the output framerate is lower, and repeated if it's
+/// higher.
+pub(super) struct FrameCadence {
+    framerate: Framerate,
+    start_pts: Duration,
+    /// Index of the next output frame, counted from `start_pts`.
+    next_frame: u64,
+    last_rendered_pts: Option<Duration>,
+}
+
+impl FrameCadence {
+    pub fn new(framerate: Framerate) -> Self {
+        Self {
+            framerate,
+            start_pts: Duration::ZERO,
+            next_frame: 0,
+            last_rendered_pts: None,
+        }
+    }
+
+    /// Returns frames that should be encoded for the rendered frame. Timestamps of
+    /// returned frames are aligned to the output framerate.
+    pub fn resample(&mut self, frame: Frame) -> Vec<Frame> {
+        let is_discontinuity = match self.last_rendered_pts {
+            Some(last_pts) => {
+                frame.pts < last_pts || frame.pts > self.frame_pts(self.next_frame) + MAX_GAP
+            }
+            None => true,
+        };
+        if is_discontinuity {
+            self.start_pts = frame.pts;
+            self.next_frame = 0;
+        }
+        self.last_rendered_pts = Some(frame.pts);
+
+        let mut frames = Vec::new();
+        loop {
+            let pts = self.frame_pts(self.next_frame);
+            if pts > frame.pts + PTS_TOLERANCE {
+                break;
+            }
+            frames.push(Frame {
+                pts,
+                ..frame.clone()
+            });
+            self.next_frame += 1;
+        }
+        frames
+    }
+
+    fn frame_pts(&self, index: u64) -> Duration {
+        let nanos =
+            index as u128 * 1_000_000_000 * self.framerate.den as u128 / self.framerate.num as u128;
+        self.start_pts + Duration::from_nanos(nanos as u64)
+    }
+}
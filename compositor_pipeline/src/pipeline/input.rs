@@ -1,28 +1,159 @@
-use crate::{error::InputInitError, pipeline::structs::EncodedChunk};
+use std::{sync::Arc, time::Duration};
 
+use compositor_render::Frame;
+
+use crate::{
+    error::InputInitError,
+    pipeline::structs::{AudioCodec, EncodedChunk},
+    queue::{Backpressure, FrameGenerator},
+};
+
+use buffer::InputBufferSize;
+use file::{FileInput, FileInputOptions};
 use rtp::{RtpReceiver, RtpReceiverOptions};
+use rtsp::{RtspInput, RtspInputOptions};
+use srt::{SrtInput, SrtInputOptions};
+use static_image::{StaticImageInput, StaticImageOptions};
+use tcp::{TcpInput, TcpInputOptions};
+use test_pattern::{TestPatternInput, TestPatternOptions};
+use transform::FrameTransform;
+use udp_ts::{UdpTsInput, UdpTsInputOptions};
 
+pub mod buffer;
+pub mod file;
 pub mod rtp;
+pub mod rtsp;
+pub mod srt;
+pub mod static_image;
+pub mod tcp;
+pub mod test_pattern;
+pub mod transform;
+pub mod udp_ts;
 
 pub enum Input {
     Rtp(RtpReceiver),
+    File(FileInput),
+    UdpTs(UdpTsInput),
+    Srt(SrtInput),
+    Rtsp(RtspInput),
+    TcpH264(TcpInput),
+    StaticImage(StaticImageInput),
+    TestPattern(TestPatternInput),
+}
+
+/// Data produced by an input.
+pub enum InputStream {
+    /// Encoded chunks that are passed to a decoder.
+    Chunks(Box<dyn Iterator<Item = EncodedChunk> + Send>),
+    /// Frame that is passed directly to the queue and shown for as long as the input
+    /// is registered.
+    StaticFrame(Frame),
+    /// Frames that are generated by the queue for every frame set.
+    Generated(FrameGenerator),
 }
 
 impl Input {
+    /// Only file inputs are paused by `backpressure`, other inputs receive live streams.
     pub fn new(
         options: InputOptions,
-    ) -> Result<(Self, Box<dyn Iterator<Item = EncodedChunk> + Send>), InputInitError> {
+        backpressure: &Arc<Backpressure>,
+    ) -> Result<(Self, InputStream), InputInitError> {
         match options {
             InputOptions::Rtp(opts) => Ok(RtpReceiver::new(opts).map(|(receiver, iter)| {
-                (
-                    Self::Rtp(receiver),
-                    Box::new(iter) as Box<dyn Iterator<Item = EncodedChunk> + Send>,
-                )
+                (Self::Rtp(receiver), InputStream::Chunks(Box::new(iter)))
+            })?),
+            InputOptions::File(opts) => Ok(FileInput::new(opts, backpressure.clone())
+                .map(|(input, iter)| (Self::File(input), InputStream::Chunks(Box::new(iter))))?),
+            InputOptions::UdpTs(opts) => Ok(UdpTsInput::new(opts)
+                .map(|(input, iter)| (Self::UdpTs(input), InputStream::Chunks(Box::new(iter))))?),
+            InputOptions::Srt(opts) => Ok(SrtInput::new(opts)
+                .map(|(input, iter)| (Self::Srt(input), InputStream::Chunks(Box::new(iter))))?),
+            InputOptions::Rtsp(opts) => Ok(RtspInput::new(opts)
+                .map(|(input, iter)| (Self::Rtsp(input), InputStream::Chunks(Box::new(iter))))?),
+            InputOptions::TcpH264(opts) => Ok(TcpInput::new(opts).map(|(input, iter)| {
+                (Self::TcpH264(input), InputStream::Chunks(Box::new(iter)))
             })?),
+            InputOptions::StaticImage(opts) => {
+                Ok(StaticImageInput::new(opts).map(|(input, frame)| {
+                    (Self::StaticImage(input), InputStream::StaticFrame(frame))
+                })?)
+            }
+            InputOptions::TestPattern(opts) => {
+                let (input, generator) = TestPatternInput::new(opts);
+                Ok((Self::TestPattern(input), InputStream::Generated(generator)))
+            }
         }
     }
 }
 
 pub enum InputOptions {
     Rtp(RtpReceiverOptions),
+    File(FileInputOptions),
+    UdpTs(UdpTsInputOptions),
+    Srt(SrtInputOptions),
+    Rtsp(RtspInputOptions),
+    TcpH264(TcpInputOptions),
+    StaticImage(StaticImageOptions),
+    TestPattern(TestPatternOptions),
+}
+
+impl InputOptions {
+    /// Overrides the global stream fallback timeout for this input.
+    pub fn stream_fallback_timeout(&self) -> Option<Duration> {
+        match self {
+            InputOptions::Rtp(opts) => Some(opts.timeout),
+            InputOptions::File(_)
+            | InputOptions::UdpTs(_)
+            | InputOptions::Srt(_)
+            | InputOptions::Rtsp(_)
+            | InputOptions::TcpH264(_)
+            | InputOptions::StaticImage(_)
+            | InputOptions::TestPattern(_) => None,
+        }
+    }
+
+    /// Size of the buffer between the input and its decoder. Chunks are passed to the
+    /// decoder directly if it's not specified.
+    pub fn buffer_size(&self) -> Option<InputBufferSize> {
+        match self {
+            InputOptions::Rtp(opts) => opts.buffer,
+            InputOptions::File(_)
+            | InputOptions::UdpTs(_)
+            | InputOptions::Srt(_)
+            | InputOptions::Rtsp(_)
+            | InputOptions::TcpH264(_)
+            | InputOptions::StaticImage(_)
+            | InputOptions::TestPattern(_) => None,
+        }
+    }
+
+    /// Transformation applied to decoded frames before they are passed to the queue.
+    pub fn frame_transform(&self) -> Option<FrameTransform> {
+        match self {
+            InputOptions::Rtp(opts) => opts.transform,
+            InputOptions::File(_)
+            | InputOptions::UdpTs(_)
+            | InputOptions::Srt(_)
+            | InputOptions::Rtsp(_)
+            | InputOptions::TcpH264(_)
+            | InputOptions::StaticImage(_)
+            | InputOptions::TestPattern(_) => None,
+        }
+    }
+
+    /// Codec of the audio chunks produced by the input. `None` if the input only
+    /// produces video.
+    pub fn audio_codec(&self) -> Option<AudioCodec> {
+        match self {
+            InputOptions::File(opts) if opts.audio => Some(AudioCodec::Aac),
+            InputOptions::Rtp(_)
+            | InputOptions::File(_)
+            | InputOptions::UdpTs(_)
+            | InputOptions::Srt(_)
+            | InputOptions::Rtsp(_)
+            | InputOptions::TcpH264(_)
+            | InputOptions::StaticImage(_)
+            | InputOptions::TestPattern(_) => None,
+        }
+    }
 }
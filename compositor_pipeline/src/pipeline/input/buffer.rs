@@ -0,0 +1,135 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use compositor_render::InputId;
+
+use crate::pipeline::{stats::InputStats, structs::EncodedChunk};
+
+/// Chunks are passed to the decoder immediately if the timestamp jumps forward by more
+/// than this, e.g. after the sender was restarted.
+const MAX_PTS_GAP: Duration = Duration::from_secs(1);
+
+/// Capacity of the buffer between an input and its decoder.
+#[derive(Debug, Clone, Copy)]
+pub enum InputBufferSize {
+    Frames(usize),
+    /// Difference between timestamps of the newest and the oldest buffered chunk.
+    Duration(Duration),
+}
+
+#[derive(Default)]
+struct BufferState {
+    chunks: VecDeque<EncodedChunk>,
+    is_finished: bool,
+}
+
+/// Receives chunks from the input on a separate thread and passes them to the decoder
+/// at the pace of their timestamps, so bursts of chunks are spread over time. It doesn't
+/// reorder chunks. When the buffer is full, the oldest chunks are dropped.
+pub(crate) struct BufferedChunks {
+    state: Arc<(Mutex<BufferState>, Condvar)>,
+    /// Time when the chunk with the pts was passed to the decoder. Following chunks
+    /// are released relative to it.
+    clock: Option<(Instant, i64)>,
+}
+
+impl BufferedChunks {
+    pub(crate) fn new(
+        chunks: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+        size: InputBufferSize,
+        input_id: &InputId,
+        stats: Arc<InputStats>,
+    ) -> Self {
+        let state = Arc::new((Mutex::new(BufferState::default()), Condvar::new()));
+
+        // Thread stops when the input is unregistered and its chunks iterator ends.
+        let thread_state = state.clone();
+        thread::Builder::new()
+            .name(format!("Input buffer {}", input_id.0))
+            .spawn(move || {
+                let (state, chunk_added) = &*thread_state;
+                for chunk in chunks {
+                    let mut state = state.lock().unwrap();
+                    state.chunks.push_back(chunk);
+                    while state.chunks.len() > 1 && is_full(&state.chunks, size) {
+                        state.chunks.pop_front();
+                        stats.on_buffer_frame_dropped();
+                    }
+                    chunk_added.notify_one();
+                }
+                state.lock().unwrap().is_finished = true;
+                chunk_added.notify_one();
+            })
+            .unwrap();
+
+        Self { state, clock: None }
+    }
+
+    fn release_time(&self, chunk: &EncodedChunk) -> Option<Instant> {
+        let (released_at, released_pts) = self.clock?;
+        if chunk.discontinuity || chunk.pts - released_pts > pts_from_duration(MAX_PTS_GAP) {
+            return None;
+        }
+        // Chunks with earlier timestamps, e.g. B-frames, are released immediately.
+        Some(released_at + pts_to_duration(i64::max(chunk.pts - released_pts, 0)))
+    }
+}
+
+impl Iterator for BufferedChunks {
+    type Item = EncodedChunk;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let shared = self.state.clone();
+        let (state, chunk_added) = &*shared;
+        let mut state = state.lock().unwrap();
+        loop {
+            let Some(chunk) = state.chunks.front() else {
+                if state.is_finished {
+                    return None;
+                }
+                // Buffer is empty, the next chunk is released as soon as it arrives.
+                self.clock = None;
+                state = chunk_added.wait(state).unwrap();
+                continue;
+            };
+
+            let now = Instant::now();
+            match self.release_time(chunk) {
+                Some(release_time) if release_time > now => {
+                    state = chunk_added
+                        .wait_timeout(state, release_time - now)
+                        .unwrap()
+                        .0;
+                }
+                Some(_) => return state.chunks.pop_front(),
+                None => {
+                    self.clock = Some((now, chunk.pts));
+                    return state.chunks.pop_front();
+                }
+            }
+        }
+    }
+}
+
+fn is_full(chunks: &VecDeque<EncodedChunk>, size: InputBufferSize) -> bool {
+    match size {
+        InputBufferSize::Frames(frames) => chunks.len() > frames,
+        InputBufferSize::Duration(duration) => match (chunks.front(), chunks.back()) {
+            (Some(first), Some(last)) => last.pts - first.pts > pts_from_duration(duration),
+            _ => false,
+        },
+    }
+}
+
+/// Converts to the 90kHz clock used by the encoded chunks.
+fn pts_from_duration(duration: Duration) -> i64 {
+    (duration.as_secs_f64() * 90000.0) as i64
+}
+
+fn pts_to_duration(pts: i64) -> Duration {
+    Duration::from_secs_f64(pts as f64 / 90000.0)
+}
@@ -0,0 +1,416 @@
+use std::{
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use compositor_render::InputId;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use ffmpeg_next::{codec::Id, format::context, media::Type, Rational};
+use log::{error, warn};
+
+use crate::{
+    pipeline::structs::{AudioCodec, Codec, EncodedChunk, EncodedChunkKind},
+    queue::Backpressure,
+};
+
+use self::{
+    adts::{AdtsConversionError, AdtsConverter},
+    annex_b::{AnnexBConversionError, AnnexBConverter},
+};
+
+mod adts;
+mod annex_b;
+
+pub struct FileInput {
+    pub path: Arc<Path>,
+    reader_thread: Option<thread::JoinHandle<()>>,
+    should_close: Arc<AtomicBool>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FileInputError {
+    #[error("Failed to open file {0:?}.")]
+    OpenFile(Arc<Path>, #[source] ffmpeg_next::Error),
+
+    #[error("File {0:?} does not contain a video stream.")]
+    NoVideoStream(Arc<Path>),
+
+    #[error("File {path:?} contains a {found:?} video stream, but the input was registered with a {expected:?} decoder.")]
+    CodecMismatch {
+        path: Arc<Path>,
+        expected: Codec,
+        found: Id,
+    },
+
+    #[error("Reading {0:?} from a file is not supported.")]
+    UnsupportedCodec(Codec),
+
+    #[error("Failed to parse codec parameters of the video stream.")]
+    InvalidCodecParameters(#[source] AnnexBConversionError),
+
+    #[error("File {0:?} does not contain an audio stream.")]
+    NoAudioStream(Arc<Path>),
+
+    #[error("File {path:?} contains a {found:?} audio stream, only AAC is supported.")]
+    UnsupportedAudioCodec { path: Arc<Path>, found: Id },
+
+    #[error("Failed to parse codec parameters of the audio stream.")]
+    InvalidAudioParameters(#[source] AdtsConversionError),
+
+    #[error(
+        "Audio can only be read from a file played back in real time (with playback speed 1)."
+    )]
+    AudioWithPlaybackSpeed,
+
+    #[error("Reading a file as fast as possible (with playback speed 0) requires queue backpressure to be enabled.")]
+    UnthrottledWithoutBackpressure,
+}
+
+pub struct FileInputOptions {
+    pub path: Arc<Path>,
+    /// Restart from the beginning of the file after reaching its end.
+    pub loop_playback: bool,
+    pub codec: Codec,
+    /// Read the audio track. Otherwise, only the video is read.
+    pub audio: bool,
+    /// Speed of the playback relative to real time, e.g. `2.0` plays the file twice as
+    /// fast. Timestamps are scaled accordingly, so the queue receives frames in real time.
+    /// If `0.0`, the file is read as fast as the queue accepts the frames, i.e. reading
+    /// is only paused by backpressure.
+    pub playback_speed: f64,
+    pub input_id: InputId,
+}
+
+struct VideoStream {
+    index: usize,
+    codec: Codec,
+    time_base: Rational,
+    /// Used to compute timestamps after looping if packets don't specify their duration.
+    frame_duration: i64,
+    converter: AnnexBConverter,
+}
+
+struct AudioStream {
+    index: usize,
+    time_base: Rational,
+    converter: AdtsConverter,
+}
+
+impl FileInput {
+    /// Reading is paused while `backpressure` pauses the queue.
+    pub fn new(
+        opts: FileInputOptions,
+        backpressure: Arc<Backpressure>,
+    ) -> Result<(Self, ChunkIter), FileInputError> {
+        if !matches!(opts.codec, Codec::H264 | Codec::H265) {
+            return Err(FileInputError::UnsupportedCodec(opts.codec));
+        }
+        if opts.audio && opts.playback_speed != 1.0 {
+            return Err(FileInputError::AudioWithPlaybackSpeed);
+        }
+        // Without backpressure nothing would stop the reader from buffering the whole
+        // file in the queue.
+        if opts.playback_speed == 0.0 && !backpressure.is_enabled() {
+            return Err(FileInputError::UnthrottledWithoutBackpressure);
+        }
+
+        let input_ctx = ffmpeg_next::format::input(&opts.path)
+            .map_err(|err| FileInputError::OpenFile(opts.path.clone(), err))?;
+
+        let stream = input_ctx
+            .streams()
+            .best(Type::Video)
+            .ok_or_else(|| FileInputError::NoVideoStream(opts.path.clone()))?;
+
+        let parameters = stream.parameters();
+        if parameters.id() != Id::from(opts.codec) {
+            return Err(FileInputError::CodecMismatch {
+                path: opts.path.clone(),
+                expected: opts.codec,
+                found: parameters.id(),
+            });
+        }
+
+        let converter = AnnexBConverter::new(opts.codec, extradata(&parameters))
+            .map_err(FileInputError::InvalidCodecParameters)?;
+
+        let frame_rate = stream.avg_frame_rate();
+        let frame_duration = match frame_rate.numerator() > 0 {
+            true => 90000 * frame_rate.denominator() as i64 / frame_rate.numerator() as i64,
+            false => 90000 / 30,
+        };
+
+        let video_stream = VideoStream {
+            index: stream.index(),
+            codec: opts.codec,
+            time_base: stream.time_base(),
+            frame_duration,
+            converter,
+        };
+
+        let audio_stream = match opts.audio {
+            true => Some(Self::audio_stream(&input_ctx, &opts.path)?),
+            false => None,
+        };
+
+        let pacing = Pacing {
+            playback_speed: opts.playback_speed,
+            backpressure,
+            start: Instant::now(),
+            first_timestamp: None,
+        };
+
+        let should_close = Arc::new(AtomicBool::new(false));
+        let should_close2 = should_close.clone();
+        let (chunks_tx, chunks_rx) = unbounded();
+
+        let reader_thread = thread::Builder::new()
+            .name(format!("File reader {}", opts.input_id))
+            .spawn(move || {
+                FileInput::reader_thread(
+                    input_ctx,
+                    video_stream,
+                    audio_stream,
+                    opts.loop_playback,
+                    chunks_tx,
+                    should_close2,
+                    pacing,
+                )
+            })
+            .unwrap();
+
+        Ok((
+            Self {
+                path: opts.path,
+                reader_thread: Some(reader_thread),
+                should_close,
+            },
+            ChunkIter {
+                receiver: chunks_rx,
+            },
+        ))
+    }
+
+    fn audio_stream(
+        input_ctx: &context::Input,
+        path: &Arc<Path>,
+    ) -> Result<AudioStream, FileInputError> {
+        let stream = input_ctx
+            .streams()
+            .best(Type::Audio)
+            .ok_or_else(|| FileInputError::NoAudioStream(path.clone()))?;
+
+        let parameters = stream.parameters();
+        if parameters.id() != Id::from(AudioCodec::Aac) {
+            return Err(FileInputError::UnsupportedAudioCodec {
+                path: path.clone(),
+                found: parameters.id(),
+            });
+        }
+        let converter = AdtsConverter::new(extradata(&parameters))
+            .map_err(FileInputError::InvalidAudioParameters)?;
+
+        Ok(AudioStream {
+            index: stream.index(),
+            time_base: stream.time_base(),
+            converter,
+        })
+    }
+
+    fn reader_thread(
+        mut input_ctx: context::Input,
+        stream: VideoStream,
+        audio_stream: Option<AudioStream>,
+        loop_playback: bool,
+        chunks_tx: Sender<EncodedChunk>,
+        should_close: Arc<AtomicBool>,
+        mut pacing: Pacing,
+    ) {
+        // Timestamps of every consecutive loop are shifted by the duration of the previous
+        // ones, so the decoder and the queue always receive monotonic timestamps.
+        let mut loop_offset = 0;
+        let mut loop_end = 0;
+
+        loop {
+            for (packet_stream, mut packet) in input_ctx.packets() {
+                if should_close.load(std::sync::atomic::Ordering::Relaxed) {
+                    return;
+                }
+
+                let audio_stream = audio_stream
+                    .as_ref()
+                    .filter(|audio_stream| packet_stream.index() == audio_stream.index);
+                if packet_stream.index() != stream.index && audio_stream.is_none() {
+                    continue;
+                }
+
+                let time_base = match audio_stream {
+                    Some(audio_stream) => audio_stream.time_base,
+                    None => stream.time_base,
+                };
+                packet.rescale_ts(time_base, Rational::new(1, 90000));
+                packet.set_pts(packet.pts().map(|pts| pts + loop_offset));
+                packet.set_dts(packet.dts().map(|dts| dts + loop_offset));
+
+                if let Some(audio_stream) = audio_stream {
+                    let mut chunk = match EncodedChunk::from_av_packet(
+                        &packet,
+                        EncodedChunkKind::Audio(AudioCodec::Aac),
+                    ) {
+                        Ok(chunk) => chunk,
+                        Err(err) => {
+                            warn!("Dropping an audio packet read from the file: {err}");
+                            continue;
+                        }
+                    };
+                    chunk.data = match audio_stream.converter.convert(&chunk.data) {
+                        Ok(data) => data,
+                        Err(err) => {
+                            warn!("Dropping an audio packet read from the file: {err}");
+                            continue;
+                        }
+                    };
+
+                    if !pacing.wait(&mut chunk, &should_close) {
+                        return;
+                    }
+                    if chunks_tx.send(chunk).is_err() {
+                        return;
+                    }
+                    continue;
+                }
+
+                let kind = EncodedChunkKind::Video(stream.codec);
+                let mut chunk = match EncodedChunk::from_av_packet(&packet, kind) {
+                    Ok(chunk) => chunk,
+                    Err(err) => {
+                        warn!("Dropping a packet read from the file: {err}");
+                        continue;
+                    }
+                };
+
+                chunk.data = match stream.converter.convert(&chunk.data, packet.is_key()) {
+                    Ok(data) => data,
+                    Err(err) => {
+                        warn!("Dropping a packet read from the file: {err}");
+                        continue;
+                    }
+                };
+
+                let duration = match packet.duration() > 0 {
+                    true => packet.duration(),
+                    false => stream.frame_duration,
+                };
+                loop_end = i64::max(loop_end, chunk.pts + duration);
+
+                if !pacing.wait(&mut chunk, &should_close) {
+                    return;
+                }
+                if chunks_tx.send(chunk).is_err() {
+                    return;
+                }
+            }
+
+            if !loop_playback {
+                return;
+            }
+
+            if let Err(err) = input_ctx.seek(0, ..) {
+                error!("Failed to seek to the beginning of the file: {err}");
+                return;
+            }
+            loop_offset = loop_end;
+        }
+    }
+}
+
+/// Packets are sent in real time, otherwise the whole file would be decoded and buffered
+/// in the queue at once.
+struct Pacing {
+    /// Relative to real time. If `0.0`, packets are only delayed by backpressure.
+    playback_speed: f64,
+    backpressure: Arc<Backpressure>,
+    start: Instant,
+    first_timestamp: Option<i64>,
+}
+
+impl Pacing {
+    /// Scales timestamps of the chunk by the playback speed and blocks until it should be
+    /// sent. Returns `false` if the input was unregistered in the meantime.
+    fn wait(&mut self, chunk: &mut EncodedChunk, should_close: &AtomicBool) -> bool {
+        // Packets are sent later by the time the queue was paused.
+        let Some(paused) = wait_while_paused(&self.backpressure, should_close) else {
+            return false;
+        };
+        self.start += paused;
+        if self.playback_speed == 0.0 {
+            return true;
+        }
+
+        if self.playback_speed != 1.0 {
+            let speed = self.playback_speed;
+            chunk.pts = (chunk.pts as f64 / speed) as i64;
+            chunk.dts = chunk.dts.map(|dts| (dts as f64 / speed) as i64);
+        }
+        let send_at = send_time(self.start, &mut self.first_timestamp, chunk);
+        thread::sleep(send_at.saturating_duration_since(Instant::now()));
+        true
+    }
+}
+
+/// Blocks while the queue is paused by backpressure. Returns how long it was paused,
+/// or `None` if the input was unregistered in the meantime.
+fn wait_while_paused(backpressure: &Backpressure, should_close: &AtomicBool) -> Option<Duration> {
+    let paused_at = Instant::now();
+    while backpressure.wait_while_paused(Duration::from_millis(100)) {
+        if should_close.load(std::sync::atomic::Ordering::Relaxed) {
+            return None;
+        }
+    }
+    Some(paused_at.elapsed())
+}
+
+fn send_time(start: Instant, first_timestamp: &mut Option<i64>, chunk: &EncodedChunk) -> Instant {
+    let timestamp = chunk.dts.unwrap_or(chunk.pts);
+    let first_timestamp = *first_timestamp.get_or_insert(timestamp);
+    let elapsed = i64::max(timestamp - first_timestamp, 0) as f64 / 90000.0;
+    start + Duration::from_secs_f64(elapsed)
+}
+
+fn extradata(parameters: &ffmpeg_next::codec::Parameters) -> &[u8] {
+    unsafe {
+        let parameters = &*parameters.as_ptr();
+        match parameters.extradata.is_null() {
+            true => &[][..],
+            false => {
+                std::slice::from_raw_parts(parameters.extradata, parameters.extradata_size as usize)
+            }
+        }
+    }
+}
+
+impl Drop for FileInput {
+    fn drop(&mut self) {
+        self.should_close
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        if let Some(thread) = self.reader_thread.take() {
+            thread.join().unwrap();
+        } else {
+            error!("File input does not hold a thread handle to the reading thread.")
+        }
+    }
+}
+
+pub struct ChunkIter {
+    receiver: Receiver<EncodedChunk>,
+}
+
+impl Iterator for ChunkIter {
+    type Item = EncodedChunk;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
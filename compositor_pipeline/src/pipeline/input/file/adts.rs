@@ -0,0 +1,82 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+const ADTS_HEADER_SIZE: usize = 7;
+
+/// Largest frame that fits in the 13-bit frame length field of the ADTS header.
+const MAX_FRAME_SIZE: usize = (1 << 13) - 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AdtsConversionError {
+    #[error("Malformed AudioSpecificConfig in the codec extradata.")]
+    MalformedExtradata,
+
+    #[error("AAC object type {0} is not supported, only AAC Main, LC, SSR and LTP are.")]
+    UnsupportedObjectType(u8),
+
+    #[error("AAC frame is too large for an ADTS header.")]
+    FrameTooLarge,
+}
+
+/// Converts raw AAC frames (used e.g. by MP4 and MKV) into frames with ADTS headers,
+/// which the decoder accepts without the codec extradata.
+///
+/// Stream parameters are stored in the AudioSpecificConfig in the extradata, so they
+/// are repeated in the header of every frame.
+pub(super) struct AdtsConverter {
+    object_type: u8,
+    frequency_index: u8,
+    channel_config: u8,
+}
+
+impl AdtsConverter {
+    pub(super) fn new(extradata: &[u8]) -> Result<Self, AdtsConversionError> {
+        let [first, second, ..] = *extradata else {
+            return Err(AdtsConversionError::MalformedExtradata);
+        };
+
+        // audioObjectType (5 bits), samplingFrequencyIndex (4 bits), channelConfiguration (4 bits)
+        let object_type = first >> 3;
+        let frequency_index = ((first & 0b111) << 1) | (second >> 7);
+        let channel_config = (second >> 3) & 0b1111;
+
+        // Profile in the ADTS header only has 2 bits.
+        if !(1..=4).contains(&object_type) {
+            return Err(AdtsConversionError::UnsupportedObjectType(object_type));
+        }
+        // Index 15 means that the frequency is written explicitly, which ADTS can't express.
+        if frequency_index == 15 || channel_config > 7 {
+            return Err(AdtsConversionError::MalformedExtradata);
+        }
+
+        Ok(Self {
+            object_type,
+            frequency_index,
+            channel_config,
+        })
+    }
+
+    pub(super) fn convert(&self, frame: &[u8]) -> Result<Bytes, AdtsConversionError> {
+        let frame_size = frame.len() + ADTS_HEADER_SIZE;
+        if frame_size > MAX_FRAME_SIZE {
+            return Err(AdtsConversionError::FrameTooLarge);
+        }
+
+        let mut data = BytesMut::with_capacity(frame_size);
+        // Syncword, MPEG-4, layer 0, no CRC
+        data.put_u8(0xFF);
+        data.put_u8(0xF1);
+        data.put_u8(
+            ((self.object_type - 1) << 6)
+                | (self.frequency_index << 2)
+                | (self.channel_config >> 2),
+        );
+        data.put_u8(((self.channel_config & 0b11) << 6) | (frame_size >> 11) as u8);
+        data.put_u8((frame_size >> 3) as u8);
+        // Buffer fullness is set to 0x7FF, which means a variable bitrate.
+        data.put_u8(((frame_size & 0b111) << 5) as u8 | 0b1_1111);
+        data.put_u8(0b1111_1100);
+        data.extend_from_slice(frame);
+
+        Ok(data.freeze())
+    }
+}
@@ -0,0 +1,149 @@
+use bytes::{Bytes, BytesMut};
+
+use crate::pipeline::structs::Codec;
+
+const START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+#[derive(Debug, thiserror::Error)]
+pub enum AnnexBConversionError {
+    #[error("Malformed codec extradata.")]
+    MalformedExtradata,
+
+    #[error("NAL unit length exceeds the packet size.")]
+    MalformedPacket,
+}
+
+/// Converts H264/H265 packets stored in the length-prefixed format (avcC/hvcC, used
+/// e.g. by MP4 and MKV) into Annex-B, which is the only format the decoder accepts.
+///
+/// Parameter sets are stored in the codec extradata instead of the bitstream, so they
+/// are inserted before every keyframe.
+pub(super) struct AnnexBConverter {
+    /// `None` if packets are already in the Annex-B format.
+    nal_length_size: Option<usize>,
+    parameter_sets: Bytes,
+}
+
+impl AnnexBConverter {
+    pub(super) fn new(codec: Codec, extradata: &[u8]) -> Result<Self, AnnexBConversionError> {
+        if extradata.is_empty()
+            || extradata.starts_with(&[0, 0, 1])
+            || extradata.starts_with(&START_CODE)
+        {
+            return Ok(Self {
+                nal_length_size: None,
+                parameter_sets: Bytes::copy_from_slice(extradata),
+            });
+        }
+
+        let mut reader = Reader(extradata);
+        let mut parameter_sets = BytesMut::new();
+
+        let nal_length_size = match codec {
+            Codec::H264 => {
+                // configurationVersion, AVCProfileIndication, profile_compatibility, AVCLevelIndication
+                reader.take(4)?;
+                let nal_length_size = (reader.u8()? & 0b11) as usize + 1;
+
+                let sps_count = reader.u8()? & 0b1_1111;
+                for _ in 0..sps_count {
+                    reader.parameter_set(&mut parameter_sets)?;
+                }
+                let pps_count = reader.u8()?;
+                for _ in 0..pps_count {
+                    reader.parameter_set(&mut parameter_sets)?;
+                }
+
+                nal_length_size
+            }
+            Codec::H265 => {
+                // Profile, tier, level and chroma format information
+                reader.take(21)?;
+                let nal_length_size = (reader.u8()? & 0b11) as usize + 1;
+
+                let arrays_count = reader.u8()?;
+                for _ in 0..arrays_count {
+                    // array_completeness and NAL unit type
+                    reader.u8()?;
+                    let nal_units_count = reader.u16()?;
+                    for _ in 0..nal_units_count {
+                        reader.parameter_set(&mut parameter_sets)?;
+                    }
+                }
+
+                nal_length_size
+            }
+            Codec::Av1 | Codec::Vp8 | Codec::Vp9 => {
+                return Err(AnnexBConversionError::MalformedExtradata)
+            }
+        };
+
+        Ok(Self {
+            nal_length_size: Some(nal_length_size),
+            parameter_sets: parameter_sets.freeze(),
+        })
+    }
+
+    pub(super) fn convert(
+        &self,
+        data: &[u8],
+        is_key: bool,
+    ) -> Result<Bytes, AnnexBConversionError> {
+        let mut output = BytesMut::with_capacity(data.len() + self.parameter_sets.len());
+        if is_key {
+            output.extend_from_slice(&self.parameter_sets);
+        }
+
+        let Some(nal_length_size) = self.nal_length_size else {
+            output.extend_from_slice(data);
+            return Ok(output.freeze());
+        };
+
+        let mut reader = Reader(data);
+        while !reader.0.is_empty() {
+            let nal_length = reader
+                .take(nal_length_size)
+                .map_err(|_| AnnexBConversionError::MalformedPacket)?
+                .iter()
+                .fold(0usize, |length, byte| (length << 8) | *byte as usize);
+            let nal_unit = reader
+                .take(nal_length)
+                .map_err(|_| AnnexBConversionError::MalformedPacket)?;
+
+            output.extend_from_slice(&START_CODE);
+            output.extend_from_slice(nal_unit);
+        }
+
+        Ok(output.freeze())
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], AnnexBConversionError> {
+        if self.0.len() < len {
+            return Err(AnnexBConversionError::MalformedExtradata);
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, AnnexBConversionError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, AnnexBConversionError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Reads a 16-bit length-prefixed parameter set and writes it in the Annex-B format.
+    fn parameter_set(&mut self, output: &mut BytesMut) -> Result<(), AnnexBConversionError> {
+        let length = self.u16()? as usize;
+        output.extend_from_slice(&START_CODE);
+        output.extend_from_slice(self.take(length)?);
+        Ok(())
+    }
+}
@@ -1,20 +1,49 @@
 use std::{
     net,
-    sync::{atomic::AtomicBool, Arc},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64},
+        Arc,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
-use crate::pipeline::structs::{Codec, EncodedChunk, EncodedChunkKind};
+use crate::{
+    event::{self, Event},
+    pipeline::structs::{Codec, EncodedChunk, EncodedChunkKind},
+};
 use bytes::BytesMut;
-use crossbeam_channel::{unbounded, Receiver, Sender};
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
 use log::{error, warn};
-use rtp::{codecs::h264::H264Packet, packetizer::Depacketizer};
+use rtp::{
+    codecs::{h264::H264Packet, vp8::Vp8Packet, vp9::Vp9Packet},
+    packetizer::Depacketizer,
+};
 use webrtc_util::Unmarshal;
 
+use super::{buffer::InputBufferSize, transform::FrameTransform};
+
+use self::{h265::H265Depayloader, jitter_buffer::JitterBuffer, vpx::VpxDepayloader};
+
+mod h265;
+mod jitter_buffer;
+mod vpx;
+
+#[cfg(test)]
+mod h265_test;
+#[cfg(test)]
+mod jitter_buffer_test;
+
+/// Payload type used by the compositor's RTP outputs and most senders for video.
+const DEFAULT_PAYLOAD_TYPE: u8 = 96;
+
 pub struct RtpReceiver {
     receiver_thread: Option<thread::JoinHandle<()>>,
     should_close: Arc<AtomicBool>,
+    reconnect_count: Arc<AtomicU32>,
+    late_packets: Arc<AtomicU64>,
     pub port: u16,
+    pub bind_addr: Option<net::Ipv4Addr>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -24,18 +53,126 @@ pub enum RtpReceiverError {
 
     #[error("Error while binding the socket.")]
     SocketBind(#[source] std::io::Error),
+
+    #[error("Receiving {0:?} over RTP is not supported.")]
+    UnsupportedCodec(Codec),
 }
 
 pub struct RtpReceiverOptions {
     pub port: u16,
+    /// Local address the socket is bound to. If not specified, packets are received on
+    /// all interfaces.
+    pub bind_addr: Option<net::Ipv4Addr>,
+    pub codec: Codec,
     pub input_id: compositor_render::InputId,
+    /// Rebind the socket if no packets were received for `timeout`.
+    pub reconnect: bool,
+    /// Input is considered disconnected if no packets were received for this duration.
+    /// Frames older than this are also replaced by the fallback in the rendered scene.
+    pub timeout: Duration,
+    /// Payload type negotiated with the sender, e.g. over SDP. Packets with other payload
+    /// types are dropped. Defaults to 96.
+    pub payload_type: Option<u8>,
+    /// Maximal time packets are held to reorder them by sequence number. If zero,
+    /// packets are depayloaded in the order they arrive.
+    pub jitter_buffer: Duration,
+    /// Buffer between the receiver and the decoder that spreads bursts of frames over time.
+    pub buffer: Option<InputBufferSize>,
+    /// Crop and scale applied to decoded frames before they are used in scenes.
+    pub transform: Option<FrameTransform>,
+}
+
+struct ReceiverThreadOptions {
+    port: u16,
+    bind_addr: Option<net::Ipv4Addr>,
+    input_id: compositor_render::InputId,
+    reconnect: bool,
+    timeout: Duration,
+}
+
+/// Messages sent from the receiver thread to the `ChunkIter`.
+enum ReceiverEvent {
+    Packet(bytes::Bytes),
+    /// Socket was rebound after the sender stopped sending packets.
+    Reconnected,
 }
 
 impl RtpReceiver {
     pub fn new(opts: RtpReceiverOptions) -> Result<(Self, ChunkIter), RtpReceiverError> {
+        let depayloader = match opts.codec {
+            Codec::H264 => Depayloader::H264(H264Packet::default()),
+            Codec::H265 => Depayloader::H265(H265Depayloader::default()),
+            Codec::Vp8 => Depayloader::Vp8(VpxDepayloader::default()),
+            Codec::Vp9 => Depayloader::Vp9(VpxDepayloader::default()),
+            codec => return Err(RtpReceiverError::UnsupportedCodec(codec)),
+        };
+
         let should_close = Arc::new(AtomicBool::new(false));
+        let reconnect_count = Arc::new(AtomicU32::new(0));
+        let late_packets = Arc::new(AtomicU64::new(0));
         let (packets_tx, packets_rx) = unbounded();
 
+        let socket = Self::bind_socket(opts.bind_addr, opts.port)?;
+
+        let should_close2 = should_close.clone();
+        let reconnect_count2 = reconnect_count.clone();
+        let receiver_options = ReceiverThreadOptions {
+            port: opts.port,
+            bind_addr: opts.bind_addr,
+            input_id: opts.input_id.clone(),
+            reconnect: opts.reconnect,
+            timeout: opts.timeout,
+        };
+
+        let receiver_thread = thread::Builder::new()
+            .name(format!("RTP receiver {}", opts.input_id))
+            .spawn(move || {
+                RtpReceiver::rtp_receiver(
+                    socket,
+                    receiver_options,
+                    packets_tx,
+                    should_close2,
+                    reconnect_count2,
+                )
+            })
+            .unwrap();
+
+        Ok((
+            Self {
+                port: opts.port,
+                bind_addr: opts.bind_addr,
+                receiver_thread: Some(receiver_thread),
+                should_close,
+                reconnect_count,
+                late_packets: late_packets.clone(),
+            },
+            ChunkIter {
+                receiver: packets_rx,
+                depayloader,
+                payload_type: opts.payload_type.unwrap_or(DEFAULT_PAYLOAD_TYPE),
+                timestamps: TimestampRebase::default(),
+                jitter_buffer: (!opts.jitter_buffer.is_zero())
+                    .then(|| JitterBuffer::new(opts.jitter_buffer, late_packets)),
+            },
+        ))
+    }
+
+    /// Number of times the socket was rebound because the sender stopped sending packets.
+    pub fn reconnect_count(&self) -> u32 {
+        self.reconnect_count
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Number of packets dropped by the jitter buffer, because they arrived after the
+    /// packets following them were already depayloaded.
+    pub fn late_packet_count(&self) -> u64 {
+        self.late_packets.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn bind_socket(
+        bind_addr: Option<net::Ipv4Addr>,
+        port: u16,
+    ) -> Result<std::net::UdpSocket, RtpReceiverError> {
         let socket = socket2::Socket::new(
             socket2::Domain::IPV4,
             socket2::Type::DGRAM,
@@ -56,8 +193,8 @@ impl RtpReceiver {
         socket
             .bind(
                 &net::SocketAddr::V4(net::SocketAddrV4::new(
-                    net::Ipv4Addr::UNSPECIFIED,
-                    opts.port,
+                    bind_addr.unwrap_or(net::Ipv4Addr::UNSPECIFIED),
+                    port,
                 ))
                 .into(),
             )
@@ -67,44 +204,62 @@ impl RtpReceiver {
             .set_read_timeout(Some(std::time::Duration::from_millis(50)))
             .map_err(RtpReceiverError::SocketOptions)?;
 
-        let socket = std::net::UdpSocket::from(socket);
-
-        let should_close2 = should_close.clone();
-
-        let receiver_thread = thread::Builder::new()
-            .name(format!("RTP receiver {}", opts.input_id))
-            .spawn(move || RtpReceiver::rtp_receiver(socket, packets_tx, should_close2))
-            .unwrap();
-
-        Ok((
-            Self {
-                port: opts.port,
-                receiver_thread: Some(receiver_thread),
-                should_close,
-            },
-            ChunkIter {
-                receiver: packets_rx,
-                depayloader: H264Packet::default(),
-            },
-        ))
+        Ok(std::net::UdpSocket::from(socket))
     }
 }
 
 impl RtpReceiver {
     fn rtp_receiver(
         socket: std::net::UdpSocket,
-        packets_tx: Sender<bytes::Bytes>,
+        opts: ReceiverThreadOptions,
+        packets_tx: Sender<ReceiverEvent>,
         should_close: Arc<AtomicBool>,
+        reconnect_count: Arc<AtomicU32>,
     ) {
         let mut buffer = BytesMut::zeroed(65536);
+        // `None` only if rebinding the socket failed.
+        let mut socket = Some(socket);
+        // `None` if no packets were received since the socket was bound or since the input
+        // was disconnected. Disconnect is reported only once per gap in the stream.
+        let mut last_packet_time: Option<Instant> = None;
+        let port = opts.port;
 
         loop {
             if should_close.load(std::sync::atomic::Ordering::Relaxed) {
                 return;
             }
 
+            if last_packet_time.is_some_and(|last_packet| last_packet.elapsed() > opts.timeout) {
+                last_packet_time = None;
+                event::emit(Event::InputDisconnected(opts.input_id.clone()));
+
+                if opts.reconnect {
+                    warn!(
+                        "No RTP packets received on port {port} for {:?}. Rebinding the socket.",
+                        opts.timeout
+                    );
+                    // The old socket has to be closed before the port can be bound again.
+                    socket = None;
+                    reconnect_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    if packets_tx.send(ReceiverEvent::Reconnected).is_err() {
+                        return;
+                    }
+                }
+            }
+
+            let Some(ref current_socket) = socket else {
+                match Self::bind_socket(opts.bind_addr, port) {
+                    Ok(new_socket) => socket = Some(new_socket),
+                    Err(err) => {
+                        error!("Failed to rebind the socket on port {port}: {err}");
+                        thread::sleep(Duration::from_millis(500));
+                    }
+                }
+                continue;
+            };
+
             // This can be faster if we batched sending the packets through the channel
-            let (received_bytes, _) = match socket.recv_from(&mut buffer) {
+            let (received_bytes, _) = match current_socket.recv_from(&mut buffer) {
                 Ok(n) => n,
                 Err(e) => match e.kind() {
                     std::io::ErrorKind::WouldBlock => continue,
@@ -115,8 +270,9 @@ impl RtpReceiver {
                 },
             };
 
+            last_packet_time = Some(Instant::now());
             let packet: bytes::Bytes = buffer[..received_bytes].to_vec().into();
-            packets_tx.send(packet).unwrap();
+            packets_tx.send(ReceiverEvent::Packet(packet)).unwrap();
         }
     }
 }
@@ -134,8 +290,87 @@ impl Drop for RtpReceiver {
 }
 
 pub struct ChunkIter {
-    receiver: Receiver<bytes::Bytes>,
-    depayloader: H264Packet,
+    receiver: Receiver<ReceiverEvent>,
+    depayloader: Depayloader,
+    payload_type: u8,
+    timestamps: TimestampRebase,
+    jitter_buffer: Option<JitterBuffer>,
+}
+
+enum Depayloader {
+    H264(H264Packet),
+    H265(H265Depayloader),
+    Vp8(VpxDepayloader<Vp8Packet>),
+    Vp9(VpxDepayloader<Vp9Packet>),
+}
+
+impl Depayloader {
+    fn reset(&mut self) {
+        match self {
+            Depayloader::H264(depayloader) => *depayloader = H264Packet::default(),
+            Depayloader::H265(depayloader) => *depayloader = H265Depayloader::default(),
+            Depayloader::Vp8(depayloader) => *depayloader = VpxDepayloader::default(),
+            Depayloader::Vp9(depayloader) => *depayloader = VpxDepayloader::default(),
+        }
+    }
+}
+
+/// A restarted sender starts from a random RTP timestamp. Timestamps after a reconnect
+/// are shifted to continue from the previous stream, so the decoder and the queue
+/// receive monotonic timestamps.
+#[derive(Default)]
+pub(super) struct TimestampRebase {
+    offset: i64,
+    last_timestamp: Option<(i64, Instant)>,
+    pub(super) after_reconnect: bool,
+}
+
+impl TimestampRebase {
+    pub(super) fn rebase(&mut self, timestamp: i64) -> i64 {
+        if self.after_reconnect {
+            self.after_reconnect = false;
+            if let Some((last_timestamp, last_time)) = self.last_timestamp {
+                let gap = (last_time.elapsed().as_secs_f64() * 90000.0) as i64;
+                self.offset = last_timestamp + gap - timestamp;
+            }
+        }
+
+        let timestamp = timestamp + self.offset;
+        self.last_timestamp = Some((timestamp, Instant::now()));
+        timestamp
+    }
+}
+
+impl ChunkIter {
+    /// Waits for the next event. If packets are buffered, it returns `None` when the
+    /// oldest of them should be released.
+    fn recv(&self) -> Result<Option<ReceiverEvent>, RecvTimeoutError> {
+        match self.jitter_buffer.as_ref().and_then(JitterBuffer::deadline) {
+            Some(deadline) => match self.receiver.recv_deadline(deadline) {
+                Ok(event) => Ok(Some(event)),
+                Err(RecvTimeoutError::Timeout) => Ok(None),
+                Err(err) => Err(err),
+            },
+            None => Ok(Some(self.receiver.recv()?)),
+        }
+    }
+
+    fn depayload(&mut self, packet: rtp::packet::Packet) -> Option<EncodedChunk> {
+        match chunk_from_rtp(packet, &mut self.depayloader, self.payload_type) {
+            Ok(Some(mut chunk)) => {
+                // Decoder has to be reset, so it does not use references and
+                // parameter sets from the previous stream.
+                chunk.discontinuity = self.timestamps.after_reconnect;
+                chunk.pts = self.timestamps.rebase(chunk.pts);
+                Some(chunk)
+            }
+            Ok(None) => None,
+            Err(err) => {
+                warn!("RTP depayloading error: {}", err);
+                None
+            }
+        }
+    }
 }
 
 impl Iterator for ChunkIter {
@@ -143,7 +378,25 @@ impl Iterator for ChunkIter {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let mut buffer = self.receiver.recv().ok()?;
+            if let Some(packet) = self.jitter_buffer.as_mut().and_then(JitterBuffer::pop) {
+                match self.depayload(packet) {
+                    Some(chunk) => return Some(chunk),
+                    None => continue,
+                }
+            }
+
+            let mut buffer = match self.recv().ok()? {
+                Some(ReceiverEvent::Packet(packet)) => packet,
+                Some(ReceiverEvent::Reconnected) => {
+                    self.depayloader.reset();
+                    self.timestamps.after_reconnect = true;
+                    if let Some(jitter_buffer) = &mut self.jitter_buffer {
+                        jitter_buffer.reset();
+                    }
+                    continue;
+                }
+                None => continue,
+            };
 
             match rtp::packet::Packet::unmarshal(&mut buffer.clone()) {
                 // https://datatracker.ietf.org/doc/html/rfc5761#section-4
@@ -155,12 +408,12 @@ impl Iterator for ChunkIter {
                 Ok(packet)
                     if packet.header.payload_type < 64 || packet.header.payload_type > 95 =>
                 {
-                    match chunk_from_rtp(packet, &mut self.depayloader) {
-                        Ok(Some(chunk)) => return Some(chunk),
-                        Ok(None) => continue,
-                        Err(err) => {
-                            warn!("RTP depayloading error: {}", err);
-                            continue;
+                    match &mut self.jitter_buffer {
+                        Some(jitter_buffer) => jitter_buffer.push(packet),
+                        None => {
+                            if let Some(chunk) = self.depayload(packet) {
+                                return Some(chunk);
+                            }
                         }
                     }
                 }
@@ -168,8 +421,6 @@ impl Iterator for ChunkIter {
                     if rtcp::packet::unmarshal(&mut buffer).is_err() {
                         warn!("Received an unexpected packet, which is not recognized either as RTP or RTCP. Dropping.");
                     }
-
-                    continue;
                 }
             };
         }
@@ -178,18 +429,35 @@ impl Iterator for ChunkIter {
 
 #[derive(Debug, thiserror::Error)]
 enum DepayloadingError {
-    #[error("Bad payload type {0}")]
-    BadPayloadType(u8),
+    #[error(
+        "Received payload type {found}, but the input expects {expected}. Dropping the packet."
+    )]
+    BadPayloadType { found: u8, expected: u8 },
+    #[error("Received a fragment of an H265 NAL unit without its first fragment")]
+    MissingH265FragmentStart,
+    #[error("H265 PACI packets are not supported")]
+    UnsupportedH265Paci,
+    #[error("Received a packet of a VP8/VP9 frame without its first packet")]
+    MissingVpxFrameStart,
     #[error(transparent)]
     Rtp(#[from] rtp::Error),
 }
 
 fn chunk_from_rtp(
     packet: rtp::packet::Packet,
-    depayloader: &mut H264Packet,
+    depayloader: &mut Depayloader,
+    payload_type: u8,
 ) -> Result<Option<EncodedChunk>, DepayloadingError> {
-    match packet.header.payload_type {
-        96 => {
+    // Packets of other streams would corrupt NAL units reassembled by the depayloader.
+    if packet.header.payload_type != payload_type {
+        return Err(DepayloadingError::BadPayloadType {
+            found: packet.header.payload_type,
+            expected: payload_type,
+        });
+    }
+
+    match depayloader {
+        Depayloader::H264(depayloader) => {
             let kind = EncodedChunkKind::Video(Codec::H264);
 
             let h264_packet = depayloader.depacketize(&packet.payload)?;
@@ -202,11 +470,52 @@ fn chunk_from_rtp(
                 data: h264_packet,
                 pts: packet.header.timestamp as i64,
                 dts: None,
+                is_keyframe: false,
+                discontinuity: false,
+                kind,
+            }))
+        }
 
+        Depayloader::H265(depayloader) => {
+            let kind = EncodedChunkKind::Video(Codec::H265);
+
+            let Some(access_unit) = depayloader.depayload(&packet)? else {
+                return Ok(None);
+            };
+
+            Ok(Some(EncodedChunk {
+                data: access_unit,
+                pts: packet.header.timestamp as i64,
+                dts: None,
+                is_keyframe: false,
+                discontinuity: false,
                 kind,
             }))
         }
 
-        v => Err(DepayloadingError::BadPayloadType(v)),
+        Depayloader::Vp8(depayloader) => {
+            let Some(frame) = depayloader.depayload(&packet)? else {
+                return Ok(None);
+            };
+            Ok(Some(vpx_chunk(frame, &packet, Codec::Vp8)))
+        }
+
+        Depayloader::Vp9(depayloader) => {
+            let Some(frame) = depayloader.depayload(&packet)? else {
+                return Ok(None);
+            };
+            Ok(Some(vpx_chunk(frame, &packet, Codec::Vp9)))
+        }
+    }
+}
+
+fn vpx_chunk(frame: bytes::Bytes, packet: &rtp::packet::Packet, codec: Codec) -> EncodedChunk {
+    EncodedChunk {
+        data: frame,
+        pts: packet.header.timestamp as i64,
+        dts: None,
+        is_keyframe: false,
+        discontinuity: false,
+        kind: EncodedChunkKind::Video(codec),
     }
 }
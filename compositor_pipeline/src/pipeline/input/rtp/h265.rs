@@ -0,0 +1,114 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use log::warn;
+use rtp::{
+    codecs::h265::{H265Packet, H265Payload},
+    packetizer::Depacketizer,
+};
+
+use super::DepayloadingError;
+
+const ANNEX_B_START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+/// Converts H265 RTP payloads (RFC 7798) into Annex-B access units.
+///
+/// `H265Packet` from the `rtp` crate only parses the payload and, unlike the H264
+/// depacketizer, returns it unchanged, so the NAL units have to be extracted here.
+#[derive(Default)]
+pub(super) struct H265Depayloader {
+    parser: H265Packet,
+    access_unit: BytesMut,
+    access_unit_timestamp: Option<u32>,
+    fragmented_nal: Option<BytesMut>,
+}
+
+impl H265Depayloader {
+    /// Returns a chunk only after the last packet of an access unit (marked with the
+    /// RTP marker bit) is received.
+    pub(super) fn depayload(
+        &mut self,
+        packet: &rtp::packet::Packet,
+    ) -> Result<Option<Bytes>, DepayloadingError> {
+        if self.access_unit_timestamp != Some(packet.header.timestamp) {
+            if !self.access_unit.is_empty() {
+                warn!("Dropping an incomplete H265 access unit. The last packet of the frame was lost.");
+            }
+            self.reset();
+            self.access_unit_timestamp = Some(packet.header.timestamp);
+        }
+
+        if let Err(err) = self.parse_payload(&packet.payload) {
+            self.reset();
+            return Err(err);
+        }
+
+        if !packet.header.marker || self.access_unit.is_empty() {
+            return Ok(None);
+        }
+
+        self.access_unit_timestamp = None;
+        Ok(Some(self.access_unit.split().freeze()))
+    }
+
+    fn parse_payload(&mut self, payload: &Bytes) -> Result<(), DepayloadingError> {
+        self.parser.depacketize(payload)?;
+
+        match self.parser.payload() {
+            H265Payload::H265SingleNALUnitPacket(packet) => {
+                self.access_unit.extend_from_slice(&ANNEX_B_START_CODE);
+                self.access_unit.put_u16(packet.payload_header().0);
+                self.access_unit.extend_from_slice(&packet.payload());
+            }
+
+            H265Payload::H265AggregationPacket(packet) => {
+                let nal_units = packet
+                    .first_unit()
+                    .map(|unit| unit.nal_unit())
+                    .into_iter()
+                    .chain(packet.other_units().iter().map(|unit| unit.nal_unit()));
+
+                for nal_unit in nal_units {
+                    self.access_unit.extend_from_slice(&ANNEX_B_START_CODE);
+                    self.access_unit.extend_from_slice(&nal_unit);
+                }
+            }
+
+            H265Payload::H265FragmentationUnitPacket(packet) => {
+                let fu_header = packet.fu_header();
+
+                if fu_header.s() {
+                    // The original NAL unit header is not transmitted. It's the payload header
+                    // with the type replaced by the one from the FU header.
+                    let nal_header = (packet.payload_header().0 & 0b1000_0001_1111_1111)
+                        | ((fu_header.fu_type() as u16) << 9);
+
+                    let mut nal_unit = BytesMut::new();
+                    nal_unit.put_u16(nal_header);
+                    self.fragmented_nal = Some(nal_unit);
+                }
+
+                let Some(nal_unit) = self.fragmented_nal.as_mut() else {
+                    return Err(DepayloadingError::MissingH265FragmentStart);
+                };
+                nal_unit.extend_from_slice(&packet.payload());
+
+                if fu_header.e() {
+                    if let Some(nal_unit) = self.fragmented_nal.take() {
+                        self.access_unit.extend_from_slice(&ANNEX_B_START_CODE);
+                        self.access_unit.extend_from_slice(&nal_unit);
+                    }
+                }
+            }
+
+            H265Payload::H265PACIPacket(_) => {
+                return Err(DepayloadingError::UnsupportedH265Paci);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.access_unit.clear();
+        self.fragmented_nal = None;
+    }
+}
@@ -0,0 +1,156 @@
+use bytes::Bytes;
+use rtp::packetizer::Payloader;
+
+use crate::pipeline::output::rtp::h265::H265Payloader;
+
+use super::{h265::H265Depayloader, DepayloadingError};
+
+const MTU: usize = 1200;
+
+/// Header of an IDR NAL unit (type 19) with temporal id 0.
+const IDR_HEADER: [u8; 2] = [19 << 1, 1];
+/// Header of a VPS NAL unit (type 32) with temporal id 0.
+const VPS_HEADER: [u8; 2] = [32 << 1, 1];
+
+fn nal_unit(header: [u8; 2], len: usize) -> Vec<u8> {
+    let body = (0..len).map(|i| (i % 250 + 1) as u8);
+    header.into_iter().chain(body).collect()
+}
+
+fn annex_b(nal_units: &[&[u8]], start_code: &[u8]) -> Bytes {
+    let mut data = Vec::new();
+    for nal_unit in nal_units {
+        data.extend_from_slice(start_code);
+        data.extend_from_slice(nal_unit);
+    }
+    Bytes::from(data)
+}
+
+/// Packets of a single access unit. The marker bit is set on the last one.
+fn packets(access_unit: &Bytes, mtu: usize, timestamp: u32) -> Vec<rtp::packet::Packet> {
+    let payloads = H265Payloader.payload(mtu, access_unit).unwrap();
+    let packets_amount = payloads.len();
+    payloads
+        .into_iter()
+        .enumerate()
+        .map(|(i, payload)| rtp::packet::Packet {
+            header: rtp::header::Header {
+                timestamp,
+                marker: i == packets_amount - 1,
+                ..Default::default()
+            },
+            payload,
+        })
+        .collect()
+}
+
+fn depayload_all(
+    depayloader: &mut H265Depayloader,
+    packets: &[rtp::packet::Packet],
+) -> Vec<Result<Option<Bytes>, DepayloadingError>> {
+    packets
+        .iter()
+        .map(|packet| depayloader.depayload(packet))
+        .collect()
+}
+
+fn access_units(results: Vec<Result<Option<Bytes>, DepayloadingError>>) -> Vec<Bytes> {
+    results
+        .into_iter()
+        .filter_map(|result| result.unwrap())
+        .collect()
+}
+
+#[test]
+fn test_single_nal_unit_packets_round_trip() {
+    let vps = nal_unit(VPS_HEADER, 20);
+    let idr = nal_unit(IDR_HEADER, 500);
+    let access_unit = annex_b(&[&vps, &idr], &[0, 0, 0, 1]);
+
+    let packets = packets(&access_unit, MTU, 3000);
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0].payload, Bytes::from(vps));
+
+    let mut depayloader = H265Depayloader::default();
+    let results = access_units(depayload_all(&mut depayloader, &packets));
+    assert_eq!(results, vec![access_unit]);
+}
+
+#[test]
+fn test_fragmentation_unit_packets_round_trip() {
+    let idr = nal_unit(IDR_HEADER, 100);
+    let access_unit = annex_b(&[&idr], &[0, 0, 0, 1]);
+
+    let packets = packets(&access_unit, 30, 3000);
+    assert_eq!(packets.len(), 4);
+    for (i, packet) in packets.iter().enumerate() {
+        // Payload header with the FU type, followed by the FU header with the start and
+        // end bits and the original NAL unit type.
+        assert_eq!(packet.payload[0] >> 1, 49);
+        let start_bit = (i == 0) as u8;
+        let end_bit = (i == packets.len() - 1) as u8;
+        assert_eq!(packet.payload[2], (start_bit << 7) | (end_bit << 6) | 19);
+    }
+
+    let mut depayloader = H265Depayloader::default();
+    let results = access_units(depayload_all(&mut depayloader, &packets));
+    assert_eq!(results, vec![access_unit]);
+}
+
+#[test]
+fn test_three_and_four_byte_start_codes() {
+    let vps = nal_unit(VPS_HEADER, 20);
+    let idr = nal_unit(IDR_HEADER, 40);
+    let short_start_codes = annex_b(&[&vps, &idr], &[0, 0, 1]);
+    let long_start_codes = annex_b(&[&vps, &idr], &[0, 0, 0, 1]);
+
+    let short_payloads = H265Payloader.payload(MTU, &short_start_codes).unwrap();
+    let long_payloads = H265Payloader.payload(MTU, &long_start_codes).unwrap();
+    assert_eq!(short_payloads, vec![Bytes::from(vps), Bytes::from(idr)]);
+    assert_eq!(short_payloads, long_payloads);
+
+    // Depayloaded access units always use 4-byte start codes.
+    let mut depayloader = H265Depayloader::default();
+    let packets = packets(&short_start_codes, MTU, 3000);
+    let results = access_units(depayload_all(&mut depayloader, &packets));
+    assert_eq!(results, vec![long_start_codes]);
+}
+
+#[test]
+fn test_lost_fragmentation_unit_start() {
+    let idr = nal_unit(IDR_HEADER, 100);
+    let access_unit = annex_b(&[&idr], &[0, 0, 0, 1]);
+    let fragments = packets(&access_unit, 30, 3000);
+
+    let mut depayloader = H265Depayloader::default();
+    let results = depayload_all(&mut depayloader, &fragments[1..]);
+    assert!(results
+        .iter()
+        .all(|result| matches!(result, Err(DepayloadingError::MissingH265FragmentStart))));
+
+    // Next access unit is depayloaded again.
+    let vps = nal_unit(VPS_HEADER, 20);
+    let next_access_unit = annex_b(&[&vps], &[0, 0, 0, 1]);
+    let next_packets = packets(&next_access_unit, MTU, 6000);
+    let results = access_units(depayload_all(&mut depayloader, &next_packets));
+    assert_eq!(results, vec![next_access_unit]);
+}
+
+#[test]
+fn test_access_unit_without_marker_packet_is_dropped() {
+    let vps = nal_unit(VPS_HEADER, 20);
+    let idr = nal_unit(IDR_HEADER, 100);
+    let first_access_unit = annex_b(&[&vps, &idr], &[0, 0, 0, 1]);
+    let second_access_unit = annex_b(&[&idr], &[0, 0, 0, 1]);
+
+    let mut first_packets = packets(&first_access_unit, 30, 3000);
+    first_packets.pop();
+    let second_packets = packets(&second_access_unit, 30, 6000);
+
+    let mut depayloader = H265Depayloader::default();
+    let results = access_units(depayload_all(
+        &mut depayloader,
+        &[first_packets, second_packets].concat(),
+    ));
+    assert_eq!(results, vec![second_access_unit]);
+}
@@ -0,0 +1,131 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Packets older than this many sequence numbers can be the start of a new sequence,
+/// e.g. after the sender was restarted with a random initial sequence number.
+const MAX_MISORDER: u64 = 100;
+
+/// Reorders RTP packets by sequence number before they are depayloaded. Packets in order
+/// are released immediately. When a packet is missing, the following packets are held
+/// for at most `delay`, waiting for it to arrive. The first packet is also held for
+/// `delay`, because packets sent before it can still arrive.
+pub(super) struct JitterBuffer {
+    delay: Duration,
+    /// Packets keyed by the extended sequence number, with the time they were received.
+    packets: BTreeMap<u64, (rtp::packet::Packet, Instant)>,
+    /// Extended sequence number of the next packet to release.
+    next_sequence_number: Option<u64>,
+    /// Highest extended sequence number received so far, used to extend 16-bit
+    /// sequence numbers after they wrap around.
+    highest_sequence_number: Option<u64>,
+    /// Packet far behind the current sequence. If the next packet follows it, the
+    /// sequence is restarted from it. Otherwise, it was just late.
+    restart_packet: Option<(u64, rtp::packet::Packet)>,
+    late_packets: Arc<AtomicU64>,
+}
+
+impl JitterBuffer {
+    pub(super) fn new(delay: Duration, late_packets: Arc<AtomicU64>) -> Self {
+        Self {
+            delay,
+            packets: BTreeMap::new(),
+            next_sequence_number: None,
+            highest_sequence_number: None,
+            restart_packet: None,
+            late_packets,
+        }
+    }
+
+    /// Drops all buffered packets, e.g. after the sender was restarted.
+    pub(super) fn reset(&mut self) {
+        self.packets.clear();
+        self.next_sequence_number = None;
+        self.highest_sequence_number = None;
+        self.restart_packet = None;
+    }
+
+    pub(super) fn push(&mut self, packet: rtp::packet::Packet) {
+        let sequence_number = self.extend_sequence_number(packet.header.sequence_number);
+
+        let restart_packet = self.restart_packet.take();
+        if let Some(next) = self.next_sequence_number {
+            if sequence_number + MAX_MISORDER < next {
+                match restart_packet {
+                    Some((previous, previous_packet)) if previous + 1 == sequence_number => {
+                        self.reset();
+                        for packet in [previous_packet, packet] {
+                            let sequence_number =
+                                self.extend_sequence_number(packet.header.sequence_number);
+                            self.insert(sequence_number, packet);
+                        }
+                    }
+                    _ => {
+                        if restart_packet.is_some() {
+                            self.late_packets.fetch_add(1, Ordering::Relaxed);
+                        }
+                        self.restart_packet = Some((sequence_number, packet));
+                    }
+                }
+                return;
+            }
+            if sequence_number < next {
+                // Packets after it were already depayloaded.
+                self.late_packets.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+        if restart_packet.is_some() {
+            self.late_packets.fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.insert(sequence_number, packet);
+    }
+
+    fn insert(&mut self, sequence_number: u64, packet: rtp::packet::Packet) {
+        self.packets
+            .entry(sequence_number)
+            .or_insert((packet, Instant::now()));
+    }
+
+    /// Returns the next packet if it's received, or if the missing packets before it
+    /// did not arrive within the delay.
+    pub(super) fn pop(&mut self) -> Option<rtp::packet::Packet> {
+        let (&sequence_number, (_, received_at)) = self.packets.first_key_value()?;
+        let is_next = self.next_sequence_number == Some(sequence_number);
+        if !is_next && received_at.elapsed() < self.delay {
+            return None;
+        }
+
+        self.next_sequence_number = Some(sequence_number + 1);
+        self.packets.pop_first().map(|(_, (packet, _))| packet)
+    }
+
+    /// Time when the oldest buffered packet should be released, even if the packets
+    /// before it are still missing.
+    pub(super) fn deadline(&self) -> Option<Instant> {
+        self.packets
+            .first_key_value()
+            .map(|(_, (_, received_at))| *received_at + self.delay)
+    }
+
+    fn extend_sequence_number(&mut self, sequence_number: u16) -> u64 {
+        // Start far enough from 0, so the sequence numbers of reordered packets don't
+        // underflow.
+        let Some(highest) = self.highest_sequence_number else {
+            let extended = (1 << 16) + sequence_number as u64;
+            self.highest_sequence_number = Some(extended);
+            return extended;
+        };
+
+        let diff = sequence_number.wrapping_sub(highest as u16) as i16;
+        let extended = highest.saturating_add_signed(diff as i64);
+        self.highest_sequence_number = Some(u64::max(highest, extended));
+        extended
+    }
+}
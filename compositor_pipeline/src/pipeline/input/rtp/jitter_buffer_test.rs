@@ -0,0 +1,152 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use bytes::Bytes;
+
+use super::jitter_buffer::JitterBuffer;
+
+const DELAY: Duration = Duration::from_millis(50);
+
+fn packet(sequence_number: u16) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            sequence_number,
+            ..Default::default()
+        },
+        payload: Bytes::new(),
+    }
+}
+
+fn push_all(buffer: &mut JitterBuffer, sequence_numbers: impl IntoIterator<Item = u16>) {
+    for sequence_number in sequence_numbers {
+        buffer.push(packet(sequence_number));
+    }
+}
+
+fn pop_all(buffer: &mut JitterBuffer) -> Vec<u16> {
+    std::iter::from_fn(|| buffer.pop())
+        .map(|packet| packet.header.sequence_number)
+        .collect()
+}
+
+/// Returns a buffer that already released the `first` packet. The first packet is held
+/// for the delay, so the buffer has to wait for it.
+fn started_buffer(first: u16) -> (JitterBuffer, Arc<AtomicU64>) {
+    let late_packets = Arc::new(AtomicU64::new(0));
+    let mut buffer = JitterBuffer::new(DELAY, late_packets.clone());
+    buffer.push(packet(first));
+    assert_eq!(pop_all(&mut buffer), Vec::<u16>::new());
+    thread::sleep(DELAY);
+    assert_eq!(pop_all(&mut buffer), vec![first]);
+    (buffer, late_packets)
+}
+
+fn late_count(late_packets: &AtomicU64) -> u64 {
+    late_packets.load(Ordering::Relaxed)
+}
+
+#[test]
+fn test_in_order_packets_are_released_immediately() {
+    let (mut buffer, late_packets) = started_buffer(1);
+
+    push_all(&mut buffer, [2, 3]);
+    assert_eq!(pop_all(&mut buffer), vec![2, 3]);
+    buffer.push(packet(4));
+    assert_eq!(pop_all(&mut buffer), vec![4]);
+    assert_eq!(buffer.deadline(), None);
+    assert_eq!(late_count(&late_packets), 0);
+}
+
+#[test]
+fn test_first_packet_received_after_second() {
+    let late_packets = Arc::new(AtomicU64::new(0));
+    let mut buffer = JitterBuffer::new(DELAY, late_packets.clone());
+
+    push_all(&mut buffer, [2, 1]);
+    assert_eq!(pop_all(&mut buffer), Vec::<u16>::new());
+    thread::sleep(DELAY);
+    assert_eq!(pop_all(&mut buffer), vec![1, 2]);
+    assert_eq!(late_count(&late_packets), 0);
+}
+
+#[test]
+fn test_gap_filled_before_delay() {
+    let (mut buffer, late_packets) = started_buffer(1);
+
+    push_all(&mut buffer, [3, 4]);
+    assert_eq!(pop_all(&mut buffer), Vec::<u16>::new());
+    assert!(buffer.deadline().is_some());
+    buffer.push(packet(2));
+    assert_eq!(pop_all(&mut buffer), vec![2, 3, 4]);
+    assert_eq!(late_count(&late_packets), 0);
+}
+
+#[test]
+fn test_gap_released_after_delay() {
+    let (mut buffer, late_packets) = started_buffer(1);
+
+    push_all(&mut buffer, [3, 4]);
+    assert_eq!(pop_all(&mut buffer), Vec::<u16>::new());
+    thread::sleep(DELAY);
+    assert_eq!(pop_all(&mut buffer), vec![3, 4]);
+
+    // Packets after the missing one were already released.
+    buffer.push(packet(2));
+    assert_eq!(pop_all(&mut buffer), Vec::<u16>::new());
+    assert_eq!(late_count(&late_packets), 1);
+}
+
+#[test]
+fn test_sequence_number_wraparound() {
+    let (mut buffer, late_packets) = started_buffer(65534);
+
+    push_all(&mut buffer, [65535, 0]);
+    assert_eq!(pop_all(&mut buffer), vec![65535, 0]);
+
+    // Reordered across the wraparound.
+    push_all(&mut buffer, [2, 1]);
+    assert_eq!(pop_all(&mut buffer), vec![1, 2]);
+    assert_eq!(late_count(&late_packets), 0);
+}
+
+#[test]
+fn test_sender_restart_with_new_sequence_number() {
+    let (mut buffer, late_packets) = started_buffer(30000);
+    push_all(&mut buffer, 30001..=30010);
+    assert_eq!(pop_all(&mut buffer), (30001..=30010).collect::<Vec<_>>());
+
+    // Restarted sender starts with a random sequence number far behind the current one.
+    buffer.push(packet(5000));
+    assert_eq!(pop_all(&mut buffer), Vec::<u16>::new());
+    buffer.push(packet(5001));
+    thread::sleep(DELAY);
+    assert_eq!(pop_all(&mut buffer), vec![5000, 5001]);
+
+    push_all(&mut buffer, [5002, 5003]);
+    assert_eq!(pop_all(&mut buffer), vec![5002, 5003]);
+    assert_eq!(late_count(&late_packets), 0);
+}
+
+#[test]
+fn test_single_late_packet_does_not_restart_sequence() {
+    let (mut buffer, late_packets) = started_buffer(1);
+    push_all(&mut buffer, 2..=300);
+    assert_eq!(pop_all(&mut buffer), (2..=300).collect::<Vec<_>>());
+
+    // Packet far behind the sequence is held as a possible restart until the next one.
+    buffer.push(packet(50));
+    push_all(&mut buffer, [301, 302]);
+    assert_eq!(pop_all(&mut buffer), vec![301, 302]);
+    assert_eq!(late_count(&late_packets), 1);
+
+    // Packet within the misorder window is counted as late right away.
+    buffer.push(packet(290));
+    assert_eq!(pop_all(&mut buffer), Vec::<u16>::new());
+    assert_eq!(late_count(&late_packets), 2);
+}
@@ -0,0 +1,63 @@
+use bytes::{Bytes, BytesMut};
+use log::warn;
+use rtp::packetizer::Depacketizer;
+
+use super::DepayloadingError;
+
+/// Reassembles VP8 (RFC 7741) or VP9 (RFC 9628) frames from RTP payloads. `T` is
+/// `Vp8Packet` or `Vp9Packet` from the `rtp` crate, which only strip the payload
+/// descriptor, so the frames are collected here.
+#[derive(Default)]
+pub(super) struct VpxDepayloader<T> {
+    parser: T,
+    frame: BytesMut,
+    frame_timestamp: Option<u32>,
+    /// Expected sequence number of the next packet of the current frame. Frames with
+    /// missing packets can't be decoded and are dropped.
+    next_sequence_number: Option<u16>,
+}
+
+impl<T: Depacketizer + Default> VpxDepayloader<T> {
+    /// Returns a frame only after its last packet (marked with the RTP marker bit)
+    /// is received.
+    pub(super) fn depayload(
+        &mut self,
+        packet: &rtp::packet::Packet,
+    ) -> Result<Option<Bytes>, DepayloadingError> {
+        let is_next = match self.next_sequence_number {
+            Some(next) => next == packet.header.sequence_number,
+            None => false,
+        };
+        if self.frame_timestamp != Some(packet.header.timestamp) || !is_next {
+            if !self.frame.is_empty() {
+                warn!("Dropping an incomplete VP8/VP9 frame. Some of its packets were lost.");
+            }
+            self.reset();
+            if !self.parser.is_partition_head(&packet.payload) {
+                return Err(DepayloadingError::MissingVpxFrameStart);
+            }
+            self.frame_timestamp = Some(packet.header.timestamp);
+        }
+
+        match self.parser.depacketize(&packet.payload) {
+            Ok(payload) => self.frame.extend_from_slice(&payload),
+            Err(err) => {
+                self.reset();
+                return Err(err.into());
+            }
+        }
+        self.next_sequence_number = Some(packet.header.sequence_number.wrapping_add(1));
+
+        if !packet.header.marker || self.frame.is_empty() {
+            return Ok(None);
+        }
+
+        self.frame_timestamp = None;
+        self.next_sequence_number = None;
+        Ok(Some(self.frame.split().freeze()))
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
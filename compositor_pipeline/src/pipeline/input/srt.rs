@@ -0,0 +1,296 @@
+use std::{
+    ffi::{c_int, c_void, CString},
+    ptr,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use compositor_render::InputId;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use ffmpeg_next::{codec::Id, ffi, format::context, media::Type, Dictionary, Packet, Rational};
+use log::{error, info, warn};
+
+use crate::pipeline::structs::{Codec, EncodedChunk, EncodedChunkKind};
+
+use super::rtp::TimestampRebase;
+
+/// Time between a failed connection attempt, or a lost connection, and the next attempt.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Receives an MPEG-TS stream over SRT. SRT retransmits lost packets within the
+/// configured latency, so it works better than plain RTP or UDP on lossy networks.
+///
+/// Depending on the URL, the input connects to a remote listener
+/// (`srt://host:port?mode=caller`) or waits for a sender (`srt://:port?mode=listener`).
+/// Connection is reestablished whenever it's lost.
+pub struct SrtInput {
+    pub url: Arc<str>,
+    connection_state: Arc<Mutex<SrtConnectionState>>,
+    reader_thread: Option<thread::JoinHandle<()>>,
+    should_close: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SrtConnectionState {
+    Connecting,
+    Connected,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SrtInputError {
+    #[error("SRT URL {0:?} contains a null byte.")]
+    InvalidUrl(Arc<str>),
+
+    #[error("Receiving {0:?} over SRT is not supported.")]
+    UnsupportedCodec(Codec),
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ConnectionError {
+    #[error("Failed to open the SRT stream.")]
+    Open(#[source] ffmpeg_next::Error),
+
+    #[error("Stream does not contain a video stream.")]
+    NoVideoStream,
+
+    #[error("Stream contains a {found:?} video stream, but the input was registered with a {expected:?} decoder.")]
+    CodecMismatch { expected: Codec, found: Id },
+}
+
+pub struct SrtInputOptions {
+    pub url: Arc<str>,
+    pub passphrase: Option<Arc<str>>,
+    /// Time the receiver waits for retransmissions of lost packets.
+    pub latency: Duration,
+    pub codec: Codec,
+    pub input_id: InputId,
+}
+
+impl SrtInput {
+    pub fn new(opts: SrtInputOptions) -> Result<(Self, ChunkIter), SrtInputError> {
+        if !matches!(opts.codec, Codec::H264 | Codec::H265) {
+            return Err(SrtInputError::UnsupportedCodec(opts.codec));
+        }
+        let url = CString::new(opts.url.as_bytes())
+            .map_err(|_| SrtInputError::InvalidUrl(opts.url.clone()))?;
+
+        ffmpeg_next::format::network::init();
+
+        let should_close = Arc::new(AtomicBool::new(false));
+        let connection_state = Arc::new(Mutex::new(SrtConnectionState::Connecting));
+        let (chunks_tx, chunks_rx) = unbounded();
+
+        let reader = Reader {
+            url,
+            passphrase: opts.passphrase,
+            latency: opts.latency,
+            codec: opts.codec,
+            input_id: opts.input_id.clone(),
+            connection_state: connection_state.clone(),
+            should_close: should_close.clone(),
+            chunks_tx,
+        };
+        let reader_thread = thread::Builder::new()
+            .name(format!("SRT reader {}", opts.input_id))
+            .spawn(move || reader.run())
+            .unwrap();
+
+        Ok((
+            Self {
+                url: opts.url,
+                connection_state,
+                reader_thread: Some(reader_thread),
+                should_close,
+            },
+            ChunkIter {
+                receiver: chunks_rx,
+            },
+        ))
+    }
+
+    pub fn connection_state(&self) -> SrtConnectionState {
+        *self.connection_state.lock().unwrap()
+    }
+}
+
+impl Drop for SrtInput {
+    fn drop(&mut self) {
+        self.should_close
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        if let Some(thread) = self.reader_thread.take() {
+            thread.join().unwrap();
+        } else {
+            error!("SRT input does not hold a thread handle to the reading thread.")
+        }
+    }
+}
+
+struct Reader {
+    url: CString,
+    passphrase: Option<Arc<str>>,
+    latency: Duration,
+    codec: Codec,
+    input_id: InputId,
+    connection_state: Arc<Mutex<SrtConnectionState>>,
+    should_close: Arc<AtomicBool>,
+    chunks_tx: Sender<EncodedChunk>,
+}
+
+impl Reader {
+    fn run(self) {
+        let mut timestamps = TimestampRebase::default();
+        let mut was_connected = false;
+
+        while !self.should_close() {
+            match self.open() {
+                Ok((input_ctx, stream_index, time_base)) => {
+                    info!("SRT input {} connected.", self.input_id);
+                    *self.connection_state.lock().unwrap() = SrtConnectionState::Connected;
+                    // Decoder is reset before the first chunk of the new connection, so it
+                    // does not use references and parameter sets from the previous stream.
+                    timestamps.after_reconnect = was_connected;
+                    was_connected = true;
+
+                    let receiver_dropped =
+                        self.read_packets(input_ctx, stream_index, time_base, &mut timestamps);
+                    *self.connection_state.lock().unwrap() = SrtConnectionState::Connecting;
+                    if receiver_dropped {
+                        return;
+                    }
+                    if !self.should_close() {
+                        warn!("SRT input {} disconnected, reconnecting.", self.input_id);
+                    }
+                }
+                // Opening also fails when it's interrupted by the input unregistration.
+                Err(_) if self.should_close() => return,
+                Err(err) => warn!("SRT input {}: {err}", self.input_id),
+            }
+
+            thread::sleep(RECONNECT_DELAY);
+        }
+    }
+
+    /// Returns `true` if chunks are no longer received by the decoder.
+    fn read_packets(
+        &self,
+        mut input_ctx: context::Input,
+        stream_index: usize,
+        time_base: Rational,
+        timestamps: &mut TimestampRebase,
+    ) -> bool {
+        loop {
+            let mut packet = Packet::empty();
+            // Reading is interrupted after the input is unregistered. Any other error
+            // means the connection was lost.
+            if let Err(err) = packet.read(&mut input_ctx) {
+                if !self.should_close() {
+                    warn!("Failed to read from SRT input {}: {err}", self.input_id);
+                }
+                return false;
+            }
+            if packet.stream() != stream_index {
+                continue;
+            }
+
+            // MPEG-TS carries H264 and H265 in the Annex-B format, so packets can be passed
+            // to the decoder as they are.
+            packet.rescale_ts(time_base, Rational::new(1, 90000));
+            let mut chunk =
+                match EncodedChunk::from_av_packet(&packet, EncodedChunkKind::Video(self.codec)) {
+                    Ok(chunk) => chunk,
+                    Err(err) => {
+                        warn!("Dropping a packet received in SRT stream: {err}");
+                        continue;
+                    }
+                };
+
+            chunk.discontinuity = timestamps.after_reconnect;
+            let pts = timestamps.rebase(chunk.pts);
+            chunk.dts = chunk.dts.map(|dts| dts + pts - chunk.pts);
+            chunk.pts = pts;
+
+            if self.chunks_tx.send(chunk).is_err() {
+                return true;
+            }
+        }
+    }
+
+    /// Blocks until the connection is established and enough data is received to probe
+    /// the stream.
+    fn open(&self) -> Result<(context::Input, usize, Rational), ConnectionError> {
+        let mut options = Dictionary::new();
+        // SRT options are specified in microseconds.
+        options.set("latency", &self.latency.as_micros().to_string());
+        if let Some(passphrase) = &self.passphrase {
+            options.set("passphrase", passphrase);
+        }
+
+        let input_ctx = unsafe {
+            let mut format_ctx = ffi::avformat_alloc_context();
+            (*format_ctx).interrupt_callback = ffi::AVIOInterruptCB {
+                callback: Some(interrupt_callback),
+                opaque: Arc::as_ptr(&self.should_close) as *mut c_void,
+            };
+
+            let mut options = options.disown();
+            // Format context is freed by `avformat_open_input` on failure.
+            let result = ffi::avformat_open_input(
+                &mut format_ctx,
+                self.url.as_ptr(),
+                ptr::null(),
+                &mut options,
+            );
+            ffi::av_dict_free(&mut options);
+            if result != 0 {
+                return Err(ConnectionError::Open(ffmpeg_next::Error::from(result)));
+            }
+
+            match ffi::avformat_find_stream_info(format_ctx, ptr::null_mut()) {
+                result if result >= 0 => context::Input::wrap(format_ctx),
+                err => {
+                    ffi::avformat_close_input(&mut format_ctx);
+                    return Err(ConnectionError::Open(ffmpeg_next::Error::from(err)));
+                }
+            }
+        };
+
+        let stream = input_ctx
+            .streams()
+            .best(Type::Video)
+            .ok_or(ConnectionError::NoVideoStream)?;
+        let found = stream.parameters().id();
+        if found != Id::from(self.codec) {
+            return Err(ConnectionError::CodecMismatch {
+                expected: self.codec,
+                found,
+            });
+        }
+        let (stream_index, time_base) = (stream.index(), stream.time_base());
+
+        Ok((input_ctx, stream_index, time_base))
+    }
+
+    fn should_close(&self) -> bool {
+        self.should_close.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+/// Aborts blocking reads and connection attempts after the input is unregistered.
+/// The reader outlives the format context, so the flag is valid whenever it's called.
+pub(super) unsafe extern "C" fn interrupt_callback(opaque: *mut c_void) -> c_int {
+    let should_close = &*(opaque as *const AtomicBool);
+    should_close.load(std::sync::atomic::Ordering::Relaxed) as c_int
+}
+
+pub struct ChunkIter {
+    receiver: Receiver<EncodedChunk>,
+}
+
+impl Iterator for ChunkIter {
+    type Item = EncodedChunk;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
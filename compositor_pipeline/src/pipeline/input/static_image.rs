@@ -0,0 +1,109 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use compositor_render::{Frame, Resolution, YuvData};
+use image::RgbImage;
+
+/// Image shown as an input stream, e.g. a background or a placeholder. The image is
+/// decoded once when the input is registered. Instead of passing frames through
+/// a decoder, the queue repeats the decoded frame in every frame set, so the input
+/// is always up to date and never falls back.
+pub struct StaticImageInput {
+    pub path: Arc<Path>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StaticImageError {
+    #[error("Failed to load image {0:?}.")]
+    Load(Arc<Path>, #[source] image::ImageError),
+
+    #[error("Image {path:?} is too small ({width}x{height}), it has to be at least 2x2.")]
+    TooSmall {
+        path: Arc<Path>,
+        width: u32,
+        height: u32,
+    },
+}
+
+pub struct StaticImageOptions {
+    pub path: Arc<Path>,
+}
+
+impl StaticImageInput {
+    /// Returns the input and the frame that should be shown for as long as the input
+    /// is registered.
+    pub fn new(opts: StaticImageOptions) -> Result<(Self, Frame), StaticImageError> {
+        let image = image::open(&opts.path)
+            .map_err(|err| StaticImageError::Load(opts.path.clone(), err))?
+            .to_rgb8();
+
+        // Chroma planes are subsampled, so the last row or column of an image with odd
+        // dimensions is cropped.
+        let resolution = Resolution {
+            width: (image.width() & !1) as usize,
+            height: (image.height() & !1) as usize,
+        };
+        if resolution.width == 0 || resolution.height == 0 {
+            return Err(StaticImageError::TooSmall {
+                path: opts.path,
+                width: image.width(),
+                height: image.height(),
+            });
+        }
+
+        let frame = Frame {
+            data: rgb_to_yuv420p(&image, resolution),
+            resolution,
+            pts: Duration::ZERO,
+        };
+        Ok((Self { path: opts.path }, frame))
+    }
+}
+
+/// Converts to full range BT.601, the same conversion that is used by the renderer.
+/// Every chroma sample is an average of a 2x2 block of pixels.
+fn rgb_to_yuv420p(image: &RgbImage, resolution: Resolution) -> YuvData {
+    let (width, height) = (resolution.width, resolution.height);
+    let mut y_plane = Vec::with_capacity(width * height);
+    let mut u_plane = Vec::with_capacity(width * height / 4);
+    let mut v_plane = Vec::with_capacity(width * height / 4);
+
+    let rgb = |x: usize, y: usize| {
+        let [r, g, b] = image.get_pixel(x as u32, y as u32).0;
+        (r as f32, g as f32, b as f32)
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            let (r, g, b) = rgb(x, y);
+            y_plane.push(
+                (0.299 * r + 0.587 * g + 0.114 * b)
+                    .round()
+                    .clamp(0.0, 255.0) as u8,
+            );
+        }
+    }
+
+    for y in (0..height).step_by(2) {
+        for x in (0..width).step_by(2) {
+            let block = [rgb(x, y), rgb(x + 1, y), rgb(x, y + 1), rgb(x + 1, y + 1)];
+            let (r, g, b) = block.iter().fold((0.0, 0.0, 0.0), |acc, pixel| {
+                (
+                    acc.0 + pixel.0 / 4.0,
+                    acc.1 + pixel.1 / 4.0,
+                    acc.2 + pixel.2 / 4.0,
+                )
+            });
+            let u = -0.168736 * r - 0.331264 * g + 0.5 * b + 128.0;
+            let v = 0.5 * r - 0.418688 * g - 0.081312 * b + 128.0;
+            u_plane.push(u.round().clamp(0.0, 255.0) as u8);
+            v_plane.push(v.round().clamp(0.0, 255.0) as u8);
+        }
+    }
+
+    YuvData {
+        y_plane: Bytes::from(y_plane),
+        u_plane: Bytes::from(u_plane),
+        v_plane: Bytes::from(v_plane),
+    }
+}
@@ -0,0 +1,279 @@
+use std::{
+    io::{self, Read},
+    net,
+    sync::{atomic::AtomicBool, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use compositor_render::InputId;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use log::{error, info, warn};
+
+use crate::pipeline::structs::{Codec, EncodedChunk, EncodedChunkKind};
+
+const START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+/// Interval of checking for new connections and of checking if the input was closed
+/// while waiting for data.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Receives a raw H264 Annex B byte stream over TCP, e.g. from encoders that write NAL
+/// units directly to a socket. The stream does not carry timestamps, so access units
+/// are timestamped when they are received.
+///
+/// Only one sender is connected at a time. After it disconnects, the input accepts a
+/// new connection.
+pub struct TcpInput {
+    pub port: u16,
+    listener_thread: Option<thread::JoinHandle<()>>,
+    should_close: Arc<AtomicBool>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TcpInputError {
+    #[error("Error while setting socket options.")]
+    SocketOptions(#[source] io::Error),
+
+    #[error("Error while binding the socket.")]
+    SocketBind(#[source] io::Error),
+}
+
+pub struct TcpInputOptions {
+    pub port: u16,
+    pub input_id: InputId,
+}
+
+impl TcpInput {
+    pub fn new(opts: TcpInputOptions) -> Result<(Self, ChunkIter), TcpInputError> {
+        let listener = net::TcpListener::bind((net::Ipv4Addr::UNSPECIFIED, opts.port))
+            .map_err(TcpInputError::SocketBind)?;
+        // Accepting is polled, so the thread can stop after the input is unregistered.
+        listener
+            .set_nonblocking(true)
+            .map_err(TcpInputError::SocketOptions)?;
+
+        let should_close = Arc::new(AtomicBool::new(false));
+        let (chunks_tx, chunks_rx) = unbounded();
+
+        let listener = Listener {
+            listener,
+            input_id: opts.input_id.clone(),
+            should_close: should_close.clone(),
+            chunks_tx,
+            start: Instant::now(),
+        };
+        let listener_thread = thread::Builder::new()
+            .name(format!("TCP H264 reader {}", opts.input_id))
+            .spawn(move || listener.run())
+            .unwrap();
+
+        Ok((
+            Self {
+                port: opts.port,
+                listener_thread: Some(listener_thread),
+                should_close,
+            },
+            ChunkIter {
+                receiver: chunks_rx,
+            },
+        ))
+    }
+}
+
+impl Drop for TcpInput {
+    fn drop(&mut self) {
+        self.should_close
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        if let Some(thread) = self.listener_thread.take() {
+            thread.join().unwrap();
+        } else {
+            error!("TCP input does not hold a thread handle to the listening thread.")
+        }
+    }
+}
+
+struct Listener {
+    listener: net::TcpListener,
+    input_id: InputId,
+    should_close: Arc<AtomicBool>,
+    chunks_tx: Sender<EncodedChunk>,
+    /// Timestamps of access units are measured from this point.
+    start: Instant,
+}
+
+impl Listener {
+    fn run(self) {
+        let mut was_connected = false;
+
+        while !self.should_close() {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    info!("TCP input {} connected from {addr}.", self.input_id);
+                    // Decoder is reset before the first chunk of the new connection, so it
+                    // does not use references and parameter sets from the previous stream.
+                    let receiver_dropped = self.read_stream(stream, was_connected);
+                    was_connected = true;
+                    if receiver_dropped {
+                        return;
+                    }
+                    if !self.should_close() {
+                        warn!(
+                            "TCP input {} disconnected, waiting for a new connection.",
+                            self.input_id
+                        );
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(err) => {
+                    error!(
+                        "Failed to accept a connection on TCP input {}: {err}",
+                        self.input_id
+                    );
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if chunks are no longer received by the decoder.
+    fn read_stream(&self, mut stream: net::TcpStream, discontinuity: bool) -> bool {
+        let result = stream
+            .set_nonblocking(false)
+            .and_then(|_| stream.set_read_timeout(Some(POLL_INTERVAL)));
+        if let Err(err) = result {
+            error!("Failed to set options of the TCP connection: {err}");
+            return false;
+        }
+
+        let mut splitter = AccessUnitSplitter::default();
+        let mut buffer = vec![0; 65536];
+        let mut discontinuity = discontinuity;
+        while !self.should_close() {
+            let received_bytes = match stream.read(&mut buffer) {
+                Ok(0) => return false,
+                Ok(received_bytes) => received_bytes,
+                Err(err) => match err.kind() {
+                    io::ErrorKind::WouldBlock
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::Interrupted => continue,
+                    _ => {
+                        warn!("Failed to read from TCP input {}: {err}", self.input_id);
+                        return false;
+                    }
+                },
+            };
+
+            for access_unit in splitter.push(&buffer[..received_bytes]) {
+                let chunk = EncodedChunk {
+                    data: access_unit,
+                    pts: (self.start.elapsed().as_secs_f64() * 90000.0) as i64,
+                    dts: None,
+                    is_keyframe: false,
+                    discontinuity,
+                    kind: EncodedChunkKind::Video(Codec::H264),
+                };
+                discontinuity = false;
+                if self.chunks_tx.send(chunk).is_err() {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn should_close(&self) -> bool {
+        self.should_close.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+/// Splits an Annex B byte stream into access units. A NAL unit is complete only after
+/// the start code of the next one is received, so access units are returned with
+/// a delay of one NAL unit.
+#[derive(Default)]
+struct AccessUnitSplitter {
+    /// Received data starting at the start code of a NAL unit that is not complete yet.
+    pending: BytesMut,
+    /// NAL units of the current access unit, with 4-byte start codes.
+    access_unit: BytesMut,
+    /// Whether the current access unit contains a slice of a picture.
+    has_slice: bool,
+}
+
+impl AccessUnitSplitter {
+    fn push(&mut self, data: &[u8]) -> Vec<Bytes> {
+        self.pending.extend_from_slice(data);
+        let start_codes = start_code_positions(&self.pending);
+        let Some(&last_start_code) = start_codes.last() else {
+            // Data before the first start code, e.g. after connecting in the middle of
+            // a stream, can't be decoded. Only the last bytes can be a part of a start code.
+            self.pending.advance(self.pending.len().saturating_sub(2));
+            return Vec::new();
+        };
+
+        let complete = self.pending.split_to(last_start_code).freeze();
+        start_codes
+            .windows(2)
+            .filter_map(|window| {
+                // Zero byte of a 4-byte start code is treated as trailing data of the
+                // previous NAL unit.
+                let nal_unit = trim_trailing_zeros(&complete[window[0] + 3..window[1]]);
+                self.push_nal_unit(nal_unit)
+            })
+            .collect()
+    }
+
+    /// Returns the previous access unit if the NAL unit starts a new one.
+    fn push_nal_unit(&mut self, nal_unit: &[u8]) -> Option<Bytes> {
+        let &header = nal_unit.first()?;
+        let nal_type = header & 0x1f;
+        let is_slice = matches!(nal_type, 1..=5);
+        // `first_mb_in_slice` is 0 only in the first slice of a picture. It's Exp-Golomb
+        // encoded, so 0 is a single set bit.
+        let is_first_slice = is_slice && nal_unit.get(1).is_some_and(|byte| byte & 0x80 != 0);
+        // SEI, SPS, PPS and access unit delimiters always precede slices of the picture.
+        let starts_access_unit = is_first_slice || matches!(nal_type, 6..=9);
+
+        let access_unit = match self.has_slice && starts_access_unit {
+            true => {
+                self.has_slice = false;
+                Some(self.access_unit.split().freeze())
+            }
+            false => None,
+        };
+        self.access_unit.extend_from_slice(&START_CODE);
+        self.access_unit.extend_from_slice(nal_unit);
+        self.has_slice |= is_slice;
+        access_unit
+    }
+}
+
+/// Positions of 3-byte start codes in the data.
+fn start_code_positions(data: &[u8]) -> Vec<usize> {
+    data.windows(3)
+        .enumerate()
+        .filter(|(_, window)| *window == [0, 0, 1])
+        .map(|(position, _)| position)
+        .collect()
+}
+
+fn trim_trailing_zeros(nal_unit: &[u8]) -> &[u8] {
+    let len = nal_unit
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |position| position + 1);
+    &nal_unit[..len]
+}
+
+pub struct ChunkIter {
+    receiver: Receiver<EncodedChunk>,
+}
+
+impl Iterator for ChunkIter {
+    type Item = EncodedChunk;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use compositor_render::{Frame, Framerate, Resolution, YuvData};
+
+use crate::queue::FrameGenerator;
+
+/// Synthetic input, e.g. for testing a pipeline without cameras. Frames are generated
+/// by the queue for every frame set instead of being decoded, so the input is never
+/// stale and never falls back.
+pub struct TestPatternInput {
+    pub kind: TestPatternKind,
+    pub resolution: Resolution,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestPatternKind {
+    /// SMPTE color bars. The frame never changes.
+    Bars,
+    /// Horizontal rainbow gradient that moves to the left by the frame width every
+    /// 4 seconds.
+    Gradient,
+    /// Number of the frame at the output framerate, counted from the start of the
+    /// compositor.
+    Counter,
+}
+
+pub struct TestPatternOptions {
+    pub kind: TestPatternKind,
+    /// Both dimensions have to be even.
+    pub resolution: Resolution,
+    /// Used to number the frames of the `Counter` pattern.
+    pub framerate: Framerate,
+}
+
+const GRADIENT_PERIOD: Duration = Duration::from_secs(4);
+
+/// 3x5 bitmaps of digits, every row is 3 bits with the leftmost pixel as the highest bit.
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+impl TestPatternInput {
+    /// Returns the input and the generator of its frames.
+    pub fn new(opts: TestPatternOptions) -> (Self, FrameGenerator) {
+        let TestPatternOptions {
+            kind,
+            resolution,
+            framerate,
+        } = opts;
+
+        let generator: FrameGenerator = match kind {
+            TestPatternKind::Bars => {
+                let data = bars(resolution);
+                Box::new(move |pts| Frame {
+                    data: data.clone(),
+                    resolution,
+                    pts,
+                })
+            }
+            TestPatternKind::Gradient => Box::new(move |pts| Frame {
+                data: gradient(resolution, pts),
+                resolution,
+                pts,
+            }),
+            TestPatternKind::Counter => Box::new(move |pts| {
+                let frame_number =
+                    (pts.as_secs_f64() * framerate.num as f64 / framerate.den as f64) as u64;
+                Frame {
+                    data: counter(resolution, frame_number),
+                    resolution,
+                    pts,
+                }
+            }),
+        };
+
+        (Self { kind, resolution }, generator)
+    }
+}
+
+/// 75% bars on the top two thirds, castellations below them, and -I, white, +Q and
+/// PLUGE on the bottom quarter.
+fn bars(resolution: Resolution) -> YuvData {
+    const TOP: [[u8; 3]; 7] = [
+        [191, 191, 191],
+        [191, 191, 0],
+        [0, 191, 191],
+        [0, 191, 0],
+        [191, 0, 191],
+        [191, 0, 0],
+        [0, 0, 191],
+    ];
+    const MIDDLE: [[u8; 3]; 7] = [
+        [0, 0, 191],
+        [19, 19, 19],
+        [191, 0, 191],
+        [19, 19, 19],
+        [0, 191, 191],
+        [19, 19, 19],
+        [191, 191, 191],
+    ];
+
+    let (width, height) = (resolution.width, resolution.height);
+    let top = [TOP.map(yuv), MIDDLE.map(yuv)];
+    let bottom = [
+        [0, 33, 76],
+        [255, 255, 255],
+        [50, 0, 106],
+        [19, 19, 19],
+        [0, 0, 0],
+        [19, 19, 19],
+        [38, 38, 38],
+        [19, 19, 19],
+    ]
+    .map(yuv);
+
+    fill(resolution, |x, y| {
+        if y < height * 3 / 4 {
+            let section = usize::from(y >= height * 2 / 3);
+            return top[section][x * 7 / width];
+        }
+        // First four bars are 5/4 of the width of the top bars, and PLUGE bars are
+        // 1/3 of it.
+        let position = x * 84 / width;
+        let index = match position {
+            0..=14 => 0,
+            15..=29 => 1,
+            30..=44 => 2,
+            45..=59 => 3,
+            60..=63 => 4,
+            64..=67 => 5,
+            68..=71 => 6,
+            _ => 7,
+        };
+        bottom[index]
+    })
+}
+
+fn gradient(resolution: Resolution, pts: Duration) -> YuvData {
+    let offset = pts.as_secs_f64() / GRADIENT_PERIOD.as_secs_f64();
+    let columns: Vec<[u8; 3]> = (0..resolution.width)
+        .map(|x| {
+            let hue = (x as f64 / resolution.width as f64 + offset).fract();
+            yuv(hue_to_rgb(hue))
+        })
+        .collect();
+    fill(resolution, |x, _| columns[x])
+}
+
+fn counter(resolution: Resolution, frame_number: u64) -> YuvData {
+    let digits: Vec<usize> = frame_number
+        .to_string()
+        .bytes()
+        .map(|digit| (digit - b'0') as usize)
+        .collect();
+
+    // Digits are separated by one scaled pixel and take at most half of the frame height.
+    let text_width_px = digits.len() * 4 - 1;
+    let scale = usize::max(
+        1,
+        usize::min(
+            resolution.height / 10,
+            resolution.width / (text_width_px + 2),
+        ),
+    );
+    let left = resolution.width.saturating_sub(text_width_px * scale) / 2;
+    let top = resolution.height.saturating_sub(5 * scale) / 2;
+
+    let background = yuv([32, 32, 32]);
+    let foreground = yuv([235, 235, 235]);
+    fill(resolution, |x, y| {
+        let (Some(x), Some(y)) = (x.checked_sub(left), y.checked_sub(top)) else {
+            return background;
+        };
+        let (column, row) = (x / scale, y / scale);
+        let Some(digit) = digits.get(column / 4) else {
+            return background;
+        };
+        let bit = column % 4;
+        if row < 5 && bit < 3 && DIGITS[*digit][row] & (0b100 >> bit) != 0 {
+            foreground
+        } else {
+            background
+        }
+    })
+}
+
+/// Creates planes from YUV colors of pixels. Chroma of every 2x2 block is taken from
+/// its top left pixel.
+fn fill(resolution: Resolution, color: impl Fn(usize, usize) -> [u8; 3]) -> YuvData {
+    let (width, height) = (resolution.width, resolution.height);
+    let mut y_plane = Vec::with_capacity(width * height);
+    let mut u_plane = Vec::with_capacity(width * height / 4);
+    let mut v_plane = Vec::with_capacity(width * height / 4);
+    for y in 0..height {
+        for x in 0..width {
+            y_plane.push(color(x, y)[0]);
+        }
+    }
+    for y in (0..height).step_by(2) {
+        for x in (0..width).step_by(2) {
+            let [_, u, v] = color(x, y);
+            u_plane.push(u);
+            v_plane.push(v);
+        }
+    }
+    YuvData {
+        y_plane: Bytes::from(y_plane),
+        u_plane: Bytes::from(u_plane),
+        v_plane: Bytes::from(v_plane),
+    }
+}
+
+/// Fully saturated color with the hue in the `[0, 1)` range.
+fn hue_to_rgb(hue: f64) -> [u8; 3] {
+    let channel = |offset: f64| {
+        let value = (hue * 6.0 + offset) % 6.0;
+        let value = 1.0 - f64::clamp(f64::min(value, 4.0 - value), 0.0, 1.0);
+        (value * 255.0).round() as u8
+    };
+    [channel(5.0), channel(3.0), channel(1.0)]
+}
+
+/// Full range BT.601, the same conversion that is used by the renderer.
+fn yuv([r, g, b]: [u8; 3]) -> [u8; 3] {
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    let y = 0.299 * r + 0.587 * g + 0.114 * b;
+    let u = -0.168736 * r - 0.331264 * g + 0.5 * b + 128.0;
+    let v = 0.5 * r - 0.418688 * g - 0.081312 * b + 128.0;
+    [y, u, v].map(|value| value.round().clamp(0.0, 255.0) as u8)
+}
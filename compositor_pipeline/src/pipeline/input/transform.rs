@@ -0,0 +1,245 @@
+use bytes::{Bytes, BytesMut};
+use compositor_render::{Frame, Resolution, YuvData};
+use ffmpeg_next::{format::Pixel, frame::Video, software::scaling};
+
+/// Transformation of decoded frames of an input, applied before they are passed to
+/// the queue, so inputs with unusual resolutions can be normalized before they are
+/// used in scenes.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameTransform {
+    /// Applied before scaling.
+    pub crop: Option<CropOptions>,
+    pub scale: Option<ScaleOptions>,
+}
+
+/// Area of the frame that is kept, in pixels. Offsets are rounded down to even values,
+/// because chroma planes are subsampled. The area is clipped to the frame.
+#[derive(Debug, Clone, Copy)]
+pub struct CropOptions {
+    pub top: usize,
+    pub left: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ScaleOptions {
+    /// Both dimensions have to be even.
+    pub resolution: Resolution,
+    pub mode: ScaleMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleMode {
+    /// Frames are scaled to the resolution, their aspect ratio is not preserved.
+    Stretch,
+    /// Frames are scaled to fit the resolution with their aspect ratio preserved. The rest
+    /// of the frame is filled with black.
+    Letterbox,
+}
+
+/// Applies a [`FrameTransform`] to consecutive frames of an input. The scaler is reused
+/// until the resolution of the input changes.
+pub(crate) struct FrameTransformer {
+    transform: FrameTransform,
+    /// Scaler with its source and destination resolution.
+    scaler: Option<(Resolution, Resolution, scaling::Context)>,
+}
+
+impl FrameTransformer {
+    pub(crate) fn new(transform: FrameTransform) -> Self {
+        Self {
+            transform,
+            scaler: None,
+        }
+    }
+
+    pub(crate) fn apply(&mut self, frame: Frame) -> Result<Frame, ffmpeg_next::Error> {
+        let frame = match self.transform.crop {
+            Some(crop) => crop_frame(frame, crop),
+            None => frame,
+        };
+        match self.transform.scale {
+            Some(scale) => self.scale_frame(frame, scale),
+            None => Ok(frame),
+        }
+    }
+
+    fn scale_frame(
+        &mut self,
+        frame: Frame,
+        scale: ScaleOptions,
+    ) -> Result<Frame, ffmpeg_next::Error> {
+        let target = match scale.mode {
+            ScaleMode::Stretch => scale.resolution,
+            ScaleMode::Letterbox => fit_resolution(frame.resolution, scale.resolution),
+        };
+
+        let scaled = if frame.resolution == target {
+            frame
+        } else {
+            let source = frame.resolution;
+            let scaler = match self.scaler.take() {
+                Some((from, to, scaler)) if from == source && to == target => scaler,
+                _ => scaling::Context::get(
+                    Pixel::YUV420P,
+                    source.width as u32,
+                    source.height as u32,
+                    Pixel::YUV420P,
+                    target.width as u32,
+                    target.height as u32,
+                    scaling::Flags::BILINEAR,
+                )?,
+            };
+            let scaler = &mut self.scaler.insert((source, target, scaler)).2;
+
+            let mut scaled = Video::new(Pixel::YUV420P, target.width as u32, target.height as u32);
+            scaler.run(&frame_to_av(&frame), &mut scaled)?;
+            Frame {
+                data: YuvData {
+                    y_plane: copy_plane_from_av(&scaled, 0, target),
+                    u_plane: copy_plane_from_av(&scaled, 1, target),
+                    v_plane: copy_plane_from_av(&scaled, 2, target),
+                },
+                resolution: target,
+                pts: frame.pts,
+            }
+        };
+
+        if scaled.resolution == scale.resolution {
+            return Ok(scaled);
+        }
+        Ok(letterbox(scaled, scale.resolution))
+    }
+}
+
+fn crop_frame(frame: Frame, crop: CropOptions) -> Frame {
+    let resolution = frame.resolution;
+    let top = crop.top & !1;
+    let left = crop.left & !1;
+    let width = usize::min(crop.width, resolution.width.saturating_sub(left)) & !1;
+    let height = usize::min(crop.height, resolution.height.saturating_sub(top)) & !1;
+    // Crop area is outside of the frame.
+    if width == 0 || height == 0 {
+        return frame;
+    }
+
+    let crop_plane = |data: &Bytes, plane: usize| {
+        let (plane_width, _) = plane_size(resolution, plane);
+        let (top, left) = match plane {
+            0 => (top, left),
+            _ => (top / 2, left / 2),
+        };
+        let (width, height) = plane_size(Resolution { width, height }, plane);
+
+        let mut output = BytesMut::with_capacity(width * height);
+        for row in data.chunks(plane_width).skip(top).take(height) {
+            output.extend_from_slice(&row[left..left + width]);
+        }
+        output.freeze()
+    };
+
+    Frame {
+        data: YuvData {
+            y_plane: crop_plane(&frame.data.y_plane, 0),
+            u_plane: crop_plane(&frame.data.u_plane, 1),
+            v_plane: crop_plane(&frame.data.v_plane, 2),
+        },
+        resolution: Resolution { width, height },
+        pts: frame.pts,
+    }
+}
+
+/// Largest resolution with even dimensions and the aspect ratio of `source` that fits
+/// in `target`.
+fn fit_resolution(source: Resolution, target: Resolution) -> Resolution {
+    let scale = f64::min(
+        target.width as f64 / source.width as f64,
+        target.height as f64 / source.height as f64,
+    );
+    let fit =
+        |size: usize, max: usize| usize::clamp((size as f64 * scale).round() as usize & !1, 2, max);
+    Resolution {
+        width: fit(source.width, target.width),
+        height: fit(source.height, target.height),
+    }
+}
+
+/// Places the frame in the middle of a black frame with the target resolution.
+fn letterbox(frame: Frame, resolution: Resolution) -> Frame {
+    let top = ((resolution.height - frame.resolution.height) / 2) & !1;
+    let left = ((resolution.width - frame.resolution.width) / 2) & !1;
+
+    // Full range BT.601 black, the same range that is used by the renderer.
+    let paste_plane = |data: &Bytes, plane: usize, background: u8| {
+        let (width, height) = plane_size(resolution, plane);
+        let (frame_width, _) = plane_size(frame.resolution, plane);
+        let (top, left) = match plane {
+            0 => (top, left),
+            _ => (top / 2, left / 2),
+        };
+
+        let mut output = vec![background; width * height];
+        for (index, row) in data.chunks(frame_width).enumerate() {
+            let start = (top + index) * width + left;
+            output[start..start + frame_width].copy_from_slice(row);
+        }
+        Bytes::from(output)
+    };
+
+    Frame {
+        data: YuvData {
+            y_plane: paste_plane(&frame.data.y_plane, 0, 0),
+            u_plane: paste_plane(&frame.data.u_plane, 1, 128),
+            v_plane: paste_plane(&frame.data.v_plane, 2, 128),
+        },
+        resolution,
+        pts: frame.pts,
+    }
+}
+
+fn frame_to_av(frame: &Frame) -> Video {
+    let resolution = frame.resolution;
+    let mut av_frame = Video::new(
+        Pixel::YUV420P,
+        resolution.width as u32,
+        resolution.height as u32,
+    );
+    let planes = [
+        &frame.data.y_plane,
+        &frame.data.u_plane,
+        &frame.data.v_plane,
+    ];
+    for (plane, data) in planes.into_iter().enumerate() {
+        let (width, _) = plane_size(resolution, plane);
+        let stride = av_frame.stride(plane);
+        for (row, av_row) in data
+            .chunks(width)
+            .zip(av_frame.data_mut(plane).chunks_mut(stride))
+        {
+            av_row[..width].copy_from_slice(row);
+        }
+    }
+    av_frame
+}
+
+fn copy_plane_from_av(av_frame: &Video, plane: usize, resolution: Resolution) -> Bytes {
+    let (width, height) = plane_size(resolution, plane);
+    let mut output = BytesMut::with_capacity(width * height);
+    for row in av_frame
+        .data(plane)
+        .chunks(av_frame.stride(plane))
+        .take(height)
+    {
+        output.extend_from_slice(&row[..width]);
+    }
+    output.freeze()
+}
+
+/// Chroma planes of YUV420P frames have half the width and height.
+fn plane_size(resolution: Resolution, plane: usize) -> (usize, usize) {
+    match plane {
+        0 => (resolution.width, resolution.height),
+        _ => (resolution.width / 2, resolution.height / 2),
+    }
+}
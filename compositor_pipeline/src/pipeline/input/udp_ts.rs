@@ -0,0 +1,309 @@
+use std::{
+    ffi::{c_int, c_void},
+    net, ptr,
+    sync::{atomic::AtomicBool, Arc},
+    thread,
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use compositor_render::InputId;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use ffmpeg_next::{codec::Id, ffi, format::context, media::Type, Rational};
+use log::{error, warn};
+
+use crate::pipeline::structs::{Codec, EncodedChunk, EncodedChunkKind};
+
+/// Size of the buffer used by the demuxer to read the stream.
+const AVIO_BUFFER_SIZE: usize = 64 * 1024;
+
+pub struct UdpTsInput {
+    pub port: u16,
+    reader_thread: Option<thread::JoinHandle<()>>,
+    should_close: Arc<AtomicBool>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UdpTsInputError {
+    #[error("Error while setting socket options.")]
+    SocketOptions(#[source] std::io::Error),
+
+    #[error("Error while binding the socket.")]
+    SocketBind(#[source] std::io::Error),
+
+    #[error("Receiving {0:?} over MPEG-TS is not supported.")]
+    UnsupportedCodec(Codec),
+}
+
+#[derive(Debug, thiserror::Error)]
+enum DemuxerError {
+    #[error("Failed to open the MPEG-TS demuxer.")]
+    Open(#[source] ffmpeg_next::Error),
+
+    #[error("Stream does not contain a video stream.")]
+    NoVideoStream,
+
+    #[error("Stream contains a {found:?} video stream, but the input was registered with a {expected:?} decoder.")]
+    CodecMismatch { expected: Codec, found: Id },
+}
+
+pub struct UdpTsInputOptions {
+    pub port: u16,
+    pub codec: Codec,
+    pub input_id: InputId,
+}
+
+impl UdpTsInput {
+    pub fn new(opts: UdpTsInputOptions) -> Result<(Self, ChunkIter), UdpTsInputError> {
+        if !matches!(opts.codec, Codec::H264 | Codec::H265) {
+            return Err(UdpTsInputError::UnsupportedCodec(opts.codec));
+        }
+
+        let socket = Self::bind_socket(opts.port)?;
+        let should_close = Arc::new(AtomicBool::new(false));
+        let (chunks_tx, chunks_rx) = unbounded();
+
+        let reader = DatagramReader {
+            socket,
+            should_close: should_close.clone(),
+            buffer: BytesMut::zeroed(65536),
+            pending: Bytes::new(),
+        };
+        let codec = opts.codec;
+        let reader_thread = thread::Builder::new()
+            .name(format!("UDP MPEG-TS reader {}", opts.input_id))
+            .spawn(move || {
+                if let Err(err) = Self::reader_thread(reader, codec, chunks_tx) {
+                    error!("Failed to read MPEG-TS stream: {err}");
+                }
+            })
+            .unwrap();
+
+        Ok((
+            Self {
+                port: opts.port,
+                reader_thread: Some(reader_thread),
+                should_close,
+            },
+            ChunkIter {
+                receiver: chunks_rx,
+            },
+        ))
+    }
+
+    fn bind_socket(port: u16) -> Result<net::UdpSocket, UdpTsInputError> {
+        let socket = socket2::Socket::new(
+            socket2::Domain::IPV4,
+            socket2::Type::DGRAM,
+            Some(socket2::Protocol::UDP),
+        )
+        .map_err(UdpTsInputError::SocketOptions)?;
+
+        if let Err(err) = socket.set_recv_buffer_size(16 * 1024 * 1024) {
+            warn!("Failed to set socket receive buffer size: {err}. This may cause packet loss, especially on high-bitrate streams.");
+        }
+
+        socket
+            .bind(
+                &net::SocketAddr::V4(net::SocketAddrV4::new(net::Ipv4Addr::UNSPECIFIED, port))
+                    .into(),
+            )
+            .map_err(UdpTsInputError::SocketBind)?;
+
+        socket
+            .set_read_timeout(Some(std::time::Duration::from_millis(50)))
+            .map_err(UdpTsInputError::SocketOptions)?;
+
+        Ok(net::UdpSocket::from(socket))
+    }
+
+    fn reader_thread(
+        reader: DatagramReader,
+        codec: Codec,
+        chunks_tx: Sender<EncodedChunk>,
+    ) -> Result<(), DemuxerError> {
+        // Opening the demuxer blocks until enough data is received to probe the stream.
+        let mut demuxer = Demuxer::open(reader)?;
+        let input_ctx = demuxer.input_ctx.as_mut().unwrap();
+
+        let (stream_index, time_base) = {
+            let stream = input_ctx
+                .streams()
+                .best(Type::Video)
+                .ok_or(DemuxerError::NoVideoStream)?;
+            let found = stream.parameters().id();
+            if found != Id::from(codec) {
+                return Err(DemuxerError::CodecMismatch {
+                    expected: codec,
+                    found,
+                });
+            }
+            (stream.index(), stream.time_base())
+        };
+
+        // Iterator ends when the input is dropped, because the reader returns EOF.
+        for (stream, mut packet) in input_ctx.packets() {
+            if stream.index() != stream_index {
+                continue;
+            }
+
+            // MPEG-TS carries H264 and H265 in the Annex-B format, so packets can be passed
+            // to the decoder as they are.
+            packet.rescale_ts(time_base, Rational::new(1, 90000));
+            let chunk = match EncodedChunk::from_av_packet(&packet, EncodedChunkKind::Video(codec))
+            {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    warn!("Dropping a packet received in MPEG-TS stream: {err}");
+                    continue;
+                }
+            };
+
+            if chunks_tx.send(chunk).is_err() {
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for UdpTsInput {
+    fn drop(&mut self) {
+        self.should_close
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        if let Some(thread) = self.reader_thread.take() {
+            thread.join().unwrap();
+        } else {
+            error!("UDP MPEG-TS input does not hold a thread handle to the reading thread.")
+        }
+    }
+}
+
+/// Provides the demuxer with the content of received datagrams.
+struct DatagramReader {
+    socket: net::UdpSocket,
+    should_close: Arc<AtomicBool>,
+    buffer: BytesMut,
+    /// Part of the last datagram that did not fit into the demuxer buffer.
+    pending: Bytes,
+}
+
+impl DatagramReader {
+    /// Returns `None` after the input is closed.
+    fn read(&mut self, output: &mut [u8]) -> Option<usize> {
+        while self.pending.is_empty() {
+            if self.should_close.load(std::sync::atomic::Ordering::Relaxed) {
+                return None;
+            }
+            match self.socket.recv(&mut self.buffer) {
+                Ok(received_bytes) => {
+                    self.pending = Bytes::copy_from_slice(&self.buffer[..received_bytes]);
+                }
+                Err(err) => match err.kind() {
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => {}
+                    _ => error!("Error while receiving UDP packet: {err}"),
+                },
+            }
+        }
+
+        let len = usize::min(output.len(), self.pending.len());
+        output[..len].copy_from_slice(&self.pending[..len]);
+        self.pending.advance(len);
+        Some(len)
+    }
+}
+
+unsafe extern "C" fn read_packet(opaque: *mut c_void, buf: *mut u8, buf_size: c_int) -> c_int {
+    let reader = &mut *(opaque as *mut DatagramReader);
+    let output = std::slice::from_raw_parts_mut(buf, buf_size as usize);
+    match reader.read(output) {
+        Some(len) => len as c_int,
+        None => ffi::AVERROR_EOF,
+    }
+}
+
+/// MPEG-TS demuxer that reads data with a custom IO context instead of opening a URL.
+struct Demuxer {
+    /// Always `Some`, `Option` is only used to close the input before the IO context.
+    input_ctx: Option<context::Input>,
+    io_ctx: *mut ffi::AVIOContext,
+    reader: *mut DatagramReader,
+}
+
+impl Demuxer {
+    fn open(reader: DatagramReader) -> Result<Self, DemuxerError> {
+        unsafe {
+            let reader = Box::into_raw(Box::new(reader));
+            let buffer = ffi::av_malloc(AVIO_BUFFER_SIZE) as *mut u8;
+            let mut io_ctx = ffi::avio_alloc_context(
+                buffer,
+                AVIO_BUFFER_SIZE as c_int,
+                0,
+                reader as *mut c_void,
+                Some(read_packet),
+                None,
+                None,
+            );
+
+            let mut format_ctx = ffi::avformat_alloc_context();
+            (*format_ctx).pb = io_ctx;
+            (*format_ctx).flags |= ffi::AVFMT_FLAG_CUSTOM_IO;
+
+            let format = ffi::av_find_input_format(c"mpegts".as_ptr());
+            // Format context is freed by `avformat_open_input` on failure.
+            let result = match ffi::avformat_open_input(
+                &mut format_ctx,
+                ptr::null(),
+                format,
+                ptr::null_mut(),
+            ) {
+                0 => match ffi::avformat_find_stream_info(format_ctx, ptr::null_mut()) {
+                    result if result >= 0 => Ok(context::Input::wrap(format_ctx)),
+                    err => {
+                        ffi::avformat_close_input(&mut format_ctx);
+                        Err(DemuxerError::Open(ffmpeg_next::Error::from(err)))
+                    }
+                },
+                err => Err(DemuxerError::Open(ffmpeg_next::Error::from(err))),
+            };
+
+            match result {
+                Ok(input_ctx) => Ok(Self {
+                    input_ctx: Some(input_ctx),
+                    io_ctx,
+                    reader,
+                }),
+                Err(err) => {
+                    ffi::av_freep(&mut (*io_ctx).buffer as *mut *mut u8 as *mut c_void);
+                    ffi::avio_context_free(&mut io_ctx);
+                    drop(Box::from_raw(reader));
+                    Err(err)
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Demuxer {
+    fn drop(&mut self) {
+        // Input has to be closed first, because it still references the IO context.
+        self.input_ctx.take();
+        unsafe {
+            ffi::av_freep(&mut (*self.io_ctx).buffer as *mut *mut u8 as *mut c_void);
+            ffi::avio_context_free(&mut self.io_ctx);
+            drop(Box::from_raw(self.reader));
+        }
+    }
+}
+
+pub struct ChunkIter {
+    receiver: Receiver<EncodedChunk>,
+}
+
+impl Iterator for ChunkIter {
+    type Item = EncodedChunk;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
@@ -1,29 +1,221 @@
-use crate::error::OutputInitError;
+use std::{sync::Arc, thread, time::Duration};
 
-use self::rtp::{RtpSender, RtpSenderOptions};
+use bytes::Bytes;
+use compositor_render::OutputId;
+use ffmpeg_next::{codec::Id, ffi, format::context, media::Type, Rational};
+
+use crate::{
+    error::OutputInitError,
+    pipeline::{
+        audio_mixer::{CHANNELS, SAMPLE_RATE},
+        stats::OutputStats,
+        structs::{AudioCodec, EncodedChunkKind},
+    },
+};
+
+use self::{
+    hls::{HlsWriter, HlsWriterOptions},
+    mp4::{Mp4FileWriter, Mp4WriterOptions},
+    rtmp::{RtmpSender, RtmpSenderOptions},
+    rtp::{RtpSender, RtpSenderOptions},
+    whip::{WhipSender, WhipSenderOptions},
+};
 
 use super::structs::EncodedChunk;
 
+pub mod hls;
+pub mod mp4;
+pub mod rtmp;
 pub mod rtp;
+pub mod whip;
 
 pub enum Output {
     Rtp(RtpSender),
+    Mp4(Mp4FileWriter),
+    Rtmp(RtmpSender),
+    Hls(HlsWriter),
+    Whip(WhipSender),
+}
+
+/// Audio track muxed together with the video.
+#[derive(Debug, Clone)]
+pub struct AudioTrack {
+    pub codec: AudioCodec,
+    /// Codec specific data, e.g. the AudioSpecificConfig for AAC.
+    pub extradata: Bytes,
 }
 
 pub enum OutputOptions {
     Rtp(RtpSenderOptions),
+    Mp4(Mp4WriterOptions),
+    Rtmp(RtmpSenderOptions),
+    Hls(HlsWriterOptions),
+    Whip(WhipSenderOptions),
+}
+
+/// Requests a keyframe from the encoder of the output, e.g. when a receiver reports
+/// a lost frame.
+pub type KeyframeRequester = Arc<dyn Fn() + Send + Sync>;
+
+/// Bounded exponential backoff used to retry sending after transient errors.
+#[derive(Debug, Clone, Copy)]
+struct Backoff {
+    initial_delay: Duration,
+    max_retries: u32,
+}
+
+impl Backoff {
+    /// Calls `send` until it succeeds, fails with an error that is not `is_transient`,
+    /// or all retries fail. The delay doubles after every retry. Retries are counted
+    /// in `stats`, but the final result is not.
+    fn retry<T, E>(
+        &self,
+        stats: &OutputStats,
+        mut send: impl FnMut() -> Result<T, E>,
+        is_transient: impl Fn(&E) -> bool,
+    ) -> Result<T, E> {
+        let mut delay = self.initial_delay;
+        let mut retries = 0;
+        loop {
+            match send() {
+                Err(err) if retries < self.max_retries && is_transient(&err) => {
+                    stats.on_send_retried();
+                    thread::sleep(delay);
+                    delay *= 2;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
 }
 
 impl Output {
+    /// `packets` include chunks of the audio track, if it's specified. Network outputs
+    /// report failed sends in `stats`.
     pub fn new(
         options: OutputOptions,
         packets: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+        audio: Option<AudioTrack>,
+        stats: Arc<OutputStats>,
+        request_keyframe: KeyframeRequester,
     ) -> Result<Self, OutputInitError> {
         match options {
             OutputOptions::Rtp(options) => {
-                let sender = rtp::RtpSender::new(options, packets)?;
+                // RTP audio would have to be sent as a separate RTP stream.
+                if audio.is_some() {
+                    return Err(OutputInitError::AudioNotSupported);
+                }
+                let sender = rtp::RtpSender::new(options, packets, stats)?;
                 Ok(Self::Rtp(sender))
             }
+            OutputOptions::Mp4(options) => {
+                let writer = mp4::Mp4FileWriter::new(options, packets, audio)?;
+                Ok(Self::Mp4(writer))
+            }
+            OutputOptions::Rtmp(options) => {
+                let sender = rtmp::RtmpSender::new(options, packets, audio, stats)?;
+                Ok(Self::Rtmp(sender))
+            }
+            OutputOptions::Hls(options) => {
+                let writer = HlsWriter::new(options, packets, audio)?;
+                Ok(Self::Hls(writer))
+            }
+            OutputOptions::Whip(options) => {
+                // Audio would have to be encoded with Opus.
+                if audio.is_some() {
+                    return Err(OutputInitError::AudioNotSupported);
+                }
+                let sender = WhipSender::new(options, packets, stats, request_keyframe)?;
+                Ok(Self::Whip(sender))
+            }
+        }
+    }
+
+    /// Waits until a file output finalizes its file after the stream of packets ends.
+    /// Other outputs are closed when they are dropped, so it returns immediately for them.
+    /// Returns `false` if the file is not finalized before the timeout.
+    pub fn wait_for_finalize(&self, timeout: Duration) -> bool {
+        match self {
+            Self::Mp4(writer) => writer.wait_for_finalize(timeout),
+            Self::Hls(writer) => writer.wait_for_finalize(timeout),
+            Self::Rtp(_) | Self::Rtmp(_) | Self::Whip(_) => true,
         }
     }
 }
+
+/// Span of sending or writing a single chunk. It's only enabled if timings are logged,
+/// see `LIVE_COMPOSITOR_LOG_TIMINGS`.
+fn send_span(output_id: &OutputId, chunk: &EncodedChunk) -> tracing::span::EnteredSpan {
+    tracing::trace_span!(
+        target: "timings",
+        "send",
+        output_id = %output_id,
+        kind = ?chunk.kind,
+    )
+    .entered()
+}
+
+/// Index of the muxer stream that receives chunks of this kind. Video is always added
+/// as the first stream, and audio as the second one.
+fn stream_index(kind: EncodedChunkKind) -> usize {
+    match kind {
+        EncodedChunkKind::Video(_) => 0,
+        EncodedChunkKind::Audio(_) => 1,
+    }
+}
+
+/// Adds the stream of the audio track. It has to be added after the video stream.
+fn add_audio_stream(
+    output_ctx: &mut context::Output,
+    audio: &AudioTrack,
+) -> Result<(), ffmpeg_next::Error> {
+    let mut parameters = ffmpeg_next::codec::Parameters::new();
+    unsafe {
+        let parameters = &mut *parameters.as_mut_ptr();
+
+        parameters.codec_type = Type::Audio.into();
+        parameters.codec_id = Id::from(audio.codec).into();
+        parameters.sample_rate = SAMPLE_RATE as i32;
+        ffi::av_channel_layout_default(&mut parameters.ch_layout, CHANNELS as i32);
+
+        // Extradata is freed together with the parameters.
+        parameters.extradata =
+            ffi::av_mallocz(audio.extradata.len() + ffi::AV_INPUT_BUFFER_PADDING_SIZE as usize)
+                as *mut u8;
+        std::ptr::copy_nonoverlapping(
+            audio.extradata.as_ptr(),
+            parameters.extradata,
+            audio.extradata.len(),
+        );
+        parameters.extradata_size = audio.extradata.len() as i32;
+    };
+
+    let mut stream = output_ctx.add_stream(Id::from(audio.codec))?;
+    stream.set_parameters(parameters);
+    stream.set_time_base(Rational::new(1, SAMPLE_RATE as i32));
+    Ok(())
+}
+
+/// Converts the chunk to a packet of the muxer stream it belongs to. `time_bases` are
+/// time bases of the muxer streams, which can be changed while writing the header.
+fn chunk_into_packet(chunk: &EncodedChunk, time_bases: &[Rational]) -> ffmpeg_next::Packet {
+    let stream = stream_index(chunk.kind);
+    let mut packet = ffmpeg_next::Packet::copy(&chunk.data);
+    packet.set_pts(Some(chunk.pts));
+    packet.set_dts(chunk.dts);
+    packet.set_stream(stream);
+    if chunk.is_keyframe {
+        packet.set_flags(ffmpeg_next::packet::Flags::KEY);
+    }
+    packet.rescale_ts(Rational::new(1, 90000), time_bases[stream]);
+    packet
+}
+
+/// Time bases of all streams of the muxer.
+fn stream_time_bases(output_ctx: &context::Output, audio: bool) -> Vec<Rational> {
+    let streams = if audio { 2 } else { 1 };
+    (0..streams)
+        .map(|index| output_ctx.stream(index).unwrap().time_base())
+        .collect()
+}
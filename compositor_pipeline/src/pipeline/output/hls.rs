@@ -0,0 +1,185 @@
+use std::{fs, io, path::Path, sync::Arc, time::Duration};
+
+use compositor_render::{OutputId, Resolution};
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+use ffmpeg_next::{codec::Id, format::context, media::Type, Dictionary, Rational};
+use log::{error, warn};
+
+use crate::{
+    error::OutputInitError,
+    pipeline::structs::{Codec, EncodedChunk, EncodedChunkKind},
+};
+
+use super::{add_audio_stream, chunk_into_packet, send_span, stream_time_bases, AudioTrack};
+
+const PLAYLIST_FILE_NAME: &str = "playlist.m3u8";
+const SEGMENT_FILE_PREFIX: &str = "segment_";
+const SEGMENT_FILE_EXTENSION: &str = ".ts";
+
+/// Writes the stream as MPEG-TS segments and a rolling `playlist.m3u8` playlist to a
+/// directory, which can be served over HTTP. Segments that fall out of the playlist are
+/// removed, and all files are removed when the output is unregistered.
+#[derive(Debug)]
+pub struct HlsWriter {
+    pub output_dir: Arc<Path>,
+    writer_thread: Option<std::thread::JoinHandle<()>>,
+    /// Disconnected by the writer thread after the stream is finalized.
+    finalized: Receiver<()>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HlsWriterOptions {
+    pub output_dir: Arc<Path>,
+    /// Target duration of a segment. Segments are split on keyframes, so the encoder
+    /// should produce a keyframe at the start of every segment.
+    pub segment_duration: Duration,
+    /// Number of segments listed in the playlist.
+    pub playlist_size: u32,
+    pub codec: Codec,
+    pub resolution: Resolution,
+    pub output_id: OutputId,
+}
+
+impl HlsWriter {
+    pub fn new(
+        options: HlsWriterOptions,
+        packets: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+        audio: Option<AudioTrack>,
+    ) -> Result<Self, OutputInitError> {
+        if options.codec != Codec::H264 {
+            return Err(OutputInitError::UnsupportedCodec(options.codec));
+        }
+
+        fs::create_dir_all(&options.output_dir)
+            .map_err(|err| OutputInitError::HlsDirectory(options.output_dir.clone(), err))?;
+        // Segments left by a previous output would be listed in the directory, but never
+        // referenced by the new playlist.
+        remove_hls_files(&options.output_dir);
+
+        let playlist_path = options.output_dir.join(PLAYLIST_FILE_NAME);
+        let segment_path = options
+            .output_dir
+            .join(format!("{SEGMENT_FILE_PREFIX}%d{SEGMENT_FILE_EXTENSION}"));
+        let mut output_ctx = ffmpeg_next::format::output_as(&playlist_path, "hls")?;
+
+        let mut parameters = ffmpeg_next::codec::Parameters::new();
+        unsafe {
+            let parameters = &mut *parameters.as_mut_ptr();
+
+            parameters.codec_type = Type::Video.into();
+            parameters.codec_id = Id::from(options.codec).into();
+            parameters.width = options.resolution.width as i32;
+            parameters.height = options.resolution.height as i32;
+        };
+
+        let mut stream = output_ctx.add_stream(Id::from(options.codec))?;
+        stream.set_parameters(parameters);
+        stream.set_time_base(Rational::new(1, 90000));
+        if let Some(ref audio) = audio {
+            add_audio_stream(&mut output_ctx, audio)?;
+        }
+
+        let segment_duration = options.segment_duration.as_secs_f64().to_string();
+        let playlist_size = options.playlist_size.to_string();
+        let segment_path = segment_path.to_string_lossy();
+        output_ctx.write_header_with(Dictionary::from_iter([
+            ("hls_time", segment_duration.as_str()),
+            ("hls_list_size", playlist_size.as_str()),
+            // Segments that are no longer listed in the playlist are removed.
+            ("hls_flags", "delete_segments+independent_segments"),
+            ("hls_segment_filename", segment_path.as_ref()),
+        ]))?;
+
+        let output_dir = options.output_dir.clone();
+        let output_id = options.output_id.clone();
+        let (finalized_sender, finalized) = crossbeam_channel::bounded(0);
+        let writer_thread = std::thread::Builder::new()
+            .name(format!("HLS writer for output {}", options.output_id))
+            .spawn(move || {
+                Self::writer_thread(output_ctx, &output_id, packets, audio.is_some());
+                remove_hls_files(&output_dir);
+                drop(finalized_sender);
+            })
+            .unwrap();
+
+        Ok(Self {
+            output_dir: options.output_dir,
+            writer_thread: Some(writer_thread),
+            finalized,
+        })
+    }
+
+    /// Blocks until the playlist is finalized and its files are removed after the stream
+    /// of packets ends. Returns `false` if it doesn't happen before the timeout.
+    pub fn wait_for_finalize(&self, timeout: Duration) -> bool {
+        matches!(
+            self.finalized.recv_timeout(timeout),
+            Err(RecvTimeoutError::Disconnected)
+        )
+    }
+
+    fn writer_thread(
+        mut output_ctx: context::Output,
+        output_id: &OutputId,
+        packets: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+        has_audio: bool,
+    ) {
+        // Muxer can change the stream time base while writing the header.
+        let time_bases = stream_time_bases(&output_ctx, has_audio);
+
+        // First segment has to start with a video keyframe to be playable on its own.
+        let packets = packets.skip_while(|chunk| {
+            !(chunk.is_keyframe && matches!(chunk.kind, EncodedChunkKind::Video(_)))
+        });
+        for chunk in packets {
+            let _span = send_span(output_id, &chunk);
+            let packet = chunk_into_packet(&chunk, &time_bases);
+
+            if let Err(err) = packet.write_interleaved(&mut output_ctx) {
+                error!("Failed to write a packet to the HLS segment: {err}");
+            }
+        }
+
+        // Packets iterator ends when the encoder is stopped during output unregistration.
+        if let Err(err) = output_ctx.write_trailer() {
+            error!("Failed to finalize the HLS playlist: {err}");
+        }
+    }
+}
+
+impl Drop for HlsWriter {
+    fn drop(&mut self) {
+        match self.writer_thread.take() {
+            Some(handle) => handle.join().unwrap(),
+            None => error!("HLS writer thread was already joined."),
+        }
+    }
+}
+
+/// Removes the playlist and segments written by the output. Other files in the directory
+/// are not touched.
+fn remove_hls_files(output_dir: &Path) {
+    let entries = match fs::read_dir(output_dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            warn!("Failed to list HLS files in {output_dir:?}: {err}");
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        let is_hls_file = file_name == PLAYLIST_FILE_NAME
+            || (file_name.starts_with(SEGMENT_FILE_PREFIX)
+                && file_name.ends_with(SEGMENT_FILE_EXTENSION));
+        if !is_hls_file {
+            continue;
+        }
+        if let Err(err) = fs::remove_file(entry.path()) {
+            if err.kind() != io::ErrorKind::NotFound {
+                warn!("Failed to remove HLS file {:?}: {err}", entry.path());
+            }
+        }
+    }
+}
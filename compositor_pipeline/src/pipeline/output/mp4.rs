@@ -0,0 +1,125 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use compositor_render::{OutputId, Resolution};
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+use ffmpeg_next::{codec::Id, format::context, media::Type, Dictionary, Rational};
+use log::error;
+
+use crate::{
+    error::OutputInitError,
+    pipeline::structs::{Codec, EncodedChunk},
+};
+
+use super::{add_audio_stream, chunk_into_packet, send_span, stream_time_bases, AudioTrack};
+
+#[derive(Debug)]
+pub struct Mp4FileWriter {
+    pub path: Arc<Path>,
+    writer_thread: Option<std::thread::JoinHandle<()>>,
+    /// Disconnected by the writer thread after the stream is finalized.
+    finalized: Receiver<()>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mp4WriterOptions {
+    pub path: Arc<Path>,
+    pub codec: Codec,
+    pub resolution: Resolution,
+    pub output_id: OutputId,
+}
+
+impl Mp4FileWriter {
+    pub fn new(
+        options: Mp4WriterOptions,
+        packets: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+        audio: Option<AudioTrack>,
+    ) -> Result<Self, OutputInitError> {
+        if !matches!(options.codec, Codec::H264 | Codec::H265) {
+            return Err(OutputInitError::UnsupportedCodec(options.codec));
+        }
+
+        let mut output_ctx = ffmpeg_next::format::output_as(&options.path, "mp4")?;
+
+        let mut parameters = ffmpeg_next::codec::Parameters::new();
+        unsafe {
+            let parameters = &mut *parameters.as_mut_ptr();
+
+            parameters.codec_type = Type::Video.into();
+            parameters.codec_id = Id::from(options.codec).into();
+            parameters.width = options.resolution.width as i32;
+            parameters.height = options.resolution.height as i32;
+        };
+
+        let mut stream = output_ctx.add_stream(Id::from(options.codec))?;
+        stream.set_parameters(parameters);
+        stream.set_time_base(Rational::new(1, 90000));
+        if let Some(ref audio) = audio {
+            add_audio_stream(&mut output_ctx, audio)?;
+        }
+
+        output_ctx.write_header_with(Dictionary::from_iter([
+            // Fragmented MP4 stays playable even if the compositor is killed before the
+            // file is finalized. Parameter sets are only known after the first keyframe
+            // is encoded, so writing the moov atom is delayed until then.
+            ("movflags", "frag_keyframe+empty_moov+delay_moov"),
+        ]))?;
+
+        let output_id = options.output_id.clone();
+        let (finalized_sender, finalized) = crossbeam_channel::bounded(0);
+        let writer_thread = std::thread::Builder::new()
+            .name(format!("MP4 writer for output {}", options.output_id))
+            .spawn(move || {
+                Self::writer_thread(output_ctx, &output_id, packets, audio.is_some());
+                drop(finalized_sender);
+            })
+            .unwrap();
+
+        Ok(Self {
+            path: options.path,
+            writer_thread: Some(writer_thread),
+            finalized,
+        })
+    }
+
+    /// Blocks until the trailer is written after the stream of packets ends. Returns
+    /// `false` if the file is not finalized before the timeout.
+    pub fn wait_for_finalize(&self, timeout: Duration) -> bool {
+        matches!(
+            self.finalized.recv_timeout(timeout),
+            Err(RecvTimeoutError::Disconnected)
+        )
+    }
+
+    fn writer_thread(
+        mut output_ctx: context::Output,
+        output_id: &OutputId,
+        packets: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+        has_audio: bool,
+    ) {
+        // Muxer can change the stream time base while writing the header.
+        let time_bases = stream_time_bases(&output_ctx, has_audio);
+
+        for chunk in packets {
+            let _span = send_span(output_id, &chunk);
+            let packet = chunk_into_packet(&chunk, &time_bases);
+
+            if let Err(err) = packet.write_interleaved(&mut output_ctx) {
+                error!("Failed to write a packet to the MP4 file: {err}");
+            }
+        }
+
+        // Packets iterator ends when the encoder is stopped during output unregistration.
+        if let Err(err) = output_ctx.write_trailer() {
+            error!("Failed to finalize the MP4 file: {err}");
+        }
+    }
+}
+
+impl Drop for Mp4FileWriter {
+    fn drop(&mut self) {
+        match self.writer_thread.take() {
+            Some(handle) => handle.join().unwrap(),
+            None => error!("MP4 writer thread was already joined."),
+        }
+    }
+}
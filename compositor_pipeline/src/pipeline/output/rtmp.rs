@@ -0,0 +1,306 @@
+use std::{sync::Arc, time::Duration};
+
+use bytes::{Bytes, BytesMut};
+use compositor_render::{OutputId, Resolution};
+use ffmpeg_next::{codec::Id, ffi, format::context, media::Type, Dictionary, Rational};
+use log::{error, info};
+
+use crate::{
+    error::OutputInitError,
+    pipeline::{
+        stats::OutputStats,
+        structs::{Codec, EncodedChunk, EncodedChunkKind},
+    },
+};
+
+use super::{
+    add_audio_stream, chunk_into_packet, send_span, stream_time_bases, AudioTrack, Backoff,
+};
+
+const START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+/// Retries writes that fail because the socket is temporarily not writable.
+const WRITE_BACKOFF: Backoff = Backoff {
+    initial_delay: Duration::from_millis(10),
+    max_retries: 3,
+};
+
+/// Reconnects after the connection is lost, for about 15 seconds in total.
+const RECONNECT_BACKOFF: Backoff = Backoff {
+    initial_delay: Duration::from_millis(500),
+    max_retries: 5,
+};
+
+/// Sends the stream to an RTMP server, e.g. an ingest endpoint of a streaming platform.
+///
+/// If the connection is lost, the sender reconnects with a backoff and resumes the stream
+/// from the next keyframe. Chunks encoded in the meantime are dropped.
+#[derive(Debug)]
+pub struct RtmpSender {
+    pub url: Arc<str>,
+    pub stream_key: Option<Arc<str>>,
+    sender_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtmpSenderOptions {
+    pub url: Arc<str>,
+    /// Appended to the URL as the last path segment.
+    pub stream_key: Option<Arc<str>>,
+    pub codec: Codec,
+    pub resolution: Resolution,
+    pub output_id: OutputId,
+}
+
+impl RtmpSender {
+    pub fn new(
+        options: RtmpSenderOptions,
+        packets: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+        audio: Option<AudioTrack>,
+        stats: Arc<OutputStats>,
+    ) -> Result<Self, OutputInitError> {
+        // FLV only supports H264 out of the codecs produced by the encoders.
+        if options.codec != Codec::H264 {
+            return Err(OutputInitError::UnsupportedCodec(options.codec));
+        }
+
+        let url = match options.stream_key {
+            Some(ref stream_key) => format!("{}/{}", options.url.trim_end_matches('/'), stream_key),
+            None => options.url.to_string(),
+        };
+
+        ffmpeg_next::format::network::init();
+        // Connection is established when the output is opened, so an unreachable server
+        // fails the registration.
+        let output_ctx = open_connection(&url)
+            .map_err(|err| OutputInitError::RtmpConnectionFailed(options.url.clone(), err))?;
+
+        let connection = Connection {
+            url,
+            resolution: options.resolution,
+            audio,
+            stats,
+        };
+        let output_id = options.output_id.clone();
+        let sender_thread = std::thread::Builder::new()
+            .name(format!("RTMP sender for output {}", options.output_id))
+            .spawn(move || Self::sender_thread(output_ctx, &output_id, connection, packets))
+            .unwrap();
+
+        Ok(Self {
+            url: options.url,
+            stream_key: options.stream_key,
+            sender_thread: Some(sender_thread),
+        })
+    }
+
+    fn sender_thread(
+        mut output_ctx: context::Output,
+        output_id: &OutputId,
+        connection: Connection,
+        packets: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+    ) {
+        let stats = &connection.stats;
+
+        // FLV header includes the parameter sets, so it can only be written after the
+        // first keyframe is encoded. Earlier chunks can't be decoded anyway.
+        let mut packets = packets
+            .skip_while(|chunk| !is_video_keyframe(chunk))
+            .peekable();
+        let Some(first_keyframe) = packets.peek() else {
+            return;
+        };
+        let parameter_sets = h264_parameter_sets(&first_keyframe.data);
+        if let Err(err) = connection.write_header(&mut output_ctx, &parameter_sets) {
+            error!("Failed to start the RTMP stream: {err}");
+            stats.on_send_failed();
+            return;
+        }
+
+        // Muxer changes the stream time base while writing the header.
+        let mut time_bases = stream_time_bases(&output_ctx, connection.audio.is_some());
+        let mut is_waiting_for_keyframe = false;
+
+        for chunk in packets {
+            // New connection has to start with a keyframe.
+            if is_waiting_for_keyframe && !is_video_keyframe(&chunk) {
+                continue;
+            }
+            let _span = send_span(output_id, &chunk);
+            is_waiting_for_keyframe = false;
+
+            // Packet is consumed by the muxer even if the write fails, so it's created
+            // again for every attempt.
+            let result = WRITE_BACKOFF.retry(
+                stats,
+                || chunk_into_packet(&chunk, &time_bases).write_interleaved(&mut output_ctx),
+                is_transient_error,
+            );
+            let Err(err) = result else {
+                stats.on_send_succeeded();
+                continue;
+            };
+
+            error!("Failed to send a packet to the RTMP server, reconnecting: {err}");
+            stats.on_send_failed();
+            // Unregistering the output waits until reconnection attempts are finished.
+            let reconnected = RECONNECT_BACKOFF.retry(
+                stats,
+                || connection.reconnect(&parameter_sets),
+                |err| {
+                    error!("Failed to reconnect to the RTMP server: {err}");
+                    true
+                },
+            );
+            match reconnected {
+                Ok(new_output_ctx) => {
+                    info!("Reconnected to the RTMP server.");
+                    output_ctx = new_output_ctx;
+                    time_bases = stream_time_bases(&output_ctx, connection.audio.is_some());
+                    is_waiting_for_keyframe = true;
+                }
+                Err(err) => {
+                    error!("Failed to reconnect to the RTMP server, stopping the stream: {err}");
+                    return;
+                }
+            }
+        }
+
+        // Packets iterator ends when the encoder is dropped during output unregistration.
+        if let Err(err) = output_ctx.write_trailer() {
+            error!("Failed to finish the RTMP stream: {err}");
+        }
+    }
+}
+
+/// Everything needed to start the stream again after the connection is lost.
+struct Connection {
+    url: String,
+    resolution: Resolution,
+    audio: Option<AudioTrack>,
+    stats: Arc<OutputStats>,
+}
+
+impl Connection {
+    fn reconnect(&self, parameter_sets: &[u8]) -> Result<context::Output, ffmpeg_next::Error> {
+        let mut output_ctx = open_connection(&self.url)?;
+        self.write_header(&mut output_ctx, parameter_sets)?;
+        Ok(output_ctx)
+    }
+
+    fn write_header(
+        &self,
+        output_ctx: &mut context::Output,
+        parameter_sets: &[u8],
+    ) -> Result<(), ffmpeg_next::Error> {
+        let resolution = self.resolution;
+        let mut parameters = ffmpeg_next::codec::Parameters::new();
+        unsafe {
+            let parameters = &mut *parameters.as_mut_ptr();
+
+            parameters.codec_type = Type::Video.into();
+            parameters.codec_id = Id::H264.into();
+            parameters.width = resolution.width as i32;
+            parameters.height = resolution.height as i32;
+
+            // Extradata is freed together with the parameters.
+            parameters.extradata =
+                ffi::av_mallocz(parameter_sets.len() + ffi::AV_INPUT_BUFFER_PADDING_SIZE as usize)
+                    as *mut u8;
+            std::ptr::copy_nonoverlapping(
+                parameter_sets.as_ptr(),
+                parameters.extradata,
+                parameter_sets.len(),
+            );
+            parameters.extradata_size = parameter_sets.len() as i32;
+        };
+
+        let mut stream = output_ctx.add_stream(Id::H264)?;
+        stream.set_parameters(parameters);
+        stream.set_time_base(Rational::new(1, 90000));
+        if let Some(ref audio) = self.audio {
+            add_audio_stream(output_ctx, audio)?;
+        }
+
+        output_ctx.write_header()
+    }
+}
+
+fn open_connection(url: &str) -> Result<context::Output, ffmpeg_next::Error> {
+    ffmpeg_next::format::output_as_with(
+        &url,
+        "flv",
+        Dictionary::from_iter([("rw_timeout", "5000000")]),
+    )
+}
+
+fn is_video_keyframe(chunk: &EncodedChunk) -> bool {
+    chunk.is_keyframe && matches!(chunk.kind, EncodedChunkKind::Video(_))
+}
+
+/// Errors after which the write can be retried on the same connection. Other errors
+/// mean that the connection is lost.
+fn is_transient_error(err: &ffmpeg_next::Error) -> bool {
+    matches!(
+        err,
+        ffmpeg_next::Error::Other {
+            errno: ffmpeg_next::error::EAGAIN | ffmpeg_next::error::EINTR
+        }
+    )
+}
+
+impl Drop for RtmpSender {
+    fn drop(&mut self) {
+        match self.sender_thread.take() {
+            Some(handle) => handle.join().unwrap(),
+            None => error!("RTMP sender thread was already joined."),
+        }
+    }
+}
+
+/// Returns SPS and PPS NAL units of an Annex-B keyframe in the Annex-B format. The encoder
+/// repeats them before every keyframe.
+fn h264_parameter_sets(data: &[u8]) -> Bytes {
+    let mut parameter_sets = BytesMut::new();
+    for nal_unit in annex_b_nal_units(data) {
+        // NAL unit types 7 and 8 are SPS and PPS.
+        if matches!(
+            nal_unit.first().map(|header| header & 0b1_1111),
+            Some(7 | 8)
+        ) {
+            parameter_sets.extend_from_slice(&START_CODE);
+            parameter_sets.extend_from_slice(nal_unit);
+        }
+    }
+    parameter_sets.freeze()
+}
+
+fn annex_b_nal_units(data: &[u8]) -> Vec<&[u8]> {
+    let mut nal_units = Vec::new();
+    let mut nal_start = None;
+    let mut i = 0;
+    while i + 3 <= data.len() {
+        if data[i..i + 3] != [0, 0, 1] {
+            i += 1;
+            continue;
+        }
+        if let Some(start) = nal_start {
+            nal_units.push(trim_trailing_zeros(&data[start..i]));
+        }
+        i += 3;
+        nal_start = Some(i);
+    }
+    if let Some(start) = nal_start {
+        nal_units.push(&data[start..]);
+    }
+    nal_units
+}
+
+/// Removes the leading zero byte of the next 4-byte start code.
+fn trim_trailing_zeros(nal_unit: &[u8]) -> &[u8] {
+    let len = nal_unit
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |last| last + 1);
+    &nal_unit[..len]
+}
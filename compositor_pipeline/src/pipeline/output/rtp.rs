@@ -1,28 +1,63 @@
 use compositor_render::OutputId;
 use log::error;
-use std::sync::Arc;
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr, ToSocketAddrs},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use crate::{
     error::OutputInitError,
-    pipeline::structs::{Codec, EncodedChunk},
+    pipeline::{
+        stats::OutputStats,
+        structs::{Codec, EncodedChunk},
+    },
 };
 
 use rand::Rng;
 use rtp::packetizer::Payloader;
 use webrtc_util::Marshal;
 
+use self::h265::H265Payloader;
+
+use super::{send_span, Backoff};
+
+pub(crate) mod h265;
+
+/// Dynamic payload type of the video stream, described in the SDP returned by
+/// [`RtpSender::sdp`].
+const PAYLOAD_TYPE: u8 = 96;
+
+/// Packets are sent in real time, so they are retried only for a few milliseconds.
+const SEND_BACKOFF: Backoff = Backoff {
+    initial_delay: Duration::from_millis(5),
+    max_retries: 3,
+};
+
 #[derive(Debug)]
 pub struct RtpSender {
     pub port: u16,
     pub ip: Arc<str>,
+    pub additional_destinations: Vec<RtpDestination>,
+    codec: Codec,
+    ssrc: u32,
+    multicast_ttl: Option<u32>,
     sender_thread: Option<std::thread::JoinHandle<()>>,
 }
 
 pub struct RtpContext {
     ssrc: u32,
     next_sequence_number: u16,
-    payloader: rtp::codecs::h264::H264Payloader,
+    payloader: Box<dyn Payloader + Send>,
     socket: std::net::UdpSocket,
+    /// Every packet is sent to all destinations.
+    destinations: Vec<SocketAddr>,
+    rtcp_interval: Duration,
+    last_sender_report: Option<Instant>,
+    packet_count: u32,
+    octet_count: u32,
+    stats: Arc<OutputStats>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -31,40 +66,100 @@ pub struct RtpSenderOptions {
     pub ip: Arc<str>,
     pub codec: Codec,
     pub output_id: OutputId,
+    /// Interval between RTCP sender reports.
+    pub rtcp_interval: Duration,
+    /// Specified if `ip` is a multicast group.
+    pub multicast: Option<RtpMulticastOptions>,
+    /// SSRC of the RTP packets and RTCP sender reports. Random if not specified.
+    pub ssrc: Option<u32>,
+    /// Sequence number of the first packet. Random if not specified.
+    pub initial_sequence_number: Option<u16>,
+    /// Receivers of the same stream in addition to `ip` and `port`, so the stream is
+    /// only encoded once.
+    pub additional_destinations: Vec<RtpDestination>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtpDestination {
+    pub ip: Arc<str>,
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtpMulticastOptions {
+    /// Number of routers the packets can pass before they are dropped.
+    pub ttl: u32,
+    /// Address of the local interface used to send packets. If not specified, the OS
+    /// selects it based on the routing table.
+    pub interface: Option<Ipv4Addr>,
 }
 
 impl RtpSender {
     pub fn new(
         options: RtpSenderOptions,
         packets: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+        stats: Arc<OutputStats>,
     ) -> Result<Self, OutputInitError> {
-        if options.codec != Codec::H264 {
-            return Err(OutputInitError::UnsupportedCodec(options.codec));
-        }
+        let payloader: Box<dyn Payloader + Send> = match options.codec {
+            Codec::H264 => Box::<rtp::codecs::h264::H264Payloader>::default(),
+            Codec::H265 => Box::<H265Payloader>::default(),
+            Codec::Av1 => Box::<rtp::codecs::av1::Av1Payloader>::default(),
+            Codec::Vp8 => {
+                let mut payloader = rtp::codecs::vp8::Vp8Payloader::default();
+                // Picture ID lets receivers detect lost frames and request a keyframe.
+                payloader.enable_picture_id = true;
+                Box::new(payloader)
+            }
+            Codec::Vp9 => Box::<rtp::codecs::vp9::Vp9Payloader>::default(),
+        };
 
         let mut rng = rand::thread_rng();
-        let ssrc = rng.gen::<u32>();
-        let next_sequence_number = rng.gen::<u16>();
-        let payloader = rtp::codecs::h264::H264Payloader::default();
+        let ssrc = options.ssrc.unwrap_or_else(|| rng.gen::<u32>());
+        let next_sequence_number = options
+            .initial_sequence_number
+            .unwrap_or_else(|| rng.gen::<u16>());
 
         let socket = std::net::UdpSocket::bind(std::net::SocketAddrV4::new(
             std::net::Ipv4Addr::UNSPECIFIED,
             0,
         ))?;
 
-        socket.connect((options.ip.as_ref(), options.port))?;
+        if let Some(ref multicast) = options.multicast {
+            socket.set_multicast_ttl_v4(multicast.ttl)?;
+            if let Some(interface) = multicast.interface {
+                socket2::SockRef::from(&socket).set_multicast_if_v4(&interface)?;
+            }
+        }
+
+        let destinations = std::iter::once((options.ip.as_ref(), options.port))
+            .chain(
+                options
+                    .additional_destinations
+                    .iter()
+                    .map(|destination| (destination.ip.as_ref(), destination.port)),
+            )
+            .map(resolve_destination)
+            .collect::<Result<Vec<_>, _>>()?;
 
         let mut ctx = RtpContext {
             ssrc,
             next_sequence_number,
             payloader,
             socket,
+            destinations,
+            rtcp_interval: options.rtcp_interval,
+            last_sender_report: None,
+            packet_count: 0,
+            octet_count: 0,
+            stats,
         };
 
+        let output_id = options.output_id.clone();
         let sender_thread = std::thread::Builder::new()
             .name(format!("RTP sender for output {}", options.output_id))
             .spawn(move || {
                 for packet in packets {
+                    let _span = send_span(&output_id, &packet);
                     Self::send_data(&mut ctx, packet);
                 }
             })
@@ -73,15 +168,65 @@ impl RtpSender {
         Ok(Self {
             port: options.port,
             ip: options.ip,
+            additional_destinations: options.additional_destinations,
+            codec: options.codec,
+            ssrc,
+            multicast_ttl: options.multicast.map(|multicast| multicast.ttl),
             sender_thread: Some(sender_thread),
         })
     }
 
+    /// Whether packets are sent to the address, either as the main or an additional
+    /// destination.
+    pub fn sends_to(&self, ip: &str, port: u16) -> bool {
+        (self.ip.as_ref() == ip && self.port == port)
+            || self
+                .additional_destinations
+                .iter()
+                .any(|destination| destination.ip.as_ref() == ip && destination.port == port)
+    }
+
+    /// Session description of the stream, e.g. for `ffplay` or VLC. Receivers have to
+    /// support RTP/RTCP multiplexing to receive sender reports.
+    pub fn sdp(&self) -> String {
+        let (encoding, fmtp) = match self.codec {
+            Codec::H264 => ("H264", Some("packetization-mode=1")),
+            Codec::H265 => ("H265", None),
+            Codec::Av1 => ("AV1", None),
+            Codec::Vp8 => ("VP8", None),
+            Codec::Vp9 => ("VP9", None),
+        };
+        let connection = match self.multicast_ttl {
+            Some(ttl) => format!("{}/{ttl}", self.ip),
+            None => self.ip.to_string(),
+        };
+
+        let mut sdp = format!(
+            "v=0\r\n\
+             o=- {ssrc} 0 IN IP4 {ip}\r\n\
+             s=VideoCompositor\r\n\
+             c=IN IP4 {connection}\r\n\
+             t=0 0\r\n\
+             m=video {port} RTP/AVP {PAYLOAD_TYPE}\r\n\
+             a=rtpmap:{PAYLOAD_TYPE} {encoding}/90000\r\n",
+            ssrc = self.ssrc,
+            ip = self.ip,
+            port = self.port,
+        );
+        if let Some(fmtp) = fmtp {
+            sdp.push_str(&format!("a=fmtp:{PAYLOAD_TYPE} {fmtp}\r\n"));
+        }
+        sdp.push_str("a=rtcp-mux\r\n");
+        sdp.push_str(&format!("a=ssrc:{} cname:video-compositor\r\n", self.ssrc));
+        sdp
+    }
+
     /// this assumes, that a "packet" contains data about a single frame (access unit)
     fn send_data(context: &mut RtpContext, packet: EncodedChunk) {
-        // TODO: check if this is h264
         let EncodedChunk { data, pts, .. } = packet;
 
+        Self::send_sender_report_if_needed(context, pts);
+
         let payloads = match context.payloader.payload(1500, &data) {
             Ok(p) => p,
             Err(e) => {
@@ -97,13 +242,14 @@ impl RtpSender {
                 padding: false,
                 extension: false,
                 marker: i == packets_amount - 1, // marker needs to be set on the last packet of each frame
-                payload_type: 96,
+                payload_type: PAYLOAD_TYPE,
                 sequence_number: context.next_sequence_number,
                 timestamp: pts as u32,
                 ssrc: context.ssrc,
                 ..Default::default()
             };
 
+            let payload_size = payload.len() as u32;
             let packet = rtp::packet::Packet { header, payload };
 
             let packet = match packet.marshal() {
@@ -114,15 +260,117 @@ impl RtpSender {
                 }
             };
 
-            if let Err(err) = context.socket.send(&packet) {
-                error!("Failed to send packet: {err}");
+            // Packets are not retried while sending is failing, so a longer outage
+            // doesn't delay every packet. Sending fails if any destination fails.
+            let mut result = Ok(());
+            for destination in &context.destinations {
+                let sent = match context.stats.is_send_failing() {
+                    true => context.socket.send_to(&packet, destination),
+                    false => SEND_BACKOFF.retry(
+                        &context.stats,
+                        || context.socket.send_to(&packet, destination),
+                        is_transient_error,
+                    ),
+                };
+                if let Err(err) = sent {
+                    result = Err(err);
+                }
+            }
+            match result {
+                Ok(_) => context.stats.on_send_succeeded(),
+                Err(err) => {
+                    // Logged only when the failure starts, not for every dropped packet.
+                    if !context.stats.is_send_failing() {
+                        error!("Failed to send packet: {err}");
+                    }
+                    context.stats.on_send_failed();
+                }
             }
 
             context.next_sequence_number = context.next_sequence_number.wrapping_add(1);
+            context.packet_count = context.packet_count.wrapping_add(1);
+            context.octet_count = context.octet_count.wrapping_add(payload_size);
+        }
+    }
+
+    /// Sends an RTCP sender report on the same socket as the media packets (RFC 5761),
+    /// which maps the RTP timestamp of `pts` to the current wall clock time.
+    ///
+    /// Reports are only sent together with media packets. It assumes that packets are sent
+    /// right after they are encoded, so `pts` corresponds to the current time.
+    fn send_sender_report_if_needed(context: &mut RtpContext, pts: i64) {
+        let now = Instant::now();
+        if context
+            .last_sender_report
+            .is_some_and(|last_report| now.duration_since(last_report) < context.rtcp_interval)
+        {
+            return;
+        }
+        context.last_sender_report = Some(now);
+
+        let report = rtcp::sender_report::SenderReport {
+            ssrc: context.ssrc,
+            ntp_time: ntp_time(SystemTime::now()),
+            rtp_time: pts as u32,
+            packet_count: context.packet_count,
+            octet_count: context.octet_count,
+            ..Default::default()
+        };
+
+        let report = match report.marshal() {
+            Ok(report) => report,
+            Err(err) => {
+                error!("Failed to marshal an RTCP sender report: {err}");
+                return;
+            }
+        };
+
+        for destination in &context.destinations {
+            if let Err(err) = context.socket.send_to(&report, destination) {
+                error!("Failed to send an RTCP sender report to {destination}: {err}");
+            }
         }
     }
 }
 
+/// Destinations can be hostnames, but only IPv4 addresses are supported, because the
+/// socket is bound to an IPv4 address.
+fn resolve_destination((ip, port): (&str, u16)) -> io::Result<SocketAddr> {
+    (ip, port)
+        .to_socket_addrs()?
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                format!("{ip} does not resolve to an IPv4 address"),
+            )
+        })
+}
+
+/// Errors after which the same packet can be sent successfully, e.g. when the socket
+/// buffer is full or a route is briefly missing.
+fn is_transient_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::HostUnreachable
+    )
+}
+
+/// Converts time to the 64-bit NTP timestamp format (32.32 fixed point number of
+/// seconds since 1900-01-01).
+fn ntp_time(time: SystemTime) -> u64 {
+    const NTP_UNIX_EPOCH_OFFSET_SECS: u64 = 2_208_988_800;
+
+    let since_unix_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_unix_epoch.as_secs() + NTP_UNIX_EPOCH_OFFSET_SECS;
+    let fraction = ((since_unix_epoch.subsec_nanos() as u64) << 32) / 1_000_000_000;
+
+    (seconds << 32) | fraction
+}
+
 impl Drop for RtpSender {
     fn drop(&mut self) {
         match self.sender_thread.take() {
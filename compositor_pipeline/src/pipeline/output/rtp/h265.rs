@@ -0,0 +1,96 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use rtp::packetizer::Payloader;
+
+const NAL_HEADER_SIZE: usize = 2;
+const FU_HEADER_SIZE: usize = 1;
+const FU_NAL_TYPE: u16 = 49;
+
+/// Payloads Annex-B H265 access units according to RFC 7798.
+///
+/// The `rtp` crate does not provide an H265 payloader. NAL units that fit in the MTU are
+/// sent as single NAL unit packets and bigger ones are split into fragmentation units.
+/// Aggregation packets are never produced.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct H265Payloader;
+
+impl Payloader for H265Payloader {
+    fn payload(&mut self, mtu: usize, payload: &Bytes) -> Result<Vec<Bytes>, rtp::Error> {
+        if mtu <= NAL_HEADER_SIZE + FU_HEADER_SIZE {
+            return Err(rtp::Error::ErrBufferTooSmall);
+        }
+
+        let mut payloads = Vec::new();
+        for nal_unit in split_nal_units(payload) {
+            if nal_unit.len() <= mtu {
+                payloads.push(nal_unit);
+                continue;
+            }
+
+            let nal_header = u16::from_be_bytes([nal_unit[0], nal_unit[1]]);
+            let nal_type = ((nal_header >> 9) & 0b11_1111) as u8;
+            let payload_header = (nal_header & 0b1000_0001_1111_1111) | (FU_NAL_TYPE << 9);
+
+            let fragments = nal_unit[NAL_HEADER_SIZE..]
+                .chunks(mtu - NAL_HEADER_SIZE - FU_HEADER_SIZE)
+                .collect::<Vec<_>>();
+            let fragments_amount = fragments.len();
+
+            for (i, fragment) in fragments.into_iter().enumerate() {
+                let mut fu_header = nal_type;
+                if i == 0 {
+                    fu_header |= 0b1000_0000;
+                }
+                if i == fragments_amount - 1 {
+                    fu_header |= 0b0100_0000;
+                }
+
+                let mut packet =
+                    BytesMut::with_capacity(NAL_HEADER_SIZE + FU_HEADER_SIZE + fragment.len());
+                packet.put_u16(payload_header);
+                packet.put_u8(fu_header);
+                packet.extend_from_slice(fragment);
+                payloads.push(packet.freeze());
+            }
+        }
+
+        Ok(payloads)
+    }
+
+    fn clone_to(&self) -> Box<dyn Payloader + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Splits an Annex-B byte stream on start codes (both 3 and 4 byte long).
+fn split_nal_units(data: &Bytes) -> Vec<Bytes> {
+    let mut nal_units = Vec::new();
+    let mut nal_start = None;
+
+    let mut push_nal_unit = |start: usize, mut end: usize| {
+        // NAL units never end with a zero byte, so trailing zeros belong to the next start code.
+        while end > start && data[end - 1] == 0 {
+            end -= 1;
+        }
+        if end - start > NAL_HEADER_SIZE {
+            nal_units.push(data.slice(start..end));
+        }
+    };
+
+    let mut i = 0;
+    while i + 3 <= data.len() {
+        if data[i..i + 3] == [0, 0, 1] {
+            if let Some(start) = nal_start {
+                push_nal_unit(start, i);
+            }
+            i += 3;
+            nal_start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+    if let Some(start) = nal_start {
+        push_nal_unit(start, data.len());
+    }
+
+    nal_units
+}
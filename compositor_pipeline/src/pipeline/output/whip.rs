@@ -0,0 +1,352 @@
+use std::{sync::Arc, time::Duration};
+
+use compositor_render::OutputId;
+use log::{error, info, warn};
+use reqwest::{
+    blocking::Client,
+    header::{AUTHORIZATION, CONTENT_TYPE, LOCATION},
+    StatusCode, Url,
+};
+use rtcp::payload_feedbacks::{
+    full_intra_request::FullIntraRequest, picture_loss_indication::PictureLossIndication,
+};
+use tokio::runtime::Runtime;
+use webrtc::{
+    api::{
+        interceptor_registry::register_default_interceptors,
+        media_engine::{MediaEngine, MIME_TYPE_H264, MIME_TYPE_VP8, MIME_TYPE_VP9},
+        APIBuilder,
+    },
+    interceptor::registry::Registry,
+    media::Sample,
+    peer_connection::{
+        configuration::RTCConfiguration, peer_connection_state::RTCPeerConnectionState,
+        sdp::session_description::RTCSessionDescription, RTCPeerConnection,
+    },
+    rtp_transceiver::{
+        rtp_codec::RTCRtpCodecCapability, rtp_sender::RTCRtpSender,
+        rtp_transceiver_direction::RTCRtpTransceiverDirection, RTCRtpTransceiverInit,
+    },
+    track::track_local::{track_local_static_sample::TrackLocalStaticSample, TrackLocal},
+};
+
+use crate::{
+    error::OutputInitError,
+    pipeline::{
+        stats::OutputStats,
+        structs::{Codec, EncodedChunk},
+    },
+};
+
+use super::{send_span, KeyframeRequester};
+
+/// Sends the video to a WebRTC server, e.g. an SFU, with the WebRTC-HTTP ingestion
+/// protocol (WHIP, RFC 9725). The session is negotiated when the output is registered,
+/// so an unreachable endpoint fails the registration. It's ended with an HTTP `DELETE`
+/// request after the output is unregistered.
+///
+/// Keyframes requested by the server with RTCP PLI or FIR messages are requested from
+/// the encoder.
+#[derive(Debug)]
+pub struct WhipSender {
+    pub endpoint_url: Arc<str>,
+    sender_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WhipSenderOptions {
+    pub endpoint_url: Arc<str>,
+    /// Sent in the `Authorization` header of all requests to the endpoint.
+    pub bearer_token: Option<Arc<str>>,
+    pub codec: Codec,
+    pub output_id: OutputId,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WhipError {
+    #[error(transparent)]
+    WebRtc(#[from] webrtc::Error),
+
+    #[error("Failed to send a request to the WHIP endpoint.")]
+    Request(#[from] reqwest::Error),
+
+    #[error("WHIP endpoint responded with status {0}: {1}")]
+    BadStatus(StatusCode, String),
+
+    #[error("Endpoint URL is not valid.")]
+    InvalidUrl,
+
+    #[error("Failed to start the WebRTC runtime.")]
+    Runtime(#[source] std::io::Error),
+}
+
+/// Established WebRTC session and the resources needed to end it.
+struct Session {
+    runtime: Runtime,
+    peer_connection: Arc<RTCPeerConnection>,
+    track: Arc<TrackLocalStaticSample>,
+    /// URL of the session resource returned by the endpoint in the `Location` header.
+    /// Servers that don't return it end the session when the peer connection is closed.
+    url: Option<Url>,
+    client: Client,
+    bearer_token: Option<Arc<str>>,
+}
+
+impl WhipSender {
+    pub fn new(
+        options: WhipSenderOptions,
+        packets: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+        stats: Arc<OutputStats>,
+        request_keyframe: KeyframeRequester,
+    ) -> Result<Self, OutputInitError> {
+        let codec = codec_capability(options.codec)
+            .ok_or(OutputInitError::UnsupportedCodec(options.codec))?;
+
+        let session =
+            Session::connect(&options, codec, stats.clone(), request_keyframe).map_err(|err| {
+                OutputInitError::WhipConnectionFailed(options.endpoint_url.clone(), err)
+            })?;
+
+        let output_id = options.output_id.clone();
+        let sender_thread = std::thread::Builder::new()
+            .name(format!("WHIP sender for output {}", options.output_id))
+            .spawn(move || Self::sender_thread(session, &output_id, packets, stats))
+            .unwrap();
+
+        Ok(Self {
+            endpoint_url: options.endpoint_url,
+            sender_thread: Some(sender_thread),
+        })
+    }
+
+    fn sender_thread(
+        session: Session,
+        output_id: &OutputId,
+        packets: Box<dyn Iterator<Item = EncodedChunk> + Send>,
+        stats: Arc<OutputStats>,
+    ) {
+        let mut last_pts = None;
+        for chunk in packets {
+            let _span = send_span(output_id, &chunk);
+            // RTP timestamps are advanced by the duration of the sample after it's sent.
+            // Duration of the next frame is not known yet, so the duration of the
+            // previous one is used instead.
+            let duration = last_pts
+                .map(|last_pts| Duration::from_secs_f64((chunk.pts - last_pts) as f64 / 90000.0))
+                .unwrap_or_default();
+            last_pts = Some(chunk.pts);
+
+            let sample = Sample {
+                data: chunk.data,
+                duration,
+                ..Default::default()
+            };
+            match session
+                .runtime
+                .block_on(session.track.write_sample(&sample))
+            {
+                Ok(()) => stats.on_send_succeeded(),
+                Err(err) => {
+                    // Logged only when the failure starts, not for every dropped frame.
+                    if !stats.is_send_failing() {
+                        error!("Failed to send a frame to the WHIP endpoint: {err}");
+                    }
+                    stats.on_send_failed();
+                }
+            }
+        }
+
+        session.close();
+    }
+}
+
+impl Session {
+    fn connect(
+        options: &WhipSenderOptions,
+        codec: RTCRtpCodecCapability,
+        stats: Arc<OutputStats>,
+        request_keyframe: KeyframeRequester,
+    ) -> Result<Self, WhipError> {
+        // ICE, DTLS and SRTP are handled by tasks running in the background, so the
+        // runtime needs its own worker threads.
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name(format!("WHIP runtime for output {}", options.output_id))
+            .enable_all()
+            .build()
+            .map_err(WhipError::Runtime)?;
+
+        let (peer_connection, track, rtp_sender) =
+            runtime.block_on(create_peer_connection(codec))?;
+        runtime.spawn(forward_keyframe_requests(
+            rtp_sender,
+            request_keyframe.clone(),
+        ));
+        let output_id = options.output_id.clone();
+        peer_connection.on_peer_connection_state_change(Box::new(move |state| {
+            match state {
+                RTCPeerConnectionState::Connected => {
+                    info!("[output {output_id}] WHIP session connected.");
+                    // Stream has to start with a keyframe, and frames sent before the
+                    // connection was established were dropped.
+                    request_keyframe();
+                }
+                RTCPeerConnectionState::Failed => {
+                    error!("[output {output_id}] WHIP session failed.");
+                    stats.on_send_failed();
+                }
+                RTCPeerConnectionState::Disconnected => {
+                    warn!("[output {output_id}] WHIP session disconnected.");
+                }
+                _ => {}
+            }
+            Box::pin(async {})
+        }));
+
+        // Candidates are sent in the offer, because not all servers support trickle ICE.
+        let offer = runtime.block_on(async {
+            let offer = peer_connection.create_offer(None).await?;
+            let mut gathering_complete = peer_connection.gathering_complete_promise().await;
+            peer_connection.set_local_description(offer).await?;
+            let _ = gathering_complete.recv().await;
+            Ok::<_, WhipError>(peer_connection.local_description().await)
+        })?;
+        let offer = offer.map(|offer| offer.sdp).unwrap_or_default();
+
+        let endpoint_url = Url::parse(&options.endpoint_url).map_err(|_| WhipError::InvalidUrl)?;
+        let client = Client::new();
+        let mut request = client
+            .post(endpoint_url.clone())
+            .header(CONTENT_TYPE, "application/sdp")
+            .body(offer);
+        if let Some(ref bearer_token) = options.bearer_token {
+            request = request.header(AUTHORIZATION, format!("Bearer {bearer_token}"));
+        }
+        let response = request.send()?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().unwrap_or_default();
+            return Err(WhipError::BadStatus(status, body));
+        }
+        // Location can be relative to the endpoint URL.
+        let url = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| endpoint_url.join(location).ok());
+        let answer = RTCSessionDescription::answer(response.text()?)?;
+        runtime.block_on(peer_connection.set_remote_description(answer))?;
+
+        Ok(Self {
+            runtime,
+            peer_connection,
+            track,
+            url,
+            client,
+            bearer_token: options.bearer_token.clone(),
+        })
+    }
+
+    fn close(self) {
+        if let Err(err) = self.runtime.block_on(self.peer_connection.close()) {
+            warn!("Failed to close the WebRTC peer connection: {err}");
+        }
+        let Some(url) = self.url else {
+            return;
+        };
+        let mut request = self.client.delete(url);
+        if let Some(bearer_token) = self.bearer_token {
+            request = request.header(AUTHORIZATION, format!("Bearer {bearer_token}"));
+        }
+        if let Err(err) = request
+            .send()
+            .and_then(|response| response.error_for_status())
+        {
+            warn!("Failed to end the WHIP session: {err}");
+        }
+    }
+}
+
+async fn create_peer_connection(
+    codec: RTCRtpCodecCapability,
+) -> Result<
+    (
+        Arc<RTCPeerConnection>,
+        Arc<TrackLocalStaticSample>,
+        Arc<RTCRtpSender>,
+    ),
+    WhipError,
+> {
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs()?;
+    // Default interceptors send RTCP sender reports and handle NACKs.
+    let registry = register_default_interceptors(Registry::new(), &mut media_engine)?;
+    let api = APIBuilder::new()
+        .with_media_engine(media_engine)
+        .with_interceptor_registry(registry)
+        .build();
+
+    let peer_connection = Arc::new(api.new_peer_connection(RTCConfiguration::default()).await?);
+    let track = Arc::new(TrackLocalStaticSample::new(
+        codec,
+        "video".to_owned(),
+        "video_compositor".to_owned(),
+    ));
+    let transceiver = peer_connection
+        .add_transceiver_from_track(
+            track.clone() as Arc<dyn TrackLocal + Send + Sync>,
+            Some(RTCRtpTransceiverInit {
+                direction: RTCRtpTransceiverDirection::Sendonly,
+                send_encodings: Vec::new(),
+            }),
+        )
+        .await?;
+    let rtp_sender = transceiver.sender().await;
+
+    Ok((peer_connection, track, rtp_sender))
+}
+
+/// Reads RTCP packets received for the video track until the peer connection is closed.
+async fn forward_keyframe_requests(
+    rtp_sender: Arc<RTCRtpSender>,
+    request_keyframe: KeyframeRequester,
+) {
+    while let Ok((packets, _)) = rtp_sender.read_rtcp().await {
+        let is_keyframe_requested = packets.iter().any(|packet| {
+            let packet = packet.as_any();
+            packet.is::<PictureLossIndication>() || packet.is::<FullIntraRequest>()
+        });
+        if is_keyframe_requested {
+            request_keyframe();
+        }
+    }
+}
+
+/// Codecs supported by browsers. H264 streams have to be encoded without B-frames,
+/// e.g. with the baseline profile.
+fn codec_capability(codec: Codec) -> Option<RTCRtpCodecCapability> {
+    let (mime_type, sdp_fmtp_line) = match codec {
+        Codec::H264 => (
+            MIME_TYPE_H264,
+            "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f",
+        ),
+        Codec::Vp8 => (MIME_TYPE_VP8, ""),
+        Codec::Vp9 => (MIME_TYPE_VP9, "profile-id=0"),
+        Codec::H265 | Codec::Av1 => return None,
+    };
+    Some(RTCRtpCodecCapability {
+        mime_type: mime_type.to_owned(),
+        clock_rate: 90000,
+        sdp_fmtp_line: sdp_fmtp_line.to_owned(),
+        ..Default::default()
+    })
+}
+
+impl Drop for WhipSender {
+    fn drop(&mut self) {
+        match self.sender_thread.take() {
+            Some(handle) => handle.join().unwrap(),
+            None => error!("WHIP sender thread was already joined."),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Arc,
+    thread,
+    time::Instant,
+};
+
+use compositor_render::{Frame, OutputId};
+use crossbeam_channel::{unbounded, Sender};
+use log::error;
+
+use super::{chunk_router::ChunkRouter, PipelineOutput};
+
+/// Passes frames produced by the render thread to outputs: updates output stats and
+/// snapshots, resamples frames to the output framerate and sends them to encoders.
+///
+/// With more than one thread, this work is split between worker threads, so the render
+/// thread can start rendering the next frame set sooner. Every output is assigned to
+/// a single worker, so its frames are delivered in order.
+pub(super) struct OutputWorkers {
+    chunk_router: ChunkRouter,
+    workers: Vec<(Sender<RenderedFrame>, thread::JoinHandle<()>)>,
+}
+
+pub(super) struct RenderedFrame {
+    pub output_id: OutputId,
+    pub output: Arc<PipelineOutput>,
+    pub frame: Frame,
+    pub ingested_at: Option<Instant>,
+    /// Length of the render queue when the frame set was taken from it.
+    pub render_queue_length: usize,
+}
+
+impl OutputWorkers {
+    /// With a single thread, frames are delivered on the render thread.
+    pub fn new(threads: usize, chunk_router: ChunkRouter) -> Self {
+        let workers = match threads {
+            0 | 1 => Vec::new(),
+            threads => (0..threads)
+                .map(|index| {
+                    let (sender, receiver) = unbounded();
+                    let chunk_router = chunk_router.clone();
+                    let handle = thread::Builder::new()
+                        .name(format!("Output worker {index}"))
+                        .spawn(move || {
+                            for frame in receiver.iter() {
+                                deliver(&chunk_router, frame);
+                            }
+                        })
+                        .unwrap();
+                    (sender, handle)
+                })
+                .collect(),
+        };
+        Self {
+            chunk_router,
+            workers,
+        }
+    }
+
+    pub fn send(&self, frame: RenderedFrame) {
+        if self.workers.is_empty() {
+            deliver(&self.chunk_router, frame);
+            return;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        frame.output_id.hash(&mut hasher);
+        let (sender, _) = &self.workers[hasher.finish() as usize % self.workers.len()];
+        if sender.send(frame).is_err() {
+            error!("Output worker is not running, dropping a rendered frame.");
+        }
+    }
+
+    /// Blocks until all frames that were already sent are delivered.
+    pub fn join(self) {
+        for (sender, handle) in self.workers {
+            drop(sender);
+            if handle.join().is_err() {
+                error!("Output worker panicked.");
+            }
+        }
+    }
+}
+
+fn deliver(chunk_router: &ChunkRouter, rendered: RenderedFrame) {
+    let RenderedFrame {
+        output_id,
+        output,
+        frame,
+        ingested_at,
+        render_queue_length,
+    } = rendered;
+
+    if let Some(ingested_at) = ingested_at {
+        output.stats.on_frame_rendered(frame.pts, ingested_at);
+    }
+    *output.last_frame.lock().unwrap() = Some(frame.clone());
+
+    // Output receives chunks from an encoder of another output, or the output and all
+    // outputs receiving chunks from its encoder are disabled.
+    if !chunk_router.is_encoder_used(&output_id) {
+        return;
+    }
+
+    if let Some(adaptive_bitrate) = &output.adaptive_bitrate {
+        adaptive_bitrate.lock().unwrap().on_render_queue_length(
+            &output_id,
+            &output.encoder,
+            render_queue_length,
+        );
+    }
+
+    match &output.cadence {
+        Some(cadence) => {
+            for frame in cadence.lock().unwrap().resample(frame) {
+                output.encoder.send_frame(frame);
+            }
+        }
+        None => output.encoder.send_frame(frame),
+    }
+}
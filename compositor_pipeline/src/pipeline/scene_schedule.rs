@@ -0,0 +1,37 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use compositor_render::scene::SceneTransition;
+
+use super::OutputScene;
+
+/// Scene update that is applied right before the frame set with `pts` is rendered.
+pub(super) struct ScheduledSceneUpdate {
+    pub(super) pts: Duration,
+    pub(super) outputs: Vec<OutputScene>,
+    pub(super) transition: Option<SceneTransition>,
+}
+
+/// Pending scene updates ordered by their timestamps. Updates are added by the pipeline
+/// and applied by the render thread.
+#[derive(Clone, Default)]
+pub(super) struct SceneSchedule(Arc<Mutex<Vec<ScheduledSceneUpdate>>>);
+
+impl SceneSchedule {
+    /// Updates with the same timestamp are applied in the order they were scheduled.
+    pub(super) fn push(&self, update: ScheduledSceneUpdate) {
+        let mut updates = self.0.lock().unwrap();
+        let index = updates.partition_point(|scheduled| scheduled.pts <= update.pts);
+        updates.insert(index, update);
+    }
+
+    /// Removes and returns updates that have to be applied before rendering the frame
+    /// set with `pts`.
+    pub(super) fn take_due(&self, pts: Duration) -> Vec<ScheduledSceneUpdate> {
+        let mut updates = self.0.lock().unwrap();
+        let count = updates.partition_point(|scheduled| scheduled.pts <= pts);
+        updates.drain(..count).collect()
+    }
+}
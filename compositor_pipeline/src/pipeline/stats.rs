@@ -0,0 +1,223 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Number of most recent frames used to calculate latency percentiles.
+const LATENCY_SAMPLES: usize = 1000;
+
+/// Maximal number of rendered frames waiting to be matched with encoded chunks. Frames
+/// are never matched if the encoder drops them.
+const MAX_PENDING_FRAMES: usize = 300;
+
+/// Counters updated by the decoder of a single input.
+#[derive(Debug, Default)]
+pub struct InputStats {
+    decoded_frames: AtomicU64,
+    buffer_dropped_frames: AtomicU64,
+    decode_errors: AtomicU64,
+    missing_parameter_sets: AtomicU64,
+    last_decode_error: Mutex<Option<String>>,
+}
+
+impl InputStats {
+    pub fn decoded_frames(&self) -> u64 {
+        self.decoded_frames.load(Ordering::Relaxed)
+    }
+
+    /// Number of chunks dropped, because the input buffer was full.
+    pub fn buffer_dropped_frames(&self) -> u64 {
+        self.buffer_dropped_frames.load(Ordering::Relaxed)
+    }
+
+    /// Number of packets the decoder failed to decode and frames dropped after decoding,
+    /// e.g. because they were corrupted.
+    pub fn decode_errors(&self) -> u64 {
+        self.decode_errors.load(Ordering::Relaxed)
+    }
+
+    /// Number of H264 or H265 chunks received before the first sequence parameter set
+    /// (SPS). They can't be decoded, which usually means that the stream was joined
+    /// between keyframes or the sender doesn't repeat parameter sets.
+    pub fn missing_parameter_sets(&self) -> u64 {
+        self.missing_parameter_sets.load(Ordering::Relaxed)
+    }
+
+    pub fn last_decode_error(&self) -> Option<String> {
+        self.last_decode_error.lock().unwrap().clone()
+    }
+
+    pub(crate) fn on_buffer_frame_dropped(&self) {
+        self.buffer_dropped_frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_frame_decoded(&self) {
+        self.decoded_frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_decode_error(&self, message: String) {
+        self.decode_errors.fetch_add(1, Ordering::Relaxed);
+        *self.last_decode_error.lock().unwrap() = Some(message);
+    }
+
+    pub(crate) fn on_missing_parameter_set(&self) {
+        self.missing_parameter_sets.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Counters updated when chunks produced by the encoder of a single output are consumed.
+#[derive(Debug, Default)]
+pub struct OutputStats {
+    encoded_frames: AtomicU64,
+    latency: Mutex<LatencyTracker>,
+    send_retries: AtomicU64,
+    send_failures: AtomicU64,
+    is_send_failing: AtomicBool,
+}
+
+/// Percentiles of the time between receiving an input chunk and sending an output chunk
+/// with the frame it was rendered into.
+#[derive(Debug, Clone, Copy)]
+pub struct LatencyPercentiles {
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+#[derive(Debug, Default)]
+struct LatencyTracker {
+    /// Rendered frames identified by pts in 90kHz units, with the time the most recent
+    /// input frame used to render them was received.
+    pending: VecDeque<(i64, Instant)>,
+    samples: VecDeque<Duration>,
+}
+
+impl OutputStats {
+    pub fn encoded_frames(&self) -> u64 {
+        self.encoded_frames.load(Ordering::Relaxed)
+    }
+
+    /// Calculated from the last 1000 frames. `None` if no frame was sent yet.
+    pub fn latency(&self) -> Option<LatencyPercentiles> {
+        let mut samples: Vec<Duration> = self
+            .latency
+            .lock()
+            .unwrap()
+            .samples
+            .iter()
+            .copied()
+            .collect();
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort();
+        let percentile = |p: usize| samples[(samples.len() * p).div_ceil(100) - 1];
+        Some(LatencyPercentiles {
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+        })
+    }
+
+    /// Number of times sending to the destination was retried after a transient error.
+    pub fn send_retries(&self) -> u64 {
+        self.send_retries.load(Ordering::Relaxed)
+    }
+
+    /// Number of times sending failed after all retries, so the data was dropped.
+    pub fn send_failures(&self) -> u64 {
+        self.send_failures.load(Ordering::Relaxed)
+    }
+
+    /// `true` if the last send failed after all retries. It's reset when sending
+    /// succeeds again.
+    pub fn is_send_failing(&self) -> bool {
+        self.is_send_failing.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn on_send_retried(&self) {
+        self.send_retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_send_failed(&self) {
+        self.send_failures.fetch_add(1, Ordering::Relaxed);
+        self.is_send_failing.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_send_succeeded(&self) {
+        self.is_send_failing.store(false, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_frame_rendered(&self, pts: Duration, ingested_at: Instant) {
+        let mut latency = self.latency.lock().unwrap();
+        // Same conversion as the one used by the encoders.
+        let pts = (pts.as_secs_f64() * 90000.0) as i64;
+        latency.pending.push_back((pts, ingested_at));
+        if latency.pending.len() > MAX_PENDING_FRAMES {
+            latency.pending.pop_front();
+        }
+    }
+
+    pub(crate) fn on_frame_encoded(&self, pts: i64) {
+        self.encoded_frames.fetch_add(1, Ordering::Relaxed);
+
+        let mut latency = self.latency.lock().unwrap();
+        // Chunks are not sent in pts order if the stream contains B-frames.
+        let Some(index) = latency
+            .pending
+            .iter()
+            .position(|(frame_pts, _)| *frame_pts == pts)
+        else {
+            return;
+        };
+        let (_, ingested_at) = latency.pending.remove(index).unwrap();
+        latency.samples.push_back(ingested_at.elapsed());
+        if latency.samples.len() > LATENCY_SAMPLES {
+            latency.samples.pop_front();
+        }
+    }
+}
+
+/// Counters updated by the render thread.
+#[derive(Debug, Default)]
+pub struct RenderStats {
+    queue_length: AtomicUsize,
+    dropped_frames: AtomicU64,
+    /// In microseconds.
+    last_pts: AtomicU64,
+}
+
+impl RenderStats {
+    /// Number of frame sets produced by the queue that are waiting to be rendered.
+    pub fn queue_length(&self) -> usize {
+        self.queue_length.load(Ordering::Relaxed)
+    }
+
+    /// Number of frame sets that were not rendered, because the render queue was too
+    /// long or rendering failed.
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames.load(Ordering::Relaxed)
+    }
+
+    /// Timestamp of the most recently rendered frame set. Zero if nothing was rendered yet.
+    pub fn last_pts(&self) -> Duration {
+        Duration::from_micros(self.last_pts.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn set_last_pts(&self, pts: Duration) {
+        self.last_pts
+            .store(pts.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_queue_length(&self, length: usize) {
+        self.queue_length.store(length, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_frame_dropped(&self) {
+        self.dropped_frames.fetch_add(1, Ordering::Relaxed);
+    }
+}
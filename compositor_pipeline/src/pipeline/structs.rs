@@ -4,16 +4,29 @@ use bytes::Bytes;
 ///
 /// Many codecs specify that encoded data is split into chunks.
 /// For example, H264 splits the data into NAL units and AV1 splits the data into OBU frames.
+/// HEVC chunks always contain a whole access unit, because the ffmpeg HEVC decoder
+/// can't reassemble frames split across multiple packets.
+///
+/// `dts` is only known for chunks produced by an encoder. Streams with B-frames
+/// have `pts` that is not monotonic, so `dts` should be used where decode order matters.
+#[derive(Clone)]
 pub struct EncodedChunk {
     pub data: Bytes,
     pub pts: i64,
     pub dts: Option<i64>,
+    /// `false` also if it's unknown whether the chunk is a keyframe, e.g. for chunks
+    /// depayloaded from RTP.
+    pub is_keyframe: bool,
+    /// First chunk after the input stream was restarted, e.g. when an RTP sender
+    /// reconnected. Decoder state is reset before decoding it.
+    pub discontinuity: bool,
     pub kind: EncodedChunkKind,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EncodedChunkKind {
     Video(Codec),
+    Audio(AudioCodec),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -38,6 +51,8 @@ impl EncodedChunk {
             data,
             pts: value.pts().ok_or(ChunkFromFfmpegError::NoPts)?,
             dts: value.dts(),
+            is_keyframe: value.is_key(),
+            discontinuity: false,
             kind,
         })
     }
@@ -46,6 +61,10 @@ impl EncodedChunk {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Codec {
     H264,
+    H265,
+    Av1,
+    Vp8,
+    Vp9,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -60,7 +79,36 @@ impl TryFrom<ffmpeg_next::Codec> for Codec {
     fn try_from(value: ffmpeg_next::Codec) -> Result<Self, Self::Error> {
         match value.id() {
             ffmpeg_next::codec::Id::H264 => Ok(Self::H264),
+            ffmpeg_next::codec::Id::HEVC => Ok(Self::H265),
+            ffmpeg_next::codec::Id::AV1 => Ok(Self::Av1),
+            ffmpeg_next::codec::Id::VP8 => Ok(Self::Vp8),
+            ffmpeg_next::codec::Id::VP9 => Ok(Self::Vp9),
             v => Err(CodecFromFfmpegError::UnsupportedCodec(v)),
         }
     }
 }
+
+impl From<Codec> for ffmpeg_next::codec::Id {
+    fn from(value: Codec) -> Self {
+        match value {
+            Codec::H264 => Self::H264,
+            Codec::H265 => Self::HEVC,
+            Codec::Av1 => Self::AV1,
+            Codec::Vp8 => Self::VP8,
+            Codec::Vp9 => Self::VP9,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioCodec {
+    Aac,
+}
+
+impl From<AudioCodec> for ffmpeg_next::codec::Id {
+    fn from(value: AudioCodec) -> Self {
+        match value {
+            AudioCodec::Aac => Self::AAC,
+        }
+    }
+}
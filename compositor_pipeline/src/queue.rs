@@ -1,17 +1,27 @@
+mod backpressure;
 mod internal_queue;
 mod queue_thread;
 
 use std::{
+    collections::HashMap,
     sync::{Arc, Mutex},
     thread,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 
 use compositor_render::{error::ErrorStack, Frame, FrameSet, Framerate, InputId};
-use crossbeam_channel::{unbounded, Receiver, Sender};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
 use log::error;
 use thiserror::Error;
 
+use crate::pipeline::{
+    audio_mixer::AudioSamples,
+    input::transform::{FrameTransform, FrameTransformer},
+};
+
+pub use self::backpressure::{Backpressure, BackpressureOptions};
+pub use self::internal_queue::LastFramePts;
+
 use self::{internal_queue::InternalQueue, queue_thread::QueueThread};
 
 #[derive(Error, Debug)]
@@ -20,6 +30,67 @@ pub enum QueueError {
     UnknownInputId(InputId),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputListenerId(u64);
+
+/// Frames from all inputs that should be rendered together.
+pub struct FramesBatch {
+    pub frames: FrameSet<InputId>,
+    /// When the most recent frame in the batch was received by the pipeline. `None` if
+    /// the batch is empty.
+    pub ingested_at: Option<Instant>,
+}
+
+/// How the queue decides that a frame set can be produced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueueSyncStrategy {
+    /// Frame set is produced when every input has a frame with a timestamp at or after
+    /// the frame set timestamp. If some inputs are late, the queue waits for them for at
+    /// most `max_wait` after the time the frame set should have been produced. Inputs
+    /// that didn't send any frames yet are not waited for.
+    ///
+    /// Late frames of jittery inputs are still rendered, so fewer frames are dropped or
+    /// repeated, but the latency of outputs grows by up to `max_wait`. An input that stops
+    /// sending frames delays every frame set by `max_wait` until it's unregistered.
+    WaitForAllInputs { max_wait: Duration },
+    /// Frame set is produced on every tick of the output framerate from the frames that
+    /// are available at that time, or earlier if all inputs already have frames for it.
+    ///
+    /// The queue never waits for late inputs, so the latency stays low, but when a frame
+    /// of an input arrives late, the previous frame of that input is repeated and the late
+    /// one may be dropped.
+    #[default]
+    LatestOnTick,
+}
+
+impl QueueSyncStrategy {
+    /// How long after its timestamp a frame set can be produced.
+    fn max_wait(&self) -> Duration {
+        match self {
+            QueueSyncStrategy::WaitForAllInputs { max_wait } => *max_wait,
+            QueueSyncStrategy::LatestOnTick => Duration::ZERO,
+        }
+    }
+}
+
+/// How timestamps of an input are mapped to the queue clock. Inputs start with arbitrary
+/// timestamps, so they are offset on the first frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PtsNormalization {
+    /// The first frame of the input is rendered when it's enqueued (after buffering),
+    /// regardless of its timestamp. Inputs are not synchronized with each other.
+    #[default]
+    FirstFrame,
+    /// All inputs in this mode share the offset calculated on the first frame of any of
+    /// them, so inputs with timestamps from the same clock, e.g. cameras synchronized
+    /// with NTP, stay aligned with each other. Frames of an input that are late relative
+    /// to the first input are dropped.
+    SharedClock,
+}
+
+/// Produces the frame of an input for a frame set with the given pts.
+pub type FrameGenerator = Box<dyn Fn(Duration) -> Frame + Send>;
+
 const DEFAULT_BUFFER_DURATION: Duration = Duration::from_millis(16 * 5); // about 5 frames at 60 fps
 
 /// Queue is responsible for consuming frames from different inputs and producing
@@ -50,45 +121,140 @@ pub struct Queue {
     /// The end goal is that resulting PTS should be in a time frame where PTS
     /// is equivalent to clock_start time of real time.
     clock_start: Instant,
+
+    /// Dropping this sender stops the queue thread started by `Queue::start`.
+    stop_sender: Mutex<Option<Sender<()>>>,
+
+    backpressure: Arc<Backpressure>,
+
+    sync_strategy: QueueSyncStrategy,
+
+    /// Frame sets are produced this long after their timestamps, so frames of jittery
+    /// inputs arrive before they are needed.
+    output_delay: Duration,
+
+    /// Transformations of inputs' frames. They are applied in `enqueue_frame` before
+    /// the internal queue is locked, so other inputs are not blocked.
+    transformers: Mutex<HashMap<InputId, Arc<Mutex<FrameTransformer>>>>,
 }
 
 impl Queue {
-    pub fn new(output_framerate: Framerate) -> Self {
+    /// If `backpressure` is not specified, the queue produces frame sets in real time,
+    /// regardless of how fast they are rendered. Every frame set is produced at least
+    /// `output_delay` after its timestamp.
+    pub fn new(
+        output_framerate: Framerate,
+        backpressure: Option<BackpressureOptions>,
+        sync_strategy: QueueSyncStrategy,
+        output_delay: Duration,
+    ) -> Self {
         Queue {
             internal_queue: Mutex::new(InternalQueue::new()),
             check_queue_channel: unbounded(),
             output_framerate,
             buffer_duration: DEFAULT_BUFFER_DURATION,
             clock_start: Instant::now(),
+            stop_sender: Mutex::new(None),
+            backpressure: Arc::new(Backpressure::new(backpressure)),
+            sync_strategy,
+            output_delay,
+            transformers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn backpressure(&self) -> &Arc<Backpressure> {
+        &self.backpressure
+    }
+
+    /// If `transform` is specified, it's applied to every frame of the input.
+    pub fn add_input(
+        &self,
+        input_id: InputId,
+        transform: Option<FrameTransform>,
+        pts_normalization: PtsNormalization,
+    ) {
+        if let Some(transform) = transform {
+            self.transformers.lock().unwrap().insert(
+                input_id.clone(),
+                Arc::new(Mutex::new(FrameTransformer::new(transform))),
+            );
         }
+        self.internal_queue
+            .lock()
+            .unwrap()
+            .add_input(input_id, pts_normalization);
     }
 
-    pub fn add_input(&self, input_id: InputId) {
-        self.internal_queue.lock().unwrap().add_input(input_id);
+    /// Adds an input whose frames are produced by `generator` for every batch, e.g. an image
+    /// repeated with the batch timestamp. The input is never stale and the queue never
+    /// waits for it.
+    pub fn add_generated_input(&self, input_id: InputId, generator: FrameGenerator) {
+        self.internal_queue
+            .lock()
+            .unwrap()
+            .add_generated_input(input_id, generator);
     }
 
     pub fn remove_input(&self, input_id: &InputId) {
+        self.transformers.lock().unwrap().remove(input_id);
         self.internal_queue.lock().unwrap().remove_input(input_id);
     }
 
-    pub fn start(self: &Arc<Self>, sender: Sender<FrameSet<InputId>>) {
+    /// Inputs that didn't produce frames for longer than `timeout`, including inputs that
+    /// didn't produce any frame since they were added. Images and other generated inputs
+    /// are never inactive.
+    pub fn inactive_inputs(&self, timeout: Duration) -> Vec<InputId> {
+        self.internal_queue.lock().unwrap().inactive_inputs(timeout)
+    }
+
+    pub fn start(self: &Arc<Self>, sender: Sender<FramesBatch>) {
         let queue = self.clone();
         let tick_duration = self.output_framerate.get_interval_duration();
+        let (stop_sender, stop_receiver) = bounded(0);
+        *self.stop_sender.lock().unwrap() = Some(stop_sender);
 
         QueueThread::new(
             queue,
             sender,
+            stop_receiver,
             queue_thread::Options {
                 tick_duration,
                 buffer_duration: self.buffer_duration,
                 output_framerate: self.output_framerate,
                 clock_start: self.clock_start,
+                sync_strategy: self.sync_strategy,
+                output_delay: self.output_delay,
             },
         )
         .spawn();
     }
 
-    pub fn enqueue_frame(&self, input_id: InputId, frame: Frame) -> Result<(), QueueError> {
+    /// Stops the queue thread. Frames sender passed to `Queue::start` is dropped
+    /// after the thread exits.
+    pub fn stop(&self) {
+        self.stop_sender.lock().unwrap().take();
+    }
+
+    /// `ingested_at` is the time when the chunk the frame was decoded from was received
+    /// by the pipeline. It's used to measure the latency of outputs.
+    pub fn enqueue_frame(
+        &self,
+        input_id: InputId,
+        frame: Frame,
+        ingested_at: Instant,
+    ) -> Result<(), QueueError> {
+        let transformer = self.transformers.lock().unwrap().get(&input_id).cloned();
+        let frame = match transformer {
+            Some(transformer) => match transformer.lock().unwrap().apply(frame) {
+                Ok(frame) => frame,
+                Err(err) => {
+                    error!("Failed to transform frame of input {input_id}, dropping it: {err}");
+                    return Ok(());
+                }
+            },
+            None => frame,
+        };
+
         let is_first_frame_for_input = !self
             .internal_queue
             .lock()
@@ -103,15 +269,19 @@ impl Queue {
 
         let mut internal_queue = self.internal_queue.lock().unwrap();
 
-        internal_queue.enqueue_frame(input_id.clone(), frame, self.clock_start)?;
+        internal_queue.enqueue_frame(input_id.clone(), frame, ingested_at, self.clock_start)?;
 
         // We don't know when pipeline is started, so we can't resolve real_next_pts,
         // but we can remove frames based on estimated PTS. This only works if queue
-        // is able to push frames in real time and is never behind more than one frame.
+        // is able to push frames in real time and is never behind more than one frame
+        // (plus the output delay and the time it can wait for late inputs).
         let framerate_tick = Duration::from_secs_f64(
             self.output_framerate.den as f64 / self.output_framerate.num as f64,
         );
-        let estimated_pts = self.clock_start.elapsed() - framerate_tick;
+        let estimated_pts = self
+            .clock_start
+            .elapsed()
+            .saturating_sub(framerate_tick + self.output_delay + self.sync_strategy.max_wait());
         if let Err(err) = internal_queue.drop_old_frames_by_input_id(&input_id, estimated_pts) {
             error!(
                 "Failed to drop frames on input {}:\n{}",
@@ -125,10 +295,78 @@ impl Queue {
         Ok(())
     }
 
-    pub fn subscribe_input_listener(&self, input_id: InputId, callback: Box<dyn FnOnce() + Send>) {
+    /// Samples are aligned to video frames of the same input, so they are dropped until
+    /// the first frame of the input is enqueued.
+    pub fn enqueue_samples(
+        &self,
+        input_id: InputId,
+        samples: AudioSamples,
+    ) -> Result<(), QueueError> {
+        self.internal_queue
+            .lock()
+            .unwrap()
+            .enqueue_samples(input_id, samples)
+    }
+
+    /// Removes and returns samples of all inputs that start before `pts`.
+    pub fn take_samples(&self, pts: Duration) -> HashMap<InputId, Vec<AudioSamples>> {
+        self.internal_queue.lock().unwrap().take_samples(pts)
+    }
+
+    /// Frames of all inputs closest to the timestamp of the frame set that would be
+    /// produced now. Unlike batches produced by the queue thread, frames are not removed
+    /// from the queue, so it can be used to render outside of the render loop, e.g. before
+    /// the pipeline is started.
+    pub fn current_frames(&self) -> FrameSet<InputId> {
+        self.internal_queue
+            .lock()
+            .unwrap()
+            .frames_closest_to(self.clock_start.elapsed().saturating_sub(self.output_delay))
+    }
+
+    /// Offset in milliseconds added to timestamps of the input to map them to the queue
+    /// clock. `None` until the first frame of the input is enqueued.
+    pub fn pts_offset_ms(&self, input_id: &InputId) -> Option<f64> {
+        self.internal_queue
+            .lock()
+            .unwrap()
+            .pts_offset(input_id)
+            .map(|offset| offset.as_millis_f64())
+    }
+
+    /// Normalized timestamp of the last frame of the input. `None` until the first frame of
+    /// the input is enqueued and for generated inputs.
+    pub fn last_frame_pts(&self, input_id: &InputId) -> Option<LastFramePts> {
+        self.internal_queue.lock().unwrap().last_frame_pts(input_id)
+    }
+
+    /// Timestamp of the frame set produced at the wall clock `time`. Frame sets are
+    /// delayed by backpressure pauses, so it's only an estimate if backpressure is enabled.
+    pub fn pts_at(&self, time: SystemTime) -> Duration {
+        let now = self.clock_start.elapsed().saturating_sub(self.output_delay);
+        match time.duration_since(SystemTime::now()) {
+            Ok(ahead) => now + ahead,
+            Err(err) => now.saturating_sub(err.duration()),
+        }
+    }
+
+    /// `callback` is called when the next frame of the input is enqueued.
+    pub fn subscribe_input_listener(
+        &self,
+        input_id: InputId,
+        callback: Box<dyn FnOnce() + Send>,
+    ) -> InputListenerId {
         self.internal_queue
             .lock()
             .unwrap()
             .subscribe_input_listener(input_id, callback)
     }
+
+    /// Removes the listener if it wasn't called yet, e.g. when the caller stopped waiting.
+    pub fn unsubscribe_input_listener(&self, input_id: &InputId, listener_id: InputListenerId) {
+        self.internal_queue
+            .lock()
+            .unwrap()
+            .unsubscribe_input_listener(input_id, listener_id)
+    }
 }
@@ -0,0 +1,68 @@
+use std::{
+    sync::{Condvar, Mutex},
+    time::Duration,
+};
+
+/// Render queue lengths at which the queue is paused and resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackpressureOptions {
+    /// Pause when at least this many frame sets are waiting to be rendered.
+    pub high_watermark: usize,
+    /// Resume when at most this many frame sets are waiting to be rendered.
+    pub low_watermark: usize,
+}
+
+/// Pauses the queue and inputs that can be read at any pace (files) while the render
+/// queue is too long, so frames are rendered later instead of being dropped. Live inputs
+/// can't be paused, their frames are buffered by the queue, so they are delayed by the
+/// total duration of the pauses.
+///
+/// If it's disabled, the render thread drops the oldest frame sets instead.
+pub struct Backpressure {
+    options: Option<BackpressureOptions>,
+    is_paused: Mutex<bool>,
+    resumed: Condvar,
+}
+
+impl Backpressure {
+    pub(super) fn new(options: Option<BackpressureOptions>) -> Self {
+        Self {
+            options,
+            is_paused: Mutex::new(false),
+            resumed: Condvar::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.options.is_some()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.is_paused.lock().unwrap()
+    }
+
+    /// Called when frame sets are added to or taken from the render queue.
+    pub(crate) fn on_render_queue_length(&self, length: usize) {
+        let Some(options) = self.options else {
+            return;
+        };
+        let mut is_paused = self.is_paused.lock().unwrap();
+        if !*is_paused && length >= options.high_watermark {
+            *is_paused = true;
+        } else if *is_paused && length <= options.low_watermark {
+            *is_paused = false;
+            self.resumed.notify_all();
+        }
+    }
+
+    /// Blocks while the queue is paused, but at most for `timeout`, so the caller can
+    /// check if it should stop. Returns `true` if the queue is still paused.
+    pub(crate) fn wait_while_paused(&self, timeout: Duration) -> bool {
+        let is_paused = self.is_paused.lock().unwrap();
+        let (is_paused, _) = self
+            .resumed
+            .wait_timeout_while(is_paused, timeout, |is_paused| *is_paused)
+            .unwrap();
+        *is_paused
+    }
+}
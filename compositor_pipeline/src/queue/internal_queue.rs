@@ -6,15 +6,81 @@ use std::collections::HashMap;
 use std::time::Duration;
 use std::time::Instant;
 
-use super::QueueError;
+use crate::pipeline::audio_mixer::AudioSamples;
+
+use super::{FrameGenerator, FramesBatch, InputListenerId, PtsNormalization, QueueError};
+
+struct QueuedFrame {
+    frame: Frame,
+    /// When the chunk the frame was decoded from was received by the pipeline.
+    ingested_at: Instant,
+}
+
+type InputListener = Box<dyn FnOnce() + Send>;
+
+/// Difference between the queue clock and timestamps of an input. It's negative if the
+/// timestamps of the input started later than the queue clock.
+#[derive(Debug, Clone, Copy)]
+pub struct PtsOffset {
+    nanos: i128,
+}
+
+impl PtsOffset {
+    /// Offset that maps `pts` to `clock_pts`.
+    fn new(pts: Duration, clock_pts: Duration) -> Self {
+        Self {
+            nanos: clock_pts.as_nanos() as i128 - pts.as_nanos() as i128,
+        }
+    }
+
+    /// Timestamps that would be before the start of the queue clock are clamped to it.
+    fn apply(&self, pts: Duration) -> Duration {
+        let nanos = pts.as_nanos() as i128 + self.nanos;
+        Duration::from_nanos(nanos.clamp(0, u64::MAX as i128) as u64)
+    }
+
+    pub fn as_millis_f64(&self) -> f64 {
+        self.nanos as f64 / 1_000_000.0
+    }
+}
+
+/// Timestamp of the last frame enqueued for an input, after normalization.
+#[derive(Debug, Clone, Copy)]
+pub struct LastFramePts {
+    pub pts: Duration,
+    /// Queue clock when the frame was enqueued.
+    pub enqueued_at: Duration,
+}
+
+impl LastFramePts {
+    /// How far the timestamp of the frame was ahead of the queue clock when the frame was
+    /// enqueued, in milliseconds. Negative if the frame arrived after its timestamp.
+    pub fn clock_lead_ms(&self) -> f64 {
+        (self.pts.as_secs_f64() - self.enqueued_at.as_secs_f64()) * 1000.0
+    }
+}
 
 pub struct InternalQueue {
     /// frames are PTS ordered. PTS include timestamps offsets
-    inputs_queues: HashMap<InputId, Vec<Frame>>,
-    inputs_listeners: HashMap<InputId, Vec<Box<dyn FnOnce() + Send>>>,
+    inputs_queues: HashMap<InputId, Vec<QueuedFrame>>,
+    inputs_listeners: HashMap<InputId, Vec<(InputListenerId, InputListener)>>,
+    next_listener_id: u64,
     /// offsets that normalize input pts to zero relative to the
     /// Queue:clock_start value.
-    timestamp_offsets: HashMap<InputId, Duration>,
+    timestamp_offsets: HashMap<InputId, PtsOffset>,
+    pts_normalization: HashMap<InputId, PtsNormalization>,
+    /// Offset of all inputs with `PtsNormalization::SharedClock`, calculated on the first
+    /// frame of any of them.
+    shared_clock_offset: Option<PtsOffset>,
+    /// Inputs that don't produce a stream of frames, e.g. images and test patterns. Their
+    /// frames are generated for every batch with the batch pts.
+    generated_inputs: HashMap<InputId, FrameGenerator>,
+    /// Decoded audio, PTS ordered. PTS include the same offsets as frames.
+    audio_queues: HashMap<InputId, Vec<AudioSamples>>,
+    /// When the last frame of the input was enqueued, or when the input was added if it
+    /// didn't produce any frames yet. Generated inputs are not included.
+    last_frame_at: HashMap<InputId, Instant>,
+    last_frame_pts: HashMap<InputId, LastFramePts>,
 }
 
 impl InternalQueue {
@@ -22,63 +88,185 @@ impl InternalQueue {
         InternalQueue {
             inputs_queues: HashMap::new(),
             inputs_listeners: HashMap::new(),
+            next_listener_id: 0,
             timestamp_offsets: HashMap::new(),
+            pts_normalization: HashMap::new(),
+            shared_clock_offset: None,
+            generated_inputs: HashMap::new(),
+            audio_queues: HashMap::new(),
+            last_frame_at: HashMap::new(),
+            last_frame_pts: HashMap::new(),
         }
     }
 
-    pub fn add_input(&mut self, input_id: InputId) {
+    pub fn add_input(&mut self, input_id: InputId, pts_normalization: PtsNormalization) {
+        self.pts_normalization
+            .insert(input_id.clone(), pts_normalization);
+        self.last_frame_at.insert(input_id.clone(), Instant::now());
         self.inputs_queues.insert(input_id, Vec::new());
     }
 
+    pub fn add_generated_input(&mut self, input_id: InputId, generator: FrameGenerator) {
+        self.generated_inputs.insert(input_id, generator);
+    }
+
     pub fn remove_input(&mut self, input_id: &InputId) {
         self.inputs_queues.remove(input_id);
         self.timestamp_offsets.remove(input_id);
+        self.pts_normalization.remove(input_id);
+        self.generated_inputs.remove(input_id);
+        self.audio_queues.remove(input_id);
+        self.last_frame_at.remove(input_id);
+        self.last_frame_pts.remove(input_id);
     }
 
     pub fn did_receive_frame(&self, input_id: &InputId) -> bool {
         self.timestamp_offsets.get(input_id).is_some()
     }
 
+    /// Inputs that didn't enqueue any frame for longer than `timeout`.
+    pub fn inactive_inputs(&self, timeout: Duration) -> Vec<InputId> {
+        self.last_frame_at
+            .iter()
+            .filter(|(_, last_frame_at)| last_frame_at.elapsed() > timeout)
+            .map(|(input_id, _)| input_id.clone())
+            .collect()
+    }
+
+    pub fn pts_offset(&self, input_id: &InputId) -> Option<PtsOffset> {
+        self.timestamp_offsets.get(input_id).copied()
+    }
+
+    pub fn last_frame_pts(&self, input_id: &InputId) -> Option<LastFramePts> {
+        self.last_frame_pts.get(input_id).copied()
+    }
+
     pub fn enqueue_frame(
         &mut self,
         input_id: InputId,
         mut frame: Frame,
+        ingested_at: Instant,
         clock_start: Instant,
     ) -> Result<(), QueueError> {
         let Some(input_queue) = self.inputs_queues.get_mut(&input_id) else {
             return Err(QueueError::UnknownInputId(input_id));
         };
+        self.last_frame_at.insert(input_id.clone(), Instant::now());
 
-        let offset = *self
-            .timestamp_offsets
-            .entry(input_id)
-            .or_insert_with(|| clock_start.elapsed().saturating_sub(frame.pts));
+        let clock_pts = clock_start.elapsed();
+        let offset = match self.timestamp_offsets.get(&input_id) {
+            Some(offset) => *offset,
+            None => {
+                let first_frame_offset = || PtsOffset::new(frame.pts, clock_pts);
+                let offset = match self.pts_normalization.get(&input_id) {
+                    Some(PtsNormalization::SharedClock) => *self
+                        .shared_clock_offset
+                        .get_or_insert_with(first_frame_offset),
+                    Some(PtsNormalization::FirstFrame) | None => first_frame_offset(),
+                };
+                self.timestamp_offsets.insert(input_id.clone(), offset);
+                offset
+            }
+        };
 
         // Modify frame pts to be at the time frame where PTS=0 represent clock_start
-        frame.pts += offset;
+        frame.pts = offset.apply(frame.pts);
+        self.last_frame_pts.insert(
+            input_id,
+            LastFramePts {
+                pts: frame.pts,
+                enqueued_at: clock_pts,
+            },
+        );
 
-        input_queue.push(frame);
+        input_queue.push(QueuedFrame { frame, ingested_at });
         Ok(())
     }
 
+    pub fn enqueue_samples(
+        &mut self,
+        input_id: InputId,
+        mut samples: AudioSamples,
+    ) -> Result<(), QueueError> {
+        if !self.inputs_queues.contains_key(&input_id) {
+            return Err(QueueError::UnknownInputId(input_id));
+        }
+        let Some(offset) = self.timestamp_offsets.get(&input_id) else {
+            return Ok(());
+        };
+
+        samples.pts = offset.apply(samples.pts);
+        self.audio_queues.entry(input_id).or_default().push(samples);
+        Ok(())
+    }
+
+    pub fn take_samples(&mut self, pts: Duration) -> HashMap<InputId, Vec<AudioSamples>> {
+        self.audio_queues
+            .iter_mut()
+            .map(|(input_id, audio_queue)| {
+                let count = audio_queue
+                    .iter()
+                    .take_while(|samples| samples.pts < pts)
+                    .count();
+                (input_id.clone(), audio_queue.drain(..count).collect())
+            })
+            .collect()
+    }
+
     /// Gets frames closest to buffer pts.
-    pub fn get_frames_batch(&mut self, buffer_pts: Duration) -> FrameSet<InputId> {
+    pub fn get_frames_batch(&mut self, buffer_pts: Duration) -> FramesBatch {
         for (_, input_queue) in self.inputs_queues.iter_mut() {
             Self::drop_old_input_frames(input_queue, buffer_pts);
         }
 
-        let mut frames_batch = FrameSet::new(buffer_pts);
+        let mut frames_batch = FramesBatch {
+            frames: FrameSet::new(buffer_pts),
+            ingested_at: None,
+        };
         for (input_id, input_queue) in &self.inputs_queues {
             if let Some(nearest_frame) = input_queue.first() {
                 frames_batch
                     .frames
-                    .insert(input_id.clone(), nearest_frame.clone());
+                    .frames
+                    .insert(input_id.clone(), nearest_frame.frame.clone());
+                frames_batch.ingested_at = frames_batch
+                    .ingested_at
+                    .max(Some(nearest_frame.ingested_at));
             }
         }
+        Self::insert_generated_frames(&self.generated_inputs, &mut frames_batch.frames);
 
         frames_batch
     }
 
+    /// Gets frames closest to pts without removing them from the queue.
+    pub fn frames_closest_to(&self, pts: Duration) -> FrameSet<InputId> {
+        let mut frames = FrameSet::new(pts);
+        for (input_id, input_queue) in &self.inputs_queues {
+            let closest_frame = input_queue
+                .iter()
+                .min_by_key(|queued| queued.frame.pts.as_nanos().abs_diff(pts.as_nanos()));
+            if let Some(closest_frame) = closest_frame {
+                frames
+                    .frames
+                    .insert(input_id.clone(), closest_frame.frame.clone());
+            }
+        }
+        Self::insert_generated_frames(&self.generated_inputs, &mut frames);
+        frames
+    }
+
+    fn insert_generated_frames(
+        generated_inputs: &HashMap<InputId, FrameGenerator>,
+        frames: &mut FrameSet<InputId>,
+    ) {
+        for (input_id, generator) in generated_inputs {
+            frames
+                .frames
+                .insert(input_id.clone(), generator(frames.pts));
+        }
+    }
+
     /// Checks if all inputs have frames closest to buffer_pts.
     ///
     /// Every input queue should have a frame with larger or equal pts than buffer pts.
@@ -89,12 +277,16 @@ impl InternalQueue {
     /// input, queue might receive frame "closer" to buffer pts in the future on some input,
     /// therefore it should wait with buffer push until it receives those frames or until
     /// ticker enforces push from the queue.
-    pub fn check_all_inputs_ready(&self, next_buffer_pts: Duration) -> bool {
+    pub fn check_all_inputs_ready(
+        &self,
+        next_buffer_pts: Duration,
+        wait_for_inputs_without_frames: bool,
+    ) -> bool {
         self.inputs_queues
             .values()
             .all(|input_queue| match input_queue.last() {
-                Some(last_frame) => last_frame.pts >= next_buffer_pts,
-                None => false,
+                Some(last_frame) => last_frame.frame.pts >= next_buffer_pts,
+                None => !wait_for_inputs_without_frames,
             })
     }
 
@@ -103,12 +295,18 @@ impl InternalQueue {
     /// Finds frame that is closest to the next_buffer_pts and removes everything older.
     /// Frames in queue have monotonically increasing pts, so we can just drop all the frames
     /// before the "closest" one.
-    fn drop_old_input_frames(input_queue: &mut Vec<Frame>, next_buffer_pts: Duration) {
+    fn drop_old_input_frames(input_queue: &mut Vec<QueuedFrame>, next_buffer_pts: Duration) {
         let next_output_buffer_nanos = next_buffer_pts.as_nanos();
         let closest_diff_frame_index = input_queue
             .iter()
             .enumerate()
-            .min_by_key(|(_index, frame)| frame.pts.as_nanos().abs_diff(next_output_buffer_nanos))
+            .min_by_key(|(_index, queued)| {
+                queued
+                    .frame
+                    .pts
+                    .as_nanos()
+                    .abs_diff(next_output_buffer_nanos)
+            })
             .map(|(index, _frame)| index);
 
         if let Some(index) = closest_diff_frame_index {
@@ -133,17 +331,29 @@ impl InternalQueue {
     pub fn subscribe_input_listener(
         &mut self,
         input_id: InputId,
-        callback: Box<dyn FnOnce() + Send>,
-    ) {
+        callback: InputListener,
+    ) -> InputListenerId {
+        let listener_id = InputListenerId(self.next_listener_id);
+        self.next_listener_id += 1;
         self.inputs_listeners
             .entry(input_id)
             .or_default()
-            .push(callback)
+            .push((listener_id, callback));
+        listener_id
+    }
+
+    pub fn unsubscribe_input_listener(&mut self, input_id: &InputId, listener_id: InputListenerId) {
+        if let Some(listeners) = self.inputs_listeners.get_mut(input_id) {
+            listeners.retain(|(id, _)| *id != listener_id);
+            if listeners.is_empty() {
+                self.inputs_listeners.remove(input_id);
+            }
+        }
     }
 
     pub fn call_input_listeners(&mut self, input_id: &InputId) {
         let callbacks = self.inputs_listeners.remove(input_id).unwrap_or_default();
-        for cb in callbacks.into_iter() {
+        for (_, cb) in callbacks.into_iter() {
             cb()
         }
     }
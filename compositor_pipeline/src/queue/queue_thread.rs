@@ -5,35 +5,52 @@ use std::{
     time::{Duration, Instant},
 };
 
-use compositor_render::{FrameSet, Framerate, InputId};
-use crossbeam_channel::{tick, Sender};
+use compositor_render::Framerate;
+use crossbeam_channel::{select, tick, Receiver, Sender};
 
-use super::Queue;
+use super::{FramesBatch, Queue, QueueSyncStrategy};
 
 pub struct Options {
     pub buffer_duration: Duration,
     pub tick_duration: Duration,
     pub clock_start: Instant,
     pub output_framerate: Framerate,
+    pub sync_strategy: QueueSyncStrategy,
+    pub output_delay: Duration,
 }
 
 pub struct QueueThread {
     queue: Arc<Queue>,
-    sender: Sender<FrameSet<InputId>>,
+    sender: Sender<FramesBatch>,
+    /// Queue thread and the ticker exit when the other side of this channel is dropped.
+    stop_receiver: Receiver<()>,
     opts: Options,
     sent_batches_counter: u32,
     output_frame_offset: Duration,
+    /// Total time the queue was paused by backpressure. Batches are not force pushed
+    /// for that long, so the queue does not skip the frames that were not produced
+    /// while file inputs were paused.
+    paused_duration: Duration,
+    paused_at: Option<Instant>,
 }
 
 impl QueueThread {
-    pub fn new(queue: Arc<Queue>, sender: Sender<FrameSet<InputId>>, opts: Options) -> Self {
+    pub fn new(
+        queue: Arc<Queue>,
+        sender: Sender<FramesBatch>,
+        stop_receiver: Receiver<()>,
+        opts: Options,
+    ) -> Self {
         let output_frame_offset = opts.clock_start.elapsed();
         Self {
             queue,
             sender,
+            stop_receiver,
             opts,
             output_frame_offset,
             sent_batches_counter: 0,
+            paused_duration: Duration::ZERO,
+            paused_at: None,
         }
     }
 
@@ -41,8 +58,27 @@ impl QueueThread {
         thread::spawn(move || self.run())
     }
 
+    /// With an output delay, frame sets are not produced before it passes, even if all
+    /// inputs already have frames for them.
+    fn is_delayed(&self, pts: Duration) -> bool {
+        !self.opts.output_delay.is_zero()
+            && self
+                .opts
+                .clock_start
+                .add(pts)
+                .add(self.paused_duration)
+                .add(self.opts.output_delay)
+                > Instant::now()
+    }
+
     fn should_push_pts(&self, pts: Duration) -> bool {
-        self.opts.clock_start.add(pts) < Instant::now()
+        self.opts
+            .clock_start
+            .add(pts)
+            .add(self.paused_duration)
+            .add(self.opts.output_delay)
+            .add(self.opts.sync_strategy.max_wait())
+            < Instant::now()
     }
 
     fn run(&mut self) {
@@ -51,23 +87,46 @@ impl QueueThread {
         self.start_ticker();
 
         loop {
-            self.queue.check_queue_channel.1.recv().unwrap();
-            self.on_queue_event()
+            select! {
+                recv(self.queue.check_queue_channel.1) -> _ => self.on_queue_event(),
+                recv(self.stop_receiver) -> _ => return,
+            }
         }
     }
 
     fn on_queue_event(&mut self) {
+        let backpressure = &self.queue.backpressure;
+        backpressure.on_render_queue_length(self.sender.len());
+        match (backpressure.is_paused(), self.paused_at) {
+            (true, None) => {
+                self.paused_at = Some(Instant::now());
+                return;
+            }
+            (true, Some(_)) => return,
+            (false, Some(paused_at)) => {
+                self.paused_duration += paused_at.elapsed();
+                self.paused_at = None;
+            }
+            (false, None) => (),
+        }
+
         let mut internal_queue = self.queue.internal_queue.lock().unwrap();
         let next_buffer_pts = self.get_next_output_buffer_pts();
 
-        let ready_to_push = internal_queue.check_all_inputs_ready(next_buffer_pts)
-            || self.should_push_pts(next_buffer_pts);
+        let wait_for_inputs_without_frames = match self.opts.sync_strategy {
+            QueueSyncStrategy::WaitForAllInputs { .. } => false,
+            QueueSyncStrategy::LatestOnTick => true,
+        };
+        let ready_to_push = !self.is_delayed(next_buffer_pts)
+            && (internal_queue
+                .check_all_inputs_ready(next_buffer_pts, wait_for_inputs_without_frames)
+                || self.should_push_pts(next_buffer_pts));
         if !ready_to_push {
             return;
         }
 
         let frames_batch = internal_queue.get_frames_batch(next_buffer_pts);
-        for input_id in frames_batch.frames.keys() {
+        for input_id in frames_batch.frames.frames.keys() {
             internal_queue.call_input_listeners(input_id)
         }
         self.sender.send(frames_batch).unwrap();
@@ -83,13 +142,16 @@ impl QueueThread {
 
     fn start_ticker(&self) {
         let check_queue_sender = self.queue.check_queue_channel.0.clone();
+        let stop_receiver = self.stop_receiver.clone();
         let tick_duration = self.opts.tick_duration;
         thread::spawn(move || {
             let ticker = tick(tick_duration);
             check_queue_sender.send(()).unwrap();
             loop {
-                ticker.recv().unwrap();
-                check_queue_sender.send(()).unwrap();
+                select! {
+                    recv(ticker) -> _ => check_queue_sender.send(()).unwrap(),
+                    recv(stop_receiver) -> _ => return,
+                }
             }
         });
     }
@@ -23,6 +23,12 @@ pub enum InitRendererEngineError {
 
     #[error("Failed to initialize apply_layout transformation.")]
     LayoutTransformationsInitError(#[source] CreateShaderError),
+
+    #[error("Failed to initialize chroma_key transformation.")]
+    ChromaKeyTransformationInitError(#[source] Box<CreateShaderError>),
+
+    #[error("Failed to initialize watermark transformation.")]
+    WatermarkTransformationInitError(#[source] Box<CreateShaderError>),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -62,6 +68,9 @@ pub enum UpdateSceneError {
     )]
     OutputNotRegistered(OutputId),
 
+    #[error("No output streams are registered. Register an output before defining its scene.")]
+    NoOutputsRegistered,
+
     #[error(transparent)]
     SceneError(#[from] SceneError),
 }
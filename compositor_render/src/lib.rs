@@ -14,9 +14,12 @@ pub use event_loop::EventLoop;
 pub use types::*;
 
 pub use registry::RegistryType;
+pub use state::InputFallback;
 pub use state::Renderer;
+pub use state::RendererInputOptions;
 pub use state::RendererOptions;
 pub use state::RendererSpec;
+pub use wgpu::GpuAdapter;
 
 pub mod image {
     pub use crate::transformations::image_renderer::{ImageSource, ImageSpec, ImageType};
@@ -26,7 +26,7 @@ pub enum RegistryType {
 }
 
 impl RegistryType {
-    fn registry_item_name(&self) -> &'static str {
+    pub(crate) fn registry_item_name(&self) -> &'static str {
         match self {
             RegistryType::Shader => "shader",
             RegistryType::WebRenderer => "web renderer instance",
@@ -52,6 +52,10 @@ impl<T: Clone> RendererRegistry<T> {
         self.registry.get(key).cloned()
     }
 
+    pub(crate) fn ids(&self) -> Vec<RendererId> {
+        self.registry.keys().cloned().collect()
+    }
+
     pub(crate) fn register(&mut self, id: RendererId, renderer: T) -> Result<(), RegisterError> {
         if self.registry.contains_key(&id) {
             return Err(RegisterError::KeyTaken {
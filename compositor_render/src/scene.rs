@@ -6,7 +6,7 @@ use crate::transformations::shader::validation::error::ParametersValidationError
 use crate::transformations::shader::Shader;
 use crate::transformations::text_renderer::TextRenderParams;
 use crate::transformations::web_renderer::WebRenderer;
-use crate::{InputId, OutputId, RendererId, Resolution};
+use crate::{InputId, OutputId, RegistryType, RendererId, Resolution};
 
 use self::image_component::StatefulImageComponent;
 use self::input_stream_component::StatefulInputStreamComponent;
@@ -18,6 +18,7 @@ use self::web_view_component::StatefulWebViewComponent;
 
 pub(crate) use layout::LayoutNode;
 pub(crate) use scene_state::{OutputNode, SceneState};
+pub(crate) use scene_transition::SceneTransitionNode;
 pub(crate) use shader_component::ShaderComponentParams;
 
 pub use components::*;
@@ -29,12 +30,14 @@ mod input_stream_component;
 mod layout;
 mod rescaler_component;
 mod scene_state;
+mod scene_transition;
 mod shader_component;
 mod text_component;
 mod tiles_component;
 mod types;
 mod validation;
 mod view_component;
+mod watermark;
 mod web_view_component;
 
 #[derive(Debug, Clone)]
@@ -42,6 +45,31 @@ pub struct OutputScene {
     pub output_id: OutputId,
     pub root: Component,
     pub resolution: Resolution,
+    /// Drawn over the rendered scene, including scene transitions.
+    pub watermark: Option<Watermark>,
+}
+
+/// Image drawn over the whole scene of an output, e.g. a logo. Its size and position are
+/// relative to the output resolution.
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    pub image_id: RendererId,
+    pub position: WatermarkPosition,
+    /// Width of the image as a fraction of the output width. The height keeps the
+    /// aspect ratio of the image.
+    pub width: f32,
+    /// Distance from the edges of the output as a fraction of the output width.
+    pub margin: f32,
+    pub opacity: f32,
+}
+
+/// Corner of the output the watermark is placed in.
+#[derive(Debug, Clone, Copy)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
 }
 
 #[derive(Debug, Clone)]
@@ -54,6 +82,7 @@ pub enum Component {
     View(ViewComponent),
     Tiles(TilesComponent),
     Rescaler(RescalerComponent),
+    ChromaKey(ChromaKeyComponent),
 }
 
 /// Stateful version of a `Component`. Represents the same element as
@@ -94,6 +123,7 @@ pub(crate) enum NodeParams {
     Image(Image),
     Text(TextRenderParams),
     Layout(LayoutNode),
+    SceneTransition(SceneTransitionNode),
 }
 
 impl StatefulComponent {
@@ -125,6 +155,14 @@ impl StatefulComponent {
         }
     }
 
+    /// Only layout components can be drawn out of the declaration order.
+    fn z_index(&self) -> i32 {
+        match self {
+            StatefulComponent::Layout(layout) => layout.z_index(),
+            _ => 0,
+        }
+    }
+
     fn intermediate_node(&self) -> IntermediateNode {
         match self {
             StatefulComponent::InputStream(input) => input.intermediate_node(),
@@ -179,6 +217,7 @@ impl Component {
             Component::View(view) => view.stateful_component(ctx),
             Component::Tiles(tiles) => tiles.stateful_component(ctx),
             Component::Rescaler(rescaler) => rescaler.stateful_component(ctx),
+            Component::ChromaKey(chroma_key) => chroma_key.stateful_component(ctx),
         }
     }
 }
@@ -191,14 +230,11 @@ pub enum SceneError {
         msg: String,
     },
 
-    #[error("Image \"{0}\" does not exist. You have to register it first before using it in the scene definition.")]
-    ImageNotFound(RendererId),
-
-    #[error("Shader \"{0}\" does not exist. You have to register it first before using it in the scene definition.")]
-    ShaderNotFound(RendererId),
-
-    #[error("Instance of web renderer \"{0}\" does not exist. You have to register it first before using it in the scene definition.")]
-    WebRendererNotFound(RendererId),
+    #[error("The \"{renderer_id}\" {} used in the scene definition does not exist. You have to register it first before using it in the scene definition.", .registry_type.registry_item_name())]
+    RendererNotFound {
+        registry_type: RegistryType,
+        renderer_id: RendererId,
+    },
 
     #[error("Invalid parameter passed to \"{1}\" shader.")]
     ShaderNodeParametersValidationError(#[source] ParametersValidationError, RendererId),
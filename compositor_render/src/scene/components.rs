@@ -2,7 +2,9 @@ use std::{fmt::Display, sync::Arc, time::Duration};
 
 use crate::{InputId, RendererId};
 
-use super::{AbsolutePosition, Component, HorizontalAlign, RGBAColor, Size, VerticalAlign};
+use super::{
+    AbsolutePosition, Component, HorizontalAlign, RGBAColor, RGBColor, Size, VerticalAlign,
+};
 
 mod interpolation;
 
@@ -47,6 +49,26 @@ pub struct ShaderParamStructField {
     pub value: ShaderParam,
 }
 
+/// Makes pixels of the child with a color close to the key color transparent, e.g. to
+/// remove a green screen. Distance between colors is measured on their chroma.
+#[derive(Debug, Clone)]
+pub struct ChromaKeyComponent {
+    pub id: Option<ComponentId>,
+    pub child: Box<Component>,
+
+    pub key_color: RGBColor,
+    /// Pixels closer to the key color than this distance are fully transparent.
+    pub tolerance: f32,
+    /// Width of the range of distances above `tolerance` where pixels are partially
+    /// transparent. Zero keys with hard edges.
+    pub softness: f32,
+    /// Width of the range of distances above `tolerance` where pixels are desaturated,
+    /// to remove the key color reflected on the foreground. Zero disables it.
+    pub spill_suppression: f32,
+
+    pub size: Size,
+}
+
 #[derive(Debug, Clone)]
 pub struct WebViewComponent {
     pub id: Option<ComponentId>,
@@ -136,6 +158,8 @@ pub struct ViewComponent {
     pub position: Position,
     pub transition: Option<Transition>,
     pub overflow: Overflow,
+    /// Children of a view with a higher value are drawn on top of its siblings.
+    pub z_index: i32,
 
     pub background_color: RGBAColor,
 }
@@ -152,6 +176,21 @@ pub struct Transition {
     pub duration: Duration,
 }
 
+/// Crossfade from the previous scene of an output to the new one.
+#[derive(Debug, Clone, Copy)]
+pub struct SceneTransition {
+    pub duration: Duration,
+    pub easing: EasingFunction,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum EasingFunction {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Position {
     Static {
@@ -174,6 +213,8 @@ pub struct RescalerComponent {
 
     pub position: Position,
     pub transition: Option<Transition>,
+    /// Children of a view with a higher value are drawn on top of its siblings.
+    pub z_index: i32,
 
     pub mode: RescaleMode,
     pub horizontal_align: HorizontalAlign,
@@ -1,4 +1,4 @@
-use crate::transformations::image_renderer::Image;
+use crate::{transformations::image_renderer::Image, RegistryType};
 
 use super::{
     scene_state::BuildStateTreeCtx, ComponentId, ImageComponent, IntermediateNode, SceneError,
@@ -30,11 +30,12 @@ impl ImageComponent {
         self,
         ctx: &BuildStateTreeCtx,
     ) -> Result<StatefulComponent, SceneError> {
-        let image = ctx
-            .renderers
-            .images
-            .get(&self.image_id)
-            .ok_or_else(|| SceneError::ImageNotFound(self.image_id.clone()))?;
+        let image = ctx.renderers.images.get(&self.image_id).ok_or_else(|| {
+            SceneError::RendererNotFound {
+                registry_type: RegistryType::Image,
+                renderer_id: self.image_id.clone(),
+            }
+        })?;
         Ok(StatefulComponent::Image(StatefulImageComponent {
             component: self,
             image,
@@ -57,6 +57,16 @@ impl StatefulLayoutComponent {
         }
     }
 
+    /// Tiles are laid out in a grid, so their children never overlap and they don't have
+    /// a `z_index`.
+    pub(super) fn z_index(&self) -> i32 {
+        match self {
+            StatefulLayoutComponent::View(view) => view.z_index(),
+            StatefulLayoutComponent::Tiles(_) => 0,
+            StatefulLayoutComponent::Rescaler(rescaler) => rescaler.z_index(),
+        }
+    }
+
     pub(crate) fn component_id(&self) -> Option<&ComponentId> {
         match self {
             StatefulLayoutComponent::View(view) => view.component_id(),
@@ -192,6 +202,7 @@ impl StatefulLayoutComponent {
                     scale_x: 1.0,
                     scale_y: 1.0,
                     crop,
+                    opacity: 1.0,
 
                     content,
                     child_nodes_count,
@@ -213,6 +224,7 @@ impl StatefulLayoutComponent {
                     scale_x: 1.0,
                     scale_y: 1.0,
                     crop,
+                    opacity: 1.0,
 
                     content,
                     child_nodes_count,
@@ -21,6 +21,7 @@ pub(super) struct StatefulRescalerComponent {
     transition: Option<Transition>,
     child: Box<StatefulComponent>,
     start_pts: Duration,
+    z_index: i32,
 }
 
 #[derive(Debug, Clone)]
@@ -72,6 +73,10 @@ impl StatefulRescalerComponent {
         self.end.id.as_ref()
     }
 
+    pub(super) fn z_index(&self) -> i32 {
+        self.z_index
+    }
+
     pub(super) fn intermediate_node(&self) -> IntermediateNode {
         let children = {
             let node = self.child.intermediate_node();
@@ -135,6 +140,7 @@ impl RescalerComponent {
             transition,
             child: Box::new(Component::stateful_component(*self.child, ctx)?),
             start_pts: ctx.last_render_pts,
+            z_index: self.z_index,
         };
         Ok(StatefulComponent::Layout(
             StatefulLayoutComponent::Rescaler(view),
@@ -113,6 +113,7 @@ impl RescalerComponentParam {
                 width: size.width,
                 height: size.height,
             }),
+            opacity: 1.0,
             content: LayoutContent::None,
             children: vec![NestedLayout {
                 top,
@@ -123,6 +124,7 @@ impl RescalerComponentParam {
                 scale_x: scale,
                 scale_y: scale,
                 crop: None,
+                opacity: 1.0,
                 content,
                 child_nodes_count,
                 children,
@@ -14,8 +14,10 @@ use super::{
     shader_component::StatefulShaderComponent,
     text_component::StatefulTextComponent,
     validation::validate_scene_update,
+    watermark::StatefulWatermark,
     web_view_component::StatefulWebViewComponent,
-    ComponentId, Node, NodeParams, OutputScene, Position, SceneError, Size, StatefulComponent,
+    ComponentId, Node, NodeParams, OutputScene, Position, SceneError, SceneTransition,
+    SceneTransitionNode, Size, StatefulComponent,
 };
 
 pub(super) struct BuildStateTreeCtx<'a> {
@@ -38,6 +40,16 @@ struct OutputSceneState {
     output_id: OutputId,
     root: StatefulComponent,
     resolution: Resolution,
+    transition: Option<SceneTransitionState>,
+    watermark: Option<StatefulWatermark>,
+}
+
+#[derive(Debug, Clone)]
+struct SceneTransitionState {
+    /// State of the output before the update. It can be in the middle of another transition.
+    previous: Box<OutputSceneState>,
+    transition: SceneTransition,
+    start_pts: Duration,
 }
 
 pub(crate) struct OutputNode {
@@ -55,23 +67,42 @@ impl SceneState {
         }
     }
 
+    /// Returns nodes of all outputs if any scene transition has finished, previous scenes
+    /// of those outputs are no longer rendered.
     pub(crate) fn register_render_event(
         &mut self,
         pts: Duration,
         input_resolutions: HashMap<InputId, Resolution>,
-    ) {
+    ) -> Option<Result<Vec<OutputNode>, SceneError>> {
         self.last_pts = pts;
         self.input_resolutions = input_resolutions;
         // TODO: pass input stream sizes and populate it in the ComponentState tree
+
+        let mut any_transition_finished = false;
+        for output in self.outputs.iter_mut() {
+            let is_finished = output
+                .transition
+                .as_ref()
+                .is_some_and(|state| state.transition.is_finished(state.start_pts, pts));
+            if is_finished {
+                output.transition = None;
+                any_transition_finished = true;
+            }
+        }
+        any_transition_finished.then(|| Self::output_nodes(&self.outputs, self.last_pts))
     }
 
+    /// Updates scenes of the listed outputs. Scenes of other outputs are not changed.
+    /// If `transition` is specified, listed outputs crossfade from their previous scenes.
+    /// Returns nodes of all outputs.
     pub(crate) fn update_scene(
         &mut self,
         outputs: Vec<OutputScene>,
+        transition: Option<SceneTransition>,
         renderers: &Renderers,
         text_renderer_ctx: &TextRendererCtx,
     ) -> Result<Vec<OutputNode>, SceneError> {
-        validate_scene_update(&outputs)?;
+        self.validate_outputs(&outputs)?;
 
         for output in self.outputs.iter_mut() {
             recalculate_layout(
@@ -82,6 +113,62 @@ impl SceneState {
             )
         }
 
+        let updated_states =
+            self.build_output_states(outputs, transition, renderers, text_renderer_ctx)?;
+
+        let mut output_states: Vec<OutputSceneState> = self
+            .outputs
+            .iter()
+            .filter(|state| {
+                !updated_states
+                    .iter()
+                    .any(|updated| updated.output_id == state.output_id)
+            })
+            .cloned()
+            .collect();
+        output_states.extend(updated_states);
+
+        let nodes = Self::output_nodes(&output_states, self.last_pts)?;
+        self.outputs = output_states;
+        Ok(nodes)
+    }
+
+    /// Checks if scenes of the listed outputs can be built without applying them.
+    /// Component ids, layouts and references to renderers are validated the same way
+    /// as in [`SceneState::update_scene`].
+    pub(crate) fn validate_scene(
+        &self,
+        outputs: Vec<OutputScene>,
+        renderers: &Renderers,
+        text_renderer_ctx: &TextRendererCtx,
+    ) -> Result<(), SceneError> {
+        self.validate_outputs(&outputs)?;
+
+        let states = self.build_output_states(outputs, None, renderers, text_renderer_ctx)?;
+        Self::output_nodes(&states, self.last_pts)?;
+        Ok(())
+    }
+
+    fn validate_outputs(&self, outputs: &[OutputScene]) -> Result<(), SceneError> {
+        let unchanged_outputs: Vec<&OutputSceneState> = self
+            .outputs
+            .iter()
+            .filter(|state| !outputs.iter().any(|o| o.output_id == state.output_id))
+            .collect();
+        let mut unchanged_components = HashMap::new();
+        for output in unchanged_outputs {
+            gather_components_with_id(&output.root, &mut unchanged_components);
+        }
+        validate_scene_update(outputs, unchanged_components.keys().collect())
+    }
+
+    fn build_output_states(
+        &self,
+        outputs: Vec<OutputScene>,
+        transition: Option<SceneTransition>,
+        renderers: &Renderers,
+        text_renderer_ctx: &TextRendererCtx,
+    ) -> Result<Vec<OutputSceneState>, SceneError> {
         let ctx = BuildStateTreeCtx {
             prev_state: self
                 .outputs
@@ -97,31 +184,88 @@ impl SceneState {
             text_renderer_ctx,
             renderers,
         };
-        let output_states = outputs
+        let transition = transition.filter(|transition| !transition.duration.is_zero());
+        outputs
             .into_iter()
             .map(|o| {
+                let previous = self
+                    .outputs
+                    .iter()
+                    .find(|state| state.output_id == o.output_id);
+                let transition = match (transition, previous) {
+                    (Some(transition), Some(previous)) => Some(SceneTransitionState {
+                        previous: Box::new(previous.clone()),
+                        transition,
+                        start_pts: self.last_pts,
+                    }),
+                    _ => None,
+                };
                 Ok(OutputSceneState {
                     output_id: o.output_id,
                     root: o.root.stateful_component(&ctx)?,
                     resolution: o.resolution,
+                    transition,
+                    watermark: o
+                        .watermark
+                        .map(|watermark| watermark.stateful_watermark(&ctx))
+                        .transpose()?,
                 })
             })
-            .collect::<Result<Vec<_>, _>>()?;
-        let nodes = output_states
+            .collect::<Result<Vec<_>, _>>()
+    }
+
+    /// Nodes of all outputs in the current state.
+    pub(crate) fn nodes(&self) -> Result<Vec<OutputNode>, SceneError> {
+        Self::output_nodes(&self.outputs, self.last_pts)
+    }
+
+    /// Removes the scene of the output. Returns nodes of the remaining outputs.
+    pub(crate) fn remove_output(
+        &mut self,
+        output_id: &OutputId,
+    ) -> Result<Vec<OutputNode>, SceneError> {
+        self.outputs.retain(|output| &output.output_id != output_id);
+        Self::output_nodes(&self.outputs, self.last_pts)
+    }
+
+    fn output_nodes(
+        outputs: &[OutputSceneState],
+        last_pts: Duration,
+    ) -> Result<Vec<OutputNode>, SceneError> {
+        outputs
             .iter()
             .map(|output| {
+                let node = Self::output_node(output, last_pts)?;
+                let node = match output.watermark {
+                    Some(ref watermark) => watermark.node(node, output.resolution),
+                    None => node,
+                };
                 Ok(OutputNode {
                     output_id: output.output_id.clone(),
-                    node: output
-                        .root
-                        .intermediate_node()
-                        .build_tree(Some(output.resolution), self.last_pts)?,
+                    node,
                     resolution: output.resolution,
                 })
             })
-            .collect::<Result<_, _>>()?;
-        self.outputs = output_states;
-        Ok(nodes)
+            .collect()
+    }
+
+    fn output_node(output: &OutputSceneState, last_pts: Duration) -> Result<Node, SceneError> {
+        let node = output
+            .root
+            .intermediate_node()
+            .build_tree(Some(output.resolution), last_pts)?;
+        let Some(ref state) = output.transition else {
+            return Ok(node);
+        };
+        Ok(Node {
+            params: NodeParams::SceneTransition(SceneTransitionNode {
+                transition: state.transition,
+                start_pts: state.start_pts,
+                resolution: output.resolution,
+                previous_resolution: state.previous.resolution,
+            }),
+            children: vec![Self::output_node(&state.previous, last_pts)?, node],
+        })
     }
 }
 
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use crate::{
+    transformations::layout::{self, LayoutContent, NestedLayout},
+    Resolution,
+};
+
+use super::{EasingFunction, SceneTransition};
+
+impl EasingFunction {
+    fn ease(&self, progress: f64) -> f64 {
+        match self {
+            EasingFunction::Linear => progress,
+            EasingFunction::EaseIn => progress.powi(3),
+            EasingFunction::EaseOut => 1.0 - (1.0 - progress).powi(3),
+            EasingFunction::EaseInOut => {
+                if progress < 0.5 {
+                    4.0 * progress.powi(3)
+                } else {
+                    1.0 - (-2.0 * progress + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+impl SceneTransition {
+    pub(super) fn is_finished(&self, start_pts: Duration, pts: Duration) -> bool {
+        start_pts + self.duration <= pts
+    }
+
+    /// Eased progress of the transition in the range 0-1.
+    fn progress(&self, start_pts: Duration, pts: Duration) -> f64 {
+        let elapsed = pts.saturating_sub(start_pts).as_secs_f64();
+        self.easing
+            .ease(f64::min(1.0, elapsed / self.duration.as_secs_f64()))
+    }
+}
+
+/// Blends the previous scene of an output (first child node) with the new one (second
+/// child node). Progress is based on the pts of rendered frames, so the transition lasts
+/// the same number of frames regardless of the rendering speed.
+#[derive(Debug)]
+pub(crate) struct SceneTransitionNode {
+    pub(super) transition: SceneTransition,
+    pub(super) start_pts: Duration,
+    pub(super) resolution: Resolution,
+    pub(super) previous_resolution: Resolution,
+}
+
+impl SceneTransitionNode {
+    fn child_layout(&self, child_resolution: Resolution, opacity: f32) -> NestedLayout {
+        NestedLayout {
+            top: 0.0,
+            left: 0.0,
+            width: self.resolution.width as f32,
+            height: self.resolution.height as f32,
+            rotation_degrees: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            crop: None,
+            opacity,
+            content: LayoutContent::ChildNode {
+                index: 0,
+                size: child_resolution.into(),
+            },
+            child_nodes_count: 1,
+            children: vec![],
+        }
+    }
+}
+
+impl layout::LayoutProvider for SceneTransitionNode {
+    fn layouts(&mut self, pts: Duration, _inputs: &[Option<Resolution>]) -> NestedLayout {
+        let progress = self.transition.progress(self.start_pts, pts) as f32;
+        // New scene is rendered on top of the previous one, so the previous one does not
+        // have to be faded out.
+        let children = vec![
+            self.child_layout(self.previous_resolution, 1.0),
+            self.child_layout(self.resolution, progress),
+        ];
+        NestedLayout {
+            top: 0.0,
+            left: 0.0,
+            width: self.resolution.width as f32,
+            height: self.resolution.height as f32,
+            rotation_degrees: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            crop: None,
+            opacity: 1.0,
+            content: LayoutContent::None,
+            child_nodes_count: 2,
+            children,
+        }
+    }
+
+    fn resolution(&self, _pts: Duration) -> Resolution {
+        self.resolution
+    }
+}
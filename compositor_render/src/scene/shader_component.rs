@@ -1,10 +1,10 @@
 use std::sync::Arc;
 
-use crate::transformations::shader::Shader;
+use crate::{transformations::shader::Shader, RegistryType};
 
 use super::{
-    scene_state::BuildStateTreeCtx, Component, ComponentId, IntermediateNode, SceneError,
-    ShaderComponent, ShaderParam, Size, StatefulComponent,
+    scene_state::BuildStateTreeCtx, ChromaKeyComponent, Component, ComponentId, IntermediateNode,
+    SceneError, ShaderComponent, ShaderParam, Size, StatefulComponent,
 };
 
 #[derive(Debug, Clone)]
@@ -45,11 +45,12 @@ impl ShaderComponent {
         self,
         ctx: &BuildStateTreeCtx,
     ) -> Result<StatefulComponent, SceneError> {
-        let shader = ctx
-            .renderers
-            .shaders
-            .get(&self.shader_id)
-            .ok_or_else(|| SceneError::ShaderNotFound(self.shader_id.clone()))?;
+        let shader = ctx.renderers.shaders.get(&self.shader_id).ok_or_else(|| {
+            SceneError::RendererNotFound {
+                registry_type: RegistryType::Shader,
+                renderer_id: self.shader_id.clone(),
+            }
+        })?;
         if let Some(params) = &self.shader_param {
             shader.validate_params(params).map_err(|err| {
                 SceneError::ShaderNodeParametersValidationError(err, self.shader_id.clone())
@@ -72,3 +73,24 @@ impl ShaderComponent {
         }))
     }
 }
+
+impl ChromaKeyComponent {
+    /// Chroma key is rendered by the built-in chroma key shader, so it's the same as a
+    /// `Shader` component with a single child.
+    pub(super) fn stateful_component(
+        self,
+        ctx: &BuildStateTreeCtx,
+    ) -> Result<StatefulComponent, SceneError> {
+        let shader_param = self.shader_param();
+        let child = Component::stateful_component(*self.child, ctx)?;
+        Ok(StatefulComponent::Shader(StatefulShaderComponent {
+            component: ShaderComponentParams {
+                id: self.id,
+                shader_param: Some(shader_param),
+                size: self.size,
+            },
+            shader: ctx.renderers.chroma_key.clone(),
+            children: vec![child],
+        }))
+    }
+}
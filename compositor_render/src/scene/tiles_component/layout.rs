@@ -29,6 +29,7 @@ pub(super) fn layout_tiles(
         scale_x: 1.0,
         scale_y: 1.0,
         crop: None,
+        opacity: 1.0,
         content: LayoutContent::Color(background_color),
         child_nodes_count: children.iter().map(|l| l.child_nodes_count).sum(),
         children,
@@ -64,6 +65,7 @@ fn layout_child(child: &mut StatefulComponent, tile: Option<Tile>, pts: Duration
                 scale_x: 1.0,
                 scale_y: 1.0,
                 crop: None,
+                opacity: 1.0,
                 content: LayoutContent::None,
                 child_nodes_count: children_layouts.child_nodes_count,
                 children: vec![children_layouts],
@@ -81,6 +83,7 @@ fn layout_child(child: &mut StatefulComponent, tile: Option<Tile>, pts: Duration
                 scale_x: 1.0,
                 scale_y: 1.0,
                 crop: None,
+                opacity: 1.0,
                 content: StatefulLayoutComponent::layout_content(child, 0),
                 child_nodes_count: 1,
                 children: vec![],
@@ -13,6 +13,7 @@ impl Component {
             Component::View(view) => view.id.as_ref(),
             Component::Tiles(tiles) => tiles.id.as_ref(),
             Component::Rescaler(rescaler) => rescaler.id.as_ref(),
+            Component::ChromaKey(chroma_key) => chroma_key.id.as_ref(),
         }
     }
 
@@ -26,18 +27,24 @@ impl Component {
             Component::View(view) => view.children.iter().collect(),
             Component::Tiles(tiles) => tiles.children.iter().collect(),
             Component::Rescaler(rescaler) => vec![rescaler.child.as_ref()],
+            Component::ChromaKey(chroma_key) => vec![chroma_key.child.as_ref()],
         }
     }
 }
 
-pub(super) fn validate_scene_update(outputs: &[OutputScene]) -> Result<(), SceneError> {
-    validate_component_ids_uniqueness(outputs)?;
+/// `unchanged_ids` are ids of components in scenes of outputs that are not updated.
+pub(super) fn validate_scene_update(
+    outputs: &[OutputScene],
+    unchanged_ids: HashSet<&ComponentId>,
+) -> Result<(), SceneError> {
+    validate_component_ids_uniqueness(outputs, unchanged_ids)?;
     Ok(())
 }
 
-fn validate_component_ids_uniqueness(outputs: &[OutputScene]) -> Result<(), SceneError> {
-    let mut ids: HashSet<&ComponentId> = HashSet::new();
-
+fn validate_component_ids_uniqueness<'a>(
+    outputs: &'a [OutputScene],
+    mut ids: HashSet<&'a ComponentId>,
+) -> Result<(), SceneError> {
     fn visit(component: &Component, ids: &mut HashSet<&ComponentId>) -> Result<(), SceneError> {
         let id = component.component_id();
         if let Some(id) = id {
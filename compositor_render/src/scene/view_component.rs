@@ -21,6 +21,7 @@ pub(super) struct StatefulViewComponent {
     transition: Option<Transition>,
     children: Vec<StatefulComponent>,
     start_pts: Duration,
+    z_index: i32,
 }
 
 #[derive(Debug, Clone)]
@@ -72,18 +73,21 @@ impl StatefulViewComponent {
         self.end.id.as_ref()
     }
 
+    pub(super) fn z_index(&self) -> i32 {
+        self.z_index
+    }
+
     pub(super) fn intermediate_node(&self) -> IntermediateNode {
-        let children = self
-            .children
-            .iter()
-            .flat_map(|component| {
-                let node = component.intermediate_node();
-                match node {
-                    IntermediateNode::Layout { root: _, children } => children,
-                    _ => vec![node],
-                }
-            })
-            .collect();
+        let children = self.children.iter().map(|component| {
+            let node = component.intermediate_node();
+            match node {
+                IntermediateNode::Layout { root: _, children } => children,
+                _ => vec![node],
+            }
+        });
+        // Nodes have to be in the same order as layouts of children, because layouts
+        // refer to nodes by their index.
+        let children = self.in_draw_order(children).into_iter().flatten().collect();
 
         IntermediateNode::Layout {
             root: StatefulLayoutComponent::View(self.clone()),
@@ -92,7 +96,24 @@ impl StatefulViewComponent {
     }
 
     pub(super) fn layout(&mut self, size: Size, pts: Duration) -> NestedLayout {
-        self.view(pts).layout(size, &mut self.children, pts)
+        let mut layout = self.view(pts).layout(size, &mut self.children, pts);
+        layout.children = self.in_draw_order(layout.children);
+        layout
+    }
+
+    /// Reorders values corresponding to children, so children with a higher `z_index`
+    /// are drawn later, on top of their siblings. Children with the same `z_index` are
+    /// drawn in the order they are declared, because the sort is stable. Static children
+    /// are still laid out in the declaration order.
+    fn in_draw_order<T>(&self, values: impl IntoIterator<Item = T>) -> Vec<T> {
+        let mut values: Vec<_> = self
+            .children
+            .iter()
+            .map(StatefulComponent::z_index)
+            .zip(values)
+            .collect();
+        values.sort_by_key(|(z_index, _)| *z_index);
+        values.into_iter().map(|(_, value)| value).collect()
     }
 }
 
@@ -142,6 +163,7 @@ impl ViewComponent {
                 .map(|c| Component::stateful_component(c, ctx))
                 .collect::<Result<_, _>>()?,
             start_pts: ctx.last_render_pts,
+            z_index: self.z_index,
         };
         Ok(StatefulComponent::Layout(StatefulLayoutComponent::View(
             view,
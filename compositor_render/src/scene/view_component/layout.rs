@@ -97,6 +97,7 @@ impl ViewComponentParam {
             scale_x: scale,
             scale_y: scale,
             crop,
+            opacity: 1.0,
             content: LayoutContent::Color(self.background_color),
             child_nodes_count: children.iter().map(|l| l.child_nodes_count).sum(),
             children,
@@ -140,6 +141,7 @@ impl ViewComponentParam {
                     scale_x: 1.0,
                     scale_y: 1.0,
                     crop: None,
+                    opacity: 1.0,
                     content: LayoutContent::None,
                     child_nodes_count: children_layouts.child_nodes_count,
                     children: vec![children_layouts],
@@ -154,6 +156,7 @@ impl ViewComponentParam {
                 scale_x: 1.0,
                 scale_y: 1.0,
                 crop: None,
+                opacity: 1.0,
                 content: StatefulLayoutComponent::layout_content(child, 0),
                 child_nodes_count: 1,
                 children: vec![],
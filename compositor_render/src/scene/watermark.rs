@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use crate::{
+    transformations::{image_renderer::Image, shader::Shader},
+    RegistryType, Resolution,
+};
+
+use super::{
+    scene_state::BuildStateTreeCtx, Node, NodeParams, SceneError, ShaderComponentParams, Watermark,
+};
+
+#[derive(Debug, Clone)]
+pub(super) struct StatefulWatermark {
+    watermark: Watermark,
+    image: Image,
+    shader: Arc<Shader>,
+}
+
+impl StatefulWatermark {
+    /// Node that renders the watermark over `scene` with the output resolution.
+    pub(super) fn node(&self, scene: Node, resolution: Resolution) -> Node {
+        Node {
+            params: NodeParams::Shader(
+                ShaderComponentParams {
+                    id: None,
+                    shader_param: Some(self.watermark.shader_param()),
+                    size: resolution.into(),
+                },
+                self.shader.clone(),
+            ),
+            children: vec![
+                scene,
+                Node {
+                    params: NodeParams::Image(self.image.clone()),
+                    children: vec![],
+                },
+            ],
+        }
+    }
+}
+
+impl Watermark {
+    pub(super) fn stateful_watermark(
+        self,
+        ctx: &BuildStateTreeCtx,
+    ) -> Result<StatefulWatermark, SceneError> {
+        let image = ctx.renderers.images.get(&self.image_id).ok_or_else(|| {
+            SceneError::RendererNotFound {
+                registry_type: RegistryType::Image,
+                renderer_id: self.image_id.clone(),
+            }
+        })?;
+        Ok(StatefulWatermark {
+            watermark: self,
+            image,
+            shader: ctx.renderers.watermark.clone(),
+        })
+    }
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::transformations::web_renderer::WebRenderer;
+use crate::{transformations::web_renderer::WebRenderer, RegistryType};
 
 use super::{
     scene_state::BuildStateTreeCtx, Component, ComponentId, IntermediateNode, SceneError, Size,
@@ -46,7 +46,10 @@ impl WebViewComponent {
             .renderers
             .web_renderers
             .get(&self.instance_id)
-            .ok_or_else(|| SceneError::WebRendererNotFound(self.instance_id.clone()))?;
+            .ok_or_else(|| SceneError::RendererNotFound {
+                registry_type: RegistryType::WebRenderer,
+                renderer_id: self.instance_id.clone(),
+            })?;
 
         let children = self
             .children
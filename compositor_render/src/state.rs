@@ -1,9 +1,12 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use log::error;
+
 use crate::error::{RegisterRendererError, UnregisterRendererError};
 use crate::image;
-use crate::scene::OutputScene;
+use crate::scene::{OutputScene, RGBAColor, SceneTransition};
 use crate::transformations::image_renderer::Image;
 use crate::transformations::shader::Shader;
 use crate::transformations::web_renderer::{self, WebRenderer};
@@ -17,16 +20,17 @@ use crate::{
 };
 use crate::{
     scene::{self, SceneState},
-    wgpu::{WgpuCtx, WgpuErrorScope},
+    wgpu::{GpuAdapter, WgpuCtx, WgpuErrorScope},
 };
 use crate::{shader, RegistryType, RendererId};
 
 use self::{
     render_graph::RenderGraph,
-    render_loop::{populate_inputs, read_outputs, run_transforms},
+    render_loop::{is_input_stale, populate_inputs, read_outputs, run_transforms},
     renderers::Renderers,
 };
 
+mod input_fallback;
 pub mod node;
 pub mod render_graph;
 mod render_loop;
@@ -38,6 +42,24 @@ pub struct RendererOptions {
     pub web_renderer: web_renderer::WebRendererInitOptions,
     pub framerate: Framerate,
     pub stream_fallback_timeout: Duration,
+    /// GPU used for rendering. If not specified, a high performance one is preferred.
+    pub gpu_adapter: Option<GpuAdapter>,
+}
+
+/// Options of a single input that override the renderer defaults.
+#[derive(Debug, Clone, Default)]
+pub struct RendererInputOptions {
+    /// Overrides `RendererOptions::stream_fallback_timeout`.
+    pub stream_fallback_timeout: Option<Duration>,
+    /// Content rendered in place of the input while it does not produce frames.
+    pub fallback: Option<InputFallback>,
+}
+
+#[derive(Debug, Clone)]
+pub enum InputFallback {
+    Color(RGBAColor),
+    /// Id of a registered image. The image is scaled to the size of the input.
+    Image(RendererId),
 }
 
 #[derive(Clone)]
@@ -54,6 +76,12 @@ struct InnerRenderer {
     renderers: Renderers,
 
     stream_fallback_timeout: Duration,
+    inputs: HashMap<InputId, RendererInputOptions>,
+    /// Inputs that did not produce a frame within their fallback timeout during
+    /// the last render.
+    inputs_in_fallback: HashSet<InputId>,
+    /// Inputs rendered as if they had no content, without their fallbacks.
+    disabled_inputs: HashSet<InputId>,
 }
 
 pub(crate) struct RenderCtx<'a> {
@@ -61,6 +89,28 @@ pub(crate) struct RenderCtx<'a> {
     pub(crate) text_renderer_ctx: &'a TextRendererCtx,
     pub(crate) renderers: &'a Renderers,
     pub(crate) stream_fallback_timeout: Duration,
+    pub(crate) inputs: &'a HashMap<InputId, RendererInputOptions>,
+    pub(crate) disabled_inputs: &'a HashSet<InputId>,
+}
+
+impl<'a> RenderCtx<'a> {
+    /// Frames older than this are not rendered and the input is replaced by its fallback.
+    pub(crate) fn stream_fallback_timeout(&self, input_id: &InputId) -> Duration {
+        self.inputs
+            .get(input_id)
+            .and_then(|input| input.stream_fallback_timeout)
+            .unwrap_or(self.stream_fallback_timeout)
+    }
+
+    /// Disabled inputs have no fallback, so they are transparent.
+    pub(crate) fn input_fallback(&self, input_id: &InputId) -> Option<&'a InputFallback> {
+        if self.disabled_inputs.contains(input_id) {
+            return None;
+        }
+        self.inputs
+            .get(input_id)
+            .and_then(|input| input.fallback.as_ref())
+    }
 }
 
 pub(crate) struct RegisterCtx {
@@ -125,6 +175,16 @@ impl Renderer {
         }
     }
 
+    /// Ids of renderers of the given type that are currently registered.
+    pub fn registered_renderers(&self, registry_type: RegistryType) -> Vec<RendererId> {
+        let guard = self.0.lock().unwrap();
+        match registry_type {
+            RegistryType::Shader => guard.renderers.shaders.ids(),
+            RegistryType::WebRenderer => guard.renderers.web_renderers.ids(),
+            RegistryType::Image => guard.renderers.images.ids(),
+        }
+    }
+
     pub fn unregister_renderer(
         &self,
         renderer_id: &RendererId,
@@ -143,14 +203,111 @@ impl Renderer {
         self.0.lock().unwrap().render(input)
     }
 
-    pub fn update_scene(&mut self, scene_specs: Vec<OutputScene>) -> Result<(), UpdateSceneError> {
-        self.0.lock().unwrap().update_scene(scene_specs)
+    /// Updates scenes of the listed outputs. Scenes of other outputs are not changed.
+    /// Without a `transition`, the new scenes replace the previous ones immediately.
+    pub fn update_scene(
+        &mut self,
+        scene_specs: Vec<OutputScene>,
+        transition: Option<SceneTransition>,
+    ) -> Result<(), UpdateSceneError> {
+        self.0.lock().unwrap().update_scene(scene_specs, transition)
+    }
+
+    /// Checks if the scenes could be applied with [`Renderer::update_scene`] without
+    /// changing the current scenes.
+    pub fn validate_scene(&self, scene_specs: Vec<OutputScene>) -> Result<(), UpdateSceneError> {
+        self.0.lock().unwrap().validate_scene(scene_specs)
+    }
+
+    /// Sets options of the input. Scenes that already use the input are rebuilt, so
+    /// they use the new fallback.
+    pub fn register_input(
+        &self,
+        input_id: InputId,
+        options: RendererInputOptions,
+    ) -> Result<(), UpdateSceneError> {
+        let mut guard = self.0.lock().unwrap();
+        let has_fallback = options.fallback.is_some();
+        let previous = guard.inputs.insert(input_id.clone(), options);
+        // Fallback nodes are created when the render graph is built.
+        let had_fallback = previous
+            .as_ref()
+            .is_some_and(|previous| previous.fallback.is_some());
+        if !has_fallback && !had_fallback {
+            return Ok(());
+        }
+        let result = guard.rebuild_render_graph();
+        if result.is_err() {
+            match previous {
+                Some(previous) => guard.inputs.insert(input_id, previous),
+                None => guard.inputs.remove(&input_id),
+            };
+        }
+        result
+    }
+
+    pub fn unregister_input(&self, input_id: &InputId) {
+        let mut guard = self.0.lock().unwrap();
+        guard.inputs_in_fallback.remove(input_id);
+        guard.disabled_inputs.remove(input_id);
+        let removed = guard.inputs.remove(input_id);
+        if removed.is_some_and(|input| input.fallback.is_some()) {
+            if let Err(err) = guard.rebuild_render_graph() {
+                error!("Failed to remove fallback of input {input_id}: {err}");
+            }
+        }
+    }
+
+    /// Returns `true` if the input did not produce a frame within its fallback timeout
+    /// during the last render, so its fallback is rendered instead.
+    pub fn is_fallback_active(&self, input_id: &InputId) -> bool {
+        self.0.lock().unwrap().inputs_in_fallback.contains(input_id)
+    }
+
+    /// Disabled input is rendered as if it had no content, i.e. it's transparent and its
+    /// fallback is not rendered. Its frames are still passed to [`Renderer::render`], so
+    /// it's visible again in the next frame after it's enabled.
+    pub fn set_input_enabled(
+        &self,
+        input_id: &InputId,
+        is_enabled: bool,
+    ) -> Result<(), UpdateSceneError> {
+        let mut guard = self.0.lock().unwrap();
+        let changed = match is_enabled {
+            true => guard.disabled_inputs.remove(input_id),
+            false => guard.disabled_inputs.insert(input_id.clone()),
+        };
+        let has_fallback = guard
+            .inputs
+            .get(input_id)
+            .is_some_and(|input| input.fallback.is_some());
+        if !changed || !has_fallback {
+            return Ok(());
+        }
+        // Fallback nodes are only created for enabled inputs.
+        let result = guard.rebuild_render_graph();
+        if result.is_err() {
+            match is_enabled {
+                true => guard.disabled_inputs.insert(input_id.clone()),
+                false => guard.disabled_inputs.remove(input_id),
+            };
+        }
+        result
+    }
+
+    pub fn is_input_enabled(&self, input_id: &InputId) -> bool {
+        !self.0.lock().unwrap().disabled_inputs.contains(input_id)
+    }
+
+    /// Stops rendering the output.
+    pub fn remove_output_scene(&mut self, output_id: &OutputId) -> Result<(), UpdateSceneError> {
+        self.0.lock().unwrap().remove_output_scene(output_id)
     }
 }
 
 impl InnerRenderer {
     pub fn new(opts: RendererOptions) -> Result<Self, InitRendererEngineError> {
-        let wgpu_ctx = Arc::new(WgpuCtx::new()?);
+        let wgpu_ctx = Arc::new(WgpuCtx::new(opts.gpu_adapter.as_ref())?);
 
         Ok(Self {
             wgpu_ctx: wgpu_ctx.clone(),
@@ -159,6 +316,9 @@ impl InnerRenderer {
             render_graph: RenderGraph::empty(),
             renderers: Renderers::new(wgpu_ctx)?,
             stream_fallback_timeout: opts.stream_fallback_timeout,
+            inputs: HashMap::new(),
+            inputs_in_fallback: HashSet::new(),
+            disabled_inputs: HashSet::new(),
             scene: SceneState::new(),
         })
     }
@@ -179,18 +339,43 @@ impl InnerRenderer {
             text_renderer_ctx: &self.text_renderer_ctx,
             renderers: &self.renderers,
             stream_fallback_timeout: self.stream_fallback_timeout,
+            inputs: &self.inputs,
+            disabled_inputs: &self.disabled_inputs,
         };
 
         let scope = WgpuErrorScope::push(&ctx.wgpu_ctx.device);
 
+        inputs
+            .frames
+            .retain(|input_id, _| !ctx.disabled_inputs.contains(input_id));
         let input_resolutions = inputs
             .frames
             .iter()
             .map(|(input_id, frame)| (input_id.clone(), frame.resolution))
             .collect();
-        self.scene
+        let output_nodes = self
+            .scene
             .register_render_event(inputs.pts, input_resolutions);
+        // Previous scenes are removed from the render graph after their transitions finish.
+        match output_nodes {
+            Some(Ok(output_nodes)) => {
+                if let Err(err) = self.render_graph.update(ctx, output_nodes) {
+                    error!("Failed to remove previous scenes after transitions: {err}");
+                }
+            }
+            Some(Err(err)) => error!("Failed to remove previous scenes after transitions: {err}"),
+            None => {}
+        }
 
+        self.inputs_in_fallback = ctx
+            .inputs
+            .keys()
+            .filter(|input_id| {
+                !ctx.disabled_inputs.contains(*input_id)
+                    && is_input_stale(ctx, input_id, inputs.frames.get(input_id), inputs.pts)
+            })
+            .cloned()
+            .collect();
         populate_inputs(ctx, &mut self.render_graph, &mut inputs).unwrap();
         run_transforms(ctx, &mut self.render_graph, inputs.pts).unwrap();
         let frames = read_outputs(ctx, &mut self.render_graph, inputs.pts).unwrap();
@@ -206,16 +391,60 @@ impl InnerRenderer {
     pub fn update_scene(
         &mut self,
         scenes: Vec<scene::OutputScene>,
+        transition: Option<SceneTransition>,
     ) -> Result<(), UpdateSceneError> {
-        let output_nodes =
-            self.scene
-                .update_scene(scenes, &self.renderers, &self.text_renderer_ctx)?;
+        let output_nodes = self.scene.update_scene(
+            scenes,
+            transition,
+            &self.renderers,
+            &self.text_renderer_ctx,
+        )?;
+        self.render_graph.update(
+            &RenderCtx {
+                wgpu_ctx: &self.wgpu_ctx,
+                text_renderer_ctx: &self.text_renderer_ctx,
+                renderers: &self.renderers,
+                stream_fallback_timeout: self.stream_fallback_timeout,
+                inputs: &self.inputs,
+                disabled_inputs: &self.disabled_inputs,
+            },
+            output_nodes,
+        )?;
+        Ok(())
+    }
+
+    fn rebuild_render_graph(&mut self) -> Result<(), UpdateSceneError> {
+        let output_nodes = self.scene.nodes()?;
+        self.render_graph.update(
+            &RenderCtx {
+                wgpu_ctx: &self.wgpu_ctx,
+                text_renderer_ctx: &self.text_renderer_ctx,
+                renderers: &self.renderers,
+                stream_fallback_timeout: self.stream_fallback_timeout,
+                inputs: &self.inputs,
+                disabled_inputs: &self.disabled_inputs,
+            },
+            output_nodes,
+        )?;
+        Ok(())
+    }
+
+    pub fn validate_scene(&self, scenes: Vec<scene::OutputScene>) -> Result<(), UpdateSceneError> {
+        self.scene
+            .validate_scene(scenes, &self.renderers, &self.text_renderer_ctx)?;
+        Ok(())
+    }
+
+    pub fn remove_output_scene(&mut self, output_id: &OutputId) -> Result<(), UpdateSceneError> {
+        let output_nodes = self.scene.remove_output(output_id)?;
         self.render_graph.update(
             &RenderCtx {
                 wgpu_ctx: &self.wgpu_ctx,
                 text_renderer_ctx: &self.text_renderer_ctx,
                 renderers: &self.renderers,
                 stream_fallback_timeout: self.stream_fallback_timeout,
+                inputs: &self.inputs,
+                disabled_inputs: &self.disabled_inputs,
             },
             output_nodes,
         )?;
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use crate::{
+    scene::{RGBAColor, SceneError},
+    transformations::layout::{LayoutContent, LayoutProvider, NestedLayout},
+    RegistryType, Resolution,
+};
+
+use super::{node::RenderNode, InputFallback, RenderCtx};
+
+/// Color fallback is stretched by the parent node to the size of the input, so its
+/// resolution does not matter.
+const COLOR_FALLBACK_RESOLUTION: Resolution = Resolution {
+    width: 16,
+    height: 16,
+};
+
+struct ColorFallback(RGBAColor);
+
+impl LayoutProvider for ColorFallback {
+    fn layouts(&mut self, _pts: Duration, _inputs: &[Option<Resolution>]) -> NestedLayout {
+        NestedLayout {
+            top: 0.0,
+            left: 0.0,
+            width: COLOR_FALLBACK_RESOLUTION.width as f32,
+            height: COLOR_FALLBACK_RESOLUTION.height as f32,
+            rotation_degrees: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            crop: None,
+            opacity: 1.0,
+            content: LayoutContent::Color(self.0),
+            child_nodes_count: 0,
+            children: vec![],
+        }
+    }
+
+    fn resolution(&self, _pts: Duration) -> Resolution {
+        COLOR_FALLBACK_RESOLUTION
+    }
+}
+
+impl RenderNode {
+    /// Node rendered in place of an input node that has no frame to render.
+    pub(super) fn new_input_fallback_node(
+        ctx: &RenderCtx,
+        fallback: &InputFallback,
+    ) -> Result<Self, SceneError> {
+        match fallback {
+            InputFallback::Color(color) => Ok(RenderNode::new_layout_node(
+                ctx,
+                vec![],
+                Box::new(ColorFallback(*color)),
+            )),
+            InputFallback::Image(image_id) => {
+                let image = ctx.renderers.images.get(image_id).ok_or_else(|| {
+                    SceneError::RendererNotFound {
+                        registry_type: RegistryType::Image,
+                        renderer_id: image_id.clone(),
+                    }
+                })?;
+                Ok(RenderNode::new_image_node(image))
+            }
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::scene::{self, ShaderComponentParams};
+use crate::scene::ShaderComponentParams;
 use crate::transformations::image_renderer::Image;
-use crate::transformations::layout::LayoutNode;
+use crate::transformations::layout::{LayoutNode, LayoutProvider};
 use crate::transformations::shader::node::ShaderNode;
 use crate::transformations::shader::Shader;
 use crate::FallbackStrategy;
@@ -163,9 +163,9 @@ impl RenderNode {
     pub(super) fn new_layout_node(
         ctx: &RenderCtx,
         inputs: Vec<NodeId>,
-        provider: scene::LayoutNode,
+        provider: Box<dyn LayoutProvider>,
     ) -> Self {
-        let node = InnerRenderNode::Layout(LayoutNode::new(ctx, Box::new(provider)));
+        let node = InnerRenderNode::Layout(LayoutNode::new(ctx, provider));
         let output = NodeTexture::new();
 
         Self {
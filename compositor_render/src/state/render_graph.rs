@@ -108,7 +108,13 @@ impl RenderGraph {
 
         match node.params {
             scene::NodeParams::InputStream(input_id) => {
-                let node = RenderNode::new_input();
+                let mut node = RenderNode::new_input();
+                if let Some(fallback) = ctx.input_fallback(&input_id) {
+                    let fallback_id = id_provider.next();
+                    let fallback_node = RenderNode::new_input_fallback_node(ctx, fallback)?;
+                    new_nodes.insert(fallback_id, fallback_node);
+                    node.fallback = Some(fallback_id);
+                }
                 new_nodes.insert(node_id, node);
                 inputs.insert(input_id.clone(), (node_id, InputTexture::new()));
             }
@@ -130,7 +136,11 @@ impl RenderGraph {
                 new_nodes.insert(node_id, node);
             }
             scene::NodeParams::Layout(layout) => {
-                let node = RenderNode::new_layout_node(ctx, input_pads, layout);
+                let node = RenderNode::new_layout_node(ctx, input_pads, Box::new(layout));
+                new_nodes.insert(node_id, node);
+            }
+            scene::NodeParams::SceneTransition(transition) => {
+                let node = RenderNode::new_layout_node(ctx, input_pads, Box::new(transition));
                 new_nodes.insert(node_id, node);
             }
         }
@@ -23,14 +23,14 @@ pub(super) fn populate_inputs(
     frame_set: &mut FrameSet<InputId>,
 ) -> Result<(), InternalSceneError> {
     for (input_id, (_node_id, input_textures)) in &mut scene.inputs {
-        let Some(frame) = frame_set.frames.remove(input_id) else {
+        let frame = frame_set.frames.remove(input_id);
+        let Some(frame) =
+            frame.filter(|frame| !is_input_stale(ctx, input_id, Some(frame), frame_set.pts))
+        else {
+            // Empty input node is replaced by its fallback node, if the input has one.
             input_textures.clear();
             continue;
         };
-        if Duration::saturating_sub(frame_set.pts, ctx.stream_fallback_timeout) > frame.pts {
-            input_textures.clear();
-            continue;
-        }
 
         input_textures.upload(ctx.wgpu_ctx, frame);
     }
@@ -55,6 +55,21 @@ pub(super) fn populate_inputs(
     Ok(())
 }
 
+/// Input is stale if it has no frame, or its frame is older than the fallback timeout.
+pub(super) fn is_input_stale(
+    ctx: &RenderCtx,
+    input_id: &InputId,
+    frame: Option<&Frame>,
+    pts: Duration,
+) -> bool {
+    match frame {
+        Some(frame) => {
+            Duration::saturating_sub(pts, ctx.stream_fallback_timeout(input_id)) > frame.pts
+        }
+        None => true,
+    }
+}
+
 pub(super) fn read_outputs(
     ctx: &RenderCtx,
     scene: &mut RenderGraph,
@@ -4,7 +4,8 @@ use crate::{
     error::InitRendererEngineError,
     registry::{RegistryType, RendererRegistry},
     transformations::{
-        image_renderer::Image, layout::LayoutRenderer, shader::Shader, web_renderer::WebRenderer,
+        chroma_key::chroma_key_shader, image_renderer::Image, layout::LayoutRenderer,
+        shader::Shader, watermark::watermark_shader, web_renderer::WebRenderer,
     },
 };
 
@@ -15,6 +16,8 @@ pub(crate) struct Renderers {
     pub(crate) web_renderers: RendererRegistry<Arc<WebRenderer>>,
     pub(crate) images: RendererRegistry<Image>,
     pub(crate) layout: LayoutRenderer,
+    pub(crate) chroma_key: Arc<Shader>,
+    pub(crate) watermark: Arc<Shader>,
 }
 
 impl Renderers {
@@ -25,6 +28,12 @@ impl Renderers {
             images: RendererRegistry::new(RegistryType::Image),
             layout: LayoutRenderer::new(&wgpu_ctx)
                 .map_err(InitRendererEngineError::LayoutTransformationsInitError)?,
+            chroma_key: Arc::new(chroma_key_shader(&wgpu_ctx).map_err(|err| {
+                InitRendererEngineError::ChromaKeyTransformationInitError(Box::new(err))
+            })?),
+            watermark: Arc::new(watermark_shader(&wgpu_ctx).map_err(|err| {
+                InitRendererEngineError::WatermarkTransformationInitError(Box::new(err))
+            })?),
         })
     }
 }
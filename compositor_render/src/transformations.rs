@@ -1,5 +1,7 @@
+pub(crate) mod chroma_key;
 pub mod image_renderer;
 pub mod layout;
 pub mod shader;
 pub mod text_renderer;
+pub(crate) mod watermark;
 pub mod web_renderer;
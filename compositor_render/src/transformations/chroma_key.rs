@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use crate::{
+    scene::{ChromaKeyComponent, ShaderParam, ShaderParamStructField},
+    wgpu::{common_pipeline::CreateShaderError, WgpuCtx},
+    FallbackStrategy, RendererId,
+};
+
+use super::shader::{Shader, ShaderSpec};
+
+/// Built-in shader used by all `ChromaKey` components. It's compiled once when the
+/// renderer starts, so it does not have to be registered.
+pub(crate) fn chroma_key_shader(wgpu_ctx: &Arc<WgpuCtx>) -> Result<Shader, CreateShaderError> {
+    Shader::new(
+        wgpu_ctx,
+        ShaderSpec {
+            shader_id: RendererId("chroma_key".into()),
+            source: include_str!("./chroma_key/chroma_key.wgsl").into(),
+            fallback_strategy: FallbackStrategy::NeverFallback,
+        },
+    )
+}
+
+impl ChromaKeyComponent {
+    /// Params in the layout of the `ChromaKeyParams` struct in the shader.
+    pub(crate) fn shader_param(&self) -> ShaderParam {
+        let field = |field_name: &str, value: f32| ShaderParamStructField {
+            field_name: field_name.to_owned(),
+            value: ShaderParam::F32(value),
+        };
+        let [r, g, b] = [self.key_color.0, self.key_color.1, self.key_color.2]
+            .map(|channel| channel as f32 / 255.0);
+        ShaderParam::Struct(vec![
+            field("key_color_r", r),
+            field("key_color_g", g),
+            field("key_color_b", b),
+            field("tolerance", self.tolerance),
+            field("softness", self.softness),
+            field("spill_suppression", self.spill_suppression),
+        ])
+    }
+}
@@ -0,0 +1,78 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+struct BaseShaderParameters {
+    plane_id: i32,
+    time: f32,
+    output_resolution: vec2<u32>,
+    texture_count: u32,
+}
+
+struct ChromaKeyParams {
+    key_color_r: f32,
+    key_color_g: f32,
+    key_color_b: f32,
+    tolerance: f32,
+    softness: f32,
+    spill_suppression: f32,
+}
+
+@group(0) @binding(0) var textures: binding_array<texture_2d<f32>, 16>;
+@group(1) @binding(0) var<uniform> params: ChromaKeyParams;
+@group(2) @binding(0) var sampler_: sampler;
+
+var<push_constant> base_params: BaseShaderParameters;
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+
+    output.position = vec4(input.position, 1.0);
+    output.tex_coords = input.tex_coords;
+
+    return output;
+}
+
+// BT.709 chroma of a color, without the 0.5 offset.
+fn cb_cr(rgb: vec3<f32>) -> vec2<f32> {
+    return vec2(
+        dot(rgb, vec3(-0.1146, -0.3854, 0.5)),
+        dot(rgb, vec3(0.5, -0.4542, -0.0458)),
+    );
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(textures[0], sampler_, input.tex_coords);
+    if (base_params.texture_count != 1u) {
+        return vec4(0.0, 0.0, 0.0, 0.0);
+    }
+
+    let key_color = vec3(params.key_color_r, params.key_color_g, params.key_color_b);
+    // Only chroma is compared, so shadows and highlights on the background are keyed
+    // the same as the rest of it.
+    let distance_from_key = distance(cb_cr(color.rgb), cb_cr(key_color)) - params.tolerance;
+
+    var alpha = f32(distance_from_key > 0.0);
+    if (params.softness > 0.0) {
+        alpha = pow(clamp(distance_from_key / params.softness, 0.0, 1.0), 1.5);
+    }
+
+    // Key color reflected on the edges of the foreground is replaced with gray
+    // of the same luminance.
+    var rgb = color.rgb;
+    if (params.spill_suppression > 0.0) {
+        let spill = pow(clamp(distance_from_key / params.spill_suppression, 0.0, 1.0), 1.5);
+        let luminance = dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+        rgb = mix(vec3(luminance), rgb, spill);
+    }
+
+    return vec4(rgb, color.a * alpha);
+}
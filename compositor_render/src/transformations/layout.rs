@@ -48,6 +48,7 @@ struct RenderLayout {
     width: f32,
     height: f32,
     rotation_degrees: f32,
+    opacity: f32,
     content: RenderLayoutContent,
 }
 
@@ -77,6 +78,8 @@ pub struct NestedLayout {
     pub scale_y: f32,
     /// Crop is applied before scaling.
     pub crop: Option<Crop>,
+    /// Opacity in the range 0-1, it's multiplied by the opacity of children.
+    pub opacity: f32,
     pub content: LayoutContent,
 
     pub(crate) children: Vec<NestedLayout>,
@@ -133,6 +136,7 @@ impl LayoutNode {
                 LayoutNodeParams {
                     is_texture,
                     background_color,
+                    opacity: layout.opacity,
                     transform_vertices_matrix: layout
                         .vertices_transformation_matrix(&output_resolution),
                     transform_texture_coords_matrix: layout
@@ -176,6 +180,7 @@ impl NestedLayout {
             scale_x: 1.0,
             scale_y: 1.0,
             crop: None,
+            opacity: 1.0,
             content: LayoutContent::None,
             children: vec![],
             child_nodes_count,
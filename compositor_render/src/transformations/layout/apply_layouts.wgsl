@@ -14,6 +14,7 @@ struct Layout {
     texture_coord_transformation: mat4x4<f32>,
     color: vec4<f32>, // used only when is_texture == 0
     is_texture: u32, // 0 -> color, 1 -> texture
+    opacity: f32,
 }
 
 @group(0) @binding(0) var texture: texture_2d<f32>;
@@ -42,10 +43,11 @@ fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
     // sampling can't be conditional, so in case of plane_id == -1
     // sample textures[0], but ignore the result.
     if (current_layout.is_texture == 0u) {
-        return current_layout.color;
+        return vec4(current_layout.color.rgb, current_layout.color.a * current_layout.opacity);
     }
     // clamp transparent, when crop > input texture
     let is_inside: f32 = round(f32(input.tex_coords.x < 1.0 && input.tex_coords.x > 0.0 && input.tex_coords.y > 0.0 && input.tex_coords.y < 1.0));
     
-    return is_inside * textureSample(texture, sampler_, input.tex_coords);
+    let color = is_inside * textureSample(texture, sampler_, input.tex_coords);
+    return vec4(color.rgb, color.a * current_layout.opacity);
 }
@@ -51,6 +51,7 @@ impl NestedLayout {
             return false;
         }
         match &layout.content {
+            _ if layout.opacity <= 0.0 => false,
             RenderLayoutContent::Color(RGBAColor(_, _, _, 0)) => false,
             RenderLayoutContent::Color(_) => true,
             RenderLayoutContent::ChildNode { crop, index } => {
@@ -76,6 +77,7 @@ impl NestedLayout {
                 width: layout.width * self.scale_x,
                 height: layout.height * self.scale_y,
                 rotation_degrees: layout.rotation_degrees + self.rotation_degrees, // TODO: not exactly correct
+                opacity: layout.opacity * self.opacity,
                 content: layout.content,
             },
             Some(crop) => {
@@ -100,6 +102,7 @@ impl NestedLayout {
                             width: cropped_width * self.scale_x,
                             height: cropped_height * self.scale_y,
                             rotation_degrees: layout.rotation_degrees + self.rotation_degrees, // TODO: not exactly correct
+                            opacity: layout.opacity * self.opacity,
                             content: RenderLayoutContent::Color(color),
                         }
                     }
@@ -131,6 +134,7 @@ impl NestedLayout {
                             width: cropped_width * self.scale_x,
                             height: cropped_height * self.scale_y,
                             rotation_degrees: layout.rotation_degrees + self.rotation_degrees, // TODO: not exactly correct
+                            opacity: layout.opacity * self.opacity,
                             content: RenderLayoutContent::ChildNode { index, crop },
                         }
                     }
@@ -146,6 +150,7 @@ impl NestedLayout {
             width: self.width,
             height: self.height,
             rotation_degrees: self.rotation_degrees,
+            opacity: self.opacity,
             content: match self.content {
                 LayoutContent::Color(color) => RenderLayoutContent::Color(color),
                 LayoutContent::ChildNode { index, size } => RenderLayoutContent::ChildNode {
@@ -9,6 +9,7 @@ pub(super) struct LayoutNodeParams {
     pub(super) transform_texture_coords_matrix: Mat4,
     pub(super) is_texture: u32,
     pub(super) background_color: RGBAColor,
+    pub(super) opacity: f32,
 }
 
 pub(super) struct ParamsBuffer {
@@ -83,6 +84,7 @@ impl LayoutNodeParams {
             transform_texture_coords_matrix,
             is_texture,
             background_color,
+            opacity,
         } = self;
         let mut result = [0; 160];
         fn from_u8_color(value: u8) -> [u8; 4] {
@@ -100,7 +102,8 @@ impl LayoutNodeParams {
         result[140..144].copy_from_slice(&from_u8_color(background_color.3));
 
         result[144..148].copy_from_slice(&is_texture.to_ne_bytes());
-        // 12 bytes padding
+        result[148..152].copy_from_slice(&opacity.to_ne_bytes());
+        // 8 bytes padding
 
         result
     }
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use crate::{
+    scene::{ShaderParam, ShaderParamStructField, Watermark, WatermarkPosition},
+    wgpu::{common_pipeline::CreateShaderError, WgpuCtx},
+    FallbackStrategy, RendererId,
+};
+
+use super::shader::{Shader, ShaderSpec};
+
+/// Built-in shader that draws watermarks of outputs over their scenes. Its first
+/// texture is the rendered scene, the second one is the watermark image.
+pub(crate) fn watermark_shader(wgpu_ctx: &Arc<WgpuCtx>) -> Result<Shader, CreateShaderError> {
+    Shader::new(
+        wgpu_ctx,
+        ShaderSpec {
+            shader_id: RendererId("watermark".into()),
+            source: include_str!("./watermark/watermark.wgsl").into(),
+            fallback_strategy: FallbackStrategy::NeverFallback,
+        },
+    )
+}
+
+impl Watermark {
+    /// Params in the layout of the `WatermarkParams` struct in the shader.
+    pub(crate) fn shader_param(&self) -> ShaderParam {
+        let field = |field_name: &str, value: f32| ShaderParamStructField {
+            field_name: field_name.to_owned(),
+            value: ShaderParam::F32(value),
+        };
+        let (corner_x, corner_y) = match self.position {
+            WatermarkPosition::TopLeft => (0.0, 0.0),
+            WatermarkPosition::TopRight => (1.0, 0.0),
+            WatermarkPosition::BottomLeft => (0.0, 1.0),
+            WatermarkPosition::BottomRight => (1.0, 1.0),
+        };
+        ShaderParam::Struct(vec![
+            field("corner_x", corner_x),
+            field("corner_y", corner_y),
+            field("width", self.width),
+            field("margin", self.margin),
+            field("opacity", self.opacity),
+        ])
+    }
+}
@@ -0,0 +1,73 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+struct BaseShaderParameters {
+    plane_id: i32,
+    time: f32,
+    output_resolution: vec2<u32>,
+    texture_count: u32,
+}
+
+struct WatermarkParams {
+    // 0.0 for the left or top edge, 1.0 for the right or bottom edge.
+    corner_x: f32,
+    corner_y: f32,
+    width: f32,
+    margin: f32,
+    opacity: f32,
+}
+
+@group(0) @binding(0) var textures: binding_array<texture_2d<f32>, 16>;
+@group(1) @binding(0) var<uniform> params: WatermarkParams;
+@group(2) @binding(0) var sampler_: sampler;
+
+var<push_constant> base_params: BaseShaderParameters;
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+
+    output.position = vec4(input.position, 1.0);
+    output.tex_coords = input.tex_coords;
+
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let output_size = vec2<f32>(base_params.output_resolution);
+    let image_size = vec2<f32>(textureDimensions(textures[1]));
+
+    // Size and margin are relative to the output width, so the watermark keeps its
+    // proportions when the output resolution changes.
+    let size = vec2(
+        params.width * output_size.x,
+        params.width * output_size.x * image_size.y / max(image_size.x, 1.0),
+    );
+    let margin = params.margin * output_size.x;
+    let corner = vec2(params.corner_x, params.corner_y);
+    let top_left = mix(vec2(margin), output_size - size - vec2(margin), corner);
+
+    let image_coords = (input.tex_coords * output_size - top_left) / size;
+    let scene = textureSample(textures[0], sampler_, input.tex_coords);
+    let image = textureSample(textures[1], sampler_, clamp(image_coords, vec2(0.0), vec2(1.0)));
+    if (base_params.texture_count != 2u) {
+        return scene;
+    }
+
+    let is_inside = all(image_coords >= vec2(0.0)) && all(image_coords <= vec2(1.0));
+    let alpha = image.a * params.opacity * f32(is_inside);
+    let out_alpha = alpha + scene.a * (1.0 - alpha);
+    if (out_alpha <= 0.0) {
+        return vec4(0.0, 0.0, 0.0, 0.0);
+    }
+    let rgb = (image.rgb * alpha + scene.rgb * scene.a * (1.0 - alpha)) / out_alpha;
+    return vec4(rgb, out_alpha);
+}
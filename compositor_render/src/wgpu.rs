@@ -6,6 +6,7 @@ pub(crate) mod format;
 pub(crate) mod texture;
 pub(crate) mod utils;
 
+pub use ctx::GpuAdapter;
 pub(crate) use ctx::WgpuCtx;
 
 #[must_use]
@@ -1,4 +1,4 @@
-use log::error;
+use log::{error, info};
 
 use super::{
     common_pipeline::plane::Plane, format::TextureFormat, texture::Texture, utils::TextureUtils,
@@ -20,20 +20,41 @@ pub struct WgpuCtx {
     pub empty_texture: Texture,
 }
 
+/// GPU used for rendering on hosts with multiple GPUs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GpuAdapter {
+    /// Position on the list of adapters logged when the renderer is created. The same
+    /// GPU can be listed once for every supported backend, e.g. Vulkan and OpenGL.
+    Index(usize),
+    /// First adapter with a name that contains this string, ignoring case.
+    Name(String),
+}
+
 impl WgpuCtx {
-    pub fn new() -> Result<Self, CreateWgpuCtxError> {
+    /// If the `gpu_adapter` is not found, the default one is used.
+    pub fn new(gpu_adapter: Option<&GpuAdapter>) -> Result<Self, CreateWgpuCtxError> {
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
             ..Default::default()
         });
 
-        let adapter =
-            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptionsBase {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: None,
-            }))
-            .ok_or(CreateWgpuCtxError::NoAdapter)?;
+        let adapter = match gpu_adapter.and_then(|gpu_adapter| find_adapter(&instance, gpu_adapter))
+        {
+            Some(adapter) => adapter,
+            None => {
+                pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptionsBase {
+                    power_preference: wgpu::PowerPreference::HighPerformance,
+                    force_fallback_adapter: false,
+                    compatible_surface: None,
+                }))
+                .ok_or(CreateWgpuCtxError::NoAdapter)?
+            }
+        };
+        let adapter_info = adapter.get_info();
+        info!(
+            "Rendering on {} ({:?}, {:?}).",
+            adapter_info.name, adapter_info.device_type, adapter_info.backend
+        );
 
         let (device, queue) = pollster::block_on(adapter.request_device(
             &wgpu::DeviceDescriptor {
@@ -81,6 +102,40 @@ impl WgpuCtx {
     }
 }
 
+fn find_adapter(instance: &wgpu::Instance, gpu_adapter: &GpuAdapter) -> Option<wgpu::Adapter> {
+    let adapters: Vec<wgpu::Adapter> = instance.enumerate_adapters(wgpu::Backends::all()).collect();
+    let available = adapters
+        .iter()
+        .enumerate()
+        .map(|(index, adapter)| {
+            let info = adapter.get_info();
+            format!(
+                "{index}: {} ({:?}, {:?})",
+                info.name, info.device_type, info.backend
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    info!("Available GPU adapters: {available}");
+
+    let index = match gpu_adapter {
+        GpuAdapter::Index(index) => Some(*index).filter(|index| *index < adapters.len()),
+        GpuAdapter::Name(name) => {
+            let name = name.to_lowercase();
+            adapters
+                .iter()
+                .position(|adapter| adapter.get_info().name.to_lowercase().contains(&name))
+        }
+    };
+    match index {
+        Some(index) => adapters.into_iter().nth(index),
+        None => {
+            error!("GPU adapter {gpu_adapter:?} not found, using the default one. Available adapters: {available}");
+            None
+        }
+    }
+}
+
 fn uniform_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
         label: Some("uniform bind group layout"),
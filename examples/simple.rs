@@ -32,7 +32,7 @@ fn main() {
         }
     });
 
-    http::Server::new(config().api_port).run();
+    http::Server::new(config().api_bind_addr, config().api_port, None).run();
 }
 
 fn start_example_client_code() -> Result<()> {
@@ -2,15 +2,13 @@ use anyhow::Result;
 use log::{error, info};
 use serde_json::json;
 use std::{
-    env,
+    env, fs,
     process::{Command, Stdio},
     thread,
     time::Duration,
 };
 use video_compositor::{config::config, http, logger, types::Resolution};
 
-use crate::common::write_example_sdp_file;
-
 #[path = "./common/common.rs"]
 mod common;
 
@@ -30,20 +28,10 @@ fn main() {
         }
     });
 
-    http::Server::new(config().api_port).run();
+    http::Server::new(config().api_bind_addr, config().api_port, None).run();
 }
 
 fn start_example_client_code() -> Result<()> {
-    info!("[example] Start listening on output port.");
-    let output_sdp = write_example_sdp_file("127.0.0.1", 8002)?;
-    Command::new("ffplay")
-        .args(["-protocol_whitelist", "file,rtp,udp", &output_sdp])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()?;
-
-    thread::sleep(Duration::from_secs(2));
-
     info!("[example] Send register output request.");
     common::post(&json!({
         "type": "register",
@@ -60,6 +48,23 @@ fn start_example_client_code() -> Result<()> {
         }
     }))?;
 
+    info!("[example] Start listening on output port.");
+    let response: serde_json::Value = common::post(&json!({
+        "type": "query",
+        "query": "sdp",
+        "output_id": "output_1",
+    }))?
+    .json()?;
+    let output_sdp = "/tmp/example_text_output.sdp";
+    fs::write(output_sdp, response["sdp"].as_str().unwrap_or_default())?;
+    Command::new("ffplay")
+        .args(["-protocol_whitelist", "file,rtp,udp", output_sdp])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    thread::sleep(Duration::from_secs(2));
+
     info!("[example] Update scene");
     common::post(&json!({
         "type": "update_scene",
@@ -1,38 +1,132 @@
-use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, UNIX_EPOCH},
+};
 
-use compositor_pipeline::pipeline::{self};
-use compositor_render::{error::InitRendererEngineError, EventLoop, RegistryType};
-use crossbeam_channel::{bounded, Receiver};
+use compositor_pipeline::{
+    error::ErrorCode,
+    pipeline::{self, decoder::hardware::HardwareDecoder, input::srt::SrtConnectionState},
+    queue::{LastFramePts, QueueSyncStrategy},
+};
+use compositor_render::{error::InitRendererEngineError, EventLoop, GpuAdapter, RegistryType};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    config::{config, Config},
-    error::ApiError,
+    config::{config, Config, LoggerFormat},
+    error::{ApiError, ApiErrorCode},
+    logger::FfmpegLogLevel,
     types::{self, InputId, OutputId, RegisterRequest, RendererId},
 };
 
+use self::idempotency::IdempotencyCache;
+
+mod idempotency;
 mod register_request;
+mod snapshot;
+
+#[cfg(test)]
+mod api_test;
+#[cfg(test)]
+mod register_request_test;
 
 pub type Pipeline = compositor_pipeline::Pipeline;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
 pub enum Request {
-    Register(RegisterRequest),
+    Register {
+        #[serde(flatten)]
+        request: Box<RegisterRequest>,
+        /// Repeating the request with the same key returns the response to the original
+        /// request instead of registering the entity again.
+        idempotency_key: Option<Arc<str>>,
+    },
+    RegisterMany {
+        requests: Vec<RegisterRequest>,
+        idempotency_key: Option<Arc<str>>,
+    },
     Unregister(UnregisterRequest),
+    /// Unregisters all outputs, inputs and renderers. Succeeds if nothing is registered.
+    UnregisterAll,
     UpdateScene(UpdateScene),
+    /// Updates scenes right before the frame with the specified timestamp is rendered.
+    ScheduleSceneUpdate(ScheduleSceneUpdate),
+    /// Checks if the scenes could be applied with `update_scene` without applying them.
+    ValidateScene {
+        outputs: Vec<types::OutputScene>,
+    },
+    UpdateOutputResolution(UpdateOutputResolution),
+    UpdateEncoderSettings(UpdateEncoderSettings),
+    /// Writes the recent video of an output, kept in its clip buffer, to an MP4 file.
+    SaveClip(SaveClip),
+    /// Encodes the next frame of an output as a keyframe.
+    RequestKeyframe {
+        output_id: OutputId,
+    },
+    /// Stops or resumes encoding and sending an output without unregistering it.
+    SetOutputEnabled {
+        output_id: OutputId,
+        enabled: bool,
+    },
+    /// Hides and mutes an input in all outputs without unregistering it.
+    SetInputEnabled {
+        input_id: InputId,
+        enabled: bool,
+    },
     Query(QueryRequest),
     Start,
+    Stop,
+    /// Stops the pipeline the same way as SIGTERM and exits the process.
+    Shutdown,
 }
 
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct UpdateScene {
     pub outputs: Vec<types::OutputScene>,
+    /// Crossfade from the previous scenes of the listed outputs. If not specified,
+    /// scenes are replaced immediately.
+    pub transition: Option<types::SceneTransition>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ScheduleSceneUpdate {
+    pub outputs: Vec<types::OutputScene>,
+    pub transition: Option<types::SceneTransition>,
+    /// Timestamp of the first frame rendered with the new scenes in milliseconds, on the
+    /// same clock as `last_pts_ms` returned by the stats query.
+    pub at_pts_ms: Option<f64>,
+    /// Unix timestamp of the first frame rendered with the new scenes in milliseconds.
+    pub at_wallclock_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct UpdateOutputResolution {
+    pub output_id: OutputId,
+    pub resolution: types::Resolution,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct UpdateEncoderSettings {
+    pub output_id: OutputId,
+    pub encoder_settings: types::EncoderSettings,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SaveClip {
+    pub output_id: OutputId,
+    /// Path to the MP4 file. The file is overwritten if it already exists.
+    pub path: Arc<str>,
+    /// Duration of the clip in seconds. The clip starts with the keyframe before it, and is
+    /// limited to the `clip_buffer_s` of the output.
+    pub duration_s: f64,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "entity_type", rename_all = "snake_case")]
 pub enum UnregisterRequest {
     InputStream { input_id: InputId },
@@ -42,20 +136,75 @@ pub enum UnregisterRequest {
     Image { image_id: RendererId },
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "query", rename_all = "snake_case")]
 pub enum QueryRequest {
-    WaitForNextFrame { input_id: InputId },
+    WaitForNextFrame {
+        input_id: InputId,
+        /// Defaults to `LIVE_COMPOSITOR_QUERY_TIMEOUT_MS`.
+        timeout_ms: Option<u32>,
+    },
     Inputs,
+    Input {
+        input_id: InputId,
+    },
     Outputs,
+    Output {
+        output_id: OutputId,
+    },
+    /// Difference between the normalized timestamps of two inputs, e.g. to tune delays of
+    /// inputs that should be in sync.
+    InputSync {
+        input_a: InputId,
+        input_b: InputId,
+    },
+    Stats,
+    /// Configuration read from environment variables when the process started.
+    Config,
+    Snapshot {
+        output_id: OutputId,
+    },
+    Sdp {
+        output_id: OutputId,
+    },
+    Scene {
+        output_id: OutputId,
+    },
+    Renderers,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged, deny_unknown_fields)]
 pub enum Response {
     Ok {},
-    Inputs { inputs: Vec<InputInfo> },
-    Outputs { outputs: Vec<OutputInfo> },
+    Inputs {
+        inputs: Vec<InputInfo>,
+    },
+    Outputs {
+        outputs: Vec<OutputInfo>,
+    },
+    Input(InputInfo),
+    Output(OutputInfo),
+    Stats(Stats),
+    InputSync(InputSyncInfo),
+    Config(Box<ConfigInfo>),
+    Renderers {
+        shaders: Vec<RendererId>,
+        web_renderers: Vec<RendererId>,
+        images: Vec<RendererId>,
+    },
+    /// Base64-encoded PNG image.
+    Snapshot {
+        png: String,
+    },
+    Sdp {
+        sdp: String,
+    },
+    /// Same shape as an entry of `outputs` in the `update_scene` request.
+    Scene(types::OutputScene),
+    Responses {
+        responses: Vec<Response>,
+    },
     RegisteredPort(u16),
 }
 
@@ -65,63 +214,495 @@ pub enum Port {
     Exact(u16),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct InputInfo {
     pub id: InputId,
-    pub port: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<Arc<Path>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect_count: Option<u32>,
+    /// Packets dropped by the jitter buffer of an RTP input, because they arrived too late
+    /// to be reordered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub late_packets: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connection_state: Option<ConnectionState>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+}
+
+impl From<SrtConnectionState> for ConnectionState {
+    fn from(state: SrtConnectionState) -> Self {
+        match state {
+            SrtConnectionState::Connecting => ConnectionState::Connecting,
+            SrtConnectionState::Connected => ConnectionState::Connected,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OutputInfo {
     pub id: OutputId,
-    pub port: u16,
-    pub ip: Arc<str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<Arc<Path>>,
+    /// RTMP server URL, without the stream key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<Arc<str>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Stats {
+    pub inputs: Vec<InputStats>,
+    pub outputs: Vec<OutputStats>,
+    /// Number of frame sets waiting to be rendered.
+    pub render_queue_length: usize,
+    /// Number of frame sets that were not rendered.
+    pub dropped_frames: u64,
+    /// Timestamp of the most recently rendered frame set in milliseconds.
+    pub last_pts_ms: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InputStats {
+    pub id: InputId,
+    pub decoded_frames: u64,
+    /// Number of frames dropped, because the input buffer was full.
+    pub buffer_dropped_frames: u64,
+    /// Number of packets that failed to decode and frames dropped after decoding.
+    pub decode_errors: u64,
+    /// Number of H264 or H265 chunks received before the first SPS.
+    pub missing_parameter_sets: u64,
+    pub last_decode_error: Option<String>,
+    /// `false` if the input was disabled with `set_input_enabled`.
+    pub is_enabled: bool,
+    /// Input did not produce frames within its fallback timeout, so its fallback is
+    /// rendered instead.
+    pub is_fallback_active: bool,
+    /// Offset added to timestamps of the stream to map them to the timeline of the
+    /// compositor. Missing until the first frame is decoded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pts_offset_ms: Option<f64>,
+}
+
+/// Values are missing until the input receives its first frame.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InputSyncInfo {
+    /// Normalized timestamp of the last frame of `input_a` in milliseconds.
+    pub input_a_pts_ms: Option<f64>,
+    /// Normalized timestamp of the last frame of `input_b` in milliseconds.
+    pub input_b_pts_ms: Option<f64>,
+    /// Difference between timestamps of frames of both inputs received at the same time.
+    /// Positive if `input_a` is ahead of `input_b`.
+    pub pts_difference_ms: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OutputStats {
+    pub id: OutputId,
+    pub encoded_frames: u64,
+    /// `false` if the output was disabled with `set_output_enabled`.
+    pub is_enabled: bool,
+    /// Number of times sending was retried after a transient network error.
+    pub send_retries: u64,
+    /// Number of times sending failed after all retries, so the data was dropped.
+    pub send_failures: u64,
+    /// `true` if the last send failed after all retries.
+    pub is_send_failing: bool,
+    /// Current target bitrate of the encoder. Missing if the encoder is not configured
+    /// with a bitrate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bitrate_kbps: Option<u32>,
+    /// Time between receiving input data and sending the output frame rendered from it.
+    /// Missing if no frame was sent yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<LatencyStats>,
+}
+
+/// Percentiles calculated from the last 1000 frames, in milliseconds.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LatencyStats {
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+/// Resolved configuration, with secrets redacted.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConfigInfo {
+    pub api_port: u16,
+    pub api_bind_addr: String,
+    pub web_socket_port: Option<u16>,
+    /// `"<redacted>"` if the token is set.
+    pub api_token: Option<String>,
+    pub tls_enabled: bool,
+    pub cors_allowed_origins: Vec<String>,
+    /// In the `NUM/DEN` format.
+    pub framerate: String,
+    pub stream_fallback_timeout_ms: f64,
+    /// Not set if inactive inputs are not unregistered.
+    pub inactive_input_timeout_ms: Option<f64>,
+    pub max_render_queue: usize,
+    pub backpressure: Option<BackpressureInfo>,
+    /// `"wait_for_all_inputs"` or `"latest_on_tick"`.
+    pub queue_sync_strategy: String,
+    /// Only set for the `"wait_for_all_inputs"` strategy.
+    pub queue_max_wait_ms: Option<f64>,
+    pub output_delay_ms: f64,
+    pub output_delivery_threads: usize,
+    pub shutdown_timeout_ms: f64,
+    pub query_timeout_ms: f64,
+    /// `"vaapi"` or `"qsv"`, if H264 inputs are decoded in hardware.
+    pub hardware_decoder: Option<String>,
+    pub decoder_max_reorder_frames: usize,
+    pub gpu_index: Option<usize>,
+    pub gpu_name: Option<String>,
+    pub web_renderer_enable: bool,
+    pub web_renderer_gpu_enable: bool,
+    pub logger_level: String,
+    pub logger_format: LoggerFormat,
+    pub ffmpeg_logger_level: FfmpegLogLevel,
+    pub log_file: Option<PathBuf>,
+    /// File from `LIVE_COMPOSITOR_CONFIG_FILE`, if it's set.
+    pub config_file: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackpressureInfo {
+    pub high_watermark: usize,
+    pub low_watermark: usize,
+}
+
+impl From<&Config> for ConfigInfo {
+    fn from(config: &Config) -> Self {
+        Self {
+            api_port: config.api_port,
+            api_bind_addr: config.api_bind_addr.to_string(),
+            web_socket_port: config.web_socket_port,
+            api_token: config.api_token.as_ref().map(|_| "<redacted>".to_string()),
+            tls_enabled: config.tls.is_some(),
+            cors_allowed_origins: config.cors_allowed_origins.clone(),
+            framerate: format!("{}/{}", config.framerate.num, config.framerate.den),
+            stream_fallback_timeout_ms: config.stream_fallback_timeout.as_secs_f64() * 1000.0,
+            inactive_input_timeout_ms: config
+                .inactive_input_timeout
+                .map(|timeout| timeout.as_secs_f64() * 1000.0),
+            max_render_queue: config.max_render_queue,
+            backpressure: config.backpressure.map(|backpressure| BackpressureInfo {
+                high_watermark: backpressure.high_watermark,
+                low_watermark: backpressure.low_watermark,
+            }),
+            queue_sync_strategy: match config.queue_sync_strategy {
+                QueueSyncStrategy::WaitForAllInputs { .. } => "wait_for_all_inputs",
+                QueueSyncStrategy::LatestOnTick => "latest_on_tick",
+            }
+            .to_string(),
+            queue_max_wait_ms: match config.queue_sync_strategy {
+                QueueSyncStrategy::WaitForAllInputs { max_wait } => {
+                    Some(max_wait.as_secs_f64() * 1000.0)
+                }
+                QueueSyncStrategy::LatestOnTick => None,
+            },
+            output_delay_ms: config.output_delay.as_secs_f64() * 1000.0,
+            output_delivery_threads: config.output_delivery_threads,
+            shutdown_timeout_ms: config.shutdown_timeout.as_secs_f64() * 1000.0,
+            query_timeout_ms: config.query_timeout.as_secs_f64() * 1000.0,
+            hardware_decoder: config.hardware_decoder.map(|decoder| {
+                match decoder {
+                    HardwareDecoder::Vaapi => "vaapi",
+                    HardwareDecoder::Qsv => "qsv",
+                }
+                .to_string()
+            }),
+            decoder_max_reorder_frames: config.decoder_max_reorder_frames,
+            gpu_index: match config.gpu_adapter {
+                Some(GpuAdapter::Index(index)) => Some(index),
+                _ => None,
+            },
+            gpu_name: match config.gpu_adapter {
+                Some(GpuAdapter::Name(ref name)) => Some(name.clone()),
+                _ => None,
+            },
+            web_renderer_enable: config.web_renderer.enable,
+            web_renderer_gpu_enable: config.web_renderer.enable_gpu,
+            logger_level: config.logger.level.clone(),
+            logger_format: config.logger.format,
+            ffmpeg_logger_level: config.logger.ffmpeg_logger_level,
+            log_file: config.logger.file.as_ref().map(|file| file.path.clone()),
+            config_file: config.file.as_ref().map(|file| file.path.clone()),
+        }
+    }
 }
 
 pub enum ResponseHandler {
     Response(Response),
     Ok,
-    DeferredResponse(Receiver<Result<Response, ApiError>>),
+    DeferredResponse(DeferredResponse),
+    /// Request was accepted. The caller has to respond and only then call
+    /// [`Api::request_shutdown`], so the response is delivered before the process exits.
+    Shutdown,
+}
+
+/// Response that is sent later, when the awaited event happens, e.g. an input receives
+/// the next frame.
+pub struct DeferredResponse {
+    response: Receiver<Result<Response, ApiError>>,
+    timeout: Duration,
+    /// Called if the response is not received before the timeout, so the event is no
+    /// longer awaited.
+    on_timeout: Box<dyn FnOnce() + Send>,
+}
+
+impl DeferredResponse {
+    /// Blocks until the response is received or the timeout passes.
+    pub fn wait(self) -> Result<Response, ApiError> {
+        match self.response.recv_timeout(self.timeout) {
+            Ok(response) => response,
+            Err(RecvTimeoutError::Timeout) => {
+                (self.on_timeout)();
+                Err(ApiError::new(
+                    ApiErrorCode::QueryTimeout,
+                    "query timed out".to_string(),
+                    tiny_http::StatusCode(408),
+                ))
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(ApiError::new(
+                ErrorCode::InternalServerError,
+                "Internal Server Error".to_string(),
+                tiny_http::StatusCode(500),
+            )),
+        }
+    }
 }
 
 pub struct Api {
     pipeline: Pipeline,
+    shutdown_sender: Sender<()>,
+    idempotency_cache: IdempotencyCache,
 }
 
 impl Api {
-    pub fn new() -> Result<(Api, Arc<dyn EventLoop>), InitRendererEngineError> {
+    /// `shutdown_sender` is notified when the shutdown is requested through the API.
+    pub fn new(
+        shutdown_sender: Sender<()>,
+    ) -> Result<(Api, Arc<dyn EventLoop>), InitRendererEngineError> {
         let Config {
             framerate,
             stream_fallback_timeout,
             web_renderer,
+            max_render_queue,
+            backpressure,
+            queue_sync_strategy,
+            output_delay,
+            output_delivery_threads,
+            hardware_decoder,
+            decoder_max_reorder_frames,
+            gpu_adapter,
+            pad_odd_resolutions,
+            max_inputs,
+            max_outputs,
             ..
         } = config();
         let (pipeline, event_loop) = Pipeline::new(pipeline::Options {
             framerate: *framerate,
             stream_fallback_timeout: *stream_fallback_timeout,
             web_renderer: *web_renderer,
+            max_render_queue: *max_render_queue,
+            backpressure: *backpressure,
+            queue_sync_strategy: *queue_sync_strategy,
+            output_delay: *output_delay,
+            output_delivery_threads: *output_delivery_threads,
+            hardware_decoder: *hardware_decoder,
+            decoder_max_reorder_frames: *decoder_max_reorder_frames,
+            gpu_adapter: gpu_adapter.clone(),
+            pad_odd_resolutions: *pad_odd_resolutions,
+            max_inputs: *max_inputs,
+            max_outputs: *max_outputs,
         })?;
-        Ok((Api { pipeline }, event_loop))
+        Ok((
+            Api {
+                pipeline,
+                shutdown_sender,
+                idempotency_cache: IdempotencyCache::default(),
+            },
+            event_loop,
+        ))
+    }
+
+    /// Set while the pipeline is started. It can be read without locking the API.
+    pub fn is_started_flag(&self) -> Arc<AtomicBool> {
+        self.pipeline.is_started_flag()
+    }
+
+    /// Stops the pipeline and finalizes all outputs.
+    pub fn shutdown(&mut self) {
+        self.pipeline.shutdown();
+    }
+
+    /// Unregisters inputs that didn't produce frames for `timeout`, see
+    /// `LIVE_COMPOSITOR_INACTIVE_INPUT_TIMEOUT_MS`.
+    pub fn unregister_inactive_inputs(&mut self, timeout: Duration) {
+        self.pipeline.unregister_inactive_inputs(timeout);
+    }
+
+    /// Starts the same shutdown as SIGTERM. It's handled on a separate thread, which
+    /// waits for the API to be unlocked.
+    pub fn request_shutdown(&self) {
+        // Fails only if the shutdown was already requested.
+        let _ = self.shutdown_sender.try_send(());
     }
 
     pub fn handle_request(&mut self, request: Request) -> Result<ResponseHandler, ApiError> {
         match request {
-            Request::Register(register_request) => {
-                match register_request::handle_register_request(self, register_request)? {
+            Request::Register {
+                request,
+                idempotency_key,
+            } => self.handle_idempotent_request(idempotency_key, request, |api, request| {
+                match register_request::handle_register_request(api, *request)? {
                     Some(response) => Ok(response),
                     None => Ok(ResponseHandler::Ok),
                 }
-            }
+            }),
+            Request::RegisterMany {
+                requests,
+                idempotency_key,
+            } => self.handle_idempotent_request(idempotency_key, requests, |api, requests| {
+                register_request::handle_register_many_request(api, requests)
+            }),
             Request::Unregister(unregister_request) => {
                 self.handle_unregister_request(unregister_request)?;
                 Ok(ResponseHandler::Ok)
             }
+            Request::UnregisterAll => {
+                self.handle_unregister_all_request()?;
+                Ok(ResponseHandler::Ok)
+            }
             Request::Start => {
                 self.pipeline.start();
                 Ok(ResponseHandler::Ok)
             }
+            Request::Stop => {
+                self.pipeline.stop();
+                Ok(ResponseHandler::Ok)
+            }
+            Request::Shutdown => Ok(ResponseHandler::Shutdown),
             Request::UpdateScene(scene_spec) => {
-                self.pipeline.update_scene(scene_spec.try_into()?)?;
+                let transition = scene_spec
+                    .transition
+                    .clone()
+                    .map(TryInto::try_into)
+                    .transpose()?;
+                self.pipeline
+                    .update_scene(scene_spec.try_into()?, transition)?;
+                Ok(ResponseHandler::Ok)
+            }
+            Request::ScheduleSceneUpdate(ScheduleSceneUpdate {
+                outputs,
+                transition,
+                at_pts_ms,
+                at_wallclock_ms,
+            }) => {
+                let pts = match (at_pts_ms, at_wallclock_ms) {
+                    (Some(at_pts_ms), None) if at_pts_ms.is_finite() && at_pts_ms >= 0.0 => {
+                        Duration::from_secs_f64(at_pts_ms / 1000.0)
+                    }
+                    (None, Some(at_wallclock_ms)) => self
+                        .pipeline
+                        .queue()
+                        .pts_at(UNIX_EPOCH + Duration::from_millis(at_wallclock_ms)),
+                    _ => {
+                        return Err(types::TypeError::new(
+                            "Exactly one of the fields \"at_pts_ms\" and \"at_wallclock_ms\" has to be specified. \"at_pts_ms\" can't be negative.",
+                        )
+                        .into())
+                    }
+                };
+                let transition = transition.map(TryInto::try_into).transpose()?;
+                let outputs = outputs
+                    .into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.pipeline
+                    .schedule_scene_update(outputs, transition, pts)?;
+                Ok(ResponseHandler::Ok)
+            }
+            Request::ValidateScene { outputs } => {
+                let outputs = outputs
+                    .into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.pipeline.validate_scene(outputs)?;
+                Ok(ResponseHandler::Ok)
+            }
+            Request::UpdateOutputResolution(UpdateOutputResolution {
+                output_id,
+                resolution,
+            }) => {
+                let resolution = resolution.into();
+                register_request::check_resolution_limit(&output_id, resolution)?;
+                self.pipeline
+                    .update_output_resolution(&output_id.into(), resolution)?;
+                Ok(ResponseHandler::Ok)
+            }
+            Request::UpdateEncoderSettings(UpdateEncoderSettings {
+                output_id,
+                encoder_settings,
+            }) => {
+                let pipeline_output_id = output_id.clone().into();
+                let resolution = self
+                    .pipeline
+                    .with_outputs(|mut iter| {
+                        iter.find(|(id, _)| **id == pipeline_output_id)
+                            .map(|(_, output)| output.encoder.resolution())
+                    })
+                    .ok_or_else(|| output_not_found(&pipeline_output_id))?;
+                let encoder_opts =
+                    encoder_settings.into_encoder_options(output_id, resolution.into())?;
+                self.pipeline
+                    .update_encoder_settings(&pipeline_output_id, encoder_opts)?;
+                Ok(ResponseHandler::Ok)
+            }
+            Request::SaveClip(SaveClip {
+                output_id,
+                path,
+                duration_s,
+            }) => {
+                if !duration_s.is_finite() || duration_s <= 0.0 {
+                    return Err(types::TypeError::new(
+                        "Field \"duration_s\" has to be a positive number.",
+                    )
+                    .into());
+                }
+                self.pipeline.save_clip(
+                    &output_id.into(),
+                    Path::new(path.as_ref()).into(),
+                    Duration::from_secs_f64(duration_s),
+                )?;
+                Ok(ResponseHandler::Ok)
+            }
+            Request::RequestKeyframe { output_id } => {
+                self.pipeline.request_keyframe(&output_id.into())?;
+                Ok(ResponseHandler::Ok)
+            }
+            Request::SetOutputEnabled { output_id, enabled } => {
+                self.pipeline
+                    .set_output_enabled(&output_id.into(), enabled)?;
+                Ok(ResponseHandler::Ok)
+            }
+            Request::SetInputEnabled { input_id, enabled } => {
+                self.pipeline.set_input_enabled(&input_id.into(), enabled)?;
                 Ok(ResponseHandler::Ok)
             }
             Request::Query(query) => self.handle_query(query),
@@ -130,45 +711,241 @@ impl Api {
 
     fn handle_query(&self, query: QueryRequest) -> Result<ResponseHandler, ApiError> {
         match query {
-            QueryRequest::WaitForNextFrame { input_id } => {
+            QueryRequest::WaitForNextFrame {
+                input_id,
+                timeout_ms,
+            } => {
+                let input_id: compositor_render::InputId = input_id.into();
+                if !self.pipeline.inputs().any(|(id, _)| *id == input_id) {
+                    return Err(input_not_found(&input_id));
+                }
+                let timeout = match timeout_ms {
+                    Some(0) => {
+                        return Err(types::TypeError::new(
+                            "Field \"timeout_ms\" has to be greater than 0.",
+                        )
+                        .into())
+                    }
+                    Some(timeout_ms) => Duration::from_millis(timeout_ms.into()),
+                    None => config().query_timeout,
+                };
+
                 let (sender, receiver) = bounded(1);
-                self.pipeline.queue().subscribe_input_listener(
-                    input_id.into(),
+                let queue = self.pipeline.queue().clone();
+                let listener_id = queue.subscribe_input_listener(
+                    input_id.clone(),
                     Box::new(move || {
-                        sender.send(Ok(Response::Ok {})).unwrap();
+                        // Receiver is dropped if the query already timed out.
+                        let _ = sender.send(Ok(Response::Ok {}));
                     }),
                 );
-                Ok(ResponseHandler::DeferredResponse(receiver))
+                Ok(ResponseHandler::DeferredResponse(DeferredResponse {
+                    response: receiver,
+                    timeout,
+                    on_timeout: Box::new(move || {
+                        queue.unsubscribe_input_listener(&input_id, listener_id)
+                    }),
+                }))
             }
             QueryRequest::Inputs => {
                 let inputs = self
                     .pipeline
                     .inputs()
-                    .map(|(id, node)| match node.input {
-                        pipeline::input::Input::Rtp(ref rtp) => InputInfo {
-                            id: id.clone().into(),
-                            port: rtp.port,
-                        },
-                    })
+                    .map(|(id, input)| input_info(id, input))
                     .collect();
                 Ok(ResponseHandler::Response(Response::Inputs { inputs }))
             }
+            QueryRequest::Input { input_id } => {
+                let input_id = input_id.into();
+                let input = self
+                    .pipeline
+                    .inputs()
+                    .find(|(id, _)| **id == input_id)
+                    .map(|(id, input)| input_info(id, input))
+                    .ok_or_else(|| input_not_found(&input_id))?;
+                Ok(ResponseHandler::Response(Response::Input(input)))
+            }
             QueryRequest::Outputs => {
                 let outputs = self.pipeline.with_outputs(|iter| {
-                    iter.map(|(id, output)| match output.output {
-                        pipeline::output::Output::Rtp(ref rtp) => OutputInfo {
-                            id: id.clone().into(),
-                            port: rtp.port,
-                            ip: rtp.ip.clone(),
-                        },
-                    })
-                    .collect()
+                    iter.map(|(id, output)| output_info(id, output)).collect()
                 });
                 Ok(ResponseHandler::Response(Response::Outputs { outputs }))
             }
+            QueryRequest::Output { output_id } => {
+                let output_id = output_id.into();
+                let output = self
+                    .pipeline
+                    .with_outputs(|mut iter| {
+                        iter.find(|(id, _)| **id == output_id)
+                            .map(|(id, output)| output_info(id, output))
+                    })
+                    .ok_or_else(|| output_not_found(&output_id))?;
+                Ok(ResponseHandler::Response(Response::Output(output)))
+            }
+            QueryRequest::InputSync { input_a, input_b } => {
+                let last_frame_pts = |input_id: InputId| {
+                    let input_id = input_id.into();
+                    if !self.pipeline.inputs().any(|(id, _)| *id == input_id) {
+                        return Err(input_not_found(&input_id));
+                    }
+                    Ok(self.pipeline.queue().last_frame_pts(&input_id))
+                };
+                let input_a = last_frame_pts(input_a)?;
+                let input_b = last_frame_pts(input_b)?;
+                let pts_difference_ms = input_a
+                    .zip(input_b)
+                    .map(|(a, b)| a.clock_lead_ms() - b.clock_lead_ms());
+                let pts_ms = |pts: LastFramePts| pts.pts.as_secs_f64() * 1000.0;
+                Ok(ResponseHandler::Response(Response::InputSync(
+                    InputSyncInfo {
+                        input_a_pts_ms: input_a.map(pts_ms),
+                        input_b_pts_ms: input_b.map(pts_ms),
+                        pts_difference_ms,
+                    },
+                )))
+            }
+            QueryRequest::Stats => Ok(ResponseHandler::Response(Response::Stats(self.stats()))),
+            QueryRequest::Config => Ok(ResponseHandler::Response(Response::Config(Box::new(
+                config().into(),
+            )))),
+            QueryRequest::Snapshot { output_id } => Ok(ResponseHandler::Response(
+                snapshot::handle_snapshot_query(self, output_id)?,
+            )),
+            QueryRequest::Sdp { output_id } => {
+                let output_id = output_id.into();
+                let sdp = self
+                    .pipeline
+                    .with_outputs(|mut iter| {
+                        iter.find(|(id, _)| **id == output_id)
+                            .map(|(_, output)| match output.output {
+                                pipeline::output::Output::Rtp(ref rtp) => Some(rtp.sdp()),
+                                _ => None,
+                            })
+                    })
+                    .ok_or_else(|| output_not_found(&output_id))?
+                    .ok_or_else(|| {
+                        ApiError::new(
+                            ErrorCode::SdpNotAvailable,
+                            format!("Output stream \"{output_id}\" is not an RTP output. SDP is only available for RTP outputs."),
+                            tiny_http::StatusCode(400),
+                        )
+                    })?;
+                Ok(ResponseHandler::Response(Response::Sdp { sdp }))
+            }
+            QueryRequest::Scene { output_id } => {
+                let output_id = output_id.into();
+                let is_registered = self
+                    .pipeline
+                    .with_outputs(|mut iter| iter.any(|(id, _)| *id == output_id));
+                if !is_registered {
+                    return Err(output_not_found(&output_id));
+                }
+                let root = self.pipeline.output_scene(&output_id).ok_or_else(|| {
+                    ApiError::new(
+                        ErrorCode::OutputSceneNotDefined,
+                        format!("Scene of output stream \"{output_id}\" is not defined."),
+                        tiny_http::StatusCode(400),
+                    )
+                })?;
+                Ok(ResponseHandler::Response(Response::Scene(
+                    types::OutputScene {
+                        output_id: output_id.into(),
+                        root: root.into(),
+                    },
+                )))
+            }
+            QueryRequest::Renderers => {
+                let renderers = |registry_type| {
+                    let mut ids = self.pipeline.registered_renderers(registry_type);
+                    ids.sort_by(|a, b| a.0.cmp(&b.0));
+                    ids.into_iter().map(RendererId::from).collect::<Vec<_>>()
+                };
+                Ok(ResponseHandler::Response(Response::Renderers {
+                    shaders: renderers(RegistryType::Shader),
+                    web_renderers: renderers(RegistryType::WebRenderer),
+                    images: renderers(RegistryType::Image),
+                }))
+            }
         }
     }
 
+    /// Current values of the pipeline counters, also exported by the `/metrics` endpoint.
+    pub fn stats(&self) -> Stats {
+        let inputs = self
+            .pipeline
+            .inputs()
+            .map(|(id, input)| InputStats {
+                id: id.clone().into(),
+                decoded_frames: input.stats.decoded_frames(),
+                buffer_dropped_frames: input.stats.buffer_dropped_frames(),
+                decode_errors: input.stats.decode_errors(),
+                missing_parameter_sets: input.stats.missing_parameter_sets(),
+                last_decode_error: input.stats.last_decode_error(),
+                is_enabled: self.pipeline.is_input_enabled(id),
+                is_fallback_active: self.pipeline.is_fallback_active(id),
+                pts_offset_ms: self.pipeline.queue().pts_offset_ms(id),
+            })
+            .collect();
+        let outputs = self.pipeline.with_outputs(|iter| {
+            iter.map(|(id, output)| OutputStats {
+                id: id.clone().into(),
+                encoded_frames: output.stats.encoded_frames(),
+                is_enabled: output.is_enabled(),
+                send_retries: output.stats.send_retries(),
+                send_failures: output.stats.send_failures(),
+                is_send_failing: output.stats.is_send_failing(),
+                bitrate_kbps: output.encoder.options().bitrate_kbps(),
+                latency_ms: output.stats.latency().map(|latency| LatencyStats {
+                    p50: latency.p50.as_secs_f64() * 1000.0,
+                    p95: latency.p95.as_secs_f64() * 1000.0,
+                    p99: latency.p99.as_secs_f64() * 1000.0,
+                }),
+            })
+            .collect()
+        });
+        let render_stats = self.pipeline.render_stats();
+        Stats {
+            inputs,
+            outputs,
+            render_queue_length: render_stats.queue_length(),
+            dropped_frames: render_stats.dropped_frames(),
+            last_pts_ms: render_stats.last_pts().as_secs_f64() * 1000.0,
+        }
+    }
+
+    /// Handles a request that, if it has an idempotency key, is only handled once. The
+    /// response is returned again for the same request with the same key.
+    fn handle_idempotent_request<T: Serialize>(
+        &mut self,
+        idempotency_key: Option<Arc<str>>,
+        request: T,
+        handler: impl FnOnce(&mut Self, T) -> Result<ResponseHandler, ApiError>,
+    ) -> Result<ResponseHandler, ApiError> {
+        let Some(idempotency_key) = idempotency_key else {
+            return handler(self, request);
+        };
+        if idempotency_key.is_empty() {
+            return Err(types::TypeError::new("Field \"idempotency_key\" can't be empty.").into());
+        }
+        let serialized_request =
+            serde_json::to_string(&request).map_err(|err| ApiError::malformed_request(&err))?;
+        if let Some(response) = self
+            .idempotency_cache
+            .get(&idempotency_key, &serialized_request)?
+        {
+            return Ok(ResponseHandler::Response(response));
+        }
+
+        let response = match handler(self, request)? {
+            ResponseHandler::Ok => Response::Ok {},
+            ResponseHandler::Response(response) => response,
+            response => return Ok(response),
+        };
+        self.idempotency_cache
+            .insert(idempotency_key, serialized_request, response.clone());
+        Ok(ResponseHandler::Response(response))
+    }
+
     fn handle_unregister_request(&mut self, request: UnregisterRequest) -> Result<(), ApiError> {
         match request {
             UnregisterRequest::InputStream { input_id } => {
@@ -188,4 +965,166 @@ impl Api {
                 .unregister_renderer(&image_id.into(), RegistryType::Image)?),
         }
     }
+
+    /// Outputs are unregistered first, so their encoders are flushed before the inputs
+    /// rendered in them are removed. Renderers are unregistered last, when they are no
+    /// longer used by scenes or input fallbacks.
+    fn handle_unregister_all_request(&mut self) -> Result<(), ApiError> {
+        let output_ids: Vec<_> = self
+            .pipeline
+            .with_outputs(|iter| iter.map(|(id, _)| id.clone()).collect());
+        for output_id in output_ids {
+            self.pipeline.unregister_output(&output_id)?;
+        }
+
+        let input_ids: Vec<_> = self.pipeline.inputs().map(|(id, _)| id.clone()).collect();
+        for input_id in input_ids {
+            self.pipeline.unregister_input(&input_id)?;
+        }
+
+        for registry_type in [
+            RegistryType::Shader,
+            RegistryType::WebRenderer,
+            RegistryType::Image,
+        ] {
+            for renderer_id in self.pipeline.registered_renderers(registry_type) {
+                self.pipeline
+                    .unregister_renderer(&renderer_id, registry_type)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn input_not_found(input_id: &compositor_render::InputId) -> ApiError {
+    ApiError::new(
+        ErrorCode::InputStreamNotFound,
+        format!("Input stream \"{input_id}\" does not exist."),
+        tiny_http::StatusCode(404),
+    )
+}
+
+fn output_not_found(output_id: &compositor_render::OutputId) -> ApiError {
+    ApiError::new(
+        ErrorCode::OutputStreamNotFound,
+        format!("Output stream \"{output_id}\" does not exist."),
+        tiny_http::StatusCode(404),
+    )
+}
+
+fn input_info(id: &compositor_render::InputId, input: &pipeline::PipelineInput) -> InputInfo {
+    match input.input {
+        pipeline::input::Input::Rtp(ref rtp) => InputInfo {
+            id: id.clone().into(),
+            port: Some(rtp.port),
+            path: None,
+            reconnect_count: Some(rtp.reconnect_count()),
+            late_packets: Some(rtp.late_packet_count()),
+            url: None,
+            connection_state: None,
+        },
+        pipeline::input::Input::UdpTs(ref udp_ts) => InputInfo {
+            id: id.clone().into(),
+            port: Some(udp_ts.port),
+            path: None,
+            reconnect_count: None,
+            late_packets: None,
+            url: None,
+            connection_state: None,
+        },
+        pipeline::input::Input::TcpH264(ref tcp) => InputInfo {
+            id: id.clone().into(),
+            port: Some(tcp.port),
+            path: None,
+            reconnect_count: None,
+            late_packets: None,
+            url: None,
+            connection_state: None,
+        },
+        pipeline::input::Input::File(ref file) => InputInfo {
+            id: id.clone().into(),
+            port: None,
+            path: Some(file.path.clone()),
+            reconnect_count: None,
+            late_packets: None,
+            url: None,
+            connection_state: None,
+        },
+        pipeline::input::Input::Srt(ref srt) => InputInfo {
+            id: id.clone().into(),
+            port: None,
+            path: None,
+            reconnect_count: None,
+            late_packets: None,
+            url: Some(srt.url.clone()),
+            connection_state: Some(srt.connection_state().into()),
+        },
+        pipeline::input::Input::Rtsp(ref rtsp) => InputInfo {
+            id: id.clone().into(),
+            port: None,
+            path: None,
+            reconnect_count: None,
+            late_packets: None,
+            url: Some(rtsp.url.clone()),
+            connection_state: None,
+        },
+        pipeline::input::Input::StaticImage(ref image) => InputInfo {
+            id: id.clone().into(),
+            port: None,
+            path: Some(image.path.clone()),
+            reconnect_count: None,
+            late_packets: None,
+            url: None,
+            connection_state: None,
+        },
+        pipeline::input::Input::TestPattern(_) => InputInfo {
+            id: id.clone().into(),
+            port: None,
+            path: None,
+            reconnect_count: None,
+            late_packets: None,
+            url: None,
+            connection_state: None,
+        },
+    }
+}
+
+fn output_info(id: &compositor_render::OutputId, output: &pipeline::PipelineOutput) -> OutputInfo {
+    match output.output {
+        pipeline::output::Output::Rtp(ref rtp) => OutputInfo {
+            id: id.clone().into(),
+            port: Some(rtp.port),
+            ip: Some(rtp.ip.clone()),
+            path: None,
+            url: None,
+        },
+        pipeline::output::Output::Mp4(ref mp4) => OutputInfo {
+            id: id.clone().into(),
+            port: None,
+            ip: None,
+            path: Some(mp4.path.clone()),
+            url: None,
+        },
+        pipeline::output::Output::Rtmp(ref rtmp) => OutputInfo {
+            id: id.clone().into(),
+            port: None,
+            ip: None,
+            path: None,
+            url: Some(rtmp.url.clone()),
+        },
+        pipeline::output::Output::Hls(ref hls) => OutputInfo {
+            id: id.clone().into(),
+            port: None,
+            ip: None,
+            path: Some(hls.output_dir.clone()),
+            url: None,
+        },
+        pipeline::output::Output::Whip(ref whip) => OutputInfo {
+            id: id.clone().into(),
+            port: None,
+            ip: None,
+            path: None,
+            url: Some(whip.endpoint_url.clone()),
+        },
+    }
 }
@@ -0,0 +1,104 @@
+use std::env;
+
+use crossbeam_channel::unbounded;
+use serde_json::json;
+
+use super::{Api, QueryRequest, Request, ResponseHandler};
+
+fn request(api: &mut Api, request: serde_json::Value) -> ResponseHandler {
+    let request: Request = serde_json::from_value(request).unwrap();
+    match api.handle_request(request) {
+        Ok(response) => response,
+        Err(err) => panic!("Request failed: {}", err.message),
+    }
+}
+
+fn register_mp4_output(api: &mut Api, output_id: &str, width: u32) {
+    let path = env::temp_dir().join(format!("api_test_{output_id}_{width}.mp4"));
+    request(
+        api,
+        json!({
+            "type": "register",
+            "entity_type": "mp4_output_stream",
+            "output_id": output_id,
+            "path": path,
+            "resolution": { "width": width, "height": 360 },
+            "encoder_settings": { "preset": "ultrafast" },
+        }),
+    );
+    request(
+        api,
+        json!({
+            "type": "update_scene",
+            "outputs": [{
+                "output_id": output_id,
+                "root": { "type": "view", "background_color_rgba": "#FF0000FF" },
+            }],
+        }),
+    );
+}
+
+fn register_rtp_output(api: &mut Api, output_id: &str) {
+    request(
+        api,
+        json!({
+            "type": "register",
+            "entity_type": "output_stream",
+            "output_id": output_id,
+            "port": "9100:9200",
+            "ip": "127.0.0.1",
+            "resolution": { "width": 640, "height": 360 },
+            "encoder_settings": { "preset": "ultrafast" },
+        }),
+    );
+    request(
+        api,
+        json!({
+            "type": "update_scene",
+            "outputs": [{
+                "output_id": output_id,
+                "root": { "type": "view", "background_color_rgba": "#00FF00FF" },
+            }],
+        }),
+    );
+}
+
+#[test]
+fn test_stop_removes_file_outputs_and_scenes() {
+    let (shutdown_sender, _shutdown_receiver) = unbounded();
+    let (mut api, _event_loop) = Api::new(shutdown_sender).unwrap();
+    let output_id = compositor_render::OutputId("output_1".into());
+    let rtp_output_id = compositor_render::OutputId("output_2".into());
+
+    register_mp4_output(&mut api, "output_1", 640);
+    register_rtp_output(&mut api, "output_2");
+    request(&mut api, json!({ "type": "start" }));
+    request(&mut api, json!({ "type": "stop" }));
+
+    assert!(api.pipeline.output_scene(&output_id).is_none());
+    assert!(api
+        .handle_query(QueryRequest::Scene {
+            output_id: output_id.clone().into(),
+        })
+        .is_err());
+
+    // Network outputs are kept, so they are rendered again after a restart.
+    assert!(api.pipeline.output_scene(&rtp_output_id).is_some());
+    let output_ids = api
+        .pipeline
+        .with_outputs(|iter| iter.map(|(id, _)| id.clone()).collect::<Vec<_>>());
+    assert_eq!(output_ids, vec![rtp_output_id.clone()]);
+
+    // Output registered under the same id after a restart is rendered with its new scene
+    // and resolution.
+    request(&mut api, json!({ "type": "start" }));
+    register_mp4_output(&mut api, "output_1", 1280);
+    assert!(api.pipeline.output_scene(&output_id).is_some());
+    let resolution = api.pipeline.with_outputs(|mut iter| {
+        iter.find(|(id, _)| **id == output_id)
+            .map(|(_, output)| output.encoder.resolution())
+    });
+    assert_eq!(resolution.map(|resolution| resolution.width), Some(1280));
+
+    request(&mut api, json!({ "type": "stop" }));
+}
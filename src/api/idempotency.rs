@@ -0,0 +1,62 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::error::{ApiError, ApiErrorCode};
+
+use super::Response;
+
+/// How long the response to a request with an idempotency key is remembered.
+const IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Responses to successful register requests sent with an `idempotency_key`. A client
+/// that retries a request after a timeout receives the response to the original request
+/// instead of an error that the entity is already registered.
+///
+/// Failed requests are not remembered, so they can be retried with the same key.
+#[derive(Default)]
+pub(super) struct IdempotencyCache {
+    responses: HashMap<Arc<str>, CachedResponse>,
+}
+
+struct CachedResponse {
+    /// Serialized request, so a key reused for a different request is rejected instead
+    /// of returning a response that does not match it.
+    request: String,
+    response: Response,
+    created_at: Instant,
+}
+
+impl IdempotencyCache {
+    /// Returns the response to an earlier request with the same key.
+    pub fn get(&mut self, key: &str, request: &str) -> Result<Option<Response>, ApiError> {
+        self.remove_expired();
+        match self.responses.get(key) {
+            Some(cached) if cached.request == request => Ok(Some(cached.response.clone())),
+            Some(_) => Err(ApiError::new(
+                ApiErrorCode::IdempotencyKeyReused,
+                format!("Idempotency key \"{key}\" was already used for a different request."),
+                tiny_http::StatusCode(422),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    pub fn insert(&mut self, key: Arc<str>, request: String, response: Response) {
+        self.responses.insert(
+            key,
+            CachedResponse {
+                request,
+                response,
+                created_at: Instant::now(),
+            },
+        );
+    }
+
+    fn remove_expired(&mut self) {
+        self.responses
+            .retain(|_, cached| cached.created_at.elapsed() < IDEMPOTENCY_KEY_TTL);
+    }
+}
@@ -1,19 +1,335 @@
 use compositor_pipeline::{
-    error::{InputInitError, RegisterInputError},
+    error::{ErrorCode, InputInitError, RegisterInputError, RegisterOutputError},
     pipeline::{
         self,
-        input::rtp::{RtpReceiverError, RtpReceiverOptions},
+        input::{
+            buffer::InputBufferSize,
+            file::FileInputOptions,
+            rtp::{RtpReceiverError, RtpReceiverOptions},
+            static_image::StaticImageOptions,
+            tcp::{TcpInputError, TcpInputOptions},
+            transform::{CropOptions, FrameTransform, ScaleOptions},
+            udp_ts::{UdpTsInputError, UdpTsInputOptions},
+        },
+        RegisterOutputOptions,
     },
+    queue::PtsNormalization,
+};
+use log::{error, trace};
+use std::{
+    collections::HashSet,
+    net::{IpAddr, Ipv4Addr},
+    path::Path,
+    sync::Arc,
+    time::Duration,
 };
-use log::trace;
 
 use crate::{
     api::Response,
-    error::{ApiError, PORT_ALREADY_IN_USE_ERROR_CODE},
-    types::{RegisterInputRequest, RegisterOutputRequest, RegisterRequest},
+    config::config,
+    error::ApiError,
+    types::{
+        self, ImageSpec, InputCrop, InputId, InputScaleMode, OutputId, OutputWatermark,
+        RegisterFileInputRequest, RegisterHlsOutputRequest, RegisterInputRequest,
+        RegisterMp4OutputRequest, RegisterOutputRequest, RegisterRequest,
+        RegisterRtmpOutputRequest, RegisterRtspInputRequest, RegisterSrtInputRequest,
+        RegisterStaticImageInputRequest, RegisterTcpH264InputRequest,
+        RegisterTestPatternInputRequest, RegisterUdpTsInputRequest, RegisterWhipOutputRequest,
+        Resolution, TypeError, VideoCodec,
+    },
 };
 
-use super::{Api, Port, ResponseHandler};
+use super::{Api, Port, ResponseHandler, UnregisterRequest};
+
+/// Registers all entities or none of them. Requests are validated before anything is
+/// registered and, if registration of any entity fails, already registered ones are
+/// unregistered.
+///
+/// Entities are registered in order, so inputs registered with a port range skip ports
+/// taken by inputs registered earlier in the same batch.
+pub fn handle_register_many_request(
+    api: &mut Api,
+    requests: Vec<RegisterRequest>,
+) -> Result<ResponseHandler, ApiError> {
+    validate_register_many(&requests)?;
+
+    let mut responses = Vec::with_capacity(requests.len());
+    let mut registered = Vec::with_capacity(requests.len());
+    for request in requests {
+        let unregister_request = unregister_request(&request);
+        match handle_register_request(api, request) {
+            Ok(response) => {
+                responses.push(match response {
+                    Some(ResponseHandler::Response(response)) => response,
+                    _ => Response::Ok {},
+                });
+                registered.push(unregister_request);
+            }
+            Err(err) => {
+                for unregister_request in registered.into_iter().rev() {
+                    if let Err(err) = api.handle_unregister_request(unregister_request) {
+                        error!(
+                            "Failed to unregister an entity after failed batch registration: {}",
+                            err
+                        );
+                    }
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(ResponseHandler::Response(Response::Responses { responses }))
+}
+
+fn validate_register_many(requests: &[RegisterRequest]) -> Result<(), ApiError> {
+    let mut input_addresses = Vec::new();
+    let mut output_addresses = HashSet::new();
+    let mut rtmp_destinations = HashSet::new();
+    let mut hls_directories = HashSet::new();
+
+    for request in requests {
+        match request {
+            RegisterRequest::InputStream(input) => validate_port_input(
+                &mut input_addresses,
+                &input.input_id,
+                input.bind_addr()?,
+                &input.port,
+                input.decoder,
+            )?,
+            RegisterRequest::UdpTsInputStream(input) => validate_port_input(
+                &mut input_addresses,
+                &input.input_id,
+                None,
+                &input.port,
+                input.decoder,
+            )?,
+            RegisterRequest::TcpH264InputStream(input) => validate_port_input(
+                &mut input_addresses,
+                &input.input_id,
+                None,
+                &input.port,
+                None,
+            )?,
+            RegisterRequest::OutputStream(output) => {
+                let port = match output.port.clone().try_into()? {
+                    Port::Exact(port) => Some((port, &output.ip)),
+                    Port::Range(_) => None,
+                };
+                for (port, ip) in port.into_iter().chain(output.additional_destinations()) {
+                    if !output_addresses.insert((port, ip.clone())) {
+                        return Err(ApiError::new(
+                            ErrorCode::PortAndIpAlreadyInUse,
+                            format!("Failed to register output stream \"{}\". Combination of port {} and IP {} is used by another output in the same request.", output.output_id, port, ip),
+                            tiny_http::StatusCode(400),
+                        ));
+                    }
+                }
+                let encoder_options: pipeline::encoder::EncoderOptions =
+                    output.clone().try_into()?;
+                if let Some(ref adaptive_bitrate) = output.adaptive_bitrate {
+                    adaptive_bitrate.clone().into_options(&encoder_options)?;
+                }
+                validate_resolution(&output.output_id, encoder_options)?;
+                validate_watermark(&output.watermark)?;
+                // Selected port does not affect validation.
+                output.output_options(0)?;
+                output.framerate()?;
+            }
+            RegisterRequest::Mp4OutputStream(output) => {
+                validate_resolution(&output.output_id, output.clone().try_into()?)?;
+                validate_watermark(&output.watermark)?;
+            }
+            RegisterRequest::RtmpOutputStream(output) => {
+                if !rtmp_destinations.insert((output.url.clone(), output.stream_key.clone())) {
+                    return Err(ApiError::new(
+                        ErrorCode::RtmpUrlAlreadyInUse,
+                        format!("Failed to register output stream \"{}\". RTMP URL {} with the same stream key is used by another output in the same request.", output.output_id, output.url),
+                        tiny_http::StatusCode(400),
+                    ));
+                }
+                validate_resolution(&output.output_id, output.clone().try_into()?)?;
+                validate_watermark(&output.watermark)?;
+                let _: pipeline::output::OutputOptions = output.clone().try_into()?;
+            }
+            RegisterRequest::HlsOutputStream(output) => {
+                if !hls_directories.insert(output.output_dir.clone()) {
+                    return Err(ApiError::new(
+                        ErrorCode::HlsDirectoryAlreadyInUse,
+                        format!("Failed to register output stream \"{}\". Directory {} is used by another HLS output in the same request.", output.output_id, output.output_dir),
+                        tiny_http::StatusCode(400),
+                    ));
+                }
+                validate_resolution(
+                    &output.output_id,
+                    output.encoder_options(config().framerate)?,
+                )?;
+                validate_watermark(&output.watermark)?;
+                output.output_options()?;
+            }
+            RegisterRequest::WhipOutputStream(output) => {
+                let encoder_options: pipeline::encoder::EncoderOptions =
+                    output.clone().try_into()?;
+                if let Some(ref adaptive_bitrate) = output.adaptive_bitrate {
+                    adaptive_bitrate.clone().into_options(&encoder_options)?;
+                }
+                validate_resolution(&output.output_id, encoder_options)?;
+                validate_watermark(&output.watermark)?;
+                let _: pipeline::output::OutputOptions = output.clone().try_into()?;
+            }
+            RegisterRequest::Shader(spec) => {
+                let _: compositor_render::RendererSpec = spec.clone().try_into()?;
+            }
+            RegisterRequest::WebRenderer(spec) => {
+                let _: compositor_render::RendererSpec = spec.clone().try_into()?;
+            }
+            RegisterRequest::Image(spec) => {
+                let _: compositor_render::RendererSpec = spec.clone().try_into()?;
+            }
+            RegisterRequest::FileInputStream(input) => {
+                let _: pipeline::decoder::DecoderOptions =
+                    input.decoder.unwrap_or(VideoCodec::H264).try_into()?;
+            }
+            RegisterRequest::SrtInputStream(input) => {
+                let decoder_opts = input.decoder.unwrap_or(VideoCodec::H264).try_into()?;
+                input.input_options(&decoder_opts)?;
+            }
+            RegisterRequest::RtspInputStream(input) => {
+                let decoder_opts = input.decoder.unwrap_or(VideoCodec::H264).try_into()?;
+                input.input_options(&decoder_opts)?;
+            }
+            RegisterRequest::StaticImageInputStream(_) => (),
+            RegisterRequest::TestPatternInputStream(input) => {
+                input.input_options(config().framerate)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_port_input(
+    input_addresses: &mut Vec<(Option<Ipv4Addr>, u16)>,
+    input_id: &InputId,
+    bind_addr: Option<Ipv4Addr>,
+    port: &types::Port,
+    decoder: Option<VideoCodec>,
+) -> Result<(), ApiError> {
+    let port: Port = port.clone().try_into()?;
+    if let Port::Exact(port) = port {
+        if input_addresses.iter().any(|(other_bind_addr, other_port)| {
+            *other_port == port && bind_addrs_overlap(*other_bind_addr, bind_addr)
+        }) {
+            return Err(ApiError::new(
+                ErrorCode::PortAlreadyInUse,
+                format!("Failed to register input stream \"{input_id}\". Port {port} is used by another input in the same request."),
+                tiny_http::StatusCode(400),
+            ));
+        }
+        input_addresses.push((bind_addr, port));
+    }
+    let _: pipeline::decoder::DecoderOptions = decoder.unwrap_or(VideoCodec::H264).try_into()?;
+    Ok(())
+}
+
+fn validate_resolution(
+    output_id: &OutputId,
+    encoder_opts: pipeline::encoder::EncoderOptions,
+) -> Result<(), ApiError> {
+    if !pipeline::is_resolution_supported(encoder_opts.resolution())
+        && !config().pad_odd_resolutions
+    {
+        return Err(RegisterOutputError::UnsupportedResolution(output_id.clone().into()).into());
+    }
+    check_resolution_limit(output_id, encoder_opts.resolution())
+}
+
+fn validate_watermark(watermark: &Option<OutputWatermark>) -> Result<(), ApiError> {
+    if let Some(watermark) = watermark {
+        let _: compositor_render::scene::Watermark = watermark.clone().try_into()?;
+    }
+    Ok(())
+}
+
+/// Resolution is limited, so a typo in a request can't exhaust memory of the renderer
+/// and the encoder.
+pub(super) fn check_resolution_limit(
+    output_id: &OutputId,
+    resolution: compositor_render::Resolution,
+) -> Result<(), ApiError> {
+    let limit = config().max_output_resolution;
+    let compositor_render::Resolution { width, height } = resolution;
+    if width > limit.width || height > limit.height || width * height > limit.pixels {
+        return Err(ApiError::new(
+            ErrorCode::ResolutionLimitExceeded,
+            format!(
+                "Resolution {width}x{height} of output stream \"{output_id}\" exceeds the limit of {}x{} and {} pixels.",
+                limit.width, limit.height, limit.pixels
+            ),
+            tiny_http::StatusCode(400),
+        ));
+    }
+    Ok(())
+}
+
+/// Request that reverts registration of the entity.
+fn unregister_request(request: &RegisterRequest) -> UnregisterRequest {
+    match request {
+        RegisterRequest::InputStream(input) => UnregisterRequest::InputStream {
+            input_id: input.input_id.clone(),
+        },
+        RegisterRequest::UdpTsInputStream(input) => UnregisterRequest::InputStream {
+            input_id: input.input_id.clone(),
+        },
+        RegisterRequest::TcpH264InputStream(input) => UnregisterRequest::InputStream {
+            input_id: input.input_id.clone(),
+        },
+        RegisterRequest::FileInputStream(input) => UnregisterRequest::InputStream {
+            input_id: input.input_id.clone(),
+        },
+        RegisterRequest::SrtInputStream(input) => UnregisterRequest::InputStream {
+            input_id: input.input_id.clone(),
+        },
+        RegisterRequest::RtspInputStream(input) => UnregisterRequest::InputStream {
+            input_id: input.input_id.clone(),
+        },
+        RegisterRequest::StaticImageInputStream(input) => UnregisterRequest::InputStream {
+            input_id: input.input_id.clone(),
+        },
+        RegisterRequest::TestPatternInputStream(input) => UnregisterRequest::InputStream {
+            input_id: input.input_id.clone(),
+        },
+        RegisterRequest::OutputStream(output) => UnregisterRequest::OutputStream {
+            output_id: output.output_id.clone(),
+        },
+        RegisterRequest::Mp4OutputStream(output) => UnregisterRequest::OutputStream {
+            output_id: output.output_id.clone(),
+        },
+        RegisterRequest::RtmpOutputStream(output) => UnregisterRequest::OutputStream {
+            output_id: output.output_id.clone(),
+        },
+        RegisterRequest::HlsOutputStream(output) => UnregisterRequest::OutputStream {
+            output_id: output.output_id.clone(),
+        },
+        RegisterRequest::WhipOutputStream(output) => UnregisterRequest::OutputStream {
+            output_id: output.output_id.clone(),
+        },
+        RegisterRequest::Shader(spec) => UnregisterRequest::Shader {
+            shader_id: spec.shader_id.clone(),
+        },
+        RegisterRequest::WebRenderer(spec) => UnregisterRequest::WebRenderer {
+            instance_id: spec.instance_id.clone(),
+        },
+        RegisterRequest::Image(
+            ImageSpec::Png { image_id, .. }
+            | ImageSpec::Jpeg { image_id, .. }
+            | ImageSpec::Svg { image_id, .. }
+            | ImageSpec::Gif { image_id, .. },
+        ) => UnregisterRequest::Image {
+            image_id: image_id.clone(),
+        },
+    }
+}
 
 pub fn handle_register_request(
     api: &mut Api,
@@ -21,8 +337,41 @@ pub fn handle_register_request(
 ) -> Result<Option<ResponseHandler>, ApiError> {
     match request {
         RegisterRequest::InputStream(input_stream) => register_input(api, input_stream).map(Some),
+        RegisterRequest::UdpTsInputStream(input_stream) => {
+            register_udp_ts_input(api, input_stream).map(Some)
+        }
+        RegisterRequest::TcpH264InputStream(input_stream) => {
+            register_tcp_h264_input(api, input_stream).map(Some)
+        }
+        RegisterRequest::FileInputStream(input_stream) => {
+            register_file_input(api, input_stream).map(|_| None)
+        }
+        RegisterRequest::SrtInputStream(input_stream) => {
+            register_srt_input(api, input_stream).map(|_| None)
+        }
+        RegisterRequest::RtspInputStream(input_stream) => {
+            register_rtsp_input(api, input_stream).map(|_| None)
+        }
+        RegisterRequest::StaticImageInputStream(input_stream) => {
+            register_static_image_input(api, input_stream).map(|_| None)
+        }
+        RegisterRequest::TestPatternInputStream(input_stream) => {
+            register_test_pattern_input(api, input_stream).map(|_| None)
+        }
         RegisterRequest::OutputStream(output_stream) => {
-            register_output(api, output_stream).map(|_| None)
+            register_output(api, output_stream).map(Some)
+        }
+        RegisterRequest::Mp4OutputStream(output_stream) => {
+            register_mp4_output(api, output_stream).map(|_| None)
+        }
+        RegisterRequest::RtmpOutputStream(output_stream) => {
+            register_rtmp_output(api, output_stream).map(|_| None)
+        }
+        RegisterRequest::HlsOutputStream(output_stream) => {
+            register_hls_output(api, output_stream).map(|_| None)
+        }
+        RegisterRequest::WhipOutputStream(output_stream) => {
+            register_whip_output(api, output_stream).map(|_| None)
         }
         RegisterRequest::Shader(spec) => {
             let spec = spec.try_into()?;
@@ -42,53 +391,544 @@ pub fn handle_register_request(
     }
 }
 
-fn register_output(api: &mut Api, request: RegisterOutputRequest) -> Result<(), ApiError> {
-    let RegisterOutputRequest {
-        output_id,
-        port,
-        ip,
-        ..
-    } = request.clone();
+fn register_output(
+    api: &mut Api,
+    request: RegisterOutputRequest,
+) -> Result<ResponseHandler, ApiError> {
+    let output_id = &request.output_id;
+    let ip = &request.ip;
+    let additional_destinations: Vec<_> = request.additional_destinations().collect();
+    for (index, &(port, ip)) in additional_destinations.iter().enumerate() {
+        if additional_destinations[..index].contains(&(port, ip)) {
+            return Err(ApiError::new(
+                ErrorCode::PortAndIpAlreadyInUse,
+                format!("Failed to register output stream \"{output_id}\". Combination of port {port} and IP {ip} is listed more than once."),
+                tiny_http::StatusCode(400)
+            ));
+        }
+        check_output_address_free(api, output_id, port, ip)?;
+    }
+
+    let port = match request.port.clone().try_into()? {
+        Port::Exact(port) => {
+            if additional_destinations.contains(&(port, ip)) {
+                return Err(ApiError::new(
+                    ErrorCode::PortAndIpAlreadyInUse,
+                    format!("Failed to register output stream \"{output_id}\". Combination of port {port} and IP {ip} is listed more than once."),
+                    tiny_http::StatusCode(400)
+                ));
+            }
+            check_output_address_free(api, output_id, port, ip)?;
+            port
+        }
+        Port::Range((start, end)) => (start..=end)
+            .find(|port| {
+                !additional_destinations.contains(&(*port, ip))
+                    && output_using_address(api, *port, ip).is_none()
+                    && (!is_local_ip(ip) || is_local_udp_port_free(*port))
+            })
+            .ok_or_else(|| {
+                ApiError::new(
+                    ErrorCode::PortAndIpAlreadyInUse,
+                    format!("Failed to register output stream \"{output_id}\". Ports {start}..{end} are already used or not available."),
+                    tiny_http::StatusCode(400)
+                )
+            })?,
+    };
+
+    let encoder_options: pipeline::encoder::EncoderOptions = request.clone().try_into()?;
+    check_resolution_limit(output_id, encoder_options.resolution())?;
+    let adaptive_bitrate = request
+        .adaptive_bitrate
+        .clone()
+        .map(|adaptive_bitrate| adaptive_bitrate.into_options(&encoder_options))
+        .transpose()?;
+    let start_on_first_frame = request.start_on_first_frame.unwrap_or(false);
+    let watermark = request
+        .watermark
+        .clone()
+        .map(TryInto::try_into)
+        .transpose()?;
+    api.pipeline.register_output(
+        output_id.clone().into(),
+        RegisterOutputOptions {
+            encoder: encoder_options,
+            output: request.output_options(port)?,
+            framerate: request.framerate()?,
+            audio_mix: None,
+            clip_buffer: clip_buffer_duration(request.clip_buffer_s)?,
+            adaptive_bitrate,
+            start_on_first_frame,
+            watermark,
+        },
+    )?;
+
+    Ok(ResponseHandler::Response(Response::RegisteredPort(port)))
+}
+
+fn check_output_address_free(
+    api: &Api,
+    output_id: &OutputId,
+    port: u16,
+    ip: &Arc<str>,
+) -> Result<(), ApiError> {
+    match output_using_address(api, port, ip) {
+        Some(node_id) => Err(ApiError::new(
+            ErrorCode::PortAndIpAlreadyInUse,
+            format!("Failed to register output stream \"{output_id}\". Combination of port {port} and IP {ip} is already used by node \"{node_id}\""),
+            tiny_http::StatusCode(400)
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Returns the id of the RTP output that sends to the port and IP, including its
+/// additional destinations.
+fn output_using_address(api: &Api, port: u16, ip: &Arc<str>) -> Option<OutputId> {
+    api.pipeline.with_outputs(|mut iter| {
+        iter.find(|(_, output)| match &output.output {
+            pipeline::output::Output::Rtp(rtp) => rtp.sends_to(ip, port),
+            pipeline::output::Output::Mp4(_)
+            | pipeline::output::Output::Rtmp(_)
+            | pipeline::output::Output::Hls(_)
+            | pipeline::output::Output::Whip(_) => false,
+        })
+        .map(|(id, _)| id.clone().into())
+    })
+}
+
+/// Returns `true` if the IP is a loopback address or an address of this machine, so the
+/// receiver of the stream runs on the same machine as the compositor.
+pub(super) fn is_local_ip(ip: &str) -> bool {
+    if ip == "localhost" {
+        return true;
+    }
+    match ip.parse::<IpAddr>() {
+        Ok(ip) if ip.is_loopback() => true,
+        // Binding only succeeds for addresses assigned to local interfaces.
+        Ok(ip) => !ip.is_multicast() && std::net::UdpSocket::bind((ip, 0)).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Port can't be used by the receiver if it's already bound on this machine. Only
+/// meaningful if the stream is sent to a local address, see [`is_local_ip`].
+fn is_local_udp_port_free(port: u16) -> bool {
+    std::net::UdpSocket::bind((std::net::Ipv4Addr::UNSPECIFIED, port)).is_ok()
+}
+
+fn register_mp4_output(api: &mut Api, request: RegisterMp4OutputRequest) -> Result<(), ApiError> {
+    let encoder_options: pipeline::encoder::EncoderOptions = request.clone().try_into()?;
+    check_resolution_limit(&request.output_id, encoder_options.resolution())?;
+    let audio = request.audio.clone().map(Into::into);
+    let clip_buffer = clip_buffer_duration(request.clip_buffer_s)?;
+    let start_on_first_frame = request.start_on_first_frame.unwrap_or(false);
+    let watermark = request
+        .watermark
+        .clone()
+        .map(TryInto::try_into)
+        .transpose()?;
+    api.pipeline.register_output(
+        request.output_id.clone().into(),
+        RegisterOutputOptions {
+            encoder: encoder_options,
+            output: request.into(),
+            framerate: None,
+            audio_mix: audio,
+            clip_buffer,
+            adaptive_bitrate: None,
+            start_on_first_frame,
+            watermark,
+        },
+    )?;
+
+    Ok(())
+}
+
+fn register_rtmp_output(api: &mut Api, request: RegisterRtmpOutputRequest) -> Result<(), ApiError> {
+    let output_options: pipeline::output::OutputOptions = request.clone().try_into()?;
+    let pipeline::output::OutputOptions::Rtmp(ref rtmp_options) = output_options else {
+        unreachable!("RTMP request is always converted to RTMP output options")
+    };
+    let output_id = &request.output_id;
+    let url = &rtmp_options.url;
 
     api.pipeline.with_outputs(|mut iter| {
         if let Some((node_id, _)) = iter.find(|(_, output)| match &output.output {
-            pipeline::output::Output::Rtp(rtp) => rtp.port == port && rtp.ip == ip,
+            pipeline::output::Output::Rtmp(rtmp) => {
+                rtmp.url == rtmp_options.url && rtmp.stream_key == rtmp_options.stream_key
+            }
+            pipeline::output::Output::Rtp(_)
+            | pipeline::output::Output::Mp4(_)
+            | pipeline::output::Output::Hls(_)
+            | pipeline::output::Output::Whip(_) => false,
         }) {
             return Err(ApiError::new(
-                "PORT_AND_IP_ALREADY_IN_USE",
-                format!("Failed to register output stream \"{output_id}\". Combination of port {port} and IP {ip} is already used by node \"{node_id}\""),
+                ErrorCode::RtmpUrlAlreadyInUse,
+                format!("Failed to register output stream \"{output_id}\". RTMP URL {url} with the same stream key is already used by node \"{node_id}\""),
                 tiny_http::StatusCode(400)
             ));
         };
         Ok(())
     })?;
 
-    api.pipeline
-        .register_output(output_id.into(), request.clone().into(), request.into())?;
+    let encoder_options: pipeline::encoder::EncoderOptions = request.clone().try_into()?;
+    check_resolution_limit(output_id, encoder_options.resolution())?;
+    let audio = request.audio.clone().map(Into::into);
+    let clip_buffer = clip_buffer_duration(request.clip_buffer_s)?;
+    let start_on_first_frame = request.start_on_first_frame.unwrap_or(false);
+    let watermark = request
+        .watermark
+        .clone()
+        .map(TryInto::try_into)
+        .transpose()?;
+    api.pipeline.register_output(
+        request.output_id.clone().into(),
+        RegisterOutputOptions {
+            encoder: encoder_options,
+            output: output_options,
+            framerate: None,
+            audio_mix: audio,
+            clip_buffer,
+            adaptive_bitrate: None,
+            start_on_first_frame,
+            watermark,
+        },
+    )?;
+
+    Ok(())
+}
+
+fn register_hls_output(api: &mut Api, request: RegisterHlsOutputRequest) -> Result<(), ApiError> {
+    let output_options = request.output_options()?;
+    let pipeline::output::OutputOptions::Hls(ref hls_options) = output_options else {
+        unreachable!("HLS request is always converted to HLS output options")
+    };
+    let output_id = &request.output_id;
+    let output_dir = &hls_options.output_dir;
+
+    api.pipeline.with_outputs(|mut iter| {
+        if let Some((node_id, _)) = iter.find(|(_, output)| match &output.output {
+            pipeline::output::Output::Hls(hls) => &hls.output_dir == output_dir,
+            pipeline::output::Output::Rtp(_)
+            | pipeline::output::Output::Mp4(_)
+            | pipeline::output::Output::Rtmp(_)
+            | pipeline::output::Output::Whip(_) => false,
+        }) {
+            return Err(ApiError::new(
+                ErrorCode::HlsDirectoryAlreadyInUse,
+                format!("Failed to register output stream \"{output_id}\". Directory {output_dir:?} is already used by node \"{node_id}\""),
+                tiny_http::StatusCode(400)
+            ));
+        };
+        Ok(())
+    })?;
+
+    let encoder_options = request.encoder_options(config().framerate)?;
+    check_resolution_limit(output_id, encoder_options.resolution())?;
+    let watermark = request
+        .watermark
+        .clone()
+        .map(TryInto::try_into)
+        .transpose()?;
+    api.pipeline.register_output(
+        request.output_id.clone().into(),
+        RegisterOutputOptions {
+            encoder: encoder_options,
+            output: output_options,
+            framerate: None,
+            audio_mix: request.audio.map(Into::into),
+            clip_buffer: clip_buffer_duration(request.clip_buffer_s)?,
+            adaptive_bitrate: None,
+            start_on_first_frame: request.start_on_first_frame.unwrap_or(false),
+            watermark,
+        },
+    )?;
+
+    Ok(())
+}
+
+fn register_whip_output(api: &mut Api, request: RegisterWhipOutputRequest) -> Result<(), ApiError> {
+    let encoder_options: pipeline::encoder::EncoderOptions = request.clone().try_into()?;
+    check_resolution_limit(&request.output_id, encoder_options.resolution())?;
+    let adaptive_bitrate = request
+        .adaptive_bitrate
+        .clone()
+        .map(|adaptive_bitrate| adaptive_bitrate.into_options(&encoder_options))
+        .transpose()?;
+    let clip_buffer = clip_buffer_duration(request.clip_buffer_s)?;
+    let start_on_first_frame = request.start_on_first_frame.unwrap_or(false);
+    let watermark = request
+        .watermark
+        .clone()
+        .map(TryInto::try_into)
+        .transpose()?;
+    api.pipeline.register_output(
+        request.output_id.clone().into(),
+        RegisterOutputOptions {
+            encoder: encoder_options,
+            output: request.try_into()?,
+            framerate: None,
+            audio_mix: None,
+            clip_buffer,
+            adaptive_bitrate,
+            start_on_first_frame,
+            watermark,
+        },
+    )?;
 
     Ok(())
 }
 
+const MAX_CLIP_BUFFER_S: u32 = 600;
+
+/// Duration of the clip buffer of an output, from the `clip_buffer_s` field.
+fn clip_buffer_duration(clip_buffer_s: Option<u32>) -> Result<Option<Duration>, TypeError> {
+    match clip_buffer_s {
+        Some(0) => Err(TypeError::new(
+            "Field \"clip_buffer_s\" has to be greater than 0.",
+        )),
+        Some(seconds) if seconds > MAX_CLIP_BUFFER_S => Err(TypeError::new(format!(
+            "Field \"clip_buffer_s\" can't be greater than {MAX_CLIP_BUFFER_S}."
+        ))),
+        seconds => Ok(seconds.map(|seconds| Duration::from_secs(seconds as u64))),
+    }
+}
+
 fn register_input(
     api: &mut Api,
     request: RegisterInputRequest,
 ) -> Result<ResponseHandler, ApiError> {
-    let RegisterInputRequest { input_id: id, port } = request;
-    let port: Port = port.try_into()?;
+    let bind_addr = request.bind_addr()?;
+    let RegisterInputRequest {
+        input_id: id,
+        port,
+        bind_addr: _,
+        decoder,
+        reconnect,
+        stream_fallback_timeout_ms,
+        payload_type,
+        jitter_buffer_ms,
+        buffer_frames,
+        buffer_ms,
+        crop,
+        scale_to,
+        scale_mode,
+        pts_normalization,
+        fallback,
+    } = request;
+    let decoder_opts: pipeline::decoder::DecoderOptions =
+        decoder.unwrap_or(VideoCodec::H264).try_into()?;
+    let reconnect = reconnect.unwrap_or(false);
+    if payload_type.is_some_and(|payload_type| !(96..=127).contains(&payload_type)) {
+        return Err(TypeError::new("Field \"payload_type\" has to be between 96 and 127.").into());
+    }
+    let timeout = match stream_fallback_timeout_ms {
+        Some(0) => {
+            return Err(TypeError::new(
+                "Field \"stream_fallback_timeout_ms\" has to be greater than 0.",
+            )
+            .into())
+        }
+        Some(timeout_ms) => Duration::from_millis(timeout_ms.into()),
+        None => config().stream_fallback_timeout,
+    };
+    let jitter_buffer = Duration::from_millis(jitter_buffer_ms.unwrap_or(0).into());
+    let buffer = match (buffer_frames, buffer_ms) {
+        (Some(0), _) | (_, Some(0)) => {
+            return Err(TypeError::new(
+                "Fields \"buffer_frames\" and \"buffer_ms\" have to be greater than 0.",
+            )
+            .into())
+        }
+        (Some(_), Some(_)) => {
+            return Err(TypeError::new(
+                "Fields \"buffer_frames\" and \"buffer_ms\" can't be specified together.",
+            )
+            .into())
+        }
+        (Some(frames), None) => Some(InputBufferSize::Frames(frames as usize)),
+        (None, Some(buffer_ms)) => Some(InputBufferSize::Duration(Duration::from_millis(
+            buffer_ms.into(),
+        ))),
+        (None, None) => None,
+    };
+    let transform = frame_transform(crop, scale_to, scale_mode)?;
+    let codec = decoder_opts.codec();
+    let fallback = fallback.map(TryInto::try_into).transpose()?;
+    let pts_normalization = pts_normalization.map(Into::into).unwrap_or_default();
 
+    register_port_input(
+        api,
+        &id,
+        InputAddress {
+            bind_addr,
+            port: port.try_into()?,
+        },
+        decoder_opts,
+        fallback,
+        pts_normalization,
+        |port| {
+            pipeline::input::InputOptions::Rtp(RtpReceiverOptions {
+                port,
+                bind_addr,
+                codec,
+                input_id: id.clone().into(),
+                reconnect,
+                timeout,
+                payload_type,
+                jitter_buffer,
+                buffer,
+                transform,
+            })
+        },
+    )
+}
+
+fn frame_transform(
+    crop: Option<InputCrop>,
+    scale_to: Option<Resolution>,
+    scale_mode: Option<InputScaleMode>,
+) -> Result<Option<FrameTransform>, TypeError> {
+    let is_valid = |width: usize, height: usize| {
+        width > 0
+            && height > 0
+            && pipeline::is_resolution_supported(compositor_render::Resolution { width, height })
+    };
+    if let Some(crop) = &crop {
+        if !is_valid(crop.width, crop.height) {
+            return Err(TypeError::new(
+                "Fields \"crop.width\" and \"crop.height\" have to be positive even numbers.",
+            ));
+        }
+    }
+    if let Some(scale_to) = &scale_to {
+        if !is_valid(scale_to.width, scale_to.height) {
+            return Err(TypeError::new(
+                "Fields \"scale_to.width\" and \"scale_to.height\" have to be positive even numbers.",
+            ));
+        }
+    }
+    if scale_mode.is_some() && scale_to.is_none() {
+        return Err(TypeError::new(
+            "Field \"scale_mode\" can only be specified together with \"scale_to\".",
+        ));
+    }
+    if crop.is_none() && scale_to.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(FrameTransform {
+        crop: crop.map(|crop| CropOptions {
+            top: crop.top,
+            left: crop.left,
+            width: crop.width,
+            height: crop.height,
+        }),
+        scale: scale_to.map(|resolution| ScaleOptions {
+            resolution: resolution.into(),
+            mode: scale_mode.unwrap_or(InputScaleMode::Letterbox).into(),
+        }),
+    }))
+}
+
+fn register_udp_ts_input(
+    api: &mut Api,
+    request: RegisterUdpTsInputRequest,
+) -> Result<ResponseHandler, ApiError> {
+    let RegisterUdpTsInputRequest {
+        input_id: id,
+        port,
+        decoder,
+        pts_normalization,
+        fallback,
+    } = request;
+    let decoder_opts: pipeline::decoder::DecoderOptions =
+        decoder.unwrap_or(VideoCodec::H264).try_into()?;
+    let codec = decoder_opts.codec();
+    let fallback = fallback.map(TryInto::try_into).transpose()?;
+    let pts_normalization = pts_normalization.map(Into::into).unwrap_or_default();
+
+    register_port_input(
+        api,
+        &id,
+        InputAddress {
+            bind_addr: None,
+            port: port.try_into()?,
+        },
+        decoder_opts,
+        fallback,
+        pts_normalization,
+        |port| {
+            pipeline::input::InputOptions::UdpTs(UdpTsInputOptions {
+                port,
+                codec,
+                input_id: id.clone().into(),
+            })
+        },
+    )
+}
+
+fn register_tcp_h264_input(
+    api: &mut Api,
+    request: RegisterTcpH264InputRequest,
+) -> Result<ResponseHandler, ApiError> {
+    let RegisterTcpH264InputRequest {
+        input_id: id,
+        port,
+        fallback,
+    } = request;
+    let decoder_opts: pipeline::decoder::DecoderOptions = VideoCodec::H264.try_into()?;
+    let fallback = fallback.map(TryInto::try_into).transpose()?;
+
+    register_port_input(
+        api,
+        &id,
+        InputAddress {
+            bind_addr: None,
+            port: port.try_into()?,
+        },
+        decoder_opts,
+        fallback,
+        // Timestamps are assigned when access units are received, so they always
+        // start at the time of the first frame.
+        PtsNormalization::FirstFrame,
+        |port| {
+            pipeline::input::InputOptions::TcpH264(TcpInputOptions {
+                port,
+                input_id: id.clone().into(),
+            })
+        },
+    )
+}
+
+/// Local address a UDP or TCP input listens on.
+struct InputAddress {
+    /// If not specified, the input listens on all interfaces.
+    bind_addr: Option<Ipv4Addr>,
+    port: Port,
+}
+
+/// Registers an input that listens on a UDP or TCP port. For a port range, the first port
+/// that is not used by other inputs and can be bound is selected.
+fn register_port_input(
+    api: &mut Api,
+    id: &InputId,
+    address: InputAddress,
+    decoder_opts: pipeline::decoder::DecoderOptions,
+    fallback: Option<compositor_render::InputFallback>,
+    pts_normalization: PtsNormalization,
+    input_opts: impl Fn(u16) -> pipeline::input::InputOptions,
+) -> Result<ResponseHandler, ApiError> {
+    let InputAddress { bind_addr, port } = address;
     match port {
         Port::Range((start, end)) => {
             for port in start..=end {
                 trace!("[input {id}] checking port {port}");
 
-                if api
-                    .pipeline
-                    .inputs()
-                    // flat_map so that you can skip other inputs in the future by doing => None on them
-                    .flat_map(|(_, input)| match input.input {
-                        pipeline::input::Input::Rtp(ref rtp) => Some(rtp),
-                    })
-                    .any(|input| input.port == port || input.port + 1 == port)
+                if used_input_ports(api, bind_addr)
+                    // RTP inputs also use the next port for RTCP.
+                    .any(|(_, input_port)| input_port == port || input_port + 1 == port)
                 {
                     trace!("[input {id}] port {port} is already used by another input",);
                     continue;
@@ -96,11 +936,10 @@ fn register_input(
 
                 let result = api.pipeline.register_input(
                     id.clone().into(),
-                    pipeline::input::InputOptions::Rtp(RtpReceiverOptions {
-                        port,
-                        input_id: id.clone().into(),
-                    }),
-                    pipeline::decoder::DecoderOptions::H264,
+                    input_opts(port),
+                    Some(decoder_opts),
+                    fallback.clone(),
+                    pts_normalization,
                 );
 
                 if check_port_not_available(&result, port).is_err() {
@@ -120,24 +959,18 @@ fn register_input(
             }
 
             Err(ApiError::new(
-                PORT_ALREADY_IN_USE_ERROR_CODE,
+                ErrorCode::PortAlreadyInUse,
                 format!("Failed to register input stream \"{id}\". Ports {start}..{end} are already used or not available."),
                 tiny_http::StatusCode(400)
             ))
         }
 
         Port::Exact(port) => {
-            if let Some((node_id, _)) = api
-                .pipeline
-                .inputs()
-                // flat_map so that you can skip other inputs in the future by doing => None on them
-                .flat_map(|(id, input)| match input.input {
-                    pipeline::input::Input::Rtp(ref rtp) => Some((id, rtp)),
-                })
-                .find(|(_, input)| input.port == port)
+            if let Some((node_id, _)) =
+                used_input_ports(api, bind_addr).find(|(_, input_port)| *input_port == port)
             {
                 return Err(ApiError::new(
-                    PORT_ALREADY_IN_USE_ERROR_CODE,
+                    ErrorCode::PortAlreadyInUse,
                     format!("Failed to register input stream \"{id}\". Port {port} is already used by node \"{node_id}\""),
                     tiny_http::StatusCode(400)
                 ));
@@ -145,11 +978,10 @@ fn register_input(
 
             let result = api.pipeline.register_input(
                 id.clone().into(),
-                pipeline::input::InputOptions::Rtp(RtpReceiverOptions {
-                    port,
-                    input_id: id.clone().into(),
-                }),
-                pipeline::decoder::DecoderOptions::H264,
+                input_opts(port),
+                Some(decoder_opts),
+                fallback,
+                pts_normalization,
             );
 
             check_port_not_available(&result, port)?;
@@ -161,6 +993,153 @@ fn register_input(
     }
 }
 
+/// Ports of inputs that listen on `bind_addr`. Ports of inputs that listen on a
+/// different local address can be used.
+fn used_input_ports(
+    api: &Api,
+    bind_addr: Option<Ipv4Addr>,
+) -> impl Iterator<Item = (&compositor_render::InputId, u16)> {
+    api.pipeline.inputs().flat_map(move |(id, input)| {
+        let (input_bind_addr, input_port) = input_address(&input.input)?;
+        bind_addrs_overlap(input_bind_addr, bind_addr).then_some((id, input_port))
+    })
+}
+
+/// Local address and port the input listens on. UDP and TCP ports are not distinguished,
+/// so a TCP input can't use the same port as a UDP input. The address is `None` if the input
+/// listens on all interfaces.
+fn input_address(input: &pipeline::input::Input) -> Option<(Option<Ipv4Addr>, u16)> {
+    match input {
+        pipeline::input::Input::Rtp(rtp) => Some((rtp.bind_addr, rtp.port)),
+        pipeline::input::Input::UdpTs(udp_ts) => Some((None, udp_ts.port)),
+        pipeline::input::Input::TcpH264(tcp) => Some((None, tcp.port)),
+        pipeline::input::Input::File(_)
+        | pipeline::input::Input::Srt(_)
+        | pipeline::input::Input::Rtsp(_)
+        | pipeline::input::Input::StaticImage(_)
+        | pipeline::input::Input::TestPattern(_) => None,
+    }
+}
+
+/// Sockets bound to all interfaces use the port on every local address.
+fn bind_addrs_overlap(addr: Option<Ipv4Addr>, other_addr: Option<Ipv4Addr>) -> bool {
+    match (addr, other_addr) {
+        (Some(addr), Some(other_addr)) => addr == other_addr,
+        _ => true,
+    }
+}
+
+fn register_file_input(api: &mut Api, request: RegisterFileInputRequest) -> Result<(), ApiError> {
+    let RegisterFileInputRequest {
+        input_id,
+        path,
+        loop_playback,
+        decoder,
+        audio,
+        playback_speed,
+        pts_normalization,
+        fallback,
+    } = request;
+    let decoder_opts: pipeline::decoder::DecoderOptions =
+        decoder.unwrap_or(VideoCodec::H264).try_into()?;
+    let playback_speed = playback_speed.unwrap_or(1.0);
+    if !playback_speed.is_finite() || playback_speed < 0.0 {
+        return Err(
+            TypeError::new("Field \"playback_speed\" has to be a positive number or 0.").into(),
+        );
+    }
+
+    api.pipeline.register_input(
+        input_id.clone().into(),
+        pipeline::input::InputOptions::File(FileInputOptions {
+            path: Path::new(path.as_ref()).into(),
+            loop_playback: loop_playback.unwrap_or(false),
+            codec: decoder_opts.codec(),
+            audio: audio.unwrap_or(false),
+            playback_speed,
+            input_id: input_id.into(),
+        }),
+        Some(decoder_opts),
+        fallback.map(TryInto::try_into).transpose()?,
+        pts_normalization.map(Into::into).unwrap_or_default(),
+    )?;
+
+    Ok(())
+}
+
+fn register_srt_input(api: &mut Api, request: RegisterSrtInputRequest) -> Result<(), ApiError> {
+    let decoder_opts: pipeline::decoder::DecoderOptions =
+        request.decoder.unwrap_or(VideoCodec::H264).try_into()?;
+    let input_opts = request.input_options(&decoder_opts)?;
+    let fallback = request.fallback.map(TryInto::try_into).transpose()?;
+
+    api.pipeline.register_input(
+        request.input_id.into(),
+        input_opts,
+        Some(decoder_opts),
+        fallback,
+        request
+            .pts_normalization
+            .map(Into::into)
+            .unwrap_or_default(),
+    )?;
+
+    Ok(())
+}
+
+fn register_rtsp_input(api: &mut Api, request: RegisterRtspInputRequest) -> Result<(), ApiError> {
+    let decoder_opts: pipeline::decoder::DecoderOptions =
+        request.decoder.unwrap_or(VideoCodec::H264).try_into()?;
+    let input_opts = request.input_options(&decoder_opts)?;
+    let fallback = request.fallback.map(TryInto::try_into).transpose()?;
+
+    api.pipeline.register_input(
+        request.input_id.into(),
+        input_opts,
+        Some(decoder_opts),
+        fallback,
+        request
+            .pts_normalization
+            .map(Into::into)
+            .unwrap_or_default(),
+    )?;
+
+    Ok(())
+}
+
+fn register_static_image_input(
+    api: &mut Api,
+    request: RegisterStaticImageInputRequest,
+) -> Result<(), ApiError> {
+    api.pipeline.register_input(
+        request.input_id.into(),
+        pipeline::input::InputOptions::StaticImage(StaticImageOptions {
+            path: Path::new(request.path.as_ref()).into(),
+        }),
+        None,
+        None,
+        PtsNormalization::default(),
+    )?;
+
+    Ok(())
+}
+
+fn register_test_pattern_input(
+    api: &mut Api,
+    request: RegisterTestPatternInputRequest,
+) -> Result<(), ApiError> {
+    let input_opts = request.input_options(config().framerate)?;
+    api.pipeline.register_input(
+        request.input_id.into(),
+        input_opts,
+        None,
+        None,
+        PtsNormalization::default(),
+    )?;
+
+    Ok(())
+}
+
 /// Returns Ok(()) if there isn't an error or the error is not a port already in use error.
 /// Returns Err(ApiError) if the error is a port already in use error.
 fn check_port_not_available<T>(
@@ -171,14 +1150,17 @@ fn check_port_not_available<T>(
         return Ok(());
     };
 
-    let InputInitError::Rtp(RtpReceiverError::SocketBind(ref err)) = err else {
+    let (InputInitError::Rtp(RtpReceiverError::SocketBind(ref err))
+    | InputInitError::UdpTs(UdpTsInputError::SocketBind(ref err))
+    | InputInitError::Tcp(TcpInputError::SocketBind(ref err))) = err
+    else {
         return Ok(());
     };
 
     match err.kind() {
         std::io::ErrorKind::AddrInUse =>
             Err(ApiError::new(
-                PORT_ALREADY_IN_USE_ERROR_CODE,
+                ErrorCode::PortAlreadyInUse,
                 format!("Failed to register input stream \"{id}\". Port {port} is already in use or not available."),
                 tiny_http::StatusCode(400)
             )),
@@ -0,0 +1,14 @@
+use super::register_request::is_local_ip;
+
+#[test]
+fn test_is_local_ip() {
+    assert!(is_local_ip("localhost"));
+    assert!(is_local_ip("127.0.0.1"));
+    assert!(is_local_ip("127.0.0.2"));
+    assert!(is_local_ip("::1"));
+
+    // Documentation range, never assigned to a local interface.
+    assert!(!is_local_ip("192.0.2.1"));
+    assert!(!is_local_ip("239.0.0.1"));
+    assert!(!is_local_ip("example.com"));
+}
@@ -0,0 +1,53 @@
+use std::io::Cursor;
+
+use base64::Engine;
+use compositor_pipeline::error::ErrorCode;
+use compositor_render::{Frame, Resolution, YuvData};
+use image::{ImageFormat, Rgb, RgbImage};
+
+use crate::{api::Response, error::ApiError, types::OutputId};
+
+use super::Api;
+
+pub fn handle_snapshot_query(api: &Api, output_id: OutputId) -> Result<Response, ApiError> {
+    let frame = api.pipeline.snapshot(&output_id.into())?;
+    let png = frame_to_png(&frame).map_err(|err| {
+        ApiError::new(
+            ErrorCode::SnapshotEncodingError,
+            format!("Failed to encode the snapshot as PNG: {err}"),
+            tiny_http::StatusCode(500),
+        )
+    })?;
+
+    Ok(Response::Snapshot {
+        png: base64::engine::general_purpose::STANDARD.encode(png),
+    })
+}
+
+fn frame_to_png(frame: &Frame) -> Result<Vec<u8>, image::ImageError> {
+    let Resolution { width, height } = frame.resolution;
+    let YuvData {
+        y_plane,
+        u_plane,
+        v_plane,
+    } = &frame.data;
+
+    // Output resolutions are always even, chroma planes are subsampled in both dimensions.
+    let image = RgbImage::from_fn(width as u32, height as u32, |x, y| {
+        let (x, y) = (x as usize, y as usize);
+        let luma = y_plane[y * width + x] as f32;
+        let chroma_index = (y / 2) * (width / 2) + x / 2;
+        let u = u_plane[chroma_index] as f32 - 128.0;
+        let v = v_plane[chroma_index] as f32 - 128.0;
+
+        Rgb([
+            (luma + 1.402 * v).clamp(0.0, 255.0) as u8,
+            (luma - 0.34414 * u - 0.71414 * v).clamp(0.0, 255.0) as u8,
+            (luma + 1.772 * u).clamp(0.0, 255.0) as u8,
+        ])
+    });
+
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(png)
+}
@@ -1,7 +1,8 @@
 use parsing::generate_docs;
 use std::{fs, path::PathBuf};
 use video_compositor::types::{
-    Image, InputStream, Rescaler, ShaderSpec, Text, Tiles, View, WebRendererSpec, WebView,
+    ChromaKey, Image, InputStream, Rescaler, ShaderSpec, Text, Tiles, View, WebRendererSpec,
+    WebView,
 };
 
 mod parsing;
@@ -27,6 +28,7 @@ fn main() {
         generate_docs::<Text>("Text"),
         generate_docs::<Tiles>("Tiles"),
         generate_docs::<Rescaler>("Rescaler"),
+        generate_docs::<ChromaKey>("ChromaKey"),
     ];
 
     for page in renderer_pages {
@@ -1,4 +1,3 @@
-use log::info;
 use video_compositor::{
     config::config,
     http::{self},
@@ -9,8 +8,10 @@ fn main() {
     ffmpeg_next::format::network::init();
     logger::init_logger();
 
-    http::Server::new(config().api_port).run();
-
-    info!("Received exit signal. Terminating...")
-    // TODO: add graceful shutdown
+    http::Server::new(
+        config().api_bind_addr,
+        config().api_port,
+        config().tls.as_ref(),
+    )
+    .run();
 }
@@ -1,25 +1,158 @@
-use std::{env, str::FromStr, sync::OnceLock, time::Duration};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    env,
+    ffi::OsStr,
+    fs,
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
 
-use compositor_render::{web_renderer::WebRendererInitOptions, Framerate};
-use log::error;
+use compositor_pipeline::{
+    pipeline::decoder::hardware::HardwareDecoder,
+    queue::{BackpressureOptions, QueueSyncStrategy},
+};
+use compositor_render::{web_renderer::WebRendererInitOptions, Framerate, GpuAdapter};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
 
 use crate::logger::FfmpegLogLevel;
 
 pub struct Config {
     pub api_port: u16,
+    /// Address of the interface the HTTP and WebSocket servers are listening on.
+    pub api_bind_addr: IpAddr,
+    /// WebSocket API is disabled if the port is not specified.
+    pub web_socket_port: Option<u16>,
+    /// If set, API requests have to include the `Authorization: Bearer <token>` header.
+    pub api_token: Option<Arc<str>>,
+    /// If set, the HTTP API is served over HTTPS.
+    pub tls: Option<TlsConfig>,
+    /// Origins of web pages that can call the HTTP API from a browser. `*` allows any
+    /// origin. CORS headers are not sent if the list is empty.
+    pub cors_allowed_origins: Vec<String>,
     pub logger: LoggerConfig,
     pub framerate: Framerate,
     pub stream_fallback_timeout: Duration,
+    /// Inputs that don't produce frames for this long are unregistered. Disabled if `None`.
+    pub inactive_input_timeout: Option<Duration>,
+    pub max_render_queue: usize,
+    /// Outputs with a larger resolution can't be registered.
+    pub max_output_resolution: MaxOutputResolution,
+    /// Odd output resolutions are padded to even dimensions instead of being rejected.
+    pub pad_odd_resolutions: bool,
+    /// Maximal number of registered inputs. Not limited if `None`.
+    pub max_inputs: Option<usize>,
+    /// Maximal number of registered outputs. Not limited if `None`.
+    pub max_outputs: Option<usize>,
+    /// If set, the queue and file inputs are paused when rendering can't keep up,
+    /// instead of dropping frames.
+    pub backpressure: Option<BackpressureOptions>,
+    pub queue_sync_strategy: QueueSyncStrategy,
+    /// How long after their timestamps frame sets are rendered.
+    pub output_delay: Duration,
+    /// Number of threads that pass rendered frames to outputs.
+    pub output_delivery_threads: usize,
+    /// How long to wait for outputs to be finalized after receiving SIGINT or SIGTERM.
+    pub shutdown_timeout: Duration,
+    /// How long queries like `wait_for_next_frame` wait for a response, if the request
+    /// doesn't specify a timeout.
+    pub query_timeout: Duration,
+    /// Used to decode H264 inputs instead of the software decoder.
+    pub hardware_decoder: Option<HardwareDecoder>,
+    /// Maximal number of decoded frames held to pass them to the queue in presentation
+    /// order. `0` disables reordering.
+    pub decoder_max_reorder_frames: usize,
+    /// GPU used for rendering on hosts with multiple GPUs.
+    pub gpu_adapter: Option<GpuAdapter>,
     pub web_renderer: WebRendererInitOptions,
+    /// File from `LIVE_COMPOSITOR_CONFIG_FILE`, if it's set.
+    pub file: Option<ConfigFile>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MaxOutputResolution {
+    pub width: usize,
+    pub height: usize,
+    /// Limit of `width * height`, so both dimensions can't be at their limits together.
+    pub pixels: usize,
+}
+
+pub struct ConfigFile {
+    pub path: PathBuf,
+    /// Keys that don't match any setting. They are ignored, so files written for newer
+    /// versions can still be used.
+    pub unknown_keys: Vec<String>,
+}
+
+/// Paths to PEM files with the certificate chain and the private key of the HTTP server.
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
 }
 
 pub struct LoggerConfig {
     pub ffmpeg_logger_level: FfmpegLogLevel,
     pub format: LoggerFormat,
     pub level: String,
+    /// Logs are written to the file in addition to stdout.
+    pub file: Option<LogFileConfig>,
+    /// Log durations of processing stages of every frame. Spans of the stages are
+    /// disabled otherwise, regardless of the log level.
+    pub timings: bool,
+}
+
+pub struct LogFileConfig {
+    pub path: PathBuf,
+    pub rotation: LogRotation,
 }
 
 #[derive(Debug, Copy, Clone)]
+pub enum LogRotation {
+    Daily,
+    /// Maximal size of the file in bytes.
+    Size(u64),
+    Never,
+}
+
+impl FromStr for LogRotation {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERROR_MESSAGE: &str = "LIVE_COMPOSITOR_LOG_FILE_ROTATION has to be \"daily\", \"never\" or a file size, e.g. \"100MB\"";
+        match s {
+            "daily" => return Ok(LogRotation::Daily),
+            "never" => return Ok(LogRotation::Never),
+            _ => {}
+        }
+
+        let s = s.to_ascii_uppercase();
+        let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+            Some(index) => s.split_at(index),
+            None => (s.as_str(), ""),
+        };
+        let multiplier = match unit.trim() {
+            "" | "B" => 1,
+            "KB" => 1024,
+            "MB" => 1024 * 1024,
+            "GB" => 1024 * 1024 * 1024,
+            _ => return Err(ERROR_MESSAGE),
+        };
+        match number.parse::<u64>() {
+            Ok(size) if size > 0 => size
+                .checked_mul(multiplier)
+                .map(LogRotation::Size)
+                .ok_or(ERROR_MESSAGE),
+            _ => Err(ERROR_MESSAGE),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum LoggerFormat {
     Pretty,
     Json,
@@ -42,27 +175,183 @@ impl FromStr for LoggerFormat {
 pub fn config() -> &'static Config {
     static CONFIG: OnceLock<Config> = OnceLock::new();
 
-    CONFIG.get_or_init(|| {
-        read_config().expect("Failed to read the config from environment variables.")
-    })
+    CONFIG.get_or_init(|| read_config().expect("Failed to read the config."))
+}
+
+/// Warnings about the config file can't be logged while the config is read, because
+/// the logger is configured by it.
+pub fn log_config_file_warnings() {
+    let Some(ref file) = config().file else {
+        return;
+    };
+    for key in file.unknown_keys.iter() {
+        warn!(
+            "Unknown key \"{key}\" in the config file {}, it's ignored.",
+            file.path.display()
+        );
+    }
+}
+
+/// Settings are read from environment variables and from the file pointed to by
+/// `LIVE_COMPOSITOR_CONFIG_FILE`. Keys in the file are names of the variables without
+/// the `LIVE_COMPOSITOR_` prefix in lowercase, e.g. `api_port`. Environment variables
+/// take precedence over the file.
+struct ConfigSource {
+    file: Option<(PathBuf, HashMap<String, String>)>,
+    /// Keys of all settings that were read, so unknown keys in the file can be reported.
+    read_keys: RefCell<HashSet<String>>,
+}
+
+/// Value of a setting in the config file. It's converted to the same string as the one
+/// expected in the environment variable, lists are joined with commas.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ConfigFileValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<String>),
+}
+
+impl From<ConfigFileValue> for String {
+    fn from(value: ConfigFileValue) -> Self {
+        match value {
+            ConfigFileValue::Bool(value) => value.to_string(),
+            ConfigFileValue::Integer(value) => value.to_string(),
+            ConfigFileValue::Float(value) => value.to_string(),
+            ConfigFileValue::String(value) => value,
+            ConfigFileValue::List(values) => values.join(","),
+        }
+    }
+}
+
+impl ConfigSource {
+    fn new() -> Result<Self, String> {
+        let path = match env::var("LIVE_COMPOSITOR_CONFIG_FILE") {
+            Ok(path) if !path.is_empty() => PathBuf::from(path),
+            Ok(_) | Err(_) => {
+                return Ok(Self {
+                    file: None,
+                    read_keys: RefCell::default(),
+                })
+            }
+        };
+
+        let content = fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read the config file {}: {err}", path.display()))?;
+        // TOML is used unless the file has the `.json` extension.
+        let values: HashMap<String, ConfigFileValue> =
+            match path.extension().and_then(OsStr::to_str) {
+                Some("json") => serde_json::from_str(&content).map_err(|err| err.to_string()),
+                _ => toml::from_str(&content).map_err(|err| err.to_string()),
+            }
+            .map_err(|err| format!("Invalid config file {}: {err}", path.display()))?;
+        let values = values
+            .into_iter()
+            .map(|(key, value)| (key, value.into()))
+            .collect();
+
+        Ok(Self {
+            file: Some((path, values)),
+            read_keys: RefCell::default(),
+        })
+    }
+
+    /// Value of the environment variable, or the value of the matching key in the file.
+    fn var(&self, name: &str) -> Result<String, env::VarError> {
+        let key = name.trim_start_matches("LIVE_COMPOSITOR_").to_lowercase();
+        let file_value = self
+            .file
+            .as_ref()
+            .and_then(|(_, values)| values.get(&key).cloned());
+        self.read_keys.borrow_mut().insert(key);
+
+        match env::var(name) {
+            Ok(value) => Ok(value),
+            Err(err) => file_value.ok_or(err),
+        }
+    }
+
+    /// Has to be called after all settings are read.
+    fn into_config_file(self) -> Option<ConfigFile> {
+        let (path, values) = self.file?;
+        let read_keys = self.read_keys.into_inner();
+        let mut unknown_keys: Vec<String> = values
+            .into_keys()
+            .filter(|key| !read_keys.contains(key))
+            .collect();
+        unknown_keys.sort();
+        Some(ConfigFile { path, unknown_keys })
+    }
 }
 
-fn read_config() -> Result<Config, &'static str> {
-    let api_port = match env::var("LIVE_COMPOSITOR_API_PORT") {
+fn read_config() -> Result<Config, String> {
+    let source = ConfigSource::new()?;
+
+    let api_port = match source.var("LIVE_COMPOSITOR_API_PORT") {
         Ok(api_port) => api_port
             .parse::<u16>()
             .map_err(|_| "LIVE_COMPOSITOR_API_PORT has to be valid port number")?,
         Err(_) => 8081,
     };
 
-    let ffmpeg_logger_level = match env::var("LIVE_COMPOSITOR_FFMPEG_LOGGER_LEVEL") {
+    let api_bind_addr = match source.var("LIVE_COMPOSITOR_API_BIND_ADDR") {
+        Ok(api_bind_addr) => api_bind_addr
+            .parse::<IpAddr>()
+            .map_err(|_| "LIVE_COMPOSITOR_API_BIND_ADDR has to be valid IP address")?,
+        Err(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+    };
+
+    let web_socket_port = match source.var("LIVE_COMPOSITOR_WEB_SOCKET_PORT") {
+        Ok(web_socket_port) => Some(
+            web_socket_port
+                .parse::<u16>()
+                .map_err(|_| "LIVE_COMPOSITOR_WEB_SOCKET_PORT has to be valid port number")?,
+        ),
+        Err(_) => None,
+    };
+
+    let api_token = match source.var("LIVE_COMPOSITOR_API_TOKEN") {
+        Ok(token) if !token.is_empty() => Some(token.into()),
+        Ok(_) | Err(_) => None,
+    };
+
+    let tls = match (
+        source.var("LIVE_COMPOSITOR_TLS_CERT"),
+        source.var("LIVE_COMPOSITOR_TLS_KEY"),
+    ) {
+        (Ok(cert_path), Ok(key_path)) => Some(TlsConfig {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+        }),
+        (Err(_), Err(_)) => None,
+        (Ok(_), Err(_)) | (Err(_), Ok(_)) => {
+            return Err(
+                "LIVE_COMPOSITOR_TLS_CERT and LIVE_COMPOSITOR_TLS_KEY have to be set together"
+                    .into(),
+            )
+        }
+    };
+
+    let cors_allowed_origins = match source.var("LIVE_COMPOSITOR_CORS_ALLOWED_ORIGINS") {
+        Ok(origins) => origins
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Err(_) => vec![],
+    };
+
+    let ffmpeg_logger_level = match source.var("LIVE_COMPOSITOR_FFMPEG_LOGGER_LEVEL") {
         Ok(ffmpeg_log_level) => {
             FfmpegLogLevel::from_str(&ffmpeg_log_level).unwrap_or(FfmpegLogLevel::Warn)
         }
         Err(_) => FfmpegLogLevel::Warn,
     };
 
-    let logger_level = match env::var("LIVE_COMPOSITOR_LOGGER_LEVEL") {
+    let logger_level = match source.var("LIVE_COMPOSITOR_LOGGER_LEVEL") {
         Ok(level) => level,
         Err(_) => "info".to_string(),
     };
@@ -72,32 +361,53 @@ fn read_config() -> Result<Config, &'static str> {
         Ok(_) => LoggerFormat::Compact,
         Err(_) => LoggerFormat::Json,
     };
-    let logger_format = match env::var("LIVE_COMPOSITOR_LOGGER_FORMAT") {
+    let logger_format = match source.var("LIVE_COMPOSITOR_LOGGER_FORMAT") {
         Ok(format) => LoggerFormat::from_str(&format).unwrap_or(default_logger_format),
         Err(_) => default_logger_format,
     };
 
+    // Read even if the log file is not set, so the key is not reported as unknown.
+    let log_file_rotation = source.var("LIVE_COMPOSITOR_LOG_FILE_ROTATION");
+    let log_file = match source.var("LIVE_COMPOSITOR_LOG_FILE") {
+        Ok(path) if !path.is_empty() => {
+            let rotation = match log_file_rotation {
+                Ok(rotation) => LogRotation::from_str(&rotation)?,
+                Err(_) => LogRotation::Daily,
+            };
+            Some(LogFileConfig {
+                path: path.into(),
+                rotation,
+            })
+        }
+        Ok(_) | Err(_) => None,
+    };
+
+    let log_timings = match source.var("LIVE_COMPOSITOR_LOG_TIMINGS") {
+        Ok(enable) => bool_env_from_str(&enable).unwrap_or(false),
+        Err(_) => false,
+    };
+
     const DEFAULT_FRAMERATE: Framerate = Framerate { num: 30, den: 1 };
-    let framerate = match env::var("LIVE_COMPOSITOR_OUTPUT_FRAMERATE") {
+    let framerate = match source.var("LIVE_COMPOSITOR_OUTPUT_FRAMERATE") {
         Ok(framerate) => framerate_from_str(&framerate).unwrap_or(DEFAULT_FRAMERATE),
         Err(_) => DEFAULT_FRAMERATE,
     };
 
     const DEFAULT_WEB_RENDERER_ENABLED: bool = cfg!(feature = "web_renderer");
-    let web_renderer_enable = match env::var("LIVE_COMPOSITOR_WEB_RENDERER_ENABLE") {
+    let web_renderer_enable = match source.var("LIVE_COMPOSITOR_WEB_RENDERER_ENABLE") {
         Ok(enable) => bool_env_from_str(&enable).unwrap_or(DEFAULT_WEB_RENDERER_ENABLED),
         Err(_) => DEFAULT_WEB_RENDERER_ENABLED,
     };
 
-    let web_renderer_gpu_enable = match env::var("LIVE_COMPOSITOR_WEB_RENDERER_GPU_ENABLE") {
+    let web_renderer_gpu_enable = match source.var("LIVE_COMPOSITOR_WEB_RENDERER_GPU_ENABLE") {
         Ok(enable) => bool_env_from_str(&enable).unwrap_or(true),
         Err(_) => true,
     };
 
     const DEFAULT_STREAM_FALLBACK_TIMEOUT: Duration = Duration::from_millis(2000);
-    let stream_fallback_timeout = match env::var("LIVE_COMPOSITOR_STREAM_FALLBACK_TIMEOUT_MS") {
+    let stream_fallback_timeout = match source.var("LIVE_COMPOSITOR_STREAM_FALLBACK_TIMEOUT_MS") {
         Ok(timeout_ms) => match timeout_ms.parse::<f64>() {
-            Ok(timeout_ms) => Duration::from_secs_f64(timeout_ms),
+            Ok(timeout_ms) => Duration::from_secs_f64(timeout_ms / 1000.0),
             Err(_) => {
                 error!("Invalid value provided for \"LIVE_COMPOSITOR_STREAM_FALLBACK_TIMEOUT_MS\". Falling back to default value 2000ms.");
                 DEFAULT_STREAM_FALLBACK_TIMEOUT
@@ -106,19 +416,270 @@ fn read_config() -> Result<Config, &'static str> {
         Err(_) => DEFAULT_STREAM_FALLBACK_TIMEOUT,
     };
 
+    let inactive_input_timeout = match source.var("LIVE_COMPOSITOR_INACTIVE_INPUT_TIMEOUT_MS") {
+        Ok(timeout_ms) => match timeout_ms.parse::<u64>() {
+            Ok(timeout_ms) if Duration::from_millis(timeout_ms) > stream_fallback_timeout => {
+                Some(Duration::from_millis(timeout_ms))
+            }
+            Ok(_) => {
+                error!("\"LIVE_COMPOSITOR_INACTIVE_INPUT_TIMEOUT_MS\" has to be longer than the stream fallback timeout ({}ms). Inactive inputs won't be unregistered.", stream_fallback_timeout.as_millis());
+                None
+            }
+            Err(_) => {
+                error!("Invalid value provided for \"LIVE_COMPOSITOR_INACTIVE_INPUT_TIMEOUT_MS\". Inactive inputs won't be unregistered.");
+                None
+            }
+        },
+        Err(_) => None,
+    };
+
+    const DEFAULT_MAX_RENDER_QUEUE: usize = 20;
+    let max_render_queue = match source.var("LIVE_COMPOSITOR_MAX_RENDER_QUEUE") {
+        Ok(max_render_queue) => match max_render_queue.parse::<usize>() {
+            Ok(max_render_queue) => max_render_queue,
+            Err(_) => {
+                error!("Invalid value provided for \"LIVE_COMPOSITOR_MAX_RENDER_QUEUE\". Falling back to default value 20.");
+                DEFAULT_MAX_RENDER_QUEUE
+            }
+        },
+        Err(_) => DEFAULT_MAX_RENDER_QUEUE,
+    };
+
+    // Portrait 8K resolution is also allowed.
+    const DEFAULT_MAX_OUTPUT_WIDTH: usize = 7680;
+    const DEFAULT_MAX_OUTPUT_HEIGHT: usize = 7680;
+    const DEFAULT_MAX_OUTPUT_PIXELS: usize = 7680 * 4320;
+    let max_output_resolution = MaxOutputResolution {
+        width: positive_usize_var(
+            &source,
+            "LIVE_COMPOSITOR_MAX_OUTPUT_WIDTH",
+            DEFAULT_MAX_OUTPUT_WIDTH,
+        ),
+        height: positive_usize_var(
+            &source,
+            "LIVE_COMPOSITOR_MAX_OUTPUT_HEIGHT",
+            DEFAULT_MAX_OUTPUT_HEIGHT,
+        ),
+        pixels: positive_usize_var(
+            &source,
+            "LIVE_COMPOSITOR_MAX_OUTPUT_PIXELS",
+            DEFAULT_MAX_OUTPUT_PIXELS,
+        ),
+    };
+
+    let pad_odd_resolutions = match source.var("LIVE_COMPOSITOR_PAD_ODD_RESOLUTIONS") {
+        Ok(enable) => bool_env_from_str(&enable).unwrap_or(false),
+        Err(_) => false,
+    };
+
+    let max_inputs = limit_var(&source, "LIVE_COMPOSITOR_MAX_INPUTS");
+    let max_outputs = limit_var(&source, "LIVE_COMPOSITOR_MAX_OUTPUTS");
+
+    let backpressure = match source.var("LIVE_COMPOSITOR_BACKPRESSURE") {
+        Ok(watermarks) => Some(backpressure_from_str(&watermarks)?),
+        Err(_) => None,
+    };
+
+    const DEFAULT_QUEUE_MAX_WAIT: Duration = Duration::from_millis(100);
+    let queue_max_wait = match source.var("LIVE_COMPOSITOR_QUEUE_MAX_WAIT_MS") {
+        Ok(max_wait_ms) => match max_wait_ms.parse::<u64>() {
+            Ok(max_wait_ms) => Duration::from_millis(max_wait_ms),
+            Err(_) => {
+                error!("Invalid value provided for \"LIVE_COMPOSITOR_QUEUE_MAX_WAIT_MS\". Falling back to default value 100ms.");
+                DEFAULT_QUEUE_MAX_WAIT
+            }
+        },
+        Err(_) => DEFAULT_QUEUE_MAX_WAIT,
+    };
+
+    let queue_sync_strategy = match source.var("LIVE_COMPOSITOR_QUEUE_SYNC_STRATEGY") {
+        Ok(strategy) => match strategy.as_str() {
+            "wait_for_all_inputs" => QueueSyncStrategy::WaitForAllInputs {
+                max_wait: queue_max_wait,
+            },
+            "latest_on_tick" => QueueSyncStrategy::LatestOnTick,
+            _ => {
+                error!("Invalid value provided for \"LIVE_COMPOSITOR_QUEUE_SYNC_STRATEGY\". Falling back to default value \"latest_on_tick\".");
+                QueueSyncStrategy::LatestOnTick
+            }
+        },
+        Err(_) => QueueSyncStrategy::LatestOnTick,
+    };
+
+    let output_delay = match source.var("LIVE_COMPOSITOR_OUTPUT_DELAY_MS") {
+        Ok(delay_ms) => match delay_ms.parse::<u64>() {
+            Ok(delay_ms) => Duration::from_millis(delay_ms),
+            Err(_) => {
+                error!("Invalid value provided for \"LIVE_COMPOSITOR_OUTPUT_DELAY_MS\". Falling back to default value 0ms.");
+                Duration::ZERO
+            }
+        },
+        Err(_) => Duration::ZERO,
+    };
+
+    const DEFAULT_OUTPUT_DELIVERY_THREADS: usize = 1;
+    let output_delivery_threads = match source.var("LIVE_COMPOSITOR_OUTPUT_DELIVERY_THREADS") {
+        Ok(output_delivery_threads) => match output_delivery_threads.parse::<usize>() {
+            Ok(output_delivery_threads) if output_delivery_threads > 0 => output_delivery_threads,
+            _ => {
+                error!("Invalid value provided for \"LIVE_COMPOSITOR_OUTPUT_DELIVERY_THREADS\". Falling back to default value 1.");
+                DEFAULT_OUTPUT_DELIVERY_THREADS
+            }
+        },
+        Err(_) => DEFAULT_OUTPUT_DELIVERY_THREADS,
+    };
+
+    const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_millis(5000);
+    let shutdown_timeout = match source.var("LIVE_COMPOSITOR_SHUTDOWN_TIMEOUT_MS") {
+        Ok(timeout_ms) => match timeout_ms.parse::<u64>() {
+            Ok(timeout_ms) => Duration::from_millis(timeout_ms),
+            Err(_) => {
+                error!("Invalid value provided for \"LIVE_COMPOSITOR_SHUTDOWN_TIMEOUT_MS\". Falling back to default value 5000ms.");
+                DEFAULT_SHUTDOWN_TIMEOUT
+            }
+        },
+        Err(_) => DEFAULT_SHUTDOWN_TIMEOUT,
+    };
+
+    const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+    let query_timeout = match source.var("LIVE_COMPOSITOR_QUERY_TIMEOUT_MS") {
+        Ok(timeout_ms) => match timeout_ms.parse::<u64>() {
+            Ok(timeout_ms) if timeout_ms > 0 => Duration::from_millis(timeout_ms),
+            _ => {
+                error!("Invalid value provided for \"LIVE_COMPOSITOR_QUERY_TIMEOUT_MS\". Falling back to default value 60000ms.");
+                DEFAULT_QUERY_TIMEOUT
+            }
+        },
+        Err(_) => DEFAULT_QUERY_TIMEOUT,
+    };
+
+    let hardware_decoder = match source.var("LIVE_COMPOSITOR_HARDWARE_DECODER") {
+        Ok(hardware_decoder) => match hardware_decoder.as_str() {
+            "vaapi" => Some(HardwareDecoder::Vaapi),
+            "qsv" => Some(HardwareDecoder::Qsv),
+            _ => {
+                error!("Invalid value provided for \"LIVE_COMPOSITOR_HARDWARE_DECODER\". Falling back to software decoding.");
+                None
+            }
+        },
+        Err(_) => None,
+    };
+
+    // Maximal number of reordered frames allowed by the H264 and H265 specifications.
+    const DEFAULT_DECODER_MAX_REORDER_FRAMES: usize = 16;
+    let decoder_max_reorder_frames = match source.var("LIVE_COMPOSITOR_DECODER_MAX_REORDER_FRAMES")
+    {
+        Ok(max_frames) => match max_frames.parse::<usize>() {
+            Ok(max_frames) => max_frames,
+            Err(_) => {
+                error!("Invalid value provided for \"LIVE_COMPOSITOR_DECODER_MAX_REORDER_FRAMES\". Falling back to default value {DEFAULT_DECODER_MAX_REORDER_FRAMES}.");
+                DEFAULT_DECODER_MAX_REORDER_FRAMES
+            }
+        },
+        Err(_) => DEFAULT_DECODER_MAX_REORDER_FRAMES,
+    };
+
+    let gpu_adapter = match (
+        source.var("LIVE_COMPOSITOR_GPU_INDEX"),
+        source.var("LIVE_COMPOSITOR_GPU_NAME"),
+    ) {
+        (Ok(index), Err(_)) => match index.parse::<usize>() {
+            Ok(index) => Some(GpuAdapter::Index(index)),
+            Err(_) => {
+                error!("Invalid value provided for \"LIVE_COMPOSITOR_GPU_INDEX\". Falling back to the default GPU.");
+                None
+            }
+        },
+        (Err(_), Ok(name)) if !name.is_empty() => Some(GpuAdapter::Name(name)),
+        (Err(_), _) => None,
+        (Ok(_), Ok(_)) => {
+            return Err(
+                "LIVE_COMPOSITOR_GPU_INDEX and LIVE_COMPOSITOR_GPU_NAME can't be set together"
+                    .into(),
+            )
+        }
+    };
+
     Ok(Config {
         api_port,
+        api_bind_addr,
+        web_socket_port,
+        api_token,
+        tls,
+        cors_allowed_origins,
         logger: LoggerConfig {
             ffmpeg_logger_level,
             format: logger_format,
             level: logger_level,
+            file: log_file,
+            timings: log_timings,
         },
         framerate,
         stream_fallback_timeout,
+        inactive_input_timeout,
+        max_render_queue,
+        max_output_resolution,
+        pad_odd_resolutions,
+        max_inputs,
+        max_outputs,
+        backpressure,
+        queue_sync_strategy,
+        output_delay,
+        output_delivery_threads,
+        shutdown_timeout,
+        query_timeout,
+        hardware_decoder,
+        decoder_max_reorder_frames,
+        gpu_adapter,
         web_renderer: WebRendererInitOptions {
             enable: web_renderer_enable,
             enable_gpu: web_renderer_gpu_enable,
         },
+        file: source.into_config_file(),
+    })
+}
+
+fn positive_usize_var(source: &ConfigSource, name: &str, default: usize) -> usize {
+    match source.var(name) {
+        Ok(value) => match value.parse::<usize>() {
+            Ok(value) if value > 0 => value,
+            _ => {
+                error!("Invalid value provided for \"{name}\". Falling back to default value {default}.");
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+/// Limit is disabled if the variable is not set or its value is invalid.
+fn limit_var(source: &ConfigSource, name: &str) -> Option<usize> {
+    let value = source.var(name).ok()?;
+    match value.parse::<usize>() {
+        Ok(limit) if limit > 0 => Some(limit),
+        _ => {
+            error!("Invalid value provided for \"{name}\". Falling back to no limit.");
+            None
+        }
+    }
+}
+
+/// Parses watermarks in the `HIGH:LOW` format.
+fn backpressure_from_str(s: &str) -> Result<BackpressureOptions, &'static str> {
+    const ERROR_MESSAGE: &str = "LIVE_COMPOSITOR_BACKPRESSURE has to be in the \"HIGH:LOW\" format, where HIGH and LOW are unsigned integers and LOW is lower than HIGH";
+    let Some((high, low)) = s.split_once(':') else {
+        return Err(ERROR_MESSAGE);
+    };
+    let (Ok(high_watermark), Ok(low_watermark)) = (high.parse::<usize>(), low.parse::<usize>())
+    else {
+        return Err(ERROR_MESSAGE);
+    };
+    if low_watermark >= high_watermark {
+        return Err(ERROR_MESSAGE);
+    }
+    Ok(BackpressureOptions {
+        high_watermark,
+        low_watermark,
     })
 }
 
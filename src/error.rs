@@ -1,15 +1,48 @@
 use std::fmt::Display;
 
-use compositor_pipeline::error::{ErrorType, PipelineErrorInfo};
+use compositor_pipeline::error::{ErrorCode, ErrorType, PipelineErrorInfo};
 use compositor_render::error::ErrorStack;
 use tiny_http::StatusCode;
 
 use crate::types::TypeError;
 
-pub const PORT_ALREADY_IN_USE_ERROR_CODE: &str = "PORT_ALREADY_IN_USE";
+/// Codes of errors returned by the API. Besides codes of pipeline errors, it includes
+/// codes of errors that only happen in the HTTP and WebSocket servers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApiErrorCode {
+    Pipeline(ErrorCode),
+    MalformedRequest,
+    Unauthorized,
+    RouteNotFound,
+    MethodNotAllowed,
+    ServerNotReady,
+    QueryTimeout,
+    IdempotencyKeyReused,
+}
+
+impl ApiErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiErrorCode::Pipeline(code) => code.as_str(),
+            ApiErrorCode::MalformedRequest => "MALFORMED_REQUEST",
+            ApiErrorCode::Unauthorized => "UNAUTHORIZED",
+            ApiErrorCode::RouteNotFound => "ROUTE_NOT_FOUND",
+            ApiErrorCode::MethodNotAllowed => "METHOD_NOT_ALLOWED",
+            ApiErrorCode::ServerNotReady => "SERVER_NOT_READY",
+            ApiErrorCode::QueryTimeout => "QUERY_TIMEOUT",
+            ApiErrorCode::IdempotencyKeyReused => "IDEMPOTENCY_KEY_REUSED",
+        }
+    }
+}
+
+impl From<ErrorCode> for ApiErrorCode {
+    fn from(code: ErrorCode) -> Self {
+        ApiErrorCode::Pipeline(code)
+    }
+}
 
 pub struct ApiError {
-    pub error_code: &'static str,
+    pub error_code: ApiErrorCode,
     pub message: String,
     pub stack: Vec<String>,
     pub http_status_code: tiny_http::StatusCode,
@@ -17,12 +50,12 @@ pub struct ApiError {
 
 impl ApiError {
     pub fn new(
-        error_code: &'static str,
+        error_code: impl Into<ApiErrorCode>,
         message: String,
         http_status_code: tiny_http::StatusCode,
     ) -> Self {
         ApiError {
-            error_code,
+            error_code: error_code.into(),
             message: message.clone(),
             stack: vec![message],
             http_status_code,
@@ -31,7 +64,7 @@ impl ApiError {
 
     pub fn malformed_request(err: &dyn Display) -> Self {
         ApiError::new(
-            "MALFORMED_REQUEST",
+            ApiErrorCode::MalformedRequest,
             format!("Received malformed request:\n{err}"),
             StatusCode(400),
         )
@@ -47,7 +80,7 @@ where
         let stack: Vec<String> = ErrorStack::new(&err).map(ToString::to_string).collect();
         let err_info = PipelineErrorInfo::from(&err);
         ApiError {
-            error_code: err_info.error_code,
+            error_code: err_info.error_code.into(),
             message: stack.first().unwrap().clone(),
             stack,
             http_status_code: match err_info.error_type {
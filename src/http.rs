@@ -1,31 +1,61 @@
 use compositor_render::error::ErrorStack;
-use crossbeam_channel::RecvTimeoutError;
-use log::{error, info};
+use crossbeam_channel::{bounded, Receiver, Sender};
+use flate2::{
+    write::{GzEncoder, ZlibEncoder},
+    Compression,
+};
+use log::{error, info, warn};
 
 use serde_json::json;
 use signal_hook::{consts, iterator::Signals};
 use std::{
-    io::{Cursor, ErrorKind},
-    net::SocketAddr,
-    sync::Arc,
+    fmt::Write,
+    io::{Cursor, ErrorKind, Write as _},
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock,
+    },
     thread,
     time::Duration,
 };
-use tiny_http::{Header, Response, StatusCode};
+use tiny_http::{ConfigListenAddr, Header, Method, Response, ServerConfig, SslConfig, StatusCode};
 
 use crate::{
     api::{self, Api, Request, ResponseHandler},
-    error::ApiError,
+    config::{config, TlsConfig},
+    error::{ApiError, ApiErrorCode},
+    websocket,
 };
 
+/// API shared with the thread that handles requests, after it's initialized.
+struct SharedApi {
+    api: Arc<Mutex<Api>>,
+    /// Read by `/health` without locking the API, which can be held for a long time,
+    /// e.g. while outputs are finalized.
+    is_started: Arc<AtomicBool>,
+}
+
 pub struct Server {
     server: tiny_http::Server,
     content_type_json: Header,
+    content_type_prometheus: Header,
 }
 
 impl Server {
-    pub fn new(port: u16) -> Arc<Self> {
-        match tiny_http::Server::http(SocketAddr::from(([0, 0, 0, 0], port))) {
+    /// If `tls` is specified, the API is served over HTTPS. Panics if the certificate or
+    /// the key can't be loaded.
+    pub fn new(bind_addr: IpAddr, port: u16, tls: Option<&TlsConfig>) -> Arc<Self> {
+        let server_config = ServerConfig {
+            addr: ConfigListenAddr::IP(vec![SocketAddr::new(bind_addr, port)]),
+            ssl: tls.map(|tls| SslConfig {
+                certificate: read_pem_file(&tls.cert_path, "LIVE_COMPOSITOR_TLS_CERT"),
+                private_key: read_pem_file(&tls.key_path, "LIVE_COMPOSITOR_TLS_KEY"),
+            }),
+        };
+        match tiny_http::Server::new(server_config) {
             Ok(server) => Self {
                 server,
                 content_type_json: Header::from_bytes(
@@ -33,6 +63,11 @@ impl Server {
                     &b"application/json"[..],
                 )
                 .unwrap(),
+                content_type_prometheus: Header::from_bytes(
+                    &b"Content-Type"[..],
+                    &b"text/plain; version=0.0.4"[..],
+                )
+                .unwrap(),
             }
             .into(),
             Err(err) => {
@@ -42,6 +77,9 @@ impl Server {
                     }
                     Some(_) | None => {}
                 };
+                if tls.is_some() {
+                    error!("Make sure that LIVE_COMPOSITOR_TLS_CERT and LIVE_COMPOSITOR_TLS_KEY point to a valid PEM certificate chain and a matching private key.");
+                }
                 panic!("Failed to start video compositor HTTP server.\nError: {err}")
             }
         }
@@ -49,21 +87,45 @@ impl Server {
 
     pub fn run(self: Arc<Self>) {
         info!("Listening on port {}", self.server.server_addr());
-        let (mut api, event_loop) = Api::new().unwrap_or_else(|err| {
+
+        // Requests are handled before the API is initialized, so `/ready` can report
+        // that the compositor is still starting.
+        let api_cell: Arc<OnceLock<SharedApi>> = Arc::new(OnceLock::new());
+        let server = self.clone();
+        let server_api_cell = api_cell.clone();
+        thread::spawn(move || {
+            for raw_request in server.server.incoming_requests() {
+                server.route_request(&server_api_cell, raw_request)
+            }
+        });
+
+        let (shutdown_sender, shutdown_receiver) = bounded(1);
+        let (api, event_loop) = Api::new(shutdown_sender.clone()).unwrap_or_else(|err| {
             panic!(
                 "Failed to start event loop.\n{}",
                 ErrorStack::new(&err).into_string()
             )
         });
-        thread::spawn(move || {
-            for raw_request in self.server.incoming_requests() {
-                self.handle_request(&mut api, raw_request)
-            }
-        });
+        // API is shared between the HTTP server and WebSocket connections.
+        let api = Arc::new(Mutex::new(api));
+        if let Some(web_socket_port) = config().web_socket_port {
+            websocket::start_server(config().api_bind_addr, web_socket_port, api.clone());
+        }
+        handle_exit_signals(shutdown_sender);
+        handle_shutdown(api.clone(), shutdown_receiver);
+        if let Some(timeout) = config().inactive_input_timeout {
+            handle_inactive_inputs(api.clone(), timeout);
+        }
+        let is_started = api.lock().unwrap().is_started_flag();
+        let shared_api = SharedApi { api, is_started };
+        if api_cell.set(shared_api).is_err() {
+            error!("API was already initialized.");
+        }
 
-        let event_loop_fallback = || {
-            let mut signals = Signals::new([consts::SIGINT]).unwrap();
-            signals.forever().next();
+        // Process is terminated from the shutdown thread, because the Chromium
+        // message loop never returns.
+        let event_loop_fallback = || loop {
+            thread::park();
         };
         if let Err(err) = event_loop.run_with_fallback(&event_loop_fallback) {
             panic!(
@@ -73,6 +135,99 @@ impl Server {
         }
     }
 
+    /// Health and readiness checks and the schema are handled without parsing the request
+    /// body and without going through the API. JSON requests are accepted on `POST /` and
+    /// `POST /api`.
+    fn route_request(self: &Arc<Self>, api: &OnceLock<SharedApi>, raw_request: tiny_http::Request) {
+        let path = raw_request.url().split('?').next().unwrap_or_default();
+        let allowed_method = match path {
+            "/health" | "/ready" | "/metrics" | "/schema" => Method::Get,
+            "/" | "/api" => Method::Post,
+            _ => {
+                let err = ApiError::new(
+                    ApiErrorCode::RouteNotFound,
+                    format!("Route {path} does not exist."),
+                    StatusCode(404),
+                );
+                return self.send_err_response(raw_request, err);
+            }
+        };
+        if *raw_request.method() == Method::Options && !config().cors_allowed_origins.is_empty() {
+            return self.send_preflight_response(raw_request, allowed_method);
+        }
+        if *raw_request.method() != allowed_method {
+            let err = ApiError::new(
+                ApiErrorCode::MethodNotAllowed,
+                format!(
+                    "Method {} is not allowed for route {path}, use {allowed_method}.",
+                    raw_request.method()
+                ),
+                StatusCode(405),
+            );
+            let allow = Header::from_bytes(&b"Allow"[..], allowed_method.as_str()).unwrap();
+            return self.respond(
+                raw_request,
+                err.http_status_code,
+                vec![self.content_type_json.clone(), allow],
+                err_body(&err),
+            );
+        }
+
+        match (raw_request.method(), path) {
+            (Method::Get, "/health") => {
+                let started = api
+                    .get()
+                    .is_some_and(|api| api.is_started.load(Ordering::Relaxed));
+                self.send_json(
+                    raw_request,
+                    StatusCode(200),
+                    json!({ "status": "ok", "started": started }),
+                );
+            }
+            (Method::Get, "/ready") => match api.get() {
+                Some(_) => {
+                    self.send_json(raw_request, StatusCode(200), json!({ "status": "ready" }))
+                }
+                None => self.send_json(
+                    raw_request,
+                    StatusCode(503),
+                    json!({ "status": "not_ready" }),
+                ),
+            },
+            (Method::Get, "/schema") => {
+                self.send_json(raw_request, StatusCode(200), request_schema().clone())
+            }
+            _ if !is_authorized(
+                raw_request
+                    .headers()
+                    .iter()
+                    .find(|header| header.field.equiv("Authorization"))
+                    .map(|header| header.value.as_str()),
+            ) =>
+            {
+                self.send_err_response(
+                    raw_request,
+                    ApiError::new(
+                        ApiErrorCode::Unauthorized,
+                        "Missing or invalid API token.".to_string(),
+                        StatusCode(401),
+                    ),
+                )
+            }
+            (Method::Get, "/metrics") => match api.get() {
+                Some(api) => {
+                    let metrics = prometheus_metrics(&api.api.lock().unwrap().stats());
+                    self.send_metrics(raw_request, metrics)
+                }
+                None => self.send_err_response(raw_request, server_not_ready_error()),
+            },
+            _ => match api.get() {
+                Some(api) => self.handle_request(&mut api.api.lock().unwrap(), raw_request),
+                None => self.send_err_response(raw_request, server_not_ready_error()),
+            },
+        }
+    }
+
     fn handle_request(self: &Arc<Self>, api: &mut Api, mut raw_request: tiny_http::Request) {
         let response =
             Server::parse_request(&mut raw_request).and_then(|request| api.handle_request(request));
@@ -83,38 +238,17 @@ impl Server {
             Ok(ResponseHandler::Response(response)) => {
                 self.send_response(raw_request, response);
             }
+            Ok(ResponseHandler::Shutdown) => {
+                // Response is flushed before this returns. Shutdown also waits for the API
+                // lock, which is held until the end of this request.
+                self.send_response(raw_request, api::Response::Ok {});
+                api.request_shutdown();
+            }
             Ok(ResponseHandler::DeferredResponse(response)) => {
                 let server = self.clone();
-                thread::spawn(move || {
-                    let response = response.recv_timeout(Duration::from_secs(60));
-                    match response {
-                        Ok(Ok(response)) => {
-                            server.send_response(raw_request, response);
-                        }
-                        Ok(Err(err)) => {
-                            server.send_err_response(raw_request, err);
-                        }
-                        Err(RecvTimeoutError::Timeout) => {
-                            server.send_err_response(
-                                raw_request,
-                                ApiError::new(
-                                    "QUERY_TIMEOUT",
-                                    "query timed out".to_string(),
-                                    StatusCode(408),
-                                ),
-                            );
-                        }
-                        Err(RecvTimeoutError::Disconnected) => {
-                            server.send_err_response(
-                                raw_request,
-                                ApiError::new(
-                                    "INTERNAL_SERVER_ERROR",
-                                    "Internal Server Error".to_string(),
-                                    StatusCode(500),
-                                ),
-                            );
-                        }
-                    };
+                thread::spawn(move || match response.wait() {
+                    Ok(response) => server.send_response(raw_request, response),
+                    Err(err) => server.send_err_response(raw_request, err),
                 });
             }
             Err(err) => {
@@ -124,38 +258,107 @@ impl Server {
     }
 
     fn send_response(&self, raw_request: tiny_http::Request, response: api::Response) {
-        let response_result = serde_json::to_string(&response)
-            .map_err(Into::into)
-            .and_then(|body| {
-                raw_request.respond(Response::new(
-                    StatusCode(200),
-                    vec![self.content_type_json.clone()],
-                    Cursor::new(&body),
-                    Some(body.len()),
-                    None,
-                ))
-            });
-        if let Err(err) = response_result {
-            error!("Failed to send response {}.", err);
+        match serde_json::to_string(&response) {
+            Ok(body) => self.respond(
+                raw_request,
+                StatusCode(200),
+                vec![self.content_type_json.clone()],
+                body,
+            ),
+            Err(err) => error!("Failed to send response {}.", err),
         }
     }
 
     fn send_err_response(&self, raw_request: tiny_http::Request, err: ApiError) {
-        let response_result = serde_json::to_string(&json!({
-            "msg": err.message,
-            "stack": err.stack,
-            "error_code": err.error_code,
-        }))
-        .map_err(Into::into)
-        .and_then(|body| {
-            raw_request.respond(Response::new(
-                err.http_status_code,
-                vec![self.content_type_json.clone()],
-                Cursor::new(&body),
-                Some(body.len()),
-                None,
-            ))
+        self.respond(
+            raw_request,
+            err.http_status_code,
+            vec![self.content_type_json.clone()],
+            err_body(&err),
+        );
+    }
+
+    fn send_json(
+        &self,
+        raw_request: tiny_http::Request,
+        status_code: StatusCode,
+        body: serde_json::Value,
+    ) {
+        self.respond(
+            raw_request,
+            status_code,
+            vec![self.content_type_json.clone()],
+            body.to_string(),
+        );
+    }
+
+    /// Browsers send the `OPTIONS` request before cross-origin requests with custom
+    /// headers. It's answered without checking the API token, because browsers never
+    /// include it in preflight requests.
+    fn send_preflight_response(&self, raw_request: tiny_http::Request, allowed_method: Method) {
+        let headers = vec![
+            Header::from_bytes(
+                &b"Access-Control-Allow-Methods"[..],
+                format!("{allowed_method}, OPTIONS"),
+            )
+            .unwrap(),
+            Header::from_bytes(
+                &b"Access-Control-Allow-Headers"[..],
+                &b"Content-Type, Authorization"[..],
+            )
+            .unwrap(),
+            Header::from_bytes(&b"Access-Control-Max-Age"[..], &b"86400"[..]).unwrap(),
+        ];
+        self.respond(raw_request, StatusCode(204), headers, String::new());
+    }
+
+    fn send_metrics(&self, raw_request: tiny_http::Request, metrics: String) {
+        self.respond(
+            raw_request,
+            StatusCode(200),
+            vec![self.content_type_prometheus.clone()],
+            metrics,
+        );
+    }
+
+    /// Bodies larger than `COMPRESSION_THRESHOLD` are compressed if the client accepts
+    /// one of the supported encodings.
+    fn respond(
+        &self,
+        raw_request: tiny_http::Request,
+        status_code: StatusCode,
+        mut headers: Vec<Header>,
+        body: String,
+    ) {
+        headers.extend(cors_headers(&raw_request));
+        let encoding = match body.len() >= COMPRESSION_THRESHOLD {
+            true => ContentEncoding::accepted_by(&raw_request),
+            false => None,
+        };
+        let compressed = encoding.and_then(|encoding| match encoding.compress(body.as_bytes()) {
+            Ok(compressed) => Some((encoding, compressed)),
+            Err(err) => {
+                warn!(
+                    "Failed to compress response with {encoding:?}, sending it uncompressed: {err}"
+                );
+                None
+            }
         });
+        let body = match compressed {
+            Some((encoding, compressed)) => {
+                headers.push(encoding.header());
+                compressed
+            }
+            None => body.into_bytes(),
+        };
+
+        let response_result = raw_request.respond(Response::new(
+            status_code,
+            headers,
+            Cursor::new(&body),
+            Some(body.len()),
+            None,
+        ));
         if let Err(err) = response_result {
             error!("Failed to send response {}.", err);
         }
@@ -166,3 +369,409 @@ impl Server {
             .map_err(|err| ApiError::malformed_request(&err))
     }
 }
+
+fn err_body(err: &ApiError) -> String {
+    json!({
+        "msg": err.message,
+        "stack": err.stack,
+        "error_code": err.error_code.as_str(),
+    })
+    .to_string()
+}
+
+/// Smaller bodies fit in a single packet anyway, so compressing them is not worth the CPU time.
+const COMPRESSION_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone, Copy)]
+enum ContentEncoding {
+    Gzip,
+    Deflate,
+}
+
+impl ContentEncoding {
+    /// Gzip is preferred if the client accepts both encodings.
+    fn accepted_by(request: &tiny_http::Request) -> Option<Self> {
+        let accepted: Vec<&str> = request
+            .headers()
+            .iter()
+            .filter(|header| header.field.equiv("Accept-Encoding"))
+            .flat_map(|header| header.value.as_str().split(','))
+            .filter_map(|value| {
+                let mut params = value.split(';').map(str::trim);
+                let encoding = params.next()?;
+                // `q=0` means that the encoding is not acceptable.
+                let is_rejected = params
+                    .filter_map(|param| param.strip_prefix("q="))
+                    .any(|q| q.parse::<f32>().is_ok_and(|q| q == 0.0));
+                (!is_rejected).then_some(encoding)
+            })
+            .collect();
+
+        [ContentEncoding::Gzip, ContentEncoding::Deflate]
+            .into_iter()
+            .find(|encoding| {
+                accepted
+                    .iter()
+                    .any(|accepted| accepted.eq_ignore_ascii_case(encoding.name()))
+            })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+
+    fn header(&self) -> Header {
+        Header::from_bytes(&b"Content-Encoding"[..], self.name().as_bytes()).unwrap()
+    }
+
+    fn compress(&self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            ContentEncoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            // HTTP "deflate" is the zlib format.
+            ContentEncoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// Allows the origin of the request if it's listed in `LIVE_COMPOSITOR_CORS_ALLOWED_ORIGINS`.
+/// No headers are returned for other origins, so browsers block the response.
+fn cors_headers(request: &tiny_http::Request) -> Vec<Header> {
+    let allowed_origins = &config().cors_allowed_origins;
+    if allowed_origins.iter().any(|origin| origin == "*") {
+        return vec![Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap()];
+    }
+    let Some(origin) = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Origin"))
+        .map(|header| header.value.as_str())
+    else {
+        return vec![];
+    };
+    if !allowed_origins.iter().any(|allowed| allowed == origin) {
+        return vec![];
+    }
+    // Response depends on the origin, so caches can't reuse it for other origins.
+    vec![
+        Header::from_bytes(&b"Access-Control-Allow-Origin"[..], origin.as_bytes()).unwrap(),
+        Header::from_bytes(&b"Vary"[..], &b"Origin"[..]).unwrap(),
+    ]
+}
+
+fn server_not_ready_error() -> ApiError {
+    ApiError::new(
+        ApiErrorCode::ServerNotReady,
+        "Compositor is not initialized yet.".to_string(),
+        StatusCode(503),
+    )
+}
+
+/// Formats the stats in the Prometheus text exposition format.
+fn prometheus_metrics(stats: &api::Stats) -> String {
+    // Writing to a `String` never fails.
+    fn write_header(metrics: &mut String, name: &str, metric_type: &str, help: &str) {
+        let _ = writeln!(metrics, "# HELP {name} {help}");
+        let _ = writeln!(metrics, "# TYPE {name} {metric_type}");
+    }
+
+    let mut metrics = String::new();
+
+    write_header(
+        &mut metrics,
+        "compositor_frames_decoded_total",
+        "counter",
+        "Number of frames decoded from the input.",
+    );
+    for input in stats.inputs.iter() {
+        let _ = writeln!(
+            metrics,
+            "compositor_frames_decoded_total{{input_id=\"{}\"}} {}",
+            escape_label_value(&input.id.to_string()),
+            input.decoded_frames
+        );
+    }
+
+    write_header(
+        &mut metrics,
+        "compositor_input_buffer_dropped_frames_total",
+        "counter",
+        "Number of frames dropped, because the input buffer was full.",
+    );
+    for input in stats.inputs.iter() {
+        let _ = writeln!(
+            metrics,
+            "compositor_input_buffer_dropped_frames_total{{input_id=\"{}\"}} {}",
+            escape_label_value(&input.id.to_string()),
+            input.buffer_dropped_frames
+        );
+    }
+
+    write_header(
+        &mut metrics,
+        "compositor_input_decode_errors_total",
+        "counter",
+        "Number of packets that failed to decode and frames dropped after decoding.",
+    );
+    for input in stats.inputs.iter() {
+        let _ = writeln!(
+            metrics,
+            "compositor_input_decode_errors_total{{input_id=\"{}\"}} {}",
+            escape_label_value(&input.id.to_string()),
+            input.decode_errors
+        );
+    }
+
+    write_header(
+        &mut metrics,
+        "compositor_input_missing_parameter_sets_total",
+        "counter",
+        "Number of H264 or H265 chunks received before the first SPS.",
+    );
+    for input in stats.inputs.iter() {
+        let _ = writeln!(
+            metrics,
+            "compositor_input_missing_parameter_sets_total{{input_id=\"{}\"}} {}",
+            escape_label_value(&input.id.to_string()),
+            input.missing_parameter_sets
+        );
+    }
+
+    write_header(
+        &mut metrics,
+        "compositor_input_enabled",
+        "gauge",
+        "0 if the input was disabled and is not rendered in any output.",
+    );
+    for input in stats.inputs.iter() {
+        let _ = writeln!(
+            metrics,
+            "compositor_input_enabled{{input_id=\"{}\"}} {}",
+            escape_label_value(&input.id.to_string()),
+            u8::from(input.is_enabled)
+        );
+    }
+
+    write_header(
+        &mut metrics,
+        "compositor_input_fallback_active",
+        "gauge",
+        "1 if the input did not produce frames within its fallback timeout.",
+    );
+    for input in stats.inputs.iter() {
+        let _ = writeln!(
+            metrics,
+            "compositor_input_fallback_active{{input_id=\"{}\"}} {}",
+            escape_label_value(&input.id.to_string()),
+            u8::from(input.is_fallback_active)
+        );
+    }
+
+    write_header(
+        &mut metrics,
+        "compositor_frames_encoded_total",
+        "counter",
+        "Number of frames encoded and sent by the output.",
+    );
+    for output in stats.outputs.iter() {
+        let _ = writeln!(
+            metrics,
+            "compositor_frames_encoded_total{{output_id=\"{}\"}} {}",
+            escape_label_value(&output.id.to_string()),
+            output.encoded_frames
+        );
+    }
+
+    write_header(
+        &mut metrics,
+        "compositor_output_enabled",
+        "gauge",
+        "0 if the output was disabled and nothing is encoded or sent for it.",
+    );
+    for output in stats.outputs.iter() {
+        let _ = writeln!(
+            metrics,
+            "compositor_output_enabled{{output_id=\"{}\"}} {}",
+            escape_label_value(&output.id.to_string()),
+            u8::from(output.is_enabled)
+        );
+    }
+
+    write_header(
+        &mut metrics,
+        "compositor_output_send_retries_total",
+        "counter",
+        "Number of times sending was retried after a transient network error.",
+    );
+    for output in stats.outputs.iter() {
+        let _ = writeln!(
+            metrics,
+            "compositor_output_send_retries_total{{output_id=\"{}\"}} {}",
+            escape_label_value(&output.id.to_string()),
+            output.send_retries
+        );
+    }
+
+    write_header(
+        &mut metrics,
+        "compositor_output_send_failures_total",
+        "counter",
+        "Number of times sending failed after all retries.",
+    );
+    for output in stats.outputs.iter() {
+        let _ = writeln!(
+            metrics,
+            "compositor_output_send_failures_total{{output_id=\"{}\"}} {}",
+            escape_label_value(&output.id.to_string()),
+            output.send_failures
+        );
+    }
+
+    write_header(
+        &mut metrics,
+        "compositor_output_send_failing",
+        "gauge",
+        "1 if the last send of the output failed after all retries.",
+    );
+    for output in stats.outputs.iter() {
+        let _ = writeln!(
+            metrics,
+            "compositor_output_send_failing{{output_id=\"{}\"}} {}",
+            escape_label_value(&output.id.to_string()),
+            u8::from(output.is_send_failing)
+        );
+    }
+
+    write_header(
+        &mut metrics,
+        "compositor_render_queue_depth",
+        "gauge",
+        "Number of frame sets waiting to be rendered.",
+    );
+    let _ = writeln!(
+        metrics,
+        "compositor_render_queue_depth {}",
+        stats.render_queue_length
+    );
+
+    write_header(
+        &mut metrics,
+        "compositor_dropped_frames_total",
+        "counter",
+        "Number of frame sets that were not rendered.",
+    );
+    let _ = writeln!(
+        metrics,
+        "compositor_dropped_frames_total {}",
+        stats.dropped_frames
+    );
+
+    metrics
+}
+
+/// JSON Schema of the requests accepted by the API. It does not change, so it's only
+/// generated once.
+fn request_schema() -> &'static serde_json::Value {
+    static SCHEMA: OnceLock<serde_json::Value> = OnceLock::new();
+    SCHEMA.get_or_init(|| json!(schemars::schema_for!(Request)))
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn read_pem_file(path: &Path, env_var: &str) -> Vec<u8> {
+    std::fs::read(path).unwrap_or_else(|err| {
+        panic!("Failed to read {path:?} specified in {env_var}.\nError: {err}")
+    })
+}
+
+/// Checks the value of the `Authorization` header against `LIVE_COMPOSITOR_API_TOKEN`.
+/// All requests are authorized if the token is not configured.
+pub(crate) fn is_authorized(authorization: Option<&str>) -> bool {
+    let Some(ref api_token) = config().api_token else {
+        return true;
+    };
+    let Some(token) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    // Compare all bytes, so the time of the comparison does not depend on the length of
+    // the matching prefix.
+    token.len() == api_token.len()
+        && token
+            .bytes()
+            .zip(api_token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Requests the shutdown on SIGINT or SIGTERM.
+fn handle_exit_signals(shutdown_sender: Sender<()>) {
+    let mut signals = Signals::new([consts::SIGINT, consts::SIGTERM]).unwrap();
+    thread::Builder::new()
+        .name("Exit signal handler".to_string())
+        .spawn(move || {
+            signals.forever().next();
+            info!("Received exit signal.");
+            let _ = shutdown_sender.try_send(());
+        })
+        .unwrap();
+}
+
+/// Periodically unregisters inputs that didn't produce frames for `timeout`. Inputs are
+/// checked a few times per timeout, so they are unregistered at most a quarter of the
+/// timeout late.
+fn handle_inactive_inputs(api: Arc<Mutex<Api>>, timeout: Duration) {
+    let interval = timeout / 4;
+    thread::Builder::new()
+        .name("Inactive inputs handler".to_string())
+        .spawn(move || loop {
+            thread::sleep(interval);
+            api.lock().unwrap().unregister_inactive_inputs(timeout);
+        })
+        .unwrap();
+}
+
+/// Stops the pipeline after a shutdown is requested with a signal or the `shutdown`
+/// request, so encoders are flushed and output files are finalized before the process
+/// exits.
+fn handle_shutdown(api: Arc<Mutex<Api>>, shutdown_receiver: Receiver<()>) {
+    thread::Builder::new()
+        .name("Shutdown handler".to_string())
+        .spawn(move || {
+            if shutdown_receiver.recv().is_err() {
+                return;
+            }
+            info!("Stopping the pipeline...");
+
+            let (done_sender, done_receiver) = bounded(1);
+            thread::spawn(move || {
+                api.lock().unwrap().shutdown();
+                let _ = done_sender.send(());
+            });
+
+            let timeout = config().shutdown_timeout;
+            if done_receiver.recv_timeout(timeout).is_err() {
+                warn!(
+                    "Pipeline did not stop within {}ms. Outputs might not be finalized.",
+                    timeout.as_millis()
+                );
+            }
+            info!("Terminating...");
+            process::exit(0);
+        })
+        .unwrap();
+}
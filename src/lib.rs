@@ -3,5 +3,6 @@ pub mod config;
 pub mod error;
 pub mod http;
 pub mod logger;
+pub mod websocket;
 
 pub mod types;
@@ -1,8 +1,30 @@
-use std::{str::FromStr, sync::OnceLock};
+use std::{
+    ffi::CStr,
+    str::FromStr,
+    sync::{Mutex, OnceLock},
+};
 
-use crate::config::{config, LoggerFormat};
+use serde::{Deserialize, Serialize};
+use tracing::Subscriber;
+use tracing_subscriber::{
+    fmt::{format::FmtSpan, MakeWriter},
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
 
-#[derive(Debug, Clone, Copy)]
+use crate::config::{config, LoggerConfig, LoggerFormat};
+
+use self::rotating_file::RotatingFile;
+
+mod rotating_file;
+
+#[cfg(test)]
+mod rotating_file_test;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum FfmpegLogLevel {
     Error,
     Warn,
@@ -37,48 +59,98 @@ extern "C" fn ffmpeg_log_callback(
     #[cfg(not(target_arch = "aarch64"))] va_list_tag: *mut ffmpeg_next::sys::__va_list_tag,
     #[cfg(target_arch = "aarch64")] va_list_tag: ffmpeg_next::sys::va_list,
 ) {
+    let is_enabled = match ffmpeg_logger_level() {
+        FfmpegLogLevel::Error => log_level <= 16,
+        FfmpegLogLevel::Warn => log_level <= 24,
+        FfmpegLogLevel::Info => log_level <= 32,
+        FfmpegLogLevel::Debug => log_level <= 48,
+    };
+    if !is_enabled {
+        return;
+    }
+
     unsafe {
-        match ffmpeg_logger_level() {
-            FfmpegLogLevel::Error if log_level <= 16 => {
-                ffmpeg_next::sys::av_log_default_callback(arg1, log_level, fmt, va_list_tag)
-            }
-            FfmpegLogLevel::Warn if log_level <= 24 => {
-                ffmpeg_next::sys::av_log_default_callback(arg1, log_level, fmt, va_list_tag)
-            }
-            FfmpegLogLevel::Info if log_level <= 32 => {
-                ffmpeg_next::sys::av_log_default_callback(arg1, log_level, fmt, va_list_tag)
-            }
-            FfmpegLogLevel::Debug if log_level <= 48 => {
-                ffmpeg_next::sys::av_log_default_callback(arg1, log_level, fmt, va_list_tag)
-            }
-            _ => (),
+        // FFmpeg prints directly to stderr, so its logs only reach the log file if they
+        // are passed to the logger.
+        if config().logger.file.is_none() {
+            ffmpeg_next::sys::av_log_default_callback(arg1, log_level, fmt, va_list_tag);
+            return;
+        }
+
+        let mut line = [0 as libc::c_char; 1024];
+        let mut print_prefix = 1;
+        ffmpeg_next::sys::av_log_format_line2(
+            arg1,
+            log_level,
+            fmt,
+            va_list_tag,
+            line.as_mut_ptr(),
+            line.len() as libc::c_int,
+            &mut print_prefix,
+        );
+        let line = CStr::from_ptr(line.as_ptr()).to_string_lossy();
+        let line = line.trim_end();
+        match log_level {
+            ..=16 => tracing::error!(target: "ffmpeg", "{line}"),
+            17..=24 => tracing::warn!(target: "ffmpeg", "{line}"),
+            25..=32 => tracing::info!(target: "ffmpeg", "{line}"),
+            _ => tracing::debug!(target: "ffmpeg", "{line}"),
         }
     }
 }
 
 pub fn init_logger() {
-    let env_filter = tracing_subscriber::EnvFilter::new(&config().logger.level);
-    match config().logger.format {
-        LoggerFormat::Pretty => {
-            tracing_subscriber::fmt()
-                .pretty()
-                .with_env_filter(env_filter)
-                .init();
-        }
-        LoggerFormat::Json => {
-            tracing_subscriber::fmt()
-                .json()
-                .with_env_filter(env_filter)
-                .init();
-        }
-        LoggerFormat::Compact => {
-            tracing_subscriber::fmt()
-                .compact()
-                .with_env_filter(env_filter)
-                .init();
-        }
+    let logger_config = &config().logger;
+    let mut env_filter = EnvFilter::new(&logger_config.level);
+    // Spans of frame processing stages are only created if timings are enabled, so
+    // they don't cost anything otherwise.
+    env_filter = match logger_config.timings {
+        true => env_filter.add_directive("timings=trace".parse().unwrap()),
+        false => env_filter.add_directive("timings=off".parse().unwrap()),
+    };
+
+    let file_layer = logger_config.file.as_ref().map(|file| {
+        let writer = RotatingFile::open(&file.path, file.rotation)
+            .unwrap_or_else(|err| panic!("Failed to open log file {:?}.\nError: {err}", file.path));
+        fmt_layer(logger_config, Mutex::new(writer), false)
+    });
+    if file_layer.is_some() {
+        // FFmpeg logs are already filtered by LIVE_COMPOSITOR_FFMPEG_LOGGER_LEVEL.
+        env_filter = env_filter.add_directive("ffmpeg=debug".parse().unwrap());
     }
+
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(fmt_layer(logger_config, std::io::stdout, true))
+        .with(file_layer)
+        .init();
+
     unsafe {
         ffmpeg_next::sys::av_log_set_callback(Some(ffmpeg_log_callback));
     }
 }
+
+fn fmt_layer<S, W>(
+    logger_config: &LoggerConfig,
+    writer: W,
+    ansi: bool,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    // Durations of spans are logged when they are closed.
+    let span_events = match logger_config.timings {
+        true => FmtSpan::CLOSE,
+        false => FmtSpan::NONE,
+    };
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi)
+        .with_span_events(span_events);
+    match logger_config.format {
+        LoggerFormat::Pretty => layer.pretty().boxed(),
+        LoggerFormat::Json => layer.json().boxed(),
+        LoggerFormat::Compact => layer.compact().boxed(),
+    }
+}
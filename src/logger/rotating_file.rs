@@ -0,0 +1,133 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::config::LogRotation;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Log file that is renamed and replaced with an empty one according to the rotation
+/// policy. Rotated files are named after the UTC date of their last write, e.g.
+/// `compositor.log.2024-01-31`, with a counter appended if that name is already taken.
+/// They are never removed.
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    file: File,
+    size: u64,
+    /// Day since the UNIX epoch, when the file was last written to.
+    day: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, rotation: LogRotation) -> io::Result<Self> {
+        let file = open_file(path)?;
+        let metadata = file.metadata()?;
+        // Logs written by the previous run should not be mixed with today's logs.
+        let day = metadata.modified().map(day_of).unwrap_or_else(|_| today());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            rotation,
+            file,
+            size: metadata.len(),
+            day,
+        })
+    }
+
+    pub(super) fn should_rotate(&self, len: usize) -> bool {
+        match self.rotation {
+            LogRotation::Daily => self.day != today(),
+            LogRotation::Size(max_size) => self.size > 0 && self.size + len as u64 > max_size,
+            LogRotation::Never => false,
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let rotated_path = self.rotated_path();
+        fs::rename(&self.path, rotated_path)?;
+        self.file = open_file(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    pub(super) fn rotated_path(&self) -> PathBuf {
+        let date = date_from_day(self.day);
+        let file_name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut rotated_path = self.path.with_file_name(format!("{file_name}.{date}"));
+        let mut counter = 1;
+        while rotated_path.exists() {
+            rotated_path = self
+                .path
+                .with_file_name(format!("{file_name}.{date}.{counter}"));
+            counter += 1;
+        }
+        rotated_path
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.should_rotate(buf.len()) {
+            // Logger can't log its own errors. Writing continues to the current file
+            // and rotation is retried after the next period.
+            if let Err(err) = self.rotate() {
+                eprintln!("Failed to rotate log file {:?}: {err}", self.path);
+                self.size = 0;
+            }
+        }
+        self.day = today();
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn today() -> u64 {
+    day_of(SystemTime::now())
+}
+
+fn day_of(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / SECONDS_PER_DAY)
+        .unwrap_or(0)
+}
+
+/// Formats the day since the UNIX epoch as `YYYY-MM-DD`.
+///
+/// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub(super) fn date_from_day(day: u64) -> String {
+    let z = day as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
@@ -0,0 +1,128 @@
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::config::LogRotation;
+
+use super::rotating_file::{date_from_day, RotatingFile};
+
+/// Returns a path of a log file in an empty directory unique for the test.
+fn log_path(test_name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!(
+        "rotating_file_test_{test_name}_{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.join("compositor.log")
+}
+
+fn file_names(path: &Path) -> Vec<String> {
+    let mut names: Vec<_> = fs::read_dir(path.parent().unwrap())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_date_from_day() {
+    assert_eq!(date_from_day(0), "1970-01-01");
+    assert_eq!(date_from_day(31), "1970-02-01");
+    assert_eq!(date_from_day(365), "1971-01-01");
+    // Leap day in a year divisible by 4.
+    assert_eq!(date_from_day(19782), "2024-02-29");
+    assert_eq!(date_from_day(19783), "2024-03-01");
+    // 2000 is a leap year, because it's divisible by 400.
+    assert_eq!(date_from_day(11016), "2000-02-29");
+    assert_eq!(date_from_day(11017), "2000-03-01");
+    assert_eq!(date_from_day(10956), "1999-12-31");
+}
+
+#[test]
+fn test_size_rotation_at_max_size() {
+    let path = log_path("size");
+    let mut file = RotatingFile::open(&path, LogRotation::Size(10)).unwrap();
+
+    // Empty file is never rotated, even if a single write exceeds the limit.
+    assert!(!file.should_rotate(20));
+    file.write_all(b"01234").unwrap();
+    assert!(!file.should_rotate(5));
+    file.write_all(b"56789").unwrap();
+    assert_eq!(file_names(&path), vec!["compositor.log"]);
+
+    // File already has `max_size` bytes, so the next write goes to a new one.
+    assert!(file.should_rotate(1));
+    file.write_all(b"a").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "a");
+    let rotated_path = path.with_file_name(file_names(&path)[1].clone());
+    assert_eq!(fs::read_to_string(rotated_path).unwrap(), "0123456789");
+}
+
+#[test]
+fn test_daily_rotation() {
+    let path = log_path("daily");
+    let yesterday = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+    fs::write(&path, "yesterday").unwrap();
+    fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(yesterday)
+        .unwrap();
+
+    let mut file = RotatingFile::open(&path, LogRotation::Daily).unwrap();
+    assert!(file.should_rotate(0));
+    let rotated_path = file.rotated_path();
+    file.write_all(b"today").unwrap();
+    assert!(!file.should_rotate(0));
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "today");
+    assert_eq!(fs::read_to_string(rotated_path).unwrap(), "yesterday");
+}
+
+#[test]
+fn test_never_rotation() {
+    let path = log_path("never");
+    let mut file = RotatingFile::open(&path, LogRotation::Never).unwrap();
+    file.write_all(b"0123456789").unwrap();
+    assert!(!file.should_rotate(usize::MAX));
+}
+
+#[test]
+fn test_size_rotation_parse() {
+    assert!(matches!(
+        "100MB".parse::<LogRotation>(),
+        Ok(LogRotation::Size(104_857_600))
+    ));
+    assert!("0MB".parse::<LogRotation>().is_err());
+    // Size in bytes doesn't fit in u64.
+    assert!("18446744073709551615GB".parse::<LogRotation>().is_err());
+}
+
+#[test]
+fn test_rotated_path_collision() {
+    let path = log_path("collision");
+    let file = RotatingFile::open(&path, LogRotation::Size(10)).unwrap();
+
+    let rotated_path = file.rotated_path();
+    let file_name = rotated_path.file_name().unwrap().to_string_lossy();
+    assert!(file_name.starts_with("compositor.log."));
+    assert!(!rotated_path.exists());
+
+    // Counter is appended if a file rotated on the same day already exists.
+    fs::write(&rotated_path, "").unwrap();
+    assert_eq!(
+        file.rotated_path(),
+        path.with_file_name(format!("{file_name}.1"))
+    );
+    fs::write(path.with_file_name(format!("{file_name}.1")), "").unwrap();
+    assert_eq!(
+        file.rotated_path(),
+        path.with_file_name(format!("{file_name}.2"))
+    );
+}
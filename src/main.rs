@@ -1,5 +1,3 @@
-use log::info;
-
 use crate::config::config;
 
 mod api;
@@ -7,6 +5,7 @@ mod config;
 mod error;
 mod http;
 mod logger;
+mod websocket;
 
 mod types;
 
@@ -15,6 +14,7 @@ mod snapshot_tests;
 
 fn main() {
     logger::init_logger();
+    config::log_config_file_warnings();
 
     #[cfg(feature = "web_renderer")]
     {
@@ -32,8 +32,10 @@ fn main() {
 
     ffmpeg_next::format::network::init();
 
-    http::Server::new(config().api_port).run();
-
-    info!("Received exit signal. Terminating...")
-    // TODO: add graceful shutdown
+    http::Server::new(
+        config().api_bind_addr,
+        config().api_port,
+        config().tls.as_ref(),
+    )
+    .run();
 }
@@ -5,7 +5,7 @@ use super::utils::{create_renderer, frame_to_rgba, snaphot_save_path, snapshots_
 use anyhow::Result;
 use compositor_pipeline::pipeline;
 use compositor_render::{
-    scene::{OutputScene, RGBColor},
+    scene::{OutputScene, RGBColor, SceneTransition, Watermark},
     Frame, FrameSet, InputId, OutputId, Renderer, RendererSpec, Resolution, YuvData,
 };
 use image::ImageBuffer;
@@ -17,6 +17,10 @@ pub struct TestCase {
     pub renderers: Vec<&'static str>,
     pub timestamps: Vec<Duration>,
     pub outputs: Outputs,
+    /// Crossfade used for scene updates after the first one, see [`Outputs::Scenes`].
+    pub scene_transition: Option<&'static str>,
+    /// Watermark drawn over every output.
+    pub watermark: Option<&'static str>,
     pub only: bool,
     pub allowed_error: f32,
 }
@@ -34,6 +38,8 @@ impl Default for TestCase {
             renderers: Vec::new(),
             timestamps: vec![Duration::from_secs(0)],
             outputs: Outputs::Scene(vec![]),
+            scene_transition: None,
+            watermark: None,
             only: false,
             allowed_error: 20.0,
         }
@@ -50,7 +56,19 @@ impl TestCaseInstance {
     pub fn new(test_case: TestCase) -> TestCaseInstance {
         fn register_requests_to_renderers(register_request: RegisterRequest) -> RendererSpec {
             match register_request {
-                RegisterRequest::InputStream(_) | RegisterRequest::OutputStream(_) => {
+                RegisterRequest::InputStream(_)
+                | RegisterRequest::UdpTsInputStream(_)
+                | RegisterRequest::TcpH264InputStream(_)
+                | RegisterRequest::FileInputStream(_)
+                | RegisterRequest::SrtInputStream(_)
+                | RegisterRequest::RtspInputStream(_)
+                | RegisterRequest::StaticImageInputStream(_)
+                | RegisterRequest::TestPatternInputStream(_)
+                | RegisterRequest::OutputStream(_)
+                | RegisterRequest::Mp4OutputStream(_)
+                | RegisterRequest::HlsOutputStream(_)
+                | RegisterRequest::RtmpOutputStream(_)
+                | RegisterRequest::WhipOutputStream(_) => {
                     panic!("Input and output streams are not supported in snapshot tests")
                 }
                 RegisterRequest::Shader(shader) => shader.try_into().unwrap(),
@@ -70,6 +88,11 @@ impl TestCaseInstance {
             .map(register_requests_to_renderers)
             .collect();
 
+        let watermark = test_case.watermark.map(|json| {
+            let watermark: types::OutputWatermark = serde_json::from_str(json).unwrap();
+            Watermark::try_from(watermark).unwrap()
+        });
+
         let outputs = match test_case.outputs {
             Outputs::Scene(ref scene) => vec![scene.clone()],
             Outputs::Scenes(ref scenes) => scenes.clone(),
@@ -86,13 +109,19 @@ impl TestCaseInstance {
                             output_id: scene.output_id,
                             root: scene.root,
                             resolution: output.1,
+                            watermark: watermark.clone(),
                         }
                     })
                     .collect::<Vec<_>>()
             })
             .collect();
 
-        let renderer = create_renderer(renderers, scenes.clone());
+        let scene_transition = test_case.scene_transition.map(|json| {
+            let transition: types::SceneTransition = serde_json::from_str(json).unwrap();
+            SceneTransition::try_from(transition).unwrap()
+        });
+
+        let renderer = create_renderer(renderers, scenes.clone(), scene_transition);
         TestCaseInstance {
             case: test_case,
             last_scene: scenes.last().unwrap().clone(),
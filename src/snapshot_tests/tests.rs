@@ -19,6 +19,8 @@ pub fn snapshot_tests() -> Vec<TestCase> {
     tests.append(&mut text_snapshot_tests());
     tests.append(&mut tiles_snapshot_tests());
     tests.append(&mut rescaler_snapshot_tests());
+    tests.append(&mut chroma_key_snapshot_tests());
+    tests.append(&mut watermark_snapshot_tests());
     tests.append(&mut shader_snapshot_tests());
     tests
 }
@@ -505,6 +507,67 @@ fn rescaler_snapshot_tests() -> Vec<TestCase> {
     ])
 }
 
+fn chroma_key_snapshot_tests() -> Vec<TestCase> {
+    Vec::from([
+        TestCase {
+            name: "chroma_key/green_input_over_background",
+            outputs: Outputs::Scene(vec![(
+                include_str!("../../snapshot_tests/chroma_key/green_input_over_background.scene.json"),
+                DEFAULT_RESOLUTION,
+            )]),
+            inputs: vec![TestInput::new(0), TestInput::new(1)],
+            ..Default::default()
+        },
+        TestCase {
+            name: "chroma_key/green_screen_with_foreground_over_background",
+            outputs: Outputs::Scene(vec![(
+                include_str!("../../snapshot_tests/chroma_key/green_screen_with_foreground_over_background.scene.json"),
+                DEFAULT_RESOLUTION,
+            )]),
+            inputs: vec![TestInput::new(0)],
+            ..Default::default()
+        },
+    ])
+}
+
+fn watermark_snapshot_tests() -> Vec<TestCase> {
+    let image_renderer = include_str!("../../snapshot_tests/register/image_jpeg.register.json");
+    let scene = include_str!("../../snapshot_tests/watermark/input_with_watermark.scene.json");
+
+    Vec::from([
+        TestCase {
+            name: "watermark/top_left",
+            outputs: Outputs::Scene(vec![(scene, DEFAULT_RESOLUTION)]),
+            watermark: Some(include_str!(
+                "../../snapshot_tests/watermark/top_left.watermark.json"
+            )),
+            renderers: vec![image_renderer],
+            inputs: vec![TestInput::new(1)],
+            ..Default::default()
+        },
+        TestCase {
+            name: "watermark/bottom_right_default",
+            outputs: Outputs::Scene(vec![(scene, DEFAULT_RESOLUTION)]),
+            watermark: Some(include_str!(
+                "../../snapshot_tests/watermark/bottom_right_default.watermark.json"
+            )),
+            renderers: vec![image_renderer],
+            inputs: vec![TestInput::new(1)],
+            ..Default::default()
+        },
+        TestCase {
+            name: "watermark/bottom_left_with_opacity",
+            outputs: Outputs::Scene(vec![(scene, DEFAULT_RESOLUTION)]),
+            watermark: Some(include_str!(
+                "../../snapshot_tests/watermark/bottom_left_with_opacity.watermark.json"
+            )),
+            renderers: vec![image_renderer],
+            inputs: vec![TestInput::new(1)],
+            ..Default::default()
+        },
+    ])
+}
+
 fn tiles_snapshot_tests() -> Vec<TestCase> {
     let input1 = TestInput::new(1);
     let input2 = TestInput::new(2);
@@ -1158,6 +1221,29 @@ fn image_snapshot_tests() -> Vec<TestCase> {
 
 fn transition_snapshot_tests() -> Vec<TestCase> {
     Vec::from([
+        TestCase {
+            name: "transition/crossfade",
+            outputs: Outputs::Scenes(vec![
+                vec![(
+                    include_str!("../../snapshot_tests/transition/crossfade_start.scene.json"),
+                    DEFAULT_RESOLUTION,
+                )],
+                vec![(
+                    include_str!("../../snapshot_tests/transition/crossfade_end.scene.json"),
+                    DEFAULT_RESOLUTION,
+                )],
+            ]),
+            scene_transition: Some(include_str!(
+                "../../snapshot_tests/transition/crossfade.transition.json"
+            )),
+            timestamps: vec![
+                Duration::from_secs(0),
+                Duration::from_secs(5),
+                Duration::from_secs(10),
+            ],
+            inputs: vec![TestInput::new(1)],
+            ..Default::default()
+        },
         TestCase {
             name: "transition/change_rescaler_absolute_and_send_next_update",
             outputs: Outputs::Scenes(vec![
@@ -1438,6 +1524,24 @@ fn view_snapshot_tests() -> Vec<TestCase> {
             )]),
             inputs: vec![TestInput::new(1)],
             ..Default::default()
+        },
+        TestCase {
+            name: "view/z_index_swapped_for_overlapping_siblings",
+            outputs: Outputs::Scene(vec![(
+                    include_str!("../../snapshot_tests/view/z_index_swapped_for_overlapping_siblings.scene.json"),
+                    DEFAULT_RESOLUTION,
+            )]),
+            inputs: vec![TestInput::new(1)],
+            ..Default::default()
+        },
+        TestCase {
+            name: "view/equal_z_index_keeps_declaration_order",
+            outputs: Outputs::Scene(vec![(
+                    include_str!("../../snapshot_tests/view/equal_z_index_keeps_declaration_order.scene.json"),
+                    DEFAULT_RESOLUTION,
+            )]),
+            inputs: vec![],
+            ..Default::default()
         }
     ])
 }
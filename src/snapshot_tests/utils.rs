@@ -1,8 +1,8 @@
 use std::{collections::HashSet, fs, path::PathBuf, time::Duration};
 
 use compositor_render::{
-    scene::OutputScene, web_renderer, Frame, Framerate, OutputId, Renderer, RendererOptions,
-    RendererSpec, YuvData,
+    scene::{OutputScene, SceneTransition},
+    web_renderer, Frame, Framerate, OutputId, Renderer, RendererOptions, RendererSpec, YuvData,
 };
 
 pub const SNAPSHOTS_DIR_NAME: &str = "snapshot_tests/snapshots/render_snapshots";
@@ -52,9 +52,12 @@ pub(super) fn snapshots_diff(old_snapshot: &[u8], new_snapshot: &[u8]) -> f32 {
     square_error / old_snapshot.len() as f32
 }
 
+/// Scene updates are applied with `scene_transition`, so outputs crossfade from the scene
+/// of the previous update.
 pub(super) fn create_renderer(
     renderers: Vec<RendererSpec>,
     scene_updates: Vec<Vec<OutputScene>>,
+    scene_transition: Option<SceneTransition>,
 ) -> Renderer {
     let (mut renderer, _event_loop) = Renderer::new(RendererOptions {
         web_renderer: web_renderer::WebRendererInitOptions {
@@ -63,6 +66,7 @@ pub(super) fn create_renderer(
         },
         framerate: Framerate { num: 30, den: 1 },
         stream_fallback_timeout: Duration::from_secs(3),
+        gpu_adapter: None,
     })
     .unwrap();
 
@@ -73,7 +77,9 @@ pub(super) fn create_renderer(
         renderer.register_renderer(spec).unwrap();
     }
     for scene_update in scene_updates {
-        renderer.update_scene(scene_update.clone()).unwrap();
+        renderer
+            .update_scene(scene_update.clone(), scene_transition)
+            .unwrap();
     }
 
     renderer
@@ -9,6 +9,7 @@ mod convert;
 mod convert_util;
 mod from_component;
 mod from_renderer;
+mod from_scene;
 mod register_request;
 mod renderer;
 mod util;
@@ -16,6 +17,8 @@ mod util;
 #[cfg(test)]
 mod convert_util_test;
 
+#[allow(unused_imports)]
+pub use component::ChromaKey;
 pub use component::Component;
 #[allow(unused_imports)]
 pub use component::Image;
@@ -34,10 +37,26 @@ pub use component::View;
 #[allow(unused_imports)]
 pub use component::WebView;
 
+pub use register_request::EncoderSettings;
+pub use register_request::InputCrop;
+pub use register_request::InputScaleMode;
+pub use register_request::OutputWatermark;
 pub use register_request::Port;
+pub use register_request::RegisterFileInputRequest;
+pub use register_request::RegisterHlsOutputRequest;
 pub use register_request::RegisterInputRequest;
+pub use register_request::RegisterMp4OutputRequest;
 pub use register_request::RegisterOutputRequest;
 pub use register_request::RegisterRequest;
+pub use register_request::RegisterRtmpOutputRequest;
+pub use register_request::RegisterRtspInputRequest;
+pub use register_request::RegisterSrtInputRequest;
+pub use register_request::RegisterStaticImageInputRequest;
+pub use register_request::RegisterTcpH264InputRequest;
+pub use register_request::RegisterTestPatternInputRequest;
+pub use register_request::RegisterUdpTsInputRequest;
+pub use register_request::RegisterWhipOutputRequest;
+pub use register_request::VideoCodec;
 
 #[allow(unused_imports)]
 pub use renderer::ImageSpec;
@@ -48,6 +67,7 @@ pub use renderer::WebRendererSpec;
 
 #[allow(unused_imports)]
 pub use util::Resolution;
+pub use util::SceneTransition;
 pub use util::TypeError;
 
 #[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
@@ -17,6 +17,7 @@ pub enum Component {
     Text(Text),
     Tiles(Tiles),
     Rescaler(Rescaler),
+    ChromaKey(ChromaKey),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
@@ -72,6 +73,11 @@ pub struct View {
 
     /// (**default=`"#00000000"`**) Background color in a `"#RRGGBBAA"` format.
     pub background_color_rgba: Option<RGBAColor>,
+
+    /// (**default=`0`**) Order in which children of a `View` component are drawn. Components
+    /// with a higher value are drawn on top of their siblings, regardless of the order of
+    /// `children`. Siblings with the same value are drawn in the order they are declared.
+    pub z_index: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
@@ -149,6 +155,11 @@ pub struct Rescaler {
     /// Defines how this component will behave during a scene update. This will only have an
     /// effect if the previous scene already contained a View component with the same id.
     pub transition: Option<Transition>,
+
+    /// (**default=`0`**) Order in which children of a `View` component are drawn. Components
+    /// with a higher value are drawn on top of their siblings, regardless of the order of
+    /// `children`. Siblings with the same value are drawn in the order they are declared.
+    pub z_index: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
@@ -223,6 +234,30 @@ pub struct Shader {
     pub resolution: Resolution,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ChromaKey {
+    /// Id of a component.
+    pub id: Option<ComponentId>,
+    /// Component that will be keyed.
+    pub child: Box<Component>,
+
+    /// (**default=`"#00FF00"`**) Color in the `"#RRGGBB"` format that will be made transparent.
+    pub key_color: Option<RGBColor>,
+    /// (**default=`0.4`**) Pixels with a chroma closer to the key color than this distance
+    /// are fully transparent. Value in the range 0-1.
+    pub tolerance: Option<f32>,
+    /// (**default=`0.08`**) Width of the range of distances above `tolerance` where pixels
+    /// are partially transparent. Value in the range 0-1, `0` keys with hard edges.
+    pub softness: Option<f32>,
+    /// (**default=`0.1`**) Width of the range of distances above `tolerance` where pixels
+    /// are desaturated, to remove the key color reflected on the foreground. Value in the
+    /// range 0-1, `0` disables it.
+    pub spill_suppression: Option<f32>,
+    /// Resolution of a texture where the child will be rendered and keyed.
+    pub resolution: Resolution,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(
     tag = "type",
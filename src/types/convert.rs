@@ -109,3 +109,16 @@ impl TryFrom<register_request::Port> for api::Port {
         }
     }
 }
+
+impl TryFrom<register_request::InputFallback> for compositor_render::InputFallback {
+    type Error = TypeError;
+
+    fn try_from(fallback: register_request::InputFallback) -> Result<Self, Self::Error> {
+        match fallback {
+            register_request::InputFallback::Color { color_rgba } => {
+                Ok(Self::Color(color_rgba.try_into()?))
+            }
+            register_request::InputFallback::Image { image_id } => Ok(Self::Image(image_id.into())),
+        }
+    }
+}
@@ -31,6 +31,15 @@ impl From<compositor_render::Resolution> for Resolution {
     }
 }
 
+impl From<scene::Size> for Resolution {
+    fn from(size: scene::Size) -> Self {
+        Self {
+            width: size.width as usize,
+            height: size.height as usize,
+        }
+    }
+}
+
 impl From<Transition> for scene::Transition {
     fn from(transition: Transition) -> Self {
         Self {
@@ -39,6 +48,36 @@ impl From<Transition> for scene::Transition {
     }
 }
 
+impl From<scene::Transition> for Transition {
+    fn from(transition: scene::Transition) -> Self {
+        Self {
+            duration_ms: transition.duration.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+impl TryFrom<SceneTransition> for scene::SceneTransition {
+    type Error = TypeError;
+
+    fn try_from(transition: SceneTransition) -> Result<Self, Self::Error> {
+        if !transition.duration_ms.is_finite() || transition.duration_ms < 0.0 {
+            return Err(TypeError::new(
+                "Scene transition duration has to be a non-negative number.",
+            ));
+        }
+        let easing = match transition.easing.unwrap_or(EasingFunction::Linear) {
+            EasingFunction::Linear => scene::EasingFunction::Linear,
+            EasingFunction::EaseIn => scene::EasingFunction::EaseIn,
+            EasingFunction::EaseOut => scene::EasingFunction::EaseOut,
+            EasingFunction::EaseInOut => scene::EasingFunction::EaseInOut,
+        };
+        Ok(Self {
+            duration: Duration::from_secs_f64(transition.duration_ms / 1000.0),
+            easing,
+        })
+    }
+}
+
 impl From<HorizontalAlign> for scene::HorizontalAlign {
     fn from(alignment: HorizontalAlign) -> Self {
         match alignment {
@@ -20,6 +20,7 @@ impl TryFrom<Component> for scene::Component {
             Component::Text(text) => Ok(Self::Text(text.try_into()?)),
             Component::Tiles(tiles) => Ok(Self::Tiles(tiles.try_into()?)),
             Component::Rescaler(rescaler) => Ok(Self::Rescaler(rescaler.try_into()?)),
+            Component::ChromaKey(chroma_key) => Ok(Self::ChromaKey(chroma_key.try_into()?)),
         }
     }
 }
@@ -109,6 +110,7 @@ impl TryFrom<View> for scene::ViewComponent {
                 .map(TryInto::try_into)
                 .unwrap_or(Ok(scene::RGBAColor(0, 0, 0, 0)))?,
             transition: view.transition.map(Into::into),
+            z_index: view.z_index.unwrap_or(0),
         })
     }
 }
@@ -181,6 +183,7 @@ impl TryFrom<Rescaler> for scene::RescalerComponent {
                 .unwrap_or(VerticalAlign::Center)
                 .into(),
             transition: rescaler.transition.map(Into::into),
+            z_index: rescaler.z_index.unwrap_or(0),
         })
     }
 }
@@ -204,6 +207,35 @@ impl TryFrom<Shader> for scene::ShaderComponent {
     }
 }
 
+impl TryFrom<ChromaKey> for scene::ChromaKeyComponent {
+    type Error = TypeError;
+
+    fn try_from(chroma_key: ChromaKey) -> Result<Self, Self::Error> {
+        fn range_param(name: &str, value: Option<f32>, default: f32) -> Result<f32, TypeError> {
+            match value {
+                Some(value) if !(0.0..=1.0).contains(&value) => Err(TypeError::new(format!(
+                    "\"{name}\" has to be a number between 0 and 1."
+                ))),
+                Some(value) => Ok(value),
+                None => Ok(default),
+            }
+        }
+
+        Ok(Self {
+            id: chroma_key.id.map(Into::into),
+            child: Box::new((*chroma_key.child).try_into()?),
+            key_color: chroma_key
+                .key_color
+                .map(TryInto::try_into)
+                .unwrap_or(Ok(scene::RGBColor(0, 255, 0)))?,
+            tolerance: range_param("tolerance", chroma_key.tolerance, 0.4)?,
+            softness: range_param("softness", chroma_key.softness, 0.08)?,
+            spill_suppression: range_param("spill_suppression", chroma_key.spill_suppression, 0.1)?,
+            size: chroma_key.resolution.into(),
+        })
+    }
+}
+
 impl From<ShaderParam> for scene::ShaderParam {
     fn from(param: ShaderParam) -> Self {
         fn from_struct_field(field: ShaderParamStructField) -> scene::ShaderParamStructField {
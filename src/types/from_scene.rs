@@ -0,0 +1,279 @@
+use compositor_render::scene;
+use compositor_render::scene::Position;
+
+use super::component::*;
+use super::util::*;
+
+// Conversions from the scene back to the API types, e.g. to return the current scene of
+// an output. Values that were filled with defaults are returned explicitly, so the result
+// describes the same scene when it's passed back to `update_scene`.
+
+impl From<scene::Component> for Component {
+    fn from(node: scene::Component) -> Self {
+        match node {
+            scene::Component::InputStream(input) => Self::InputStream(input.into()),
+            scene::Component::View(view) => Self::View(view.into()),
+            scene::Component::WebView(web) => Self::WebView(web.into()),
+            scene::Component::Shader(shader) => Self::Shader(shader.into()),
+            scene::Component::Image(image) => Self::Image(image.into()),
+            scene::Component::Text(text) => Self::Text(text.into()),
+            scene::Component::Tiles(tiles) => Self::Tiles(tiles.into()),
+            scene::Component::Rescaler(rescaler) => Self::Rescaler(rescaler.into()),
+            scene::Component::ChromaKey(chroma_key) => Self::ChromaKey(chroma_key.into()),
+        }
+    }
+}
+
+fn from_children(children: Vec<scene::Component>) -> Option<Vec<Component>> {
+    Some(children.into_iter().map(Into::into).collect())
+}
+
+/// Fields of the `View` and `Rescaler` components that define their position.
+#[derive(Default)]
+struct PositionFields {
+    width: Option<f32>,
+    height: Option<f32>,
+    top: Option<f32>,
+    left: Option<f32>,
+    bottom: Option<f32>,
+    right: Option<f32>,
+    rotation: Option<f32>,
+}
+
+impl From<Position> for PositionFields {
+    fn from(position: Position) -> Self {
+        match position {
+            Position::Static { width, height } => Self {
+                width,
+                height,
+                ..Default::default()
+            },
+            Position::Absolute(position) => {
+                let (top, bottom) = match position.position_vertical {
+                    scene::VerticalPosition::TopOffset(top) => (Some(top), None),
+                    scene::VerticalPosition::BottomOffset(bottom) => (None, Some(bottom)),
+                };
+                let (left, right) = match position.position_horizontal {
+                    scene::HorizontalPosition::LeftOffset(left) => (Some(left), None),
+                    scene::HorizontalPosition::RightOffset(right) => (None, Some(right)),
+                };
+                Self {
+                    width: Some(position.width),
+                    height: Some(position.height),
+                    top,
+                    left,
+                    bottom,
+                    right,
+                    rotation: Some(position.rotation_degrees),
+                }
+            }
+        }
+    }
+}
+
+impl From<scene::InputStreamComponent> for InputStream {
+    fn from(input: scene::InputStreamComponent) -> Self {
+        Self {
+            id: input.id.map(Into::into),
+            input_id: input.input_id.into(),
+        }
+    }
+}
+
+impl From<scene::ViewComponent> for View {
+    fn from(view: scene::ViewComponent) -> Self {
+        let position = PositionFields::from(view.position);
+        let direction = match view.direction {
+            scene::ViewChildrenDirection::Row => ViewDirection::Row,
+            scene::ViewChildrenDirection::Column => ViewDirection::Column,
+        };
+        let overflow = match view.overflow {
+            scene::Overflow::Visible => Overflow::Visible,
+            scene::Overflow::Hidden => Overflow::Hidden,
+            scene::Overflow::Fit => Overflow::Fit,
+        };
+        Self {
+            id: view.id.map(Into::into),
+            children: from_children(view.children),
+            width: position.width,
+            height: position.height,
+            direction: Some(direction),
+            top: position.top,
+            left: position.left,
+            bottom: position.bottom,
+            right: position.right,
+            rotation: position.rotation,
+            transition: view.transition.map(Into::into),
+            z_index: Some(view.z_index),
+            overflow: Some(overflow),
+            background_color_rgba: Some(view.background_color.into()),
+        }
+    }
+}
+
+impl From<scene::RescalerComponent> for Rescaler {
+    fn from(rescaler: scene::RescalerComponent) -> Self {
+        let position = PositionFields::from(rescaler.position);
+        let mode = match rescaler.mode {
+            scene::RescaleMode::Fit => RescaleMode::Fit,
+            scene::RescaleMode::Fill => RescaleMode::Fill,
+        };
+        Self {
+            id: rescaler.id.map(Into::into),
+            child: Box::new((*rescaler.child).into()),
+            mode: Some(mode),
+            horizontal_align: Some(rescaler.horizontal_align.into()),
+            vertical_align: Some(rescaler.vertical_align.into()),
+            width: position.width,
+            height: position.height,
+            top: position.top,
+            left: position.left,
+            bottom: position.bottom,
+            right: position.right,
+            rotation: position.rotation,
+            transition: rescaler.transition.map(Into::into),
+            z_index: Some(rescaler.z_index),
+        }
+    }
+}
+
+impl From<scene::ShaderComponent> for Shader {
+    fn from(shader: scene::ShaderComponent) -> Self {
+        Self {
+            id: shader.id.map(Into::into),
+            children: from_children(shader.children),
+            shader_id: shader.shader_id.into(),
+            shader_param: shader.shader_param.map(Into::into),
+            resolution: shader.size.into(),
+        }
+    }
+}
+
+impl From<scene::ChromaKeyComponent> for ChromaKey {
+    fn from(chroma_key: scene::ChromaKeyComponent) -> Self {
+        Self {
+            id: chroma_key.id.map(Into::into),
+            child: Box::new((*chroma_key.child).into()),
+            key_color: Some(chroma_key.key_color.into()),
+            tolerance: Some(chroma_key.tolerance),
+            softness: Some(chroma_key.softness),
+            spill_suppression: Some(chroma_key.spill_suppression),
+            resolution: chroma_key.size.into(),
+        }
+    }
+}
+
+impl From<scene::ShaderParam> for ShaderParam {
+    fn from(param: scene::ShaderParam) -> Self {
+        fn from_struct_field(field: scene::ShaderParamStructField) -> ShaderParamStructField {
+            ShaderParamStructField {
+                field_name: field.field_name,
+                value: field.value.into(),
+            }
+        }
+        match param {
+            scene::ShaderParam::F32(v) => ShaderParam::F32(v),
+            scene::ShaderParam::U32(v) => ShaderParam::U32(v),
+            scene::ShaderParam::I32(v) => ShaderParam::I32(v),
+            scene::ShaderParam::List(v) => {
+                ShaderParam::List(v.into_iter().map(Into::into).collect())
+            }
+            scene::ShaderParam::Struct(v) => {
+                ShaderParam::Struct(v.into_iter().map(from_struct_field).collect())
+            }
+        }
+    }
+}
+
+impl From<scene::ImageComponent> for Image {
+    fn from(image: scene::ImageComponent) -> Self {
+        Self {
+            id: image.id.map(Into::into),
+            image_id: image.image_id.into(),
+        }
+    }
+}
+
+impl From<scene::TextComponent> for Text {
+    fn from(text: scene::TextComponent) -> Self {
+        let style = match text.style {
+            scene::TextStyle::Normal => TextStyle::Normal,
+            scene::TextStyle::Italic => TextStyle::Italic,
+            scene::TextStyle::Oblique => TextStyle::Oblique,
+        };
+        let wrap = match text.wrap {
+            scene::TextWrap::None => TextWrapMode::None,
+            scene::TextWrap::Word => TextWrapMode::Word,
+            scene::TextWrap::Glyph => TextWrapMode::Glyph,
+        };
+        let weight = match text.weight {
+            scene::TextWeight::Thin => TextWeight::Thin,
+            scene::TextWeight::ExtraLight => TextWeight::ExtraLight,
+            scene::TextWeight::Light => TextWeight::Light,
+            scene::TextWeight::Normal => TextWeight::Normal,
+            scene::TextWeight::Medium => TextWeight::Medium,
+            scene::TextWeight::SemiBold => TextWeight::SemiBold,
+            scene::TextWeight::Bold => TextWeight::Bold,
+            scene::TextWeight::ExtraBold => TextWeight::ExtraBold,
+            scene::TextWeight::Black => TextWeight::Black,
+        };
+        let (width, height, max_width, max_height) = match text.dimensions {
+            scene::TextDimensions::Fixed { width, height } => {
+                (Some(width), Some(height), None, None)
+            }
+            scene::TextDimensions::FittedColumn { width, max_height } => {
+                (Some(width), None, None, Some(max_height))
+            }
+            scene::TextDimensions::Fitted {
+                max_width,
+                max_height,
+            } => (None, None, Some(max_width), Some(max_height)),
+        };
+        Self {
+            id: text.id.map(Into::into),
+            text: text.text,
+            width,
+            height,
+            max_width,
+            max_height,
+            font_size: text.font_size,
+            line_height: Some(text.line_height),
+            color_rgba: Some(text.color.into()),
+            background_color_rgba: Some(text.background_color.into()),
+            font_family: Some(text.font_family),
+            style: Some(style),
+            align: Some(text.align.into()),
+            wrap: Some(wrap),
+            weight: Some(weight),
+        }
+    }
+}
+
+impl From<scene::WebViewComponent> for WebView {
+    fn from(web: scene::WebViewComponent) -> Self {
+        Self {
+            id: web.id.map(Into::into),
+            children: from_children(web.children),
+            instance_id: web.instance_id.into(),
+        }
+    }
+}
+
+impl From<scene::TilesComponent> for Tiles {
+    fn from(tiles: scene::TilesComponent) -> Self {
+        let (width, height) = tiles.tile_aspect_ratio;
+        Self {
+            id: tiles.id.map(Into::into),
+            children: from_children(tiles.children),
+            width: tiles.width,
+            height: tiles.height,
+            background_color_rgba: Some(tiles.background_color.into()),
+            tile_aspect_ratio: Some(AspectRatio(format!("{width}:{height}"))),
+            margin: Some(tiles.margin),
+            padding: Some(tiles.padding),
+            horizontal_align: Some(tiles.horizontal_align.into()),
+            vertical_align: Some(tiles.vertical_align.into()),
+            transition: tiles.transition.map(Into::into),
+        }
+    }
+}
@@ -17,6 +17,24 @@ pub struct Transition {
     pub duration_ms: f64,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct SceneTransition {
+    /// Duration of a crossfade from the previous scene in milliseconds. Value `0` replaces
+    /// the scene immediately.
+    pub duration_ms: f64,
+    /// (**default=`"linear"`**) Easing function used to calculate the progress of the crossfade.
+    pub easing: Option<EasingFunction>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EasingFunction {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HorizontalAlign {
@@ -0,0 +1,263 @@
+use std::{
+    io::ErrorKind,
+    net::{IpAddr, SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use compositor_pipeline::event::{self, Event};
+use crossbeam_channel::{unbounded, Sender};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tungstenite::{handshake::server, Message, WebSocket};
+
+use crate::{
+    api::{self, Api, Request, ResponseHandler},
+    error::ApiError,
+    http::is_authorized,
+    types::{InputId, OutputId},
+};
+
+/// Requests sent over the WebSocket use the same format as the HTTP API. The optional
+/// `request_id` is copied to the response, so responses to deferred requests (e.g.
+/// `wait_for_next_frame` query) can be matched with requests.
+#[derive(Deserialize)]
+struct WebSocketRequest {
+    request_id: Option<String>,
+    #[serde(flatten)]
+    request: Request,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WebSocketMessage {
+    Response {
+        request_id: Option<String>,
+        response: api::Response,
+    },
+    Error {
+        request_id: Option<String>,
+        error: ErrorMessage,
+    },
+    Event {
+        event: EventMessage,
+    },
+}
+
+#[derive(Serialize)]
+struct ErrorMessage {
+    msg: String,
+    stack: Vec<String>,
+    error_code: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum EventMessage {
+    InputDisconnected {
+        input_id: InputId,
+    },
+    InputUnregistered {
+        input_id: InputId,
+    },
+    EncoderError {
+        output_id: OutputId,
+        message: String,
+    },
+}
+
+impl From<Event> for EventMessage {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::InputDisconnected(input_id) => EventMessage::InputDisconnected {
+                input_id: input_id.into(),
+            },
+            Event::InputUnregistered(input_id) => EventMessage::InputUnregistered {
+                input_id: input_id.into(),
+            },
+            Event::EncoderError { output_id, message } => EventMessage::EncoderError {
+                output_id: output_id.into(),
+                message,
+            },
+        }
+    }
+}
+
+impl WebSocketMessage {
+    fn error(request_id: Option<String>, err: ApiError) -> Self {
+        WebSocketMessage::Error {
+            request_id,
+            error: ErrorMessage {
+                msg: err.message,
+                stack: err.stack,
+                error_code: err.error_code.as_str(),
+            },
+        }
+    }
+}
+
+/// How often the connection thread stops waiting for client messages to send
+/// responses and events.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub fn start_server(bind_addr: IpAddr, port: u16, api: Arc<Mutex<Api>>) {
+    let listener = match TcpListener::bind(SocketAddr::new(bind_addr, port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            if err.kind() == ErrorKind::AddrInUse {
+                error!("Port {port} is already used. Stop using it or specify port using LIVE_COMPOSITOR_WEB_SOCKET_PORT environment variable.");
+            }
+            panic!("Failed to start video compositor WebSocket server.\nError: {err}")
+        }
+    };
+    info!("WebSocket API listening on port {port}");
+
+    thread::Builder::new()
+        .name("WebSocket server".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        warn!("Failed to accept WebSocket connection: {err}");
+                        continue;
+                    }
+                };
+                let api = api.clone();
+                thread::spawn(move || handle_connection(stream, api));
+            }
+        })
+        .unwrap();
+}
+
+fn handle_connection(stream: TcpStream, api: Arc<Mutex<Api>>) {
+    let mut socket = match tungstenite::accept_hdr(stream, check_api_token) {
+        Ok(socket) => socket,
+        Err(err) => {
+            warn!("WebSocket handshake failed: {err}");
+            return;
+        }
+    };
+    if let Err(err) = socket.get_mut().set_read_timeout(Some(POLL_INTERVAL)) {
+        error!("Failed to set WebSocket read timeout: {err}");
+        return;
+    }
+
+    let events = event::subscribe();
+    let (messages_sender, messages_receiver) = unbounded();
+
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => handle_message(&text, &api, &messages_sender),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                return;
+            }
+            Err(err) => {
+                warn!("WebSocket connection error: {err}");
+                return;
+            }
+        }
+
+        let messages = messages_receiver
+            .try_iter()
+            .chain(events.try_iter().map(|event| WebSocketMessage::Event {
+                event: event.into(),
+            }));
+        for message in messages {
+            if let Err(err) = send_message(&mut socket, &message) {
+                warn!("Failed to send WebSocket message: {err}");
+                return;
+            }
+        }
+    }
+}
+
+// Error type is defined by tungstenite.
+#[allow(clippy::result_large_err)]
+fn check_api_token(
+    request: &server::Request,
+    response: server::Response,
+) -> Result<server::Response, server::ErrorResponse> {
+    let authorization = request
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok());
+    if is_authorized(authorization) {
+        return Ok(response);
+    }
+    let mut error_response =
+        server::ErrorResponse::new(Some("Missing or invalid API token.".into()));
+    *error_response.status_mut() = tungstenite::http::StatusCode::UNAUTHORIZED;
+    Err(error_response)
+}
+
+fn handle_message(text: &str, api: &Mutex<Api>, messages_sender: &Sender<WebSocketMessage>) {
+    let request = match serde_json::from_str::<WebSocketRequest>(text) {
+        Ok(request) => request,
+        Err(err) => {
+            let message = WebSocketMessage::error(None, ApiError::malformed_request(&err));
+            messages_sender.send(message).unwrap();
+            return;
+        }
+    };
+    let WebSocketRequest {
+        request_id,
+        request,
+    } = request;
+
+    let response = api.lock().unwrap().handle_request(request);
+    let message = match response {
+        Ok(ResponseHandler::Ok) => WebSocketMessage::Response {
+            request_id,
+            response: api::Response::Ok {},
+        },
+        Ok(ResponseHandler::Response(response)) => WebSocketMessage::Response {
+            request_id,
+            response,
+        },
+        Ok(ResponseHandler::Shutdown) => {
+            let message = WebSocketMessage::Response {
+                request_id,
+                response: api::Response::Ok {},
+            };
+            messages_sender.send(message).unwrap();
+            api.lock().unwrap().request_shutdown();
+            return;
+        }
+        Ok(ResponseHandler::DeferredResponse(response)) => {
+            let messages_sender = messages_sender.clone();
+            thread::spawn(move || {
+                let message = match response.wait() {
+                    Ok(response) => WebSocketMessage::Response {
+                        request_id,
+                        response,
+                    },
+                    Err(err) => WebSocketMessage::error(request_id, err),
+                };
+                // Connection could be already closed.
+                let _ = messages_sender.send(message);
+            });
+            return;
+        }
+        Err(err) => WebSocketMessage::error(request_id, err),
+    };
+    messages_sender.send(message).unwrap();
+}
+
+fn send_message(
+    socket: &mut WebSocket<TcpStream>,
+    message: &WebSocketMessage,
+) -> Result<(), tungstenite::Error> {
+    let text = match serde_json::to_string(message) {
+        Ok(text) => text,
+        Err(err) => {
+            error!("Failed to serialize WebSocket message: {err}");
+            return Ok(());
+        }
+    };
+    socket.send(Message::Text(text))
+}